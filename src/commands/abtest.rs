@@ -0,0 +1,113 @@
+//! `abtest`: alternate optimized and baseline phases and compare latency
+
+use anyhow::Result;
+use log::{info, error};
+
+use crate::{config, network};
+use crate::error::HifiError;
+use crate::utils::i18n::t;
+use super::revert::{run_revert, RevertScope};
+
+/// Sleep for `duration`, returning true if Ctrl+C arrived first
+async fn sleep_or_interrupted(duration: std::time::Duration) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => false,
+        _ = tokio::signal::ctrl_c() => true,
+    }
+}
+
+/// Switch the system into one A/B phase configuration
+fn set_abtest_mode(mode: network::abtest::Mode) -> Result<()> {
+    use std::process::Command;
+
+    match mode {
+        network::abtest::Mode::Baseline => {
+            Command::new("systemctl").args(["stop", "hifi-wifi.service"]).output()?;
+            run_revert(&RevertScope::all())
+        }
+        network::abtest::Mode::Optimized => {
+            Command::new("systemctl").args(["start", "hifi-wifi.service"]).output()?;
+            Ok(())
+        }
+    }
+}
+
+/// Automatic A/B experiment: alternate baseline and optimized phases while
+/// sampling gateway latency, then print a statistical comparison
+pub async fn run_abtest(duration: std::time::Duration, phase_len: std::time::Duration, config: &config::structs::Config) -> Result<()> {
+    use crate::network::abtest::{self, Mode, PhaseResult};
+    use crate::network::stats::LatencyMonitor;
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    if !crate::system::sysext::service_installed() {
+        error!("{}", t("on.not_installed"));
+        return Ok(());
+    }
+    if phase_len < Duration::from_secs(20) {
+        anyhow::bail!("Phase length must be at least 20s");
+    }
+    let Some((interface, gateway)) = network::latency::default_route() else {
+        return Err(HifiError::NotConnected("No default route - connect to a network first".to_string()).into());
+    };
+    let target = config.metrics.probe_target.clone().unwrap_or(gateway);
+
+    let phases = (duration.as_secs() / phase_len.as_secs()).max(2);
+    // Give CAKE and power save time to settle after each switch before measuring
+    let settle = Duration::from_secs(10);
+    let was_active = Command::new("systemctl")
+        .args(["is-active", "--quiet", "hifi-wifi.service"])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+
+    info!("=== A/B Experiment: {} phases of {}s on {} (probing {}) ===", phases, phase_len.as_secs(), interface, target);
+    info!("Press Ctrl+C to stop early and report what was collected.\n");
+
+    let mut results = Vec::new();
+    // The restore below has to run even when a phase fails
+    let phases_run = async {
+        let mut mode = Mode::Baseline;
+        for i in 0..phases {
+            info!("Phase {}/{}: {:?}", i + 1, phases, mode);
+            set_abtest_mode(mode)?;
+            if sleep_or_interrupted(settle).await {
+                break;
+            }
+
+            let monitor = LatencyMonitor::start_with_retention(
+                &interface,
+                &target,
+                phase_len,
+                config.metrics.spike_threshold_ms,
+                Duration::from_millis(config.metrics.spike_min_duration_ms),
+            )?;
+            let started = Instant::now();
+            let interrupted = sleep_or_interrupted(phase_len - settle).await;
+            results.push(PhaseResult {
+                mode,
+                samples: monitor.samples(),
+                spikes: monitor.take_spikes().len(),
+                duration: started.elapsed(),
+            });
+            if interrupted {
+                break;
+            }
+            mode = mode.other();
+        }
+        Ok::<_, anyhow::Error>(())
+    }.await;
+
+    // Leave the system the way we found it
+    info!("Restoring original state...");
+    if was_active {
+        set_abtest_mode(Mode::Optimized)?;
+    } else {
+        set_abtest_mode(Mode::Baseline)?;
+    }
+    phases_run?;
+
+    println!();
+    println!("{}", abtest::report(&results));
+    Ok(())
+}
@@ -0,0 +1,240 @@
+//! `apply`: one-shot optimization pass (also run by `monitor` and `bootstrap`)
+
+use anyhow::Result;
+use log::{info, error, warn};
+
+use crate::{config, network, system};
+use crate::error::HifiError;
+use crate::config::structs::{CakeMode, Role};
+use crate::network::wifi::{WifiManager, WifiInterface};
+use crate::network::backend_tuner::BackendTuner;
+use crate::network::power_save::{PowerSaveController, Source as PowerSaveSource};
+use crate::system::power::PowerManager;
+use crate::system::optimizer::SystemOptimizer;
+use crate::utils::hooks::{self, Hook};
+
+pub fn run_apply(config: &config::structs::Config, reload_driver: bool) -> Result<()> {
+    info!("=== hifi-wifi v3.0 ===");
+    info!("Applying Wi-Fi optimizations...\n");
+
+    // 1. Detect Wi-Fi interfaces (hotspot/P2P ones are left alone)
+    let wifi_mgr = WifiManager::new()?.retain_optimizable(config.wifi.optimize_hotspot);
+    let interfaces = wifi_mgr.interfaces();
+    
+    if interfaces.is_empty() {
+        return Err(HifiError::NoInterfaces.into());
+    }
+
+    for ifc in interfaces {
+        info!("Found: {} (driver: {}, category: {:?})", 
+              ifc.name, ifc.driver, ifc.category);
+    }
+
+    // 2. Detect power state
+    let power_mgr = PowerManager::new();
+    info!("Device type: {:?}", power_mgr.device_type());
+    info!("Power source: {:?}", power_mgr.power_source());
+
+    // 3. Apply system optimizations
+    let mut unsupported_reload = Vec::new();
+    if config.system.sysctl_enabled || config.system.driver_tweaks_enabled || config.system.irq_affinity_enabled {
+        let sys_opt = SystemOptimizer::new(
+            config.system.sysctl_enabled,
+            config.system.irq_affinity_enabled,
+            config.system.driver_tweaks_enabled,
+        ).with_sysctl_settings(config.system.sysctl_settings());
+        
+        // Only optimize connected/active interfaces
+        let active_interfaces: Vec<WifiInterface> = interfaces
+            .iter()
+            .filter(|ifc| wifi_mgr.is_interface_connected(ifc))
+            .cloned()
+            .collect();
+        
+        if active_interfaces.is_empty() {
+            warn!("No active network connections - skipping IRQ optimizations");
+        } else {
+            info!("Optimizing {} active interface(s)", active_interfaces.len());
+            sys_opt.apply(&active_interfaces)?;
+        }
+
+        if config.system.driver_tweaks_enabled {
+            for ifc in active_interfaces.iter().filter(|i| i.interface_type == crate::network::wifi::InterfaceType::Wifi) {
+                if reload_driver && ifc.category == crate::network::wifi::DriverCategory::Generic {
+                    unsupported_reload.push(format!("{} ({})", ifc.name, ifc.driver));
+                } else if reload_driver {
+                    if let Err(e) = system::driver_reload::reload(ifc) {
+                        warn!("Driver reload on {} failed: {}", ifc.name, e);
+                    }
+                } else {
+                    let pending = system::driver_reload::pending(ifc);
+                    if !pending.is_empty() {
+                        info!("{} driver parameter(s) for {} take effect after a reboot (or run: sudo hifi-wifi apply --reload-driver)",
+                              pending.len(), ifc.name);
+                    }
+                }
+            }
+        }
+    }
+
+    // 4. Apply power-aware settings
+    let host = config.global.role == Role::Host;
+    let egress = if host { network::latency::default_route().map(|(dev, _)| dev) } else { None };
+    let mut power_save = PowerSaveController::new();
+    for ifc in interfaces {
+        // Skip disconnected interfaces
+        if !wifi_mgr.is_interface_connected(ifc) {
+            info!("Skipping {} (not connected)", ifc.name);
+            continue;
+        }
+        
+        info!("Optimizing connected interface: {}", ifc.name);
+        match config.power.wlan_power_save.as_str() {
+            "on" => power_save.request(&ifc.name, PowerSaveSource::Config, true, "config"),
+            "off" => power_save.request(&ifc.name, PowerSaveSource::Config, false, "config"),
+            _ if host => power_save.request(&ifc.name, PowerSaveSource::Adaptive, false, "streaming host"),
+            _ if power_mgr.should_enable_power_save() => power_save.request(&ifc.name, PowerSaveSource::Adaptive, true, "battery"),
+            _ => power_save.request(&ifc.name, PowerSaveSource::Adaptive, false, "AC/desktop"),
+        }
+        power_save.apply_now(&wifi_mgr, ifc)?;
+
+        // 5. Get link stats and apply CAKE
+        // Always apply CAKE, even if we can't get link stats
+        let bandwidth = match wifi_mgr.get_link_stats(ifc) {
+            Ok(stats) if stats.tx_bitrate_mbps > 0.0 => {
+                info!("Link: {}Mbps TX, {}dBm signal", stats.tx_bitrate_mbps, stats.signal_dbm);
+                // Use 60% of link rate for realistic Wi-Fi throughput
+                (stats.tx_bitrate_mbps * 0.60) as u32
+            }
+            Ok(stats) => {
+                warn!("Link stats returned 0 bitrate (signal: {}dBm), using 200Mbit default", stats.signal_dbm);
+                200
+            }
+            Err(e) => {
+                warn!("Failed to get link stats: {}, using 200Mbit default", e);
+                200
+            }
+        };
+        
+        match config.governor.cake_mode {
+            // Streaming host: shape only the NIC the stream leaves through, at near line rate
+            CakeMode::Always if host => {
+                if egress.as_deref() == Some(ifc.name.as_str()) {
+                    let link_mbit = wifi_mgr.get_link_stats(ifc).map(|s| s.tx_bitrate_mbps).unwrap_or(0.0);
+                    let mbit = if link_mbit > 0.0 { (link_mbit * config.host.cake_overhead_factor) as u32 } else { 1000 };
+                    let mut tc = network::tc::TcManager::default().for_host(config.host.sunshine_port);
+                    tc.seed(mbit.max(1));
+                    if let Err(e) = tc.apply_cake(&ifc.name) {
+                        error!("Failed to apply host CAKE on {}: {}", ifc.name, e);
+                    }
+                } else {
+                    info!("{} is not the egress interface - no CAKE in host role", ifc.name);
+                }
+            }
+            CakeMode::Always => {
+                if let Err(e) = wifi_mgr.apply_cake(ifc, bandwidth.max(1)) {
+                    error!("Failed to apply CAKE on {}: {}", ifc.name, e);
+                }
+            }
+            mode => {
+                info!("CAKE mode {:?}: leaving the default qdisc on {}", mode, ifc.name);
+                wifi_mgr.remove_cake(ifc)?;
+            }
+        }
+
+        if config.wifi.mtu_fix != network::mtu::MtuFix::Off {
+            if let Some(report) = network::mtu::probe(&ifc.name, config.wifi.stream_host.as_deref()) {
+                if let Err(e) = network::mtu::apply_fix(&report, config.wifi.mtu_fix) {
+                    warn!("Failed to work around the MTU black hole on {}: {}", ifc.name, e);
+                }
+            }
+        }
+
+        if ifc.interface_type == crate::network::wifi::InterfaceType::Wifi {
+            if let Err(e) = network::mac80211::apply(ifc, &config.mac80211) {
+                warn!("Failed to tune mac80211 queues on {}: {}", ifc.name, e);
+            }
+        }
+    }
+
+    if let Err(e) = network::ipv6::apply(config.wifi.ipv6_preference) {
+        warn!("Failed to apply IPv6 preference: {}", e);
+    }
+    if let Err(e) = network::mac_privacy::apply(config.privacy.mac_randomization) {
+        warn!("Failed to apply MAC randomization policy: {}", e);
+    }
+    if config.wifi.lock_bssid.is_some() || config.wifi.lock_band.is_some() {
+        if let Err(e) = network::lock::lock(None, config.wifi.lock_bssid.as_deref(), config.wifi.lock_band) {
+            warn!("Failed to apply the configured BSSID/band lock: {}", e);
+        }
+    }
+
+    // 6. Keep TLP / tuned away from the Wi-Fi device
+    if config.system.power_daemon_coordination {
+        let names: Vec<String> = interfaces
+            .iter()
+            .filter(|ifc| ifc.interface_type == crate::network::wifi::InterfaceType::Wifi)
+            .map(|ifc| ifc.name.clone())
+            .collect();
+        if let Err(e) = system::conflicts::coordinate(&names) {
+            warn!("Power daemon coordination failed: {}", e);
+        }
+    } else {
+        for status in system::conflicts::detect() {
+            if status.daemon.conflicts() && !status.coordinated {
+                warn!(daemon = status.daemon.name();
+                      "{} is running and may override {} - set [system] power_daemon_coordination = true",
+                      status.daemon.name(), status.daemon.overlap());
+            }
+        }
+    }
+
+    // 7. Apply backend tuning
+    BackendTuner::from_config(config).apply()?;
+
+    info!("\n=== Optimization Complete ===");
+    let names: Vec<&str> = interfaces.iter().map(|ifc| ifc.name.as_str()).collect();
+    hooks::run(&config.hooks, Hook::PostApply, &[("HIFI_WIFI_INTERFACES", names.join(" "))]);
+    if !unsupported_reload.is_empty() {
+        return Err(HifiError::UnsupportedHardware(
+            format!("no known driver parameters to reload for {}", unsupported_reload.join(", "))).into());
+    }
+    Ok(())
+}
+
+pub fn run_dry_run() -> Result<()> {
+    let wifi_mgr = WifiManager::new()?;
+    let power_mgr = PowerManager::new();
+    
+    info!("  - Detected {} Wi-Fi interface(s)", wifi_mgr.interfaces().len());
+    for ifc in wifi_mgr.interfaces() {
+        info!("    * {} ({:?})", ifc.name, ifc.category);
+    }
+    
+    info!("  - Device type: {:?}", power_mgr.device_type());
+    info!("  - Power source: {:?}", power_mgr.power_source());
+    
+    if power_mgr.should_enable_power_save() {
+        info!("  - Would ENABLE power save (on battery)");
+    } else {
+        info!("  - Would DISABLE power save (performance mode)");
+    }
+    
+    info!("  - Would create /etc/sysctl.d/99-hifi-wifi.conf");
+    info!("  - Would create driver-specific modprobe config");
+    info!("  - Would apply CAKE qdisc for bufferbloat mitigation");
+    info!("  - Would optimize IRQ affinity");
+    
+    Ok(())
+}
+
+/// Stop NetworkManager from re-enabling power save on reconnect (see network::nm_powersave)
+pub async fn deconflict_nm_powersave(config: &config::structs::Config) {
+    let result = match network::nm::NmClient::new().await {
+        Ok(nm) => network::nm_powersave::deconflict(&nm, config.power.nm_powersave_override).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!("Could not check NetworkManager powersave setting: {}", e);
+    }
+}
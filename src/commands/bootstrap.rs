@@ -0,0 +1,88 @@
+//! `bootstrap`: boot-time repair after SteamOS updates
+
+use anyhow::Result;
+use log::{info, error, warn};
+
+use crate::config::loader::load_config;
+use super::apply::run_apply;
+use super::check;
+use super::install::{on_demand_installed, BINARY_PATH};
+use crate::system::{readonly, repair_log};
+
+/// Bootstrap: Ensure hifi-wifi is running and optimizations are applied
+/// Called by the user repair service on EVERY boot to guarantee:
+/// 1. Installed files are intact (recreate the service file etc. if a SteamOS update wiped them)
+/// 2. Service is running
+/// 3. Optimizations are applied (CAKE, power save, etc.)
+///
+/// Repairs run at most once an hour unless `force`d, so a state they can't fix
+/// doesn't get "repaired" on every invocation.
+pub fn run_bootstrap(force: bool) -> Result<()> {
+    use std::process::Command;
+    use std::path::Path;
+    
+    let binary_path = Path::new(BINARY_PATH);
+    
+    // Check if binary exists (if not, nothing we can do)
+    if !binary_path.exists() {
+        warn!("Bootstrap: Binary not found at {}, skipping", binary_path.display());
+        return Ok(());
+    }
+    
+    // A run killed while /usr was writable leaves it that way
+    readonly::recover();
+
+    // Step 1: Repair what an update wiped or changed (unit, polkit actions, dispatcher,
+    // SELinux label); same checks as `hifi-wifi check --repair`
+    let items = check::run_checks();
+    for item in &items {
+        if let (Some(problem), None) = (&item.problem, item.fix) {
+            warn!("Bootstrap: {}: {}", item.name, problem);
+        }
+    }
+    let wait = repair_log::auto_repair_wait().filter(|_| !force);
+    let repaired = match wait {
+        Some(secs) if items.iter().any(|i| i.fix.is_some()) => {
+            warn!("Bootstrap: Skipping repair, last one was under an hour ago (next in {} min; --force overrides)",
+                  secs.div_ceil(60));
+            false
+        }
+        _ => check::repair(&items, if force { "bootstrap --force" } else { "bootstrap" }) > 0,
+    };
+
+    // Step 2: Always apply optimizations on bootstrap
+    // This ensures CAKE, power save, sysctl, etc. are applied on every boot
+    // even if service is about to start (monitor mode also calls apply, but
+    // this guarantees it happens immediately)
+    // With `install --on-demand` both wait for the first client on the control socket
+    if on_demand_installed() {
+        info!("Bootstrap: On-demand install, making sure the control socket is listening");
+        let _ = Command::new("systemctl").args(["start", "hifi-wifi.socket"]).output();
+        return Ok(());
+    }
+    info!("Bootstrap: Applying optimizations...");
+    let config = load_config();
+    if let Err(e) = run_apply(&config, false) {
+        error!("Bootstrap: Failed to apply optimizations: {}", e);
+    }
+    
+    // Step 3: Ensure service is running
+    let service_running = Command::new("systemctl")
+        .args(["is-active", "--quiet", "hifi-wifi.service"])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    
+    if !service_running {
+        info!("Bootstrap: Starting monitor service...");
+        let _ = Command::new("systemctl").args(["start", "hifi-wifi.service"]).output();
+    }
+    
+    if repaired {
+        info!("Bootstrap: Full repair complete - hifi-wifi restored after SteamOS update");
+    } else {
+        info!("Bootstrap: Optimizations applied successfully");
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! `calibrate`: measure internet capacity for per-SSID CAKE ceilings
+
+use anyhow::{bail, Result};
+use log::info;
+
+use crate::error::HifiError;
+use crate::network::metered::MeteredMode;
+
+/// Measure unshaped internet capacity and store per-SSID CAKE ceilings
+pub async fn run_calibrate(server: Option<&str>, port: u16, metered: MeteredMode) -> Result<()> {
+    use crate::network::nm::NmClient;
+    use crate::network::speedtest::{self, SpeedResult};
+    use crate::network::state::PersistentState;
+    use crate::network::tc::TcManager;
+    use crate::network::throughput::{self, Direction};
+    use std::process::Command;
+
+    let nm = NmClient::new().await?;
+    let Some(device) = nm.get_wireless_devices().await?.into_iter().find(|d| d.active_ap.is_some()) else {
+        return Err(HifiError::NotConnected("No active Wi-Fi connection - calibration is stored per SSID".to_string()).into());
+    };
+    let ssid = device.active_ap.as_ref().map(|ap| ap.ssid.clone()).unwrap_or_default();
+    // A public speedtest moves hundreds of MB
+    if server.is_none() && metered.resolve(device.metered) {
+        bail!("'{}' is a metered connection (phone hotspot?); a speedtest would use a lot of mobile data. \
+               Use --server with a LAN iperf3 server, or set [wifi] metered = \"no\" if it isn't metered", ssid);
+    }
+
+    info!("=== Calibrating CAKE for '{}' on {} ===\n", ssid, device.interface);
+
+    // Stop the governor (it persists its state and removes CAKE) so the test sees the raw link
+    let was_active = Command::new("systemctl")
+        .args(["is-active", "--quiet", "hifi-wifi.service"])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if was_active {
+        Command::new("systemctl").args(["stop", "hifi-wifi.service"]).output()?;
+    }
+    let _ = TcManager::default().remove_cake(&device.interface);
+
+    info!("Running bandwidth test (this takes about 30 seconds)...");
+    let result = match server {
+        Some(host) => throughput::run_iperf3(host, port, 10, Direction::Download).and_then(|down| {
+            Ok(SpeedResult {
+                download_mbit: down,
+                upload_mbit: throughput::run_iperf3(host, port, 10, Direction::Upload)?,
+                server: format!("iperf3 {}:{}", host, port),
+            })
+        }),
+        None => speedtest::run(),
+    };
+
+    // Saved before the restart, or the daemon loads the old state and later writes it back
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let saved = result.and_then(|result| {
+        let ceiling = result.ceiling(now);
+        let mut state = PersistentState::load();
+        state.set_ceiling(&ssid, ceiling.clone());
+        state.save()?;
+        Ok((result, ceiling))
+    });
+
+    if was_active {
+        Command::new("systemctl").args(["start", "hifi-wifi.service"]).output()?;
+    }
+    let (result, ceiling) = saved?;
+
+    info!("Server:   {}", result.server);
+    info!("Measured: {:.1} Mbit down / {:.1} Mbit up", result.download_mbit, result.upload_mbit);
+    info!("Stored CAKE ceiling for '{}': {} Mbit (upload), {} Mbit (download)", ssid, ceiling.upload_mbit, ceiling.download_mbit);
+    if was_active {
+        info!("Service restarted; Breathing CAKE is now clamped to the calibrated ceiling.");
+    }
+    Ok(())
+}
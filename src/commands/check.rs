@@ -0,0 +1,262 @@
+//! `check`: installation integrity, with `--repair` to fix what can be fixed in place
+//!
+//! Compares what `install` set up against what it would write now: the binary, the
+//! service unit, polkit actions, SELinux label, the install manifest and the config.
+//! `bootstrap` runs the same checks with repair at every boot. Each fix is recorded in
+//! the repair audit log (see `repair_log`).
+
+use anyhow::Result;
+use log::{info, warn};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::loader::{read_config, LoadOutcome};
+use crate::error::HifiError;
+use crate::network::usage::unix_now;
+use crate::system::lsm::{self, Lsm};
+use crate::system::manifest::{self, Drift, Manifest};
+use crate::system::repair_log::{self, RepairRecord};
+use crate::system::sysext;
+use crate::utils;
+use super::install::{
+    fix_selinux_context, hardened_installed, install_nm_dispatcher, install_polkit_actions, on_demand_installed,
+    record_manifest, service_unit, BINARY_PATH, DISPATCHER_PATH, HELPER_PATH, POLKIT_POLICY_PATH,
+    SERVICE_PATH,
+};
+
+/// What `--repair` does about a failed item
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fix {
+    /// Copy the running binary over the installed one
+    CopyBinary,
+    /// Rewrite the service unit from the template
+    WriteUnit,
+    /// Relabel the binary for SELinux
+    Relabel,
+    /// Reinstall polkit actions and helper
+    PolkitActions,
+    /// Reinstall the NetworkManager dispatcher
+    Dispatcher,
+}
+
+/// One checked item
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub name: String,
+    /// None if it passed, else what's wrong
+    pub problem: Option<String>,
+    pub fix: Option<Fix>,
+}
+
+impl Item {
+    fn pass(name: impl Into<String>) -> Self {
+        Self { name: name.into(), problem: None, fix: None }
+    }
+
+    fn fail(name: impl Into<String>, problem: impl Into<String>, fix: Option<Fix>) -> Self {
+        Self { name: name.into(), problem: Some(problem.into()), fix }
+    }
+}
+
+fn check_binary() -> Item {
+    let name = format!("Binary {}", BINARY_PATH);
+    let installed = Path::new(BINARY_PATH);
+    if !installed.exists() {
+        return Item::fail(name, "missing", Some(Fix::CopyBinary));
+    }
+    let running = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(_) => return Item::pass(name),
+    };
+    if fs::canonicalize(&running).ok() == fs::canonicalize(installed).ok() {
+        return Item::pass(name);
+    }
+    if manifest::sha256_file(&running) == manifest::sha256_file(installed) {
+        Item::pass(name)
+    } else {
+        Item::fail(name, format!("differs from the running binary ({})", running.display()), Some(Fix::CopyBinary))
+    }
+}
+
+fn check_unit() -> Item {
+    let expected = service_unit(hardened_installed());
+    if sysext::installed() {
+        let path = Path::new(sysext::EXTENSION_DIR).join("usr/lib/systemd/system/hifi-wifi.service");
+        let name = format!("Service unit {}", path.display());
+        return match fs::read_to_string(&path) {
+            Ok(content) if content == expected => Item::pass(name),
+            // The extension is a read-only image; rebuilding it is `install`'s job
+            Ok(_) => Item::fail(name, "differs from the template (run: sudo hifi-wifi install --sysext)", None),
+            Err(_) => Item::fail(name, "missing (run: sudo hifi-wifi install --sysext)", None),
+        };
+    }
+    let name = format!("Service unit {}", SERVICE_PATH);
+    match fs::read_to_string(SERVICE_PATH) {
+        Ok(content) if content == expected => Item::pass(name),
+        Ok(_) => Item::fail(name, "differs from the template", Some(Fix::WriteUnit)),
+        Err(_) => Item::fail(name, "missing", Some(Fix::WriteUnit)),
+    }
+}
+
+/// None where polkit isn't installed or the actions ship in the extension
+fn check_polkit() -> Option<Item> {
+    let policy = Path::new(POLKIT_POLICY_PATH);
+    if sysext::installed() || !policy.parent().is_some_and(Path::exists) {
+        return None;
+    }
+    let missing: Vec<&str> = [POLKIT_POLICY_PATH, HELPER_PATH].into_iter()
+        .filter(|p| !Path::new(p).exists())
+        .collect();
+    Some(if missing.is_empty() {
+        Item::pass("polkit actions")
+    } else {
+        Item::fail("polkit actions", format!("missing {}", missing.join(", ")), Some(Fix::PolkitActions))
+    })
+}
+
+/// None unless SELinux is active
+fn check_selinux() -> Option<Item> {
+    if lsm::detect() != Lsm::SELinux || !Path::new(BINARY_PATH).exists() {
+        return None;
+    }
+    let context = lsm::file_context(Path::new(BINARY_PATH)).unwrap_or_default();
+    Some(match lsm::context_type(&context) {
+        Some(ty) if lsm::EXEC_TYPES.contains(&ty) => Item::pass(format!("SELinux label ({})", ty)),
+        _ => Item::fail("SELinux label", format!("binary is labeled '{}', systemd can't execute it", context), Some(Fix::Relabel)),
+    })
+}
+
+/// Everything else `install` recorded; items covered by the checks above are skipped
+fn check_manifest() -> Vec<Item> {
+    let manifest = Manifest::load();
+    if manifest.artifacts.is_empty() {
+        return vec![Item::fail("Install manifest", format!("{} missing or empty", manifest::MANIFEST_PATH), None)];
+    }
+    let covered = [BINARY_PATH, SERVICE_PATH, POLKIT_POLICY_PATH, HELPER_PATH];
+    let drift: Vec<(PathBuf, Drift)> = manifest.verify().into_iter()
+        .filter(|(path, _)| !covered.iter().any(|c| path == Path::new(c)))
+        .collect();
+    if drift.is_empty() {
+        return vec![Item::pass(format!("{} installed files match the manifest", manifest.artifacts.len()))];
+    }
+    drift.into_iter().map(|(path, drift)| {
+        let name = path.display().to_string();
+        match drift {
+            Drift::Missing if path == Path::new(DISPATCHER_PATH) => Item::fail(name, "missing", Some(Fix::Dispatcher)),
+            Drift::Missing => Item::fail(name, "missing (run: sudo hifi-wifi install)", None),
+            Drift::Modified => Item::fail(name, "modified since install", None),
+        }
+    }).collect()
+}
+
+fn check_config() -> Item {
+    match read_config().1 {
+        LoadOutcome::Loaded => Item::pass("Config parses"),
+        LoadOutcome::Missing => Item::pass("No config file (defaults)"),
+        LoadOutcome::ParseError(e) => Item::fail("Config", format!("parse error: {}", e), None),
+        LoadOutcome::ReadError(e) => Item::fail("Config", format!("unreadable: {}", e), None),
+    }
+}
+
+/// Run every integrity check
+pub fn run_checks() -> Vec<Item> {
+    let mut items = vec![check_binary(), check_unit()];
+    items.extend(check_polkit());
+    items.extend(check_selinux());
+    items.extend(check_manifest());
+    items.push(check_config());
+    items
+}
+
+/// Copy via a temp file: the installed binary may be running (ETXTBSY on write)
+fn copy_binary() -> Result<()> {
+    let tmp = format!("{}.new", BINARY_PATH);
+    fs::copy(std::env::current_exe()?, &tmp)?;
+    fs::set_permissions(&tmp, fs::Permissions::from_mode(0o755))?;
+    fs::rename(&tmp, BINARY_PATH)?;
+    fix_selinux_context(Path::new(BINARY_PATH));
+    Ok(())
+}
+
+fn apply_fix(fix: Fix) -> Result<()> {
+    match fix {
+        Fix::CopyBinary => copy_binary(),
+        Fix::WriteUnit => {
+            fs::write(SERVICE_PATH, service_unit(hardened_installed()))?;
+            Command::new("systemctl").args(["daemon-reload"]).output()?;
+            let unit = if on_demand_installed() { "hifi-wifi.socket" } else { "hifi-wifi.service" };
+            Command::new("systemctl").args(["enable", unit]).output()?;
+            Ok(())
+        }
+        Fix::Relabel => {
+            let _ = Command::new("restorecon").args(["-R", "/var/lib/hifi-wifi"]).output();
+            fix_selinux_context(Path::new(BINARY_PATH));
+            Ok(())
+        }
+        Fix::PolkitActions => install_polkit_actions(),
+        Fix::Dispatcher => install_nm_dispatcher(),
+    }
+}
+
+/// Fix every failed item that has a fix, recording each attempt under `trigger`;
+/// returns how many were fixed
+pub fn repair(items: &[Item], trigger: &str) -> usize {
+    let mut fixed = 0;
+    for item in items {
+        let Some(fix) = item.fix else { continue };
+        info!("Repairing {}...", item.name);
+        let result = apply_fix(fix);
+        match &result {
+            Ok(()) => fixed += 1,
+            Err(e) => warn!("Failed to repair {}: {}", item.name, e),
+        }
+        repair_log::record(&RepairRecord {
+            ts: unix_now(),
+            trigger: trigger.to_string(),
+            item: item.name.clone(),
+            problem: item.problem.clone().unwrap_or_default(),
+            fix: format!("{:?}", fix),
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+    // Rewritten files get fresh checksums; modified ones keep failing until reinstalled
+    if fixed > 0 {
+        record_manifest(None);
+    }
+    fixed
+}
+
+/// Print the checks (after repairing, with `--repair`); fails with `ChecksFailed` if any remain
+pub fn run_check(repair_items: bool) -> Result<()> {
+    // Nothing to check (or repair into a half install)
+    if !Path::new(BINARY_PATH).exists() && !sysext::service_installed() {
+        return Err(HifiError::ServiceMissing.into());
+    }
+    let c = utils::style::palette();
+    let mut items = run_checks();
+    if repair_items && repair(&items, "check") > 0 {
+        items = run_checks();
+    }
+
+    println!("{}hifi-wifi check{}", c.bold, c.reset);
+    for item in &items {
+        match &item.problem {
+            None => println!("  {}PASS{}  {}", c.green, c.reset, item.name),
+            Some(problem) => {
+                println!("  {}FAIL{}  {}: {}", c.red, c.reset, item.name, problem);
+                if item.fix.is_some() && !repair_items {
+                    println!("        {}Fix with: sudo hifi-wifi check --repair{}", c.dim, c.reset);
+                }
+            }
+        }
+    }
+
+    let failures = items.iter().filter(|i| i.problem.is_some()).count();
+    println!("\n{} passed, {} failed", items.len() - failures, failures);
+    if failures > 0 {
+        return Err(HifiError::ChecksFailed(failures).into());
+    }
+    Ok(())
+}
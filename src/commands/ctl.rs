@@ -0,0 +1,53 @@
+//! `ctl` and `game-mode`: requests to the running daemon over its control socket
+
+use anyhow::{bail, Result};
+use std::time::Duration;
+
+use crate::utils::control::{self, GameModeSwitch, Request};
+
+/// A `set` value as JSON: numbers, booleans and arrays as written, anything else as a string
+pub fn parse_value(raw: &str) -> serde_json::Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| serde_json::Value::String(raw.to_string()))
+}
+
+pub async fn run_ctl(request: Request) -> Result<()> {
+    let response = control::send(&request).await?;
+    if !response.ok {
+        bail!("{}", response.error.unwrap_or_else(|| "request failed".to_string()));
+    }
+    match response.data {
+        Some(data) => println!("{}", serde_json::to_string_pretty(&data)?),
+        None => println!("OK"),
+    }
+    Ok(())
+}
+
+/// `game-mode on|off|auto [--for 2h]`
+pub async fn run_game_mode(mode: GameModeSwitch, duration: Option<Duration>) -> Result<()> {
+    if mode == GameModeSwitch::Auto && duration.is_some() {
+        bail!("--for only applies to `on` and `off`");
+    }
+    let for_secs = duration.map(|d| d.as_secs());
+    let response = control::send(&Request::GameMode { mode, for_secs }).await?;
+    if !response.ok {
+        bail!("{}", response.error.unwrap_or_else(|| "request failed".to_string()));
+    }
+    match (mode.forced(), for_secs) {
+        (None, _) => println!("Game mode: automatic (PPS detection)"),
+        (Some(on), None) => println!("Game mode: forced {} until `hifi-wifi game-mode auto`", if on { "on" } else { "off" }),
+        (Some(on), Some(secs)) => println!("Game mode: forced {} for {}m", if on { "on" } else { "off" }, secs.div_ceil(60)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("300"), serde_json::json!(300));
+        assert_eq!(parse_value("game_only"), serde_json::json!("game_only"));
+        assert_eq!(parse_value(r#"["eee"]"#), serde_json::json!(["eee"]));
+    }
+}
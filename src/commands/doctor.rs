@@ -0,0 +1,34 @@
+//! `doctor`: environment checks
+
+use anyhow::Result;
+
+use crate::{config, system, utils};
+use crate::error::HifiError;
+
+/// Print environment checks; fails with `ChecksFailed` if any check failed
+pub async fn run_doctor(config: &config::structs::Config) -> Result<()> {
+    use system::doctor::Level;
+
+    let c = utils::style::palette();
+    let checks = system::doctor::run_checks(config).await;
+    println!("{}hifi-wifi doctor{}", c.bold, c.reset);
+    for check in &checks {
+        let (tag, color) = match check.level {
+            Level::Ok => ("OK", c.green),
+            Level::Warn => ("WARN", c.yellow),
+            Level::Fail => ("FAIL", c.red),
+        };
+        println!("  {}{:<5}{} {}", color, tag, c.reset, check.message);
+        if let Some(hint) = &check.hint {
+            println!("        {}{}{}", c.dim, hint, c.reset);
+        }
+    }
+
+    let count = |level| checks.iter().filter(|ch| ch.level == level).count();
+    let failures = count(Level::Fail);
+    println!("\n{} ok, {} warning(s), {} failure(s)", count(Level::Ok), count(Level::Warn), failures);
+    if failures > 0 {
+        return Err(HifiError::ChecksFailed(failures).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! `export`: tick history or the event log as CSV / NDJSON for offline analysis
+
+use anyhow::Result;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::network::history::{self, Row};
+use crate::network::usage::unix_now;
+use crate::utils::events;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportSource {
+    /// Per-tick link metrics (RTT, jitter, loss, signal, CAKE, throughput)
+    Metrics,
+    /// The event log (roams, spikes, game mode, ...)
+    Events,
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `export [--since 2h] [--format csv|ndjson] [--source metrics|events]` to stdout
+pub fn run_export(since: Duration, format: ExportFormat, source: ExportSource) -> Result<()> {
+    let from = unix_now().saturating_sub(since.as_secs());
+    let mut out = io::stdout().lock();
+    match source {
+        ExportSource::Metrics => {
+            let rows = history::read_since(from);
+            if rows.is_empty() {
+                eprintln!("No metric history in {} for that period (needs the daemon with [metrics] enabled)",
+                          history::HISTORY_PATH);
+            }
+            if format == ExportFormat::Csv {
+                writeln!(out, "{}", Row::CSV_HEADER)?;
+            }
+            for row in rows {
+                match format {
+                    ExportFormat::Csv => writeln!(out, "{}", row.to_csv())?,
+                    ExportFormat::Ndjson => writeln!(out, "{}", serde_json::to_string(&row)?)?,
+                }
+            }
+        }
+        ExportSource::Events => {
+            if format == ExportFormat::Csv {
+                writeln!(out, "ts,kind,interface,data")?;
+            }
+            for event in events::read_recent(usize::MAX).into_iter().filter(|e| e.ts >= from) {
+                match format {
+                    ExportFormat::Csv => writeln!(out, "{},{},{},{}", event.ts, csv_field(&event.kind),
+                                                  csv_field(event.interface.as_deref().unwrap_or_default()),
+                                                  csv_field(&serde_json::Value::Object(event.data).to_string()))?,
+                    ExportFormat::Ndjson => writeln!(out, "{}", serde_json::to_string(&event)?)?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("wlan0"), "wlan0");
+        assert_eq!(csv_field(r#"{"from":"a","to":"b"}"#), r#""{""from"":""a"",""to"":""b""}""#);
+    }
+}
@@ -0,0 +1,1017 @@
+//! `install` / `uninstall`: systemd service, NM dispatcher, PATH and the user repair service
+
+use anyhow::Result;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::config::loader::load_config;
+use crate::system::{lsm, manifest, sysext};
+use crate::system::manifest::{ArtifactKind, Manifest};
+use crate::system::readonly::ReadonlyGuard;
+use crate::utils::i18n::{t, tf};
+use super::apply::run_apply;
+use super::revert::{run_revert, RevertScope};
+
+/// Loaded by bash-completion's compat directory
+const BASH_COMPLETION_PATH: &str = "/etc/bash_completion.d/hifi-wifi";
+/// System user the daemon runs as after `install --hardened`
+pub const SERVICE_USER: &str = "hifi-wifi";
+/// Lets the service user change NetworkManager settings (DNS override, band steering)
+const NM_POLKIT_RULE: &str = "/etc/polkit-1/rules.d/50-hifi-wifi-nm.rules";
+/// NetworkManager actions the hardened daemon needs: reapply/reconnect and DNS, profile
+/// changes (band/BSSID locks, MAC policy), and scans for band steering and safe mode
+const NM_POLKIT_ACTIONS: &[&str] = &[
+    "org.freedesktop.NetworkManager.network-control",
+    "org.freedesktop.NetworkManager.settings.modify.system",
+    "org.freedesktop.NetworkManager.wifi.scan",
+];
+/// Privileged entry point for desktop frontends, run through pkexec
+pub const HELPER_PATH: &str = "/var/lib/hifi-wifi/hifi-wifi-helper";
+/// polkit only reads actions from /usr/share, which SteamOS updates wipe (bootstrap restores it)
+pub const POLKIT_POLICY_PATH: &str = "/usr/share/polkit-1/actions/org.hifiwifi.policy";
+/// Files the daemon itself rewrites under /var/lib/hifi-wifi
+const DAEMON_STATE_FILES: &[&str] = &["state.json", "events.jsonl", "mtu-backup.json", "mac80211-backup.json"];
+
+/// Check if we're running on SteamOS
+pub fn is_steamos() -> bool {
+    if let Ok(content) = std::fs::read_to_string("/etc/os-release") {
+        content.contains("ID=steamos")
+    } else {
+        false
+    }
+}
+
+/// Install the systemd service
+/// Per rewrite.md: Binary in /var/lib/hifi-wifi (survives SteamOS updates)
+pub fn run_install(hardened: bool, sysext: bool, on_demand: bool) -> Result<()> {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+    
+    info!("{}\n", t("install.title"));
+
+    // Create persistent directory (survives SteamOS A/B updates)
+    let var_lib = std::path::Path::new("/var/lib/hifi-wifi");
+    fs::create_dir_all(var_lib)?;
+    
+    // Copy binary to persistent location
+    let current_exe = std::env::current_exe()?;
+    let target_bin = PathBuf::from(BINARY_PATH);
+    
+    info!("Copying binary to {}", target_bin.display());
+    fs::copy(&current_exe, &target_bin)?;
+    
+    // Make executable
+    let mut perms = fs::metadata(&target_bin)?.permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(&target_bin, perms)?;
+
+    // Confinement for the active LSM; the SELinux module also labels the binary
+    // executable. If it can't be loaded, fall back to relabeling by hand.
+    if let Err(e) = lsm::install(load_config().system.lsm_enforce) {
+        warn!("Could not install LSM policy: {}", e);
+        fix_selinux_context(&target_bin);
+    }
+
+    // Create systemd service
+    if hardened {
+        setup_service_user(var_lib)?;
+    } else if hardened_installed() {
+        // Back to the root service: bootstrap would otherwise recreate the hardened unit
+        info!("Removing system user {} from a previous hardened install", SERVICE_USER);
+        let _ = Command::new("userdel").arg(SERVICE_USER).output();
+        let _ = fs::remove_file(NM_POLKIT_RULE);
+    }
+    setup_control_group(hardened);
+    let service_content = service_unit(hardened);
+
+    let service_path = std::path::Path::new(SERVICE_PATH);
+    if sysext {
+        // A unit in /etc would shadow the one in the extension
+        let _ = fs::remove_file(service_path);
+        install_sysext(&target_bin, &service_content)?;
+    } else {
+        if sysext::installed() {
+            info!("Removing system extension from a previous --sysext install");
+            sysext::remove();
+        }
+        info!("Creating systemd service: {}", service_path.display());
+
+        let mut file = File::create(service_path)?;
+        file.write_all(service_content.as_bytes())?;
+    }
+
+    // The unprivileged daemon can't write /etc, so do the persistent part now
+    if hardened {
+        info!("Applying persistent optimizations as root...");
+        if let Err(e) = run_apply(&load_config(), false) {
+            warn!("Initial apply failed: {}", e);
+        }
+    }
+
+    // On demand, the socket starts the service on first use instead of boot
+    let socket_path = std::path::Path::new(SOCKET_UNIT_PATH);
+    if on_demand {
+        info!("Creating control socket unit: {}", socket_path.display());
+        fs::write(socket_path, socket_unit())?;
+    } else if socket_path.exists() {
+        info!("Removing control socket unit from a previous --on-demand install");
+        let _ = Command::new("systemctl").args(["disable", "--now", "hifi-wifi.socket"]).output();
+        let _ = fs::remove_file(socket_path);
+    }
+
+    // Reload systemd and enable service
+    info!("Enabling service...");
+    Command::new("systemctl").args(["daemon-reload"]).output()?;
+    if on_demand {
+        let _ = Command::new("systemctl").args(["disable", "hifi-wifi.service"]).output();
+        let _ = Command::new("systemctl").args(["stop", "hifi-wifi.service"]).output();
+        Command::new("systemctl").args(["enable", "--now", "hifi-wifi.socket"]).output()?;
+    } else {
+        Command::new("systemctl").args(["enable", "hifi-wifi.service"]).output()?;
+        Command::new("systemctl").args(["start", "hifi-wifi.service"]).output()?;
+    }
+
+    // Install NetworkManager dispatcher for connection events (per roadmap-beta2.md)
+    install_nm_dispatcher()?;
+
+    // polkit actions so desktop frontends get a standard auth dialog
+    // (with --sysext the policy ships in the extension)
+    if sysext {
+        install_helper()?;
+    } else {
+        install_polkit_actions()?;
+    }
+
+    info!("\n{}", t("install.complete"));
+    info!("{}", t("install.started"));
+    info!("  Status: systemctl status hifi-wifi");
+    info!("  Logs:   journalctl -u hifi-wifi -f");
+
+    // The extension provides /usr/bin/hifi-wifi and completion, and nothing needs repairing
+    if sysext {
+        let user = sudo_user();
+        remove_user_path(&user);
+        remove_user_repair_service(&user);
+        record_manifest(Some(&user));
+        return Ok(());
+    }
+    
+    // Setup CLI access via PATH in .bashrc (persists across SteamOS updates!)
+    setup_user_path()?;
+
+    // Tab completion; /etc survives SteamOS updates, /usr/share doesn't
+    install_bash_completion(&target_bin);
+    
+    // Install user-level auto-repair service for SteamOS (survives updates in ~/.config/)
+    if is_steamos() {
+        install_user_repair_service()?;
+    }
+
+    record_manifest(Some(&sudo_user()));
+    
+    Ok(())
+}
+
+/// User who ran `sudo hifi-wifi ...` (the Deck's default user otherwise)
+fn sudo_user() -> String {
+    std::env::var("SUDO_USER").unwrap_or_else(|_| "deck".to_string())
+}
+
+fn user_home(user: &str) -> String {
+    std::process::Command::new("getent")
+        .args(["passwd", user])
+        .output()
+        .ok()
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .split(':')
+                .nth(5)
+                .map(|s| s.to_string())
+        })
+        .unwrap_or_else(|| format!("/home/{}", user))
+}
+
+/// Installed daemon binary (persistent across SteamOS updates)
+pub const BINARY_PATH: &str = "/var/lib/hifi-wifi/hifi-wifi";
+/// Daemon unit path when not shipped in the system extension
+pub const SERVICE_PATH: &str = "/etc/systemd/system/hifi-wifi.service";
+/// Control socket unit of an `--on-demand` install
+pub const SOCKET_UNIT_PATH: &str = "/etc/systemd/system/hifi-wifi.socket";
+/// Signals the daemon on reconnect
+pub const DISPATCHER_PATH: &str = "/etc/NetworkManager/dispatcher.d/99-hifi-wifi-connect";
+
+/// Everything `install` can create for `user`, the single list uninstall and the manifest use
+fn install_artifacts(user: &str) -> Vec<(PathBuf, ArtifactKind)> {
+    use ArtifactKind::*;
+    let mut artifacts: Vec<(PathBuf, ArtifactKind)> = [
+        (SERVICE_PATH, Unit),
+        (SOCKET_UNIT_PATH, Unit),
+        ("/etc/systemd/system/hifi-wifi-bootstrap.service", Unit),
+        ("/etc/systemd/system/hifi-wifi-bootstrap.timer", Unit),
+        ("/var/lib/hifi-wifi/hifi-wifi-bootstrap.service", Unit),
+        ("/var/lib/hifi-wifi/hifi-wifi-bootstrap.timer", Unit),
+        (DISPATCHER_PATH, File),
+        ("/etc/polkit-1/rules.d/49-hifi-wifi.rules", File),
+        (BINARY_PATH, File),
+        ("/var/lib/hifi-wifi/repair.sh", File),
+        (BASH_COMPLETION_PATH, File),
+        (NM_POLKIT_RULE, File),
+        (HELPER_PATH, File),
+        (POLKIT_POLICY_PATH, File),
+        (sysext::EXTENSION_DIR, Dir),
+        (lsm::SELINUX_CIL_PATH, File),
+        (lsm::APPARMOR_PROFILE_PATH, File),
+        ("inet hifi_wifi_guard", NftTable),
+    ].iter().map(|(path, kind)| (PathBuf::from(path), *kind)).collect();
+    artifacts.push((format!("{}/.config/systemd/user/hifi-wifi-repair.service", user_home(user)).into(), Unit));
+    artifacts
+}
+
+/// Re-record what is installed now (for `user` plus every user recorded before)
+pub fn record_manifest(user: Option<&str>) {
+    let mut manifest = Manifest::load();
+    if let Some(user) = user {
+        manifest.users.insert(user.to_string());
+    }
+    let users: Vec<String> = manifest.users.iter().cloned().collect();
+    for user in &users {
+        for (path, kind) in install_artifacts(user) {
+            manifest.track(path, kind);
+        }
+    }
+    if let Err(e) = manifest.save() {
+        warn!("Could not write install manifest: {}", e);
+    }
+}
+
+/// Give the binary an executable SELinux label without a policy module (Fedora-based systems)
+/// Without this, systemd cannot execute the binary due to var_lib_t context
+pub fn fix_selinux_context(target_bin: &std::path::Path) {
+    use std::process::Command;
+
+    if std::path::Path::new("/usr/sbin/restorecon").exists() {
+        info!("Setting SELinux context for binary...");
+        // First try restorecon (uses default policy)
+        let restorecon = Command::new("restorecon")
+            .arg("-v")
+            .arg(target_bin)
+            .output();
+        
+        // If restorecon doesn't set bin_t (var_lib default is var_lib_t), use chcon
+        if restorecon.is_ok() {
+            // Verify context - if still var_lib_t, force bin_t
+            let context_check = Command::new("ls")
+                .args(["-Z", target_bin.to_str().unwrap()])
+                .output();
+            
+            if let Ok(output) = context_check {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                if stdout.contains("var_lib_t") {
+                    // Force bin_t context so systemd can execute it
+                    let _ = Command::new("chcon")
+                        .args(["-t", "bin_t", target_bin.to_str().unwrap()])
+                        .output();
+                    info!("Applied bin_t SELinux context");
+                }
+            }
+        }
+    } else if std::path::Path::new("/usr/bin/chcon").exists() {
+        // Fallback: direct chcon if restorecon not available
+        info!("Setting SELinux context (chcon fallback)...");
+        let _ = Command::new("chcon")
+            .args(["-t", "bin_t", target_bin.to_str().unwrap()])
+            .output();
+    }
+}
+
+/// systemd unit for the daemon; hardened runs it as `SERVICE_USER` with network capabilities only
+pub fn service_unit(hardened: bool) -> String {
+    let security = if hardened {
+        format!(r#"# Hardened: unprivileged user, network capabilities only
+# Persistent tuning (/etc/modprobe.d, /etc/sysctl.d, /etc/iwd) is applied by `install`/`apply` as root
+User={user}
+Group={user}
+RuntimeDirectory=hifi-wifi
+RuntimeDirectoryPreserve=yes
+ProtectSystem=strict
+ReadWritePaths=/var/lib/hifi-wifi
+ProtectHome=true
+NoNewPrivileges=true
+CapabilityBoundingSet=CAP_NET_ADMIN CAP_NET_RAW
+AmbientCapabilities=CAP_NET_ADMIN CAP_NET_RAW"#, user = SERVICE_USER)
+    } else {
+        r#"# Security hardening
+# Note: ProtectSystem cannot be used - we need to write to /etc/modprobe.d, /etc/sysctl.d, /etc/iwd
+ProtectHome=true
+NoNewPrivileges=false
+CapabilityBoundingSet=CAP_NET_ADMIN CAP_NET_RAW CAP_SYS_ADMIN
+AmbientCapabilities=CAP_NET_ADMIN CAP_NET_RAW CAP_SYS_ADMIN"#.to_string()
+    };
+    format!(r#"[Unit]
+Description=hifi-wifi Network Optimizer
+Documentation=https://github.com/doughty247/hifi-wifi
+After=network-online.target NetworkManager.service
+Wants=network-online.target
+
+[Service]
+Type=simple
+ExecStart=/var/lib/hifi-wifi/hifi-wifi monitor
+Restart=on-failure
+RestartSec=5
+
+{}
+
+# Resource limits
+MemoryMax=64M
+CPUQuota=10%
+
+[Install]
+WantedBy=multi-user.target
+"#, security)
+}
+
+/// systemd listens on the control socket and starts hifi-wifi.service on the first connection
+fn socket_unit() -> String {
+    format!(r#"[Unit]
+Description=hifi-wifi control socket (starts the optimizer on demand)
+Documentation=https://github.com/doughty247/hifi-wifi
+
+[Socket]
+ListenStream={}
+SocketMode=0660
+SocketGroup={}
+RemoveOnStop=yes
+
+[Install]
+WantedBy=sockets.target
+"#, crate::utils::control::SOCKET_PATH, crate::utils::control::CONTROL_GROUP)
+}
+
+/// Whether `install --on-demand` set up socket activation
+pub fn on_demand_installed() -> bool {
+    std::path::Path::new(SOCKET_UNIT_PATH).exists()
+}
+
+/// Whether the service user from `install --hardened` exists
+pub fn hardened_installed() -> bool {
+    std::process::Command::new("id")
+        .args(["-u", SERVICE_USER])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Create the group allowed on the control socket and put the installing user in it
+/// (and the service user, which has to hand the socket to the group when it binds it)
+fn setup_control_group(hardened: bool) {
+    use crate::utils::control::{control_gid, CONTROL_GROUP};
+    use std::process::Command;
+
+    if control_gid().is_none() {
+        info!("Creating group {} for the control socket", CONTROL_GROUP);
+        let _ = Command::new("groupadd").args(["--system", CONTROL_GROUP]).output();
+    }
+    let mut members = vec![sudo_user()];
+    if hardened {
+        members.push(SERVICE_USER.to_string());
+    }
+    for user in members {
+        let ok = Command::new("usermod").args(["-aG", CONTROL_GROUP, &user]).output()
+            .map(|o| o.status.success()).unwrap_or(false);
+        if !ok {
+            warn!("Could not add {} to {}; it won't reach the control socket", user, CONTROL_GROUP);
+        }
+    }
+}
+
+/// Create the service user and give it the state files it rewrites
+///
+/// /var/lib/hifi-wifi becomes group-writable with the sticky bit, so the daemon can
+/// create its own files there but can't replace the root-owned binary.
+fn setup_service_user(var_lib: &std::path::Path) -> Result<()> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+
+    if !hardened_installed() {
+        info!("Creating system user {}", SERVICE_USER);
+        let output = Command::new("useradd")
+            .args(["--system", "--no-create-home", "--home-dir", "/var/lib/hifi-wifi",
+                   "--shell", "/usr/sbin/nologin", SERVICE_USER])
+            .output()?;
+        if !output.status.success() {
+            anyhow::bail!("useradd failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+    }
+    let ids = |flag: &str| -> Option<u32> {
+        let out = Command::new("id").args([flag, SERVICE_USER]).output().ok()?;
+        String::from_utf8_lossy(&out.stdout).trim().parse().ok()
+    };
+    let (uid, gid) = (ids("-u"), ids("-g"));
+
+    std::os::unix::fs::chown(var_lib, Some(0), gid)?;
+    fs::set_permissions(var_lib, fs::Permissions::from_mode(0o1775))?;
+    for name in DAEMON_STATE_FILES {
+        let path = var_lib.join(name);
+        if !path.exists() {
+            fs::write(&path, "")?;
+        }
+        std::os::unix::fs::chown(&path, uid, gid)?;
+    }
+
+    if std::path::Path::new("/etc/polkit-1").exists() {
+        let actions = NM_POLKIT_ACTIONS.iter().map(|a| format!("\"{}\"", a)).collect::<Vec<_>>().join(", ");
+        let rule = format!(r#"// Allow the hifi-wifi daemon to reapply and reconnect Wi-Fi devices
+polkit.addRule(function(action, subject) {{
+    if ([{}].indexOf(action.id) >= 0 &&
+        subject.user == "{}") {{
+        return polkit.Result.YES;
+    }}
+}});
+"#, actions, SERVICE_USER);
+        let _ = fs::create_dir_all("/etc/polkit-1/rules.d");
+        match fs::write(NM_POLKIT_RULE, rule) {
+            Ok(()) => info!("Created polkit rule for NetworkManager access"),
+            Err(e) => warn!("Could not write polkit rule (DNS override and band steering will fail): {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// polkit action definitions, one per helper verb
+///
+/// pkexec picks the action whose `exec.path` and `exec.argv1` match the command line.
+pub fn polkit_policy() -> String {
+    let actions = [
+        ("apply", "Apply Wi-Fi optimizations", "Authentication is required to apply Wi-Fi optimizations", "auth_admin_keep"),
+        ("toggle", "Turn Wi-Fi optimizations on or off", "Authentication is required to turn Wi-Fi optimizations on or off", "auth_admin_keep"),
+        ("bootstrap", "Repair the hifi-wifi service", "Authentication is required to repair the hifi-wifi service", "auth_admin"),
+    ];
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE policyconfig PUBLIC "-//freedesktop//DTD PolicyKit Policy Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/PolicyKit/1/policyconfig.dtd">
+<policyconfig>
+  <vendor>hifi-wifi</vendor>
+  <vendor_url>https://github.com/doughty247/hifi-wifi</vendor_url>
+"#);
+    for (verb, description, message, active) in actions {
+        xml.push_str(&format!(r#"
+  <action id="org.hifiwifi.{verb}">
+    <description>{description}</description>
+    <message>{message}</message>
+    <icon_name>network-wireless</icon_name>
+    <defaults>
+      <allow_any>auth_admin</allow_any>
+      <allow_inactive>auth_admin</allow_inactive>
+      <allow_active>{active}</allow_active>
+    </defaults>
+    <annotate key="org.freedesktop.policykit.exec.path">{helper}</annotate>
+    <annotate key="org.freedesktop.policykit.exec.argv1">{verb}</annotate>
+  </action>
+"#, helper = HELPER_PATH));
+    }
+    xml.push_str("</policyconfig>\n");
+    xml
+}
+
+/// Build and merge the system extension (binary, unit, polkit actions, completion)
+fn install_sysext(binary: &std::path::Path, unit: &str) -> Result<()> {
+    use sysext::ExtensionFile;
+
+    info!("Building system extension: {}", sysext::EXTENSION_DIR);
+    let mut files = vec![
+        ExtensionFile { path: "usr/bin/hifi-wifi", content: std::fs::read(binary)?, mode: 0o755 },
+        ExtensionFile { path: "usr/lib/systemd/system/hifi-wifi.service", content: unit.as_bytes().to_vec(), mode: 0o644 },
+        ExtensionFile { path: "usr/share/polkit-1/actions/org.hifiwifi.policy", content: polkit_policy().into_bytes(), mode: 0o644 },
+    ];
+    match bash_completion(binary) {
+        Some(script) => files.push(ExtensionFile { path: "usr/share/bash-completion/completions/hifi-wifi", content: script, mode: 0o644 }),
+        None => warn!("Could not generate bash completion"),
+    }
+    sysext::build(std::path::Path::new(sysext::EXTENSION_DIR), &files)?;
+    sysext::refresh()
+}
+
+/// Write the pkexec helper the polkit actions point at
+fn install_helper() -> Result<()> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let helper = r#"#!/bin/bash
+# hifi-wifi-helper: privileged entry point for desktop frontends
+# Run it through pkexec; each verb is its own polkit action (org.hifiwifi.<verb>)
+
+BINARY="/var/lib/hifi-wifi/hifi-wifi"
+
+case "$1" in
+    apply)
+        # The running daemon re-applies everything when it starts
+        if systemctl is-active --quiet hifi-wifi.service; then
+            exec systemctl restart hifi-wifi.service
+        fi
+        exec "$BINARY" apply ;;
+    toggle)
+        if systemctl is-active --quiet hifi-wifi.service; then
+            exec "$BINARY" off
+        else
+            exec "$BINARY" on
+        fi ;;
+    bootstrap)
+        exec "$BINARY" bootstrap ;;
+    *)
+        echo "Usage: pkexec $0 apply|toggle|bootstrap" >&2
+        exit 64 ;;
+esac
+"#;
+    fs::write(HELPER_PATH, helper)?;
+    fs::set_permissions(HELPER_PATH, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+/// Install the helper and its polkit actions (`pkexec hifi-wifi-helper apply|toggle|bootstrap`)
+pub fn install_polkit_actions() -> Result<()> {
+    use std::fs;
+
+    install_helper()?;
+
+    let policy_dir = std::path::Path::new(POLKIT_POLICY_PATH).parent().unwrap_or(std::path::Path::new("/"));
+    if !policy_dir.exists() {
+        info!("polkit not installed - skipping action definitions");
+        return Ok(());
+    }
+    // /usr is read-only on SteamOS
+    let written = {
+        let _writable = ReadonlyGuard::acquire();
+        fs::write(POLKIT_POLICY_PATH, polkit_policy())
+    };
+    match written {
+        Ok(()) => info!("Installed polkit actions: {}", POLKIT_POLICY_PATH),
+        Err(e) => warn!("Could not install polkit actions (frontends will fall back to pkexec's generic prompt): {}", e),
+    }
+    Ok(())
+}
+
+/// Bash completion script generated by the installed binary
+fn bash_completion(binary: &std::path::Path) -> Option<Vec<u8>> {
+    std::process::Command::new(binary)
+        .args(["completions", "bash"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success() && !o.stdout.is_empty())
+        .map(|o| o.stdout)
+}
+
+/// Write the bash completion script generated by the installed binary
+fn install_bash_completion(binary: &std::path::Path) {
+    let Some(script) = bash_completion(binary) else {
+        warn!("Could not generate bash completion");
+        return;
+    };
+    let result = std::fs::create_dir_all("/etc/bash_completion.d")
+        .and_then(|_| std::fs::write(BASH_COMPLETION_PATH, &script));
+    match result {
+        Ok(()) => info!("Installed bash completion: {}", BASH_COMPLETION_PATH),
+        Err(e) => warn!("Could not install bash completion: {}", e),
+    }
+}
+
+/// Install NetworkManager dispatcher for connection events
+/// Per roadmap-beta2.md: This signals the daemon when WiFi reconnects
+pub fn install_nm_dispatcher() -> Result<()> {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+    
+    let dispatcher_path = std::path::Path::new(DISPATCHER_PATH);
+    let dispatcher_dir = dispatcher_path.parent().unwrap_or(std::path::Path::new("/"));
+    
+    info!("Installing NetworkManager dispatcher: {}", dispatcher_path.display());
+    
+    // Create directory if needed (shouldn't be necessary, but be safe)
+    if !dispatcher_dir.exists() {
+        fs::create_dir_all(dispatcher_dir)?;
+    }
+    
+    // The dispatcher script - signals the daemon on connection up
+    let dispatcher_content = r#"#!/bin/bash
+# hifi-wifi NetworkManager dispatcher
+# Signals the daemon when WiFi connects so it can apply fresh optimizations
+# Per roadmap-beta2.md: This fixes the "reconnection problem" (Issue #10)
+
+INTERFACE="$1"
+ACTION="$2"
+
+# Only trigger on connection up for wireless interfaces
+[[ "$ACTION" != "up" ]] && exit 0
+
+# Check if this is a wireless interface
+if [[ ! -d "/sys/class/net/$INTERFACE/wireless" ]]; then
+    exit 0
+fi
+
+# Ensure run directory exists
+mkdir -p /run/hifi-wifi
+
+# Signal the daemon by touching the event file
+# The daemon watches this with inotify and triggers re-optimization
+touch /run/hifi-wifi/connection-changed
+
+logger -t hifi-wifi "Connection event: $INTERFACE $ACTION - signaled daemon"
+"#;
+
+    let mut file = File::create(dispatcher_path)?;
+    file.write_all(dispatcher_content.as_bytes())?;
+    
+    // Must be executable
+    let mut perms = fs::metadata(dispatcher_path)?.permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(dispatcher_path, perms)?;
+    
+    // Create the run directory and event file
+    let run_dir = std::path::Path::new("/run/hifi-wifi");
+    if !run_dir.exists() {
+        fs::create_dir_all(run_dir)?;
+    }
+    fs::write("/run/hifi-wifi/connection-changed", "")?;
+    
+    info!("NetworkManager dispatcher installed");
+    Ok(())
+}
+
+/// Add /var/lib/hifi-wifi to user's PATH via .bashrc
+/// This is the PERSISTENT way to provide CLI access on immutable distros like SteamOS
+/// ~/.bashrc lives in /home which is NEVER touched by SteamOS updates
+fn setup_user_path() -> Result<()> {
+    use std::fs::{self, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Command;
+    
+    let sudo_user = std::env::var("SUDO_USER").unwrap_or_else(|_| "deck".to_string());
+    let home = Command::new("getent")
+        .args(["passwd", &sudo_user])
+        .output()
+        .ok()
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .split(':')
+                .nth(5)
+                .map(|s| s.to_string())
+        })
+        .unwrap_or_else(|| format!("/home/{}", sudo_user));
+    
+    let bashrc_path = format!("{}/.bashrc", home);
+    let path_line = r#"export PATH="$PATH:/var/lib/hifi-wifi""#;
+    
+    info!("Setting up CLI access via PATH in {}", bashrc_path);
+    
+    // Check if already present
+    if let Ok(file) = fs::File::open(&bashrc_path) {
+        let reader = BufReader::new(file);
+        for line in reader.lines().map_while(Result::ok) {
+            if line.contains("/var/lib/hifi-wifi") {
+                info!("PATH already configured in .bashrc");
+                return Ok(());
+            }
+        }
+    }
+    
+    // Append to bashrc
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&bashrc_path)?;
+    
+    writeln!(file)?;
+    writeln!(file, "# hifi-wifi CLI access (survives SteamOS updates)")?;
+    writeln!(file, "{}", path_line)?;
+    
+    // Fix ownership
+    let uid_output = Command::new("id").args(["-u", &sudo_user]).output()?;
+    let gid_output = Command::new("id").args(["-g", &sudo_user]).output()?;
+    let uid: u32 = String::from_utf8_lossy(&uid_output.stdout).trim().parse().unwrap_or(1000);
+    let gid: u32 = String::from_utf8_lossy(&gid_output.stdout).trim().parse().unwrap_or(1000);
+    
+    let _ = std::os::unix::fs::chown(&bashrc_path, Some(uid), Some(gid));
+    
+    info!("Added /var/lib/hifi-wifi to PATH in .bashrc");
+    info!("Run 'source ~/.bashrc' or open a new terminal to use 'hifi-wifi' command");
+    
+    Ok(())
+}
+
+/// Install a user-level systemd service that auto-repairs hifi-wifi after SteamOS updates
+/// Lives in ~/.config/systemd/user/ which PERSISTS across SteamOS updates!
+fn install_user_repair_service() -> Result<()> {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process::Command;
+    use std::os::unix::fs::PermissionsExt;
+    
+    // Get the real user info
+    let sudo_user = std::env::var("SUDO_USER").unwrap_or_else(|_| "deck".to_string());
+    let home = std::process::Command::new("getent")
+        .args(["passwd", &sudo_user])
+        .output()
+        .ok()
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .split(':')
+                .nth(5)
+                .map(|s| s.to_string())
+        })
+        .unwrap_or_else(|| format!("/home/{}", sudo_user));
+    
+    let user_systemd_dir = format!("{}/.config/systemd/user", home);
+    let repair_script_path = "/var/lib/hifi-wifi/repair.sh";
+    
+    info!("Installing user repair service in {}", user_systemd_dir);
+    
+    // Create user systemd directory
+    fs::create_dir_all(&user_systemd_dir)?;
+    
+    // Create a repair script that handles the sudo/polkit interaction
+    // This script ensures hifi-wifi is running and optimizations are applied on EVERY boot
+    // It handles both SteamOS update recovery AND normal boot optimization
+    let repair_script = r#"#!/bin/bash
+# hifi-wifi auto-repair script - runs at user login
+# Ensures optimizations are applied on every boot (CAKE, power save, etc.)
+# Also handles SteamOS update recovery (recreates service file if wiped)
+
+BINARY="/var/lib/hifi-wifi/hifi-wifi"
+SERVICE="/etc/systemd/system/hifi-wifi.service"
+
+# Exit if binary doesn't exist (not installed)
+if [[ ! -x "$BINARY" ]]; then
+    exit 0
+fi
+
+# Check if service is already running with optimizations applied
+# If CAKE is active, we don't need to do anything
+if tc qdisc show 2>/dev/null | grep -q "cake"; then
+    exit 0
+fi
+
+# Service not running or optimizations not applied - run bootstrap
+# bootstrap will: recreate service file if missing, start service, apply optimizations
+exec pkexec /var/lib/hifi-wifi/hifi-wifi-helper bootstrap
+"#;
+
+    // Write repair script to persistent location
+    let mut script_file = File::create(repair_script_path)?;
+    script_file.write_all(repair_script.as_bytes())?;
+    let mut perms = fs::metadata(repair_script_path)?.permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(repair_script_path, perms)?;
+    
+    // Create polkit rule to allow passwordless bootstrap (better UX)
+    let polkit_dir = "/etc/polkit-1/rules.d";
+    if std::path::Path::new("/etc/polkit-1").exists() {
+        let _ = fs::create_dir_all(polkit_dir);
+        // The generic exec match covers the window after a SteamOS update wiped our
+        // action definitions and before bootstrap has put them back
+        let polkit_rule = format!(r#"// Allow hifi-wifi bootstrap without password for {user}
+polkit.addRule(function(action, subject) {{
+    if (subject.user != "{user}") {{
+        return polkit.Result.NOT_HANDLED;
+    }}
+    if (action.id == "org.hifiwifi.bootstrap" ||
+        (action.id == "org.freedesktop.policykit.exec" &&
+         action.lookup("command_line") == "{helper} bootstrap")) {{
+        return polkit.Result.YES;
+    }}
+}});
+"#, user = sudo_user, helper = HELPER_PATH);
+        
+        let polkit_path = format!("{}/49-hifi-wifi.rules", polkit_dir);
+        if let Ok(mut f) = File::create(&polkit_path) {
+            let _ = f.write_all(polkit_rule.as_bytes());
+            info!("Created polkit rule for passwordless repair");
+        }
+    }
+    
+    // Create user systemd service
+    // Note: With lingering enabled, this runs when user@.service starts at boot,
+    // which happens early - even before graphical session in SteamOS Game Mode
+    let service_content = format!(r#"[Unit]
+Description=hifi-wifi Auto-Repair (restores after SteamOS updates)
+After=network-online.target
+
+[Service]
+Type=oneshot
+ExecStart={}
+RemainAfterExit=yes
+
+[Install]
+WantedBy=default.target
+"#, repair_script_path);
+
+    let service_path = format!("{}/hifi-wifi-repair.service", user_systemd_dir);
+    let mut service_file = File::create(&service_path)?;
+    service_file.write_all(service_content.as_bytes())?;
+    
+    // Fix ownership of user config directory
+    let uid_output = Command::new("id").args(["-u", &sudo_user]).output()?;
+    let gid_output = Command::new("id").args(["-g", &sudo_user]).output()?;
+    let uid: u32 = String::from_utf8_lossy(&uid_output.stdout).trim().parse().unwrap_or(1000);
+    let gid: u32 = String::from_utf8_lossy(&gid_output.stdout).trim().parse().unwrap_or(1000);
+    
+    // Recursively chown the .config/systemd directory
+    let _ = Command::new("chown")
+        .args(["-R", &format!("{}:{}", uid, gid), &format!("{}/.config/systemd", home)])
+        .output();
+    
+    // Enable lingering for the user - ensures user systemd instance starts at boot
+    // This is critical for SteamOS Game Mode where gamescope session might not
+    // trigger graphical-session.target the same way as KDE Plasma desktop
+    let _ = Command::new("loginctl")
+        .args(["enable-linger", &sudo_user])
+        .output();
+    info!("Enabled user lingering for early boot service start");
+    
+    // Enable the user service (must run as the user)
+    let _ = Command::new("sudo")
+        .args(["-u", &sudo_user, "systemctl", "--user", "daemon-reload"])
+        .output();
+    let _ = Command::new("sudo")
+        .args(["-u", &sudo_user, "systemctl", "--user", "enable", "hifi-wifi-repair.service"])
+        .output();
+    
+    info!("User repair service installed - will auto-repair at boot after SteamOS updates");
+    
+    Ok(())
+}
+
+/// Uninstall the systemd service
+pub fn run_uninstall(purge: bool) -> Result<()> {
+    use std::process::Command;
+    
+    info!("{}\n", t("uninstall.title"));
+
+    // Read before anything under /var/lib goes away
+    let manifest = Manifest::load();
+    let user = sudo_user();
+
+    // Stop and disable services
+    info!("Stopping services...");
+    let _ = Command::new("systemctl").args(["disable", "--now", "hifi-wifi.socket"]).output();
+    let _ = Command::new("systemctl").args(["stop", "hifi-wifi.service"]).output();
+    let _ = Command::new("systemctl").args(["stop", "hifi-wifi-bootstrap.timer"]).output();
+    let _ = Command::new("systemctl").args(["disable", "hifi-wifi.service"]).output();
+    let _ = Command::new("systemctl").args(["disable", "hifi-wifi-bootstrap.timer"]).output();
+
+    // Remove user repair service (disabled first, the file goes with the rest)
+    remove_user_repair_service(&user);
+
+    // Remove everything install created: the recorded artifacts, plus the full list
+    // for installs that predate the manifest
+    sysext::remove();
+    lsm::remove();
+    let mut artifacts: BTreeMap<PathBuf, ArtifactKind> = manifest.artifacts.iter()
+        .map(|(path, a)| (path.clone(), a.kind))
+        .collect();
+    artifacts.extend(install_artifacts(&user));
+    // The polkit actions live under /usr
+    let writable = ReadonlyGuard::acquire();
+    for path in manifest::remove(artifacts.iter().map(|(p, k)| (p.as_path(), *k))) {
+        warn!("{}", tf("uninstall.leftover", &[&path.display()]));
+    }
+    drop(writable);
+
+    // Reload systemd
+    Command::new("systemctl").args(["daemon-reload"]).output()?;
+
+    if hardened_installed() {
+        info!("Removing system user {}...", SERVICE_USER);
+        let _ = Command::new("userdel").arg(SERVICE_USER).output();
+    }
+    let _ = Command::new("groupdel").arg(crate::utils::control::CONTROL_GROUP).output();
+
+    // Remove PATH from .bashrc
+    remove_user_path(&user);
+
+    // Revert optimizations
+    run_revert(&RevertScope::all())?;
+
+    if purge {
+        purge_leftovers(&manifest, &user);
+    }
+
+    info!("\n{}", t("uninstall.complete"));
+    Ok(())
+}
+
+/// Remove leftover /etc files from `apply`, other users' entries and our data directories
+fn purge_leftovers(manifest: &Manifest, user: &str) {
+    for other in manifest.users.iter().filter(|u| *u != user) {
+        remove_user_path(other);
+        remove_user_repair_service(other);
+    }
+
+    let mut paths: BTreeMap<PathBuf, ArtifactKind> = manifest::runtime_artifacts().into_iter().collect();
+    paths.extend(manifest::DATA_DIRS.iter().map(|d| (PathBuf::from(d), ArtifactKind::Dir)));
+    let left = manifest::remove(paths.iter().map(|(p, k)| (p.as_path(), *k)));
+
+    if left.is_empty() {
+        info!("{}", t("uninstall.purged"));
+    }
+    for path in left {
+        warn!("{}", tf("uninstall.leftover", &[&path.display()]));
+    }
+}
+
+/// Remove /var/lib/hifi-wifi from user's PATH in .bashrc
+fn remove_user_path(sudo_user: &str) {
+    use std::io::{BufRead, BufReader, Write};
+    
+    let home = user_home(sudo_user);
+    
+    let bashrc_path = format!("{}/.bashrc", home);
+    
+    if let Ok(file) = std::fs::File::open(&bashrc_path) {
+        let reader = BufReader::new(file);
+        let lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
+        
+        // Filter out hifi-wifi PATH lines
+        let filtered: Vec<&String> = lines.iter()
+            .filter(|line| !line.contains("/var/lib/hifi-wifi") && !line.contains("# hifi-wifi CLI access"))
+            .collect();
+        
+        if filtered.len() != lines.len() {
+            // Write back filtered content
+            if let Ok(mut file) = std::fs::File::create(&bashrc_path) {
+                for line in filtered {
+                    let _ = writeln!(file, "{}", line);
+                }
+                info!("Removed PATH entry from .bashrc");
+                
+                // Fix ownership
+                let uid_output = std::process::Command::new("id").args(["-u", sudo_user]).output();
+                let gid_output = std::process::Command::new("id").args(["-g", sudo_user]).output();
+                if let (Ok(uid_out), Ok(gid_out)) = (uid_output, gid_output) {
+                    let uid: u32 = String::from_utf8_lossy(&uid_out.stdout).trim().parse().unwrap_or(1000);
+                    let gid: u32 = String::from_utf8_lossy(&gid_out.stdout).trim().parse().unwrap_or(1000);
+                    let _ = std::os::unix::fs::chown(&bashrc_path, Some(uid), Some(gid));
+                }
+            }
+        }
+    }
+}
+
+/// Remove the user repair service
+fn remove_user_repair_service(sudo_user: &str) {
+    use std::process::Command;
+    
+    let home = user_home(sudo_user);
+    
+    // Disable and remove user service
+    let _ = Command::new("sudo")
+        .args(["-u", sudo_user, "systemctl", "--user", "disable", "hifi-wifi-repair.service"])
+        .output();
+    let _ = Command::new("sudo")
+        .args(["-u", sudo_user, "systemctl", "--user", "stop", "hifi-wifi-repair.service"])
+        .output();
+    
+    let service_path = format!("{}/.config/systemd/user/hifi-wifi-repair.service", home);
+    if std::path::Path::new(&service_path).exists() {
+        let _ = std::fs::remove_file(&service_path);
+        info!("Removed user repair service");
+    }
+    
+    // Remove repair script
+    let _ = std::fs::remove_file("/var/lib/hifi-wifi/repair.sh");
+    
+    // Remove polkit rule
+    let _ = std::fs::remove_file("/etc/polkit-1/rules.d/49-hifi-wifi.rules");
+    
+    // Disable lingering (only if no other user services need it)
+    // Note: We disable this cautiously - user may have other services that need it
+    let _ = Command::new("loginctl")
+        .args(["disable-linger", sudo_user])
+        .output();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polkit_policy() {
+        let policy = polkit_policy();
+        for verb in ["apply", "toggle", "bootstrap"] {
+            assert!(policy.contains(&format!("<action id=\"org.hifiwifi.{}\">", verb)));
+            assert!(policy.contains(&format!("exec.argv1\">{}</annotate>", verb)));
+        }
+        assert!(policy.trim_end().ends_with("</policyconfig>"));
+    }
+
+    #[test]
+    fn test_hardened_service_unit() {
+        let hardened = service_unit(true);
+        assert!(hardened.contains("User=hifi-wifi"));
+        assert!(hardened.contains("AmbientCapabilities=CAP_NET_ADMIN CAP_NET_RAW"));
+        assert!(!service_unit(false).contains("User="));
+    }
+}
@@ -0,0 +1,22 @@
+//! `lock` and `unlock`: pin the Wi-Fi connection to one AP or band
+
+use anyhow::Result;
+
+use crate::network::lock::{self, LockBand};
+
+/// `lock [--bssid <mac>] [--band 2.4ghz|5ghz] [--interface <ifc>]`
+pub fn run_lock(bssid: Option<&str>, band: Option<LockBand>, interface: Option<&str>) -> Result<()> {
+    let devices = lock::lock(interface, bssid, band)?;
+    println!("Locked {}; band steering is off there until `sudo hifi-wifi unlock`", devices.join(", "));
+    Ok(())
+}
+
+pub fn run_unlock(interface: Option<&str>) -> Result<()> {
+    let devices = lock::unlock(interface)?;
+    if devices.is_empty() {
+        println!("Nothing was locked");
+    } else {
+        println!("Unlocked {}", devices.join(", "));
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! `logs`: the service units' journal, filtered down to what matters for a bug report
+//!
+//! Reads `journalctl -o json` for the daemon, the bootstrap unit and the user repair
+//! unit. By default only warnings, errors and Governor decisions (log lines carrying an
+//! `event`) of the daemon are kept, plus everything the short-lived units and systemd
+//! itself say about them; `--errors` narrows that to problems, `--all` turns it off.
+
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::network::usage;
+use crate::utils;
+
+/// (unit, label, user unit)
+const UNITS: &[(&str, &str, bool)] = &[
+    ("hifi-wifi.service", "main", false),
+    ("hifi-wifi-bootstrap.service", "bootstrap", false),
+    ("hifi-wifi-repair.service", "repair", true),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Warnings, errors and decisions
+    Decisions,
+    Errors,
+    All,
+}
+
+#[derive(Debug, PartialEq)]
+enum Class {
+    Error,
+    Decision,
+    Noise,
+}
+
+/// One parsed journal entry
+#[derive(Debug, PartialEq)]
+struct Entry {
+    ts: u64,
+    unit: &'static str,
+    /// Sent by systemd about the unit rather than by hifi-wifi
+    manager: bool,
+    priority: u8,
+    message: String,
+}
+
+/// `_SYSTEMD_UNIT=a + UNIT=a + ...`: the units' own output and systemd's messages about them
+fn matches() -> Vec<String> {
+    let mut args = Vec::new();
+    for (unit, _, user) in UNITS {
+        let (own, about) = if *user { ("_SYSTEMD_USER_UNIT", "USER_UNIT") } else { ("_SYSTEMD_UNIT", "UNIT") };
+        for field in [own, about] {
+            if !args.is_empty() {
+                args.push("+".to_string());
+            }
+            args.push(format!("{}={}", field, unit));
+        }
+    }
+    args
+}
+
+/// MESSAGE is a string, or a byte array when it isn't clean UTF-8
+fn field_str(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes.iter().filter_map(|b| b.as_u64().map(|b| b as u8)).collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    }
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let json: serde_json::Value = serde_json::from_str(line).ok()?;
+    let get = |key: &str| json.get(key).and_then(field_str);
+    let label = |name: Option<String>| {
+        let name = name?;
+        UNITS.iter().find(|(u, _, _)| *u == name).map(|(_, label, _)| *label)
+    };
+    let own = label(get("_SYSTEMD_UNIT").or_else(|| get("_SYSTEMD_USER_UNIT")));
+    let about = label(get("UNIT").or_else(|| get("USER_UNIT")));
+    Some(Entry {
+        ts: get("__REALTIME_TIMESTAMP")?.parse::<u64>().ok()? / 1_000_000,
+        unit: own.or(about)?,
+        manager: own.is_none(),
+        priority: get("PRIORITY").and_then(|p| p.parse().ok()).unwrap_or(6),
+        message: get("MESSAGE")?,
+    })
+}
+
+/// (level, event) of one of our log lines, text (`[ts LEVEL target] msg event=x`) or JSON
+fn level_and_event(message: &str) -> (Option<String>, Option<String>) {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(message) {
+        let get = |key: &str| json.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        return (get("level").map(|l| l.to_uppercase()), get("event"));
+    }
+    let level = message.strip_prefix('[')
+        .and_then(|rest| rest.split(']').next())
+        .and_then(|header| header.split_whitespace()
+            .find(|w| matches!(*w, "ERROR" | "WARN" | "INFO" | "DEBUG" | "TRACE")))
+        .map(str::to_string);
+    let event = message.split_whitespace()
+        .find_map(|w| w.strip_prefix("event="))
+        .map(|e| e.trim_matches('"').to_string());
+    (level, event)
+}
+
+fn classify(entry: &Entry) -> Class {
+    let (level, event) = level_and_event(&entry.message);
+    match level.as_deref() {
+        Some("ERROR" | "WARN") => Class::Error,
+        // systemd's own lines and plain output (bootstrap/repair scripts) go by priority
+        None if entry.priority <= 4 => Class::Error,
+        None => Class::Decision,
+        _ if event.is_some() => Class::Decision,
+        // One-shot units are short enough to show whole
+        Some("INFO") if entry.unit != "main" => Class::Decision,
+        _ => Class::Noise,
+    }
+}
+
+fn keep(entry: &Entry, filter: Filter) -> bool {
+    match (filter, classify(entry)) {
+        (Filter::All, _) => true,
+        (Filter::Errors, Class::Error) => true,
+        (Filter::Errors, _) => entry.manager && entry.message.contains("Failed"),
+        (Filter::Decisions, class) => class != Class::Noise,
+    }
+}
+
+fn print_entry(entry: &Entry, offset: i64) {
+    let c = utils::style::palette();
+    let color = match classify(entry) {
+        Class::Error => c.red,
+        _ if entry.manager => c.dim,
+        _ => "",
+    };
+    println!("{}{} {:<9}{} {}{}{}", c.dim, usage::date_time_secs(entry.ts, offset), entry.unit, c.reset,
+             color, entry.message.trim_end(), if color.is_empty() { "" } else { c.reset });
+}
+
+/// `logs [--follow] [--errors | --all] [--since 1h]`
+pub fn run_logs(follow: bool, filter: Filter, since: Duration) -> Result<()> {
+    let mut args = vec!["-o".to_string(), "json".to_string(), "--no-pager".to_string(),
+                        "--since".to_string(), format!("-{}s", since.as_secs())];
+    if follow {
+        args.push("--follow".to_string());
+    }
+    args.extend(matches());
+    let mut child = Command::new("journalctl")
+        .args(&args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run journalctl")?;
+    let stdout = child.stdout.take().context("journalctl has no output")?;
+
+    let offset = usage::local_offset();
+    let (mut shown, mut hidden) = (0, 0);
+    for line in BufReader::new(stdout).lines() {
+        let Some(entry) = parse_entry(&line?) else { continue };
+        if keep(&entry, filter) {
+            print_entry(&entry, offset);
+            shown += 1;
+        } else {
+            hidden += 1;
+        }
+    }
+    child.wait()?;
+
+    if shown == 0 && hidden == 0 && !utils::privilege::is_root() {
+        println!("No log entries visible; system logs may need `sudo hifi-wifi logs` (or the systemd-journal group)");
+    } else if hidden > 0 {
+        println!("({} routine lines hidden; --all shows everything)", hidden);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_classify() {
+        let line = r#"{"__REALTIME_TIMESTAMP":"1792189800123456","_SYSTEMD_UNIT":"hifi-wifi.service","PRIORITY":"6",
+            "MESSAGE":"[2026-10-16T13:11:54Z INFO  hifi_wifi::network::governor] Game mode ACTIVATED: 900 PPS on wlan0 event=game_mode interface=wlan0"}"#;
+        let entry = parse_entry(&line.replace('\n', "")).unwrap();
+        assert_eq!((entry.ts, entry.unit, entry.manager), (1_792_189_800, "main", false));
+        assert_eq!(classify(&entry), Class::Decision);
+
+        let noise = Entry { message: "[2026-10-16T13:11:54Z INFO  hifi_wifi::network::nm] Found 3 APs".to_string(), ..entry };
+        assert_eq!(classify(&noise), Class::Noise);
+        assert!(!keep(&noise, Filter::Decisions));
+        assert!(keep(&noise, Filter::All));
+
+        let json = r#"{"ts":1.0,"level":"WARN","module":"x","msg":"Beacon loss","event":"beacon_loss"}"#;
+        assert_eq!(level_and_event(json), (Some("WARN".to_string()), Some("beacon_loss".to_string())));
+
+        let failed = r#"{"__REALTIME_TIMESTAMP":"1","_SYSTEMD_UNIT":"init.scope","UNIT":"hifi-wifi.service","PRIORITY":"3","MESSAGE":"hifi-wifi.service: Failed with result 'exit-code'."}"#;
+        let entry = parse_entry(failed).unwrap();
+        assert!(entry.manager);
+        assert!(keep(&entry, Filter::Errors));
+        assert!(parse_entry(r#"{"__REALTIME_TIMESTAMP":"1","_SYSTEMD_UNIT":"sshd.service","MESSAGE":"x"}"#).is_none());
+    }
+}
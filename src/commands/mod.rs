@@ -0,0 +1,20 @@
+//! CLI command handlers (the binary in main.rs only parses arguments and dispatches)
+
+pub mod abtest;
+pub mod apply;
+pub mod bootstrap;
+pub mod calibrate;
+pub mod check;
+pub mod ctl;
+pub mod doctor;
+pub mod export;
+pub mod install;
+pub mod lock;
+pub mod logs;
+pub mod monitor;
+pub mod revert;
+pub mod service;
+pub mod sessions;
+pub mod status;
+pub mod throughput;
+pub mod usage;
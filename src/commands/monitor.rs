@@ -0,0 +1,155 @@
+//! `monitor`: the daemon (apply once, then run the Governor)
+
+use anyhow::Result;
+use log::{debug, info, error, warn};
+use std::time::{Duration, Instant};
+
+use crate::{config, utils};
+use crate::error::HifiError;
+use crate::config::structs::Role;
+use crate::network::governor::Governor;
+use crate::network::nm::{DeviceState, NmClient};
+use super::apply::{run_apply, run_dry_run, deconflict_nm_powersave};
+
+/// How often startup re-checks NetworkManager and the interfaces
+const STARTUP_POLL: Duration = Duration::from_secs(2);
+/// How often a still-waiting startup says so
+const STARTUP_LOG_EVERY: Duration = Duration::from_secs(30);
+
+/// On a cold boot the service can start before NetworkManager answers on D-Bus or the
+/// Wi-Fi driver has registered, and the first apply then has nothing to work on. Wait
+/// for NetworkManager for as long as it takes (the Governor can't run without it) and,
+/// with `need_wifi`, up to `timeout` for a managed Wi-Fi interface; past that the
+/// Governor picks the interface up when it appears.
+async fn wait_for_network(timeout: Duration, need_wifi: bool) {
+    let start = Instant::now();
+    let mut last_log: Option<Instant> = None;
+    let mut nm: Option<NmClient> = None;
+    loop {
+        if nm.is_none() {
+            nm = NmClient::new().await.map_err(|e| debug!("NetworkManager not ready: {}", e)).ok();
+        }
+        let waiting_for = match &nm {
+            None => "NetworkManager",
+            Some(_) if !need_wifi => break,
+            Some(nm) => match nm.get_wireless_devices().await {
+                Ok(devices) if devices.iter().any(|d| !matches!(d.state, DeviceState::Unknown | DeviceState::Unmanaged)) => break,
+                Ok(_) if start.elapsed() >= timeout => {
+                    warn!(event = "startup_timeout", waited_secs = start.elapsed().as_secs();
+                          "No managed Wi-Fi interface after {}s - continuing, it will be picked up when it appears",
+                          start.elapsed().as_secs());
+                    return;
+                }
+                Ok(_) => "a managed Wi-Fi interface",
+                Err(e) => {
+                    debug!("NetworkManager device query failed: {}", e);
+                    "NetworkManager's device list"
+                }
+            },
+        };
+        if last_log.is_none_or(|t| t.elapsed() >= STARTUP_LOG_EVERY) {
+            info!("Waiting for {} ({}s so far)", waiting_for, start.elapsed().as_secs());
+            last_log = Some(Instant::now());
+        }
+        tokio::time::sleep(STARTUP_POLL).await;
+    }
+    if last_log.is_some() {
+        info!("Network ready after {}s", start.elapsed().as_secs());
+    }
+}
+
+/// Run the Governor in monitor mode (daemon)
+///
+/// With `dry_run` the Governor decides as usual but only logs what it would change,
+/// so it can run next to the real daemon.
+pub async fn run_monitor(config: &config::structs::Config, dry_run: bool) -> Result<()> {
+    info!("=== hifi-wifi v3.0 Monitor Mode ===");
+
+    // Released when the process exits
+    let lock = if dry_run { None } else { Some(utils::daemon_lock::acquire()?) };
+    if !dry_run {
+        utils::crash::install_panic_hook(std::path::Path::new(config::loader::CONFIG_PATH));
+        utils::notify::init(&config.notify);
+    }
+    if let Some(pid) = lock.as_ref().and_then(|l| l.unclean_exit) {
+        warn!(event = "daemon_restart", previous_pid = pid; "The previous daemon (PID {}) did not shut down cleanly", pid);
+        utils::events::record("daemon_restart", None, serde_json::json!({ "previous_pid": pid }));
+    }
+
+    info!("Starting continuous optimization daemon...\n");
+
+    if !dry_run {
+        wait_for_network(Duration::from_secs(config.global.startup_wait_secs), config.global.role == Role::Client).await;
+    }
+
+    // Apply initial optimizations; with no adapter yet, the Governor waits for a hotplug.
+    // Unprivileged (install --hardened) the /etc side was done at install time.
+    if dry_run {
+        info!("[DRY-RUN] Would apply the following optimizations:");
+        run_dry_run()?;
+    } else if utils::privilege::is_root() {
+        match run_apply(config, false) {
+            Err(e) if matches!(e.downcast_ref::<HifiError>(), Some(HifiError::NoInterfaces)) => {
+                warn!("No Wi-Fi interfaces yet - waiting for one to appear");
+            }
+            result => result?,
+        }
+        deconflict_nm_powersave(config).await;
+    } else {
+        info!("Running with network capabilities only - persistent tuning is left to `sudo hifi-wifi apply --force`");
+    }
+
+    // Start the Governor
+    let mut governor = Governor::new(
+        config.governor.clone(),
+        config.wifi.clone(),
+        config.metrics.clone(),
+        config.power.clone(),
+        config.system.clone(),
+        config.mac80211.clone(),
+        config.hooks.clone(),
+    ).await?.with_schedule(&config.schedule).with_backend(config);
+    if config.global.role == Role::Host {
+        governor = governor.with_host(config.host.sunshine_port);
+    }
+    if dry_run {
+        governor = governor.with_dry_run();
+    }
+    if utils::control::socket_activated() {
+        governor = governor.with_idle_exit(config.global.idle_exit_secs);
+    } else if config.global.idle_exit_secs > 0 {
+        info!("idle_exit_secs only applies when started through hifi-wifi.socket (install --on-demand)");
+    }
+    
+    info!("Governor initialized, entering main loop (tick: {}s)", 
+          config.global.tick_rate_secs);
+    
+    // Handle graceful shutdown (Ctrl+C, or SIGTERM from `systemctl stop`)
+    let ctrl_c = tokio::signal::ctrl_c();
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    
+    let clean = tokio::select! {
+        result = governor.run(config.global.tick_rate_secs) => {
+            if let Err(e) = &result {
+                error!("Governor error: {}", e);
+            }
+            result.is_ok()
+        }
+        _ = ctrl_c => {
+            info!("\nReceived shutdown signal");
+            governor.stop();
+            true
+        }
+        _ = sigterm.recv() => {
+            info!("Received SIGTERM");
+            governor.stop();
+            true
+        }
+    };
+    if let Some(lock) = lock.filter(|_| clean) {
+        lock.release();
+    }
+
+    info!("Monitor mode stopped");
+    Ok(())
+}
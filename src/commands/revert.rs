@@ -0,0 +1,152 @@
+//! `revert`: undo optimizations, per subsystem
+
+use anyhow::Result;
+use log::info;
+
+use crate::{network, system};
+use crate::config::loader::load_config;
+use crate::utils::hooks::{self, Hook};
+use crate::network::wifi::WifiManager;
+use crate::network::backend_tuner::BackendTuner;
+use crate::network::power_save::{PowerSaveController, Source as PowerSaveSource};
+use crate::system::optimizer::SystemOptimizer;
+
+/// Which subsystems `revert` should touch
+#[derive(Debug, Clone, Copy)]
+pub struct RevertScope {
+    pub qdisc: bool,
+    pub sysctl: bool,
+    pub driver: bool,
+    pub power: bool,
+    pub backend: bool,
+}
+
+impl RevertScope {
+    pub fn all() -> Self {
+        Self { qdisc: true, sysctl: true, driver: true, power: true, backend: true }
+    }
+
+    /// No flags given means "revert everything" (previous behavior)
+    pub fn or_all(self) -> Self {
+        if self.qdisc || self.sysctl || self.driver || self.power || self.backend {
+            self
+        } else {
+            Self::all()
+        }
+    }
+}
+
+/// What `run_revert` would do with this scope, without touching anything
+pub fn run_dry_run(scope: &RevertScope) -> Result<()> {
+    let wifi_mgr = WifiManager::new()?;
+
+    if scope.qdisc || scope.power {
+        for ifc in wifi_mgr.interfaces() {
+            if !wifi_mgr.is_interface_connected(ifc) {
+                info!("  - Would skip {} (not connected)", ifc.name);
+                continue;
+            }
+            if scope.qdisc {
+                info!("  - Would remove CAKE from {}", ifc.name);
+            }
+            if scope.power {
+                match ifc.interface_type {
+                    crate::network::wifi::InterfaceType::Wifi => info!("  - Would re-enable power save on {}", ifc.name),
+                    crate::network::wifi::InterfaceType::Ethernet => info!("  - Would re-enable EEE on {}", ifc.name),
+                }
+            }
+        }
+    }
+    if scope.qdisc {
+        info!("  - Would restore mac80211 queue settings, Ethernet ring and pause frame settings and MTU, and remove bulk-flow limits");
+    }
+    if scope.power {
+        info!("  - Would restore NetworkManager powersave values and the previous tuned profile");
+    }
+    if scope.sysctl {
+        info!("  - Would remove /etc/sysctl.d/99-hifi-wifi.conf and restore kernel defaults");
+    }
+    if scope.driver {
+        info!("  - Would remove the driver modprobe config");
+    }
+    if scope.backend {
+        info!("  - Would revert backend tuning, IPv6 and DNS overrides and BSSID/band locks");
+    }
+    Ok(())
+}
+
+pub fn run_revert(scope: &RevertScope) -> Result<()> {
+    info!("=== Reverting hifi-wifi Optimizations ===\n");
+    hooks::run(&load_config().hooks, Hook::PreRevert, &[]);
+
+    let wifi_mgr = WifiManager::new()?;
+    
+    // Remove CAKE qdiscs and restore defaults
+    if scope.qdisc || scope.power {
+        for ifc in wifi_mgr.interfaces() {
+            // Only operate on connected interfaces
+            if !wifi_mgr.is_interface_connected(ifc) {
+                info!("Skipping {} (not connected)", ifc.name);
+                continue;
+            }
+            
+            info!("Reverting optimizations on {}", ifc.name);
+            if scope.qdisc {
+                wifi_mgr.remove_cake(ifc)?;
+            }
+            
+            // Restore power-related defaults based on interface type
+            if scope.power {
+                match ifc.interface_type {
+                    crate::network::wifi::InterfaceType::Wifi => {
+                        // Re-enable WiFi power save (safe default)
+                        let mut power_save = PowerSaveController::new();
+                        power_save.request(&ifc.name, PowerSaveSource::Config, true, "default");
+                        let _ = power_save.apply_now(&wifi_mgr, ifc);
+                    },
+                    crate::network::wifi::InterfaceType::Ethernet => {
+                        // Re-enable EEE on ethernet (power saving default)
+                        let _ = crate::network::tc::EthtoolManager::enable_eee(&ifc.name);
+                        info!("Re-enabled EEE on {}", ifc.name);
+                    }
+                }
+            }
+        }
+    }
+
+    if scope.qdisc {
+        network::mac80211::revert();
+        network::ethernet::revert();
+        network::mtu::revert();
+        if network::stream_guard::remove_table() {
+            info!("Removed bulk-flow limits");
+        }
+    }
+
+    if scope.power {
+        network::nm_powersave::revert();
+        system::conflicts::revert();
+    }
+
+    // Revert system optimizations
+    let sys_opt = SystemOptimizer::default();
+    if scope.sysctl {
+        sys_opt.revert_sysctl();
+    }
+    if scope.driver {
+        sys_opt.revert_driver_config();
+    }
+
+    // Revert backend tuning
+    if scope.backend {
+        let backend_tuner = BackendTuner::default();
+        backend_tuner.revert()?;
+        network::ipv6::revert();
+        network::mac_privacy::revert();
+        network::dns::revert();
+        network::lock::revert();
+    }
+
+    info!("\n=== Revert Complete ===");
+    Ok(())
+}
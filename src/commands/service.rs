@@ -0,0 +1,53 @@
+//! `on` / `off`: start or stop the service for A/B testing
+
+use anyhow::Result;
+use log::{info, error};
+
+use crate::error::HifiError;
+use crate::utils::i18n::t;
+use super::revert::{run_revert, RevertScope};
+
+/// Turn off hifi-wifi (stop service, revert optimizations) for A/B testing
+pub fn run_off() -> Result<()> {
+    use std::process::Command;
+    
+    info!("{}\n", t("off.title"));
+
+    // Stop service if running
+    if Command::new("systemctl").args(["is-active", "--quiet", "hifi-wifi"]).status()?.success() {
+        info!("Stopping hifi-wifi service...");
+        Command::new("systemctl").args(["stop", "hifi-wifi.service"]).output()?;
+    } else {
+        info!("Service not running.");
+    }
+
+    // Revert all optimizations
+    run_revert(&RevertScope::all())?;
+
+    info!("\n{}", t("off.done"));
+    info!("{}", t("off.defaults"));
+    info!("{}", t("off.hint"));
+    Ok(())
+}
+
+/// Turn on hifi-wifi (start service, apply optimizations) for A/B testing
+pub fn run_on() -> Result<()> {
+    use std::process::Command;
+    
+    info!("{}\n", t("on.title"));
+
+    // Check if service exists
+    if !crate::system::sysext::service_installed() {
+        error!("{}", t("on.not_installed"));
+        return Err(HifiError::ServiceMissing.into());
+    }
+
+    // Start service
+    info!("Starting hifi-wifi service...");
+    Command::new("systemctl").args(["start", "hifi-wifi.service"]).output()?;
+
+    info!("\n{}", t("on.done"));
+    info!("{}", t("on.active"));
+    info!("{}", t("on.hint"));
+    Ok(())
+}
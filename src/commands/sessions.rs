@@ -0,0 +1,81 @@
+//! `sessions`: quality summaries of past game-mode sessions
+
+use anyhow::Result;
+
+use crate::network::session::{self, Summary};
+use crate::network::usage;
+use crate::utils;
+
+fn ms(value: Option<f64>) -> String {
+    value.map(|v| format!("{:.1}ms", v)).unwrap_or("-".to_string())
+}
+
+/// "-71..-58 dBm"
+fn signal_range(summary: &Summary) -> String {
+    match (summary.signal_min_dbm, summary.signal_max_dbm) {
+        (Some(min), Some(max)) if min == max => format!("{} dBm", min),
+        (Some(min), Some(max)) => format!("{}..{} dBm", min, max),
+        _ => "-".to_string(),
+    }
+}
+
+/// The last `count` sessions, newest first (on `interface` only if given)
+pub fn run_sessions(count: usize, interface: Option<&str>) -> Result<()> {
+    let c = utils::style::palette();
+    let offset = usage::local_offset();
+    let summaries: Vec<Summary> = session::load().into_iter()
+        .filter(|s| interface.is_none_or(|i| i == s.interface))
+        .collect();
+
+    println!("{}hifi-wifi sessions{} {}(quality 0-100: RTT, jitter, loss and roams){}", c.bold, c.reset, c.dim, c.reset);
+    if summaries.is_empty() {
+        println!("  none yet (a summary is written when game mode ends)");
+        return Ok(());
+    }
+    println!("  {:<16}  {:>6}  {:<8} {:>7}  {:>8}  {:>7}  {:>6}  {:>5}  {:>4}  signal",
+             "start", "length", "iface", "quality", "avg RTT", "jitter", "loss", "roams", "CAKE");
+    for s in summaries.iter().rev().take(count) {
+        let minutes = s.duration_secs() / 60;
+        let color = match s.grade() {
+            "A" | "B" => c.green,
+            "C" => c.yellow,
+            _ => c.red,
+        };
+        println!("  {:<16}  {:>3}h{:02}m  {:<8} {}{:>4} {}{}  {:>8}  {:>7}  {:>6}  {:>5}  {:>4}  {}",
+                 usage::date_time(s.start, offset), minutes / 60, minutes % 60, s.interface,
+                 color, s.score, s.grade(), c.reset, ms(s.avg_rtt_ms), ms(s.jitter_ms),
+                 s.loss_pct.map(|l| format!("{:.1}%", l)).unwrap_or("-".to_string()),
+                 s.roams, s.cake_changes, signal_range(s));
+    }
+    let shown: Vec<&Summary> = summaries.iter().rev().take(count).collect();
+    let average = shown.iter().map(|s| s.score as f64).sum::<f64>() / shown.len() as f64;
+    println!("{}Average quality over these {}: {:.0}{}", c.dim, shown.len(), average, c.reset);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_range() {
+        let mut summary = Summary {
+            interface: "wlan0".to_string(),
+            start: 0,
+            end: 600,
+            avg_rtt_ms: None,
+            jitter_ms: None,
+            loss_pct: None,
+            roams: 0,
+            cake_changes: 0,
+            signal_min_dbm: Some(-71),
+            signal_max_dbm: Some(-58),
+            score: 100,
+        };
+        assert_eq!(signal_range(&summary), "-71..-58 dBm");
+        summary.signal_max_dbm = Some(-71);
+        assert_eq!(signal_range(&summary), "-71 dBm");
+        summary.signal_min_dbm = None;
+        assert_eq!(signal_range(&summary), "-");
+    }
+}
@@ -0,0 +1,658 @@
+//! `status`: Wi-Fi, QoS and power panel
+
+use anyhow::Result;
+
+use crate::{network, system, utils};
+use crate::config::loader::load_config;
+use crate::config::structs::CakeMode;
+use crate::network::wifi::WifiManager;
+use crate::network::backend_tuner::BackendTuner;
+use crate::network::metrics::{MetricsSnapshot, METRICS_DIR};
+use crate::network::nm::WifiBand;
+use crate::network::probe::IwProbe;
+use crate::system::power::PowerManager;
+use crate::utils::i18n::{t, tf};
+
+/// Game mode pinned with `hifi-wifi game-mode`, as (forced, minutes left), asked from the daemon
+async fn game_mode_override() -> Option<(bool, Option<u64>)> {
+    use crate::utils::control::{self, Request};
+    let response = tokio::time::timeout(std::time::Duration::from_secs(1), control::send(&Request::Status))
+        .await.ok()?.ok()?;
+    let o = response.data?.get("game_mode_override")?.clone();
+    let forced = o.get("forced")?.as_bool()?;
+    let minutes = o.get("remaining_secs").and_then(|s| s.as_u64()).map(|s| s.div_ceil(60));
+    Some((forced, minutes))
+}
+
+/// Gateway RTT per address family for `status` (one ping burst each)
+fn gateway_rtt(interface: &str) -> String {
+    let c = utils::style::palette();
+    let (v4, v6) = network::ipv6::gateway_rtts(interface);
+    let fmt = |family: &str, rtt: Option<f64>| match rtt {
+        Some(ms) => format!("{} {:.1}ms", family, ms),
+        None => format!("{} {}{}{}", family, c.dim, t("status.na"), c.reset),
+    };
+    format!("{}  {}", fmt("IPv4", v4), fmt("IPv6", v6))
+}
+
+/// Resolver timings for `status` (None until the daemon has probed them)
+fn dns_health(interface: &str, metrics: Option<&MetricsSnapshot>) -> Option<String> {
+    let c = utils::style::palette();
+    let resolvers = metrics
+        .and_then(|m| m.links.iter().find(|l| l.interface == interface))
+        .map(|l| &l.dns)
+        .filter(|d| !d.is_empty())?;
+    Some(resolvers.iter().map(|r| match r.latency_ms {
+        None => format!("{} {}{}{}", r.server, c.red, t("status.dns_down"), c.reset),
+        Some(ms) if r.slow => format!("{} {}{:.0}ms {}{}", r.server, c.yellow, ms, t("status.dns_slow"), c.reset),
+        Some(ms) => format!("{} {}{:.0}ms{}", r.server, c.green, ms, c.reset),
+    }).collect::<Vec<_>>().join("  "))
+}
+
+/// Latency health for `status`, from the daemon's metrics file and event log
+fn latency_health(interface: &str, metrics: Option<&MetricsSnapshot>) -> String {
+    let c = utils::style::palette();
+    let summary = metrics
+        .and_then(|m| m.links.iter().find(|l| l.interface == interface))
+        .and_then(|l| l.latency.as_ref());
+
+    let Some(lat) = summary else {
+        return format!("{}{}{} {}", c.dim, t("status.na"), c.reset, t("status.no_latency_data"));
+    };
+
+    let grade = lat.grade();
+    let color = match grade {
+        "A" | "B" => c.green,
+        "C" => c.yellow,
+        _ => c.red,
+    };
+    let mut line = format!("{}{}{} {}", color, grade, c.reset, tf("status.latency_detail", &[
+        &format!("{:.0}", lat.p50_ms), &format!("{:.0}", lat.p95_ms), &format!("{:.0}", lat.p99_ms),
+        &format!("{:.1}", lat.jitter_ms), &format!("{:.1}", lat.loss_pct),
+    ]));
+
+    if lat.recent_spikes > 0 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let last = utils::events::read_recent(100).into_iter().rev()
+            .find(|e| e.kind == "latency_spike" && e.interface.as_deref() == Some(interface));
+        line.push_str(&format!(" {}{}{}", c.yellow, tf("status.spikes", &[&lat.recent_spikes]), c.reset));
+        if let Some(event) = last {
+            line.push_str(&format!(" {}", tf("status.last_spike", &[&now.saturating_sub(event.ts)])));
+        }
+    }
+    line
+}
+
+/// Bufferbloat grade of the current association plus the last few sessions, if any
+fn bufferbloat_health(interface: &str, metrics: Option<&MetricsSnapshot>) -> Option<String> {
+    let c = utils::style::palette();
+    let report = metrics
+        .and_then(|m| m.links.iter().find(|l| l.interface == interface))
+        .and_then(|l| l.bufferbloat.as_ref())?;
+    let color = match report.grade.as_str() {
+        "A" | "B" => c.green,
+        "C" => c.yellow,
+        _ => c.red,
+    };
+    let mut line = format!("{}{}{} {}", color, report.grade, c.reset, tf("status.bloat_detail", &[
+        &format!("{:.0}", report.increase_ms()), &format!("{:.1}", report.drop_pct),
+    ]));
+
+    let history: Vec<String> = utils::events::read_recent(200).into_iter()
+        .filter(|e| e.kind == "bufferbloat_grade" && e.interface.as_deref() == Some(interface))
+        .filter_map(|e| e.data.get("grade").and_then(|g| g.as_str()).map(str::to_string))
+        .collect();
+    if !history.is_empty() {
+        let recent = &history[history.len().saturating_sub(4)..];
+        line.push_str(&format!(" {}", tf("status.bloat_trend", &[&recent.join(" → ")])));
+    }
+    Some(line)
+}
+
+/// Band steering roams of the last 24h from the event log, if any
+fn roam_history(interface: &str) -> Option<String> {
+    let c = utils::style::palette();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let recent: Vec<utils::events::Event> = utils::events::read_recent(200).into_iter()
+        .filter(|e| e.interface.as_deref() == Some(interface) && now.saturating_sub(e.ts) < 24 * 3600)
+        .collect();
+    let roams: Vec<&utils::events::Event> = recent.iter().filter(|e| e.kind == "roam").collect();
+    let last = roams.last()?;
+    let mut line = tf("status.roam_detail", &[&roams.len(), &(now.saturating_sub(last.ts) / 60)]);
+    if recent.iter().any(|e| e.kind == "roam_damped" && now.saturating_sub(e.ts) < 3600) {
+        line.push_str(&format!(" {}{}{}", c.yellow, t("status.roam_damped"), c.reset));
+    }
+    Some(line)
+}
+
+/// "2h 05m" / "12m"
+fn format_uptime(secs: u64) -> String {
+    match secs / 3600 {
+        0 => format!("{}m", secs / 60),
+        hours => format!("{}h {:02}m", hours, secs % 3600 / 60),
+    }
+}
+
+/// Time on the current AP and today's disconnects from the event log
+fn connection_uptime(interface: &str, iw: &IwProbe) -> Option<String> {
+    let secs = iw.connected_secs()?;
+    let offset = network::usage::local_offset();
+    let today = network::usage::date_key(network::usage::unix_now(), offset);
+    let reconnects = utils::events::read_recent(500).iter()
+        .filter(|e| e.kind == "disconnect" && e.interface.as_deref() == Some(interface)
+                && network::usage::date_key(e.ts, offset) == today)
+        .count();
+    Some(tf("status.uptime_detail", &[&format_uptime(secs), &reconnects]))
+}
+
+/// Minutes of signal history shown as a sparkline
+const SIGNAL_TREND_MINUTES: u64 = 5;
+
+/// Signal over the last few minutes from the daemon's history (needs `[metrics]`)
+fn signal_trend(interface: &str) -> Option<String> {
+    let since = network::usage::unix_now().saturating_sub(SIGNAL_TREND_MINUTES * 60);
+    let signals: Vec<i32> = network::history::read_since(since).into_iter()
+        .filter(|row| row.interface == interface)
+        .filter_map(|row| row.signal_dbm)
+        .collect();
+    if signals.len() < 2 {
+        return None;
+    }
+    let values: Vec<f64> = signals.iter().map(|&s| s as f64).collect();
+    let line = utils::style::sparkline(&values, -90.0, -30.0, 30);
+    let (lo, hi) = (signals.iter().min()?, signals.iter().max()?);
+    Some(tf("status.trend_detail", &[&line, lo, hi, &SIGNAL_TREND_MINUTES]))
+}
+
+/// Convert WiFi frequency (MHz) to channel number
+fn freq_to_channel(freq: u32) -> u32 {
+    match freq {
+        2484 => 14,
+        f if (2400..2484).contains(&f) => (f - 2407) / 5,
+        f if (5150..=5900).contains(&f) => (f - 5000) / 5,
+        // 6 GHz: channel 2 sits below channel 1
+        5935 => 2,
+        f if (5950..=7125).contains(&f) => (f - 5950) / 5,
+        _ => 0,
+    }
+}
+
+/// Run status, either once or re-rendered every `watch` seconds
+pub async fn run_status_async(watch: Option<u64>) -> Result<()> {
+    let interval = match watch {
+        Some(secs) => std::time::Duration::from_secs(secs.max(1)),
+        None => {
+            let mut out = String::new();
+            render_status(&mut out).await?;
+            print!("{}", out);
+            return Ok(());
+        }
+    };
+
+    let c = utils::style::palette();
+    let terminal = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    let mut previous: Vec<String> = Vec::new();
+
+    loop {
+        let mut out = String::new();
+        render_status(&mut out).await?;
+        let lines: Vec<String> = out.lines().map(str::to_string).collect();
+
+        // Clear screen and home cursor (piped: a blank line between frames instead),
+        // then mark lines that changed since last refresh
+        let mut frame = String::from(match (terminal, previous.is_empty()) {
+            (true, _) => "\x1b[2J\x1b[H",
+            (false, true) => "",
+            (false, false) => "\n",
+        });
+        for (i, line) in lines.iter().enumerate() {
+            let changed = !previous.is_empty() && previous.get(i) != Some(line);
+            let marker = if changed { format!("{}{}▶{}", c.bold, c.yellow, c.reset) } else { " ".to_string() };
+            frame.push_str(&format!("{}{}\n", marker, line));
+        }
+        frame.push_str(&format!("\n Refreshing every {}s (changed lines marked ▶) - Ctrl+C to exit\n", interval.as_secs()));
+        print!("{}", frame);
+        previous = lines;
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!();
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn service_active() -> bool {
+    std::process::Command::new("systemctl")
+        .args(["is-active", "--quiet", "hifi-wifi.service"])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Latency figures come from the running daemon; ignore a metrics file it left behind
+fn daemon_metrics(service_active: bool) -> Option<MetricsSnapshot> {
+    MetricsSnapshot::read(std::path::Path::new(METRICS_DIR))
+        .filter(|m| service_active && network::usage::unix_now().saturating_sub(m.timestamp) < 30)
+}
+
+fn band_label(band: WifiBand) -> &'static str {
+    match band {
+        WifiBand::Band2_4GHz => "2.4GHz",
+        WifiBand::Band5GHz => "5GHz",
+        WifiBand::Band6GHz => "6GHz",
+        WifiBand::Unknown => "?",
+    }
+}
+
+/// One line for status bars: `OK wlan0 5GHz -54dBm 866Mbit CAKE:420Mbit game:off`
+async fn render_brief() -> Result<String> {
+    use crate::network::nm::NmClient;
+
+    let active = service_active();
+    let metrics = daemon_metrics(active);
+    let mut links = Vec::new();
+    for device in NmClient::new().await?.get_wireless_devices().await? {
+        let Some(ap) = device.active_ap else { continue };
+        let link = metrics.as_ref().and_then(|m| m.links.iter().find(|l| l.interface == device.interface));
+        let cake = link.and_then(|l| l.cake_mbit).map(|m| format!("{}Mbit", m)).unwrap_or_else(|| "-".to_string());
+        let game = link.map(|l| if l.game_mode { "on" } else { "off" }).unwrap_or("-");
+        links.push(format!("{} {} {}dBm {}Mbit CAKE:{} game:{}", device.interface, band_label(ap.band),
+                           ap.signal_strength, device.bitrate / 1000, cake, game));
+    }
+    if links.is_empty() {
+        links.push("disconnected".to_string());
+    }
+    Ok(format!("{} {}", if active { "OK" } else { "STOPPED" }, links.join(" | ")))
+}
+
+/// Print the one-line status once, or a new line every `watch` seconds
+pub async fn run_brief(watch: Option<u64>) -> Result<()> {
+    loop {
+        println!("{}", render_brief().await?);
+        let Some(secs) = watch else { return Ok(()) };
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(secs.max(1))) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Render the status panel into `out`
+async fn render_status(out: &mut String) -> Result<()> {
+    use crate::network::nm::NmClient;
+    use std::fmt::Write;
+    use std::process::Command;
+
+    // ANSI colors (empty when NO_COLOR / --no-color / not a terminal)
+    let c = utils::style::palette();
+
+    writeln!(out)?;
+    writeln!(out, "{}{}══════════════════════════════════════", c.bold, c.cyan)?;
+    writeln!(out, "       {}", t("status.title"))?;
+    writeln!(out, "{}{}══════════════════════════════════════", c.bold, c.cyan)?;
+    writeln!(out)?;
+
+    // 1. Service Status
+    let service_active = service_active();
+
+    if service_active {
+        writeln!(out, "{}{:<12}{} {}{}{}", c.bold, t("status.status"), c.reset, c.green, t("status.active"), c.reset)?;
+    } else {
+        writeln!(out, "{}{:<12}{} {}{}{}", c.bold, t("status.status"), c.reset, c.red, t("status.inactive"), c.reset)?;
+    }
+    let (crashes, latest) = utils::crash::recent();
+    if crashes > 0 {
+        let latest = latest.map(|p| p.display().to_string()).unwrap_or_default();
+        writeln!(out, "{}{:<12}{} {}{}{}", c.bold, t("status.crashes"), c.reset, c.red, tf("status.crash_detail", &[&crashes, &latest]), c.reset)?;
+    }
+    if let Some(repair) = system::repair_log::last() {
+        let when = network::usage::date_time(repair.ts, network::usage::local_offset());
+        let mut detail = tf("status.repair_detail", &[&when, &repair.item, &repair.trigger]);
+        if let Some(error) = &repair.error {
+            detail = format!("{}{}; {}{}", c.red, detail, tf("status.repair_failed", &[error]), c.reset);
+        }
+        writeln!(out, "{}{:<12}{} {}", c.bold, t("status.last_repair"), c.reset, detail)?;
+    }
+    writeln!(out)?;
+
+    let metrics = daemon_metrics(service_active);
+
+    // 2. System and Power
+    let power_mgr = PowerManager::new();
+    writeln!(out, "{}{}{}┌─ {}{}", c.bold, c.blue, c.reset, t("status.system_info"), c.reset)?;
+    writeln!(out, "{}│{}  {:<8}{:?}", c.blue, c.reset, t("status.device"), power_mgr.device_type())?;
+    let bat_pct = power_mgr.battery_percentage().map(|p| format!("{}%", p)).unwrap_or("N/A".to_string());
+    writeln!(out, "{}│{}  {:<8}{:?} ({})", c.blue, c.reset, t("status.power"), power_mgr.power_source(), tf("status.battery", &[&bat_pct]))?;
+    let daemons = system::conflicts::detect();
+    if !daemons.is_empty() {
+        let list: Vec<String> = daemons.iter().map(|d| {
+            if !d.daemon.conflicts() {
+                d.daemon.name().to_string()
+            } else if d.coordinated {
+                format!("{} {}({}){}", d.daemon.name(), c.green, t("status.coordinated"), c.reset)
+            } else {
+                format!("{} {}({}){}", d.daemon.name(), c.yellow, t("status.conflict"), c.reset)
+            }
+        }).collect();
+        writeln!(out, "{}│{}  {:<8}{}", c.blue, c.reset, t("status.daemons"), list.join(", "))?;
+    }
+    writeln!(out, "{}└{}", c.blue, c.reset)?;
+    writeln!(out)?;
+
+    // 3. Interfaces & Tweaks (CAKE, Power Save)
+    let wifi_mgr = WifiManager::new_quiet()?;
+    writeln!(out, "{}{}{}┌─ {}{}", c.bold, c.blue, c.reset, t("status.interfaces"), c.reset)?;
+    
+    if wifi_mgr.interfaces().is_empty() {
+         writeln!(out, "{}│{}  {}{}{}", c.blue, c.reset, c.dim, t("status.no_interfaces"), c.reset)?;
+    }
+
+    for ifc in wifi_mgr.interfaces() {
+        let ifc_type = match ifc.interface_type {
+            crate::network::wifi::InterfaceType::Wifi => "WiFi",
+            crate::network::wifi::InterfaceType::Ethernet => "Ethernet",
+        };
+        let mode = match ifc.mode {
+            crate::network::wifi::WifiMode::AccessPoint => format!(" {}", t("status.mode_hotspot")),
+            crate::network::wifi::WifiMode::P2p => format!(" {}", t("status.mode_p2p")),
+            _ => String::new(),
+        };
+        writeln!(out, "{}│{}  {}{}{} {}{}", c.blue, c.reset, c.bold, ifc.name, c.reset, tf("status.interface_desc", &[&ifc_type, &ifc.driver, &format!("{:?}", ifc.category)]), mode)?;
+
+        // CAKE Status (tc)
+        let qdisc_out = Command::new("tc")
+            .args(["-s", "qdisc", "show", "dev", &ifc.name])
+            .output()
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+            .unwrap_or_default();
+        
+        if qdisc_out.contains("cake") {
+             // Extract bandwidth if possible
+             let bw = qdisc_out.split("bandwidth ").nth(1)
+                .and_then(|s| s.split_whitespace().next())
+                .unwrap_or("unknown");
+             writeln!(out, "{}│{}    ├─ {:<12}{}{}{} {}", c.blue, c.reset, t("status.cake"), c.green, t("status.active"), c.reset, tf("status.bandwidth", &[&bw]))?;
+             if let Some(stats) = network::tc::parse_cake_stats(&qdisc_out) {
+                 writeln!(out, "{}│{}    ├─ {:<12}{}", c.blue, c.reset, t("status.queue"), stats.summary())?;
+             }
+        } else {
+             writeln!(out, "{}│{}    ├─ {:<12}{}{}{}", c.blue, c.reset, t("status.cake"), c.red, t("status.inactive"), c.reset)?;
+        }
+
+        // Power Save (iw) - WiFi only
+        if ifc.interface_type == crate::network::wifi::InterfaceType::Wifi {
+            let ps_out = Command::new("iw")
+                .args(["dev", &ifc.name, "get", "power_save"])
+                .output()
+                .ok()
+                .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+                .unwrap_or_default();
+            
+            let ps_status = if ps_out.contains("on") {
+                 format!("{}{}{} {}", c.yellow, t("status.on"), c.reset, t("status.power_saving"))
+            } else {
+                 format!("{}{}{} {}", c.green, t("status.off"), c.reset, t("status.performance"))
+            };
+            writeln!(out, "{}│{}    ├─ {:<12}{}", c.blue, c.reset, t("status.power_save"), ps_status)?;
+
+            // Wi-Fi 7 multi-link association: one entry per link
+            let links = network::mlo::links(&ifc.name);
+            if !links.is_empty() {
+                let desc: Vec<String> = links.iter().map(network::mlo::describe).collect();
+                writeln!(out, "{}│{}    ├─ {:<12}{}", c.blue, c.reset, t("status.mlo"), desc.join(", "))?;
+            }
+
+            // mac80211 fq_codel (debugfs is root only, so fall back to the daemon's copy;
+            // absent for drivers without TXQs)
+            let aqm = network::mac80211::read_aqm(&ifc.name).or_else(|| metrics.as_ref()
+                .and_then(|m| m.links.iter().find(|l| l.interface == ifc.name))
+                .map(|l| l.aqm.clone())
+                .filter(|a| !a.is_empty()));
+            if let Some(aqm) = aqm {
+                let value = |name: &str| aqm.iter().find(|p| p.name == name)
+                    .map(|p| p.value.to_string())
+                    .unwrap_or_else(|| "?".to_string());
+                writeln!(out, "{}│{}    ├─ {:<12}{}", c.blue, c.reset, t("status.aqm"),
+                         tf("status.aqm_values", &[&value("fq_limit"), &value("fq_quantum")]))?;
+            }
+        } else {
+            // For ethernet, show EEE status instead
+            let eee_out = Command::new("ethtool")
+                .args(["--show-eee", &ifc.name])
+                .output()
+                .ok()
+                .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+                .unwrap_or_default();
+            
+            let eee_status = if eee_out.contains("EEE status: disabled") {
+                format!("{}{}{} {}", c.green, t("status.disabled_tag"), c.reset, t("status.low_latency"))
+            } else if eee_out.contains("EEE status: enabled") {
+                format!("{}{}{} {}", c.yellow, t("status.enabled_tag"), c.reset, t("status.power_saving"))
+            } else if eee_out.contains("not supported") || eee_out.contains("Operation not supported") {
+                format!("{}{}{} {}", c.dim, t("status.na"), c.reset, t("status.not_supported"))
+            } else {
+                format!("{}{}{}", c.dim, t("status.unknown"), c.reset)
+            };
+            writeln!(out, "{}│{}    ├─ {:<12}{}", c.blue, c.reset, t("status.eee"), eee_status)?;
+        }
+
+        // IRQ Affinity
+        let irq_out = std::fs::read_to_string("/proc/interrupts").unwrap_or_default();
+        
+        // USB devices don't have dedicated IRQs we can pin easily
+        let is_usb = ifc.driver.contains("usb") || ifc.name.contains("usb") || ifc.driver.starts_with("rt2800usb");
+
+        let irq_status = if is_usb {
+             format!("{}{}{} {}", c.dim, t("status.na"), c.reset, t("status.usb_device"))
+        } else {
+            let search_terms = system::quirks::get().irq_names(&ifc.driver, &ifc.name);
+
+            // Find ALL matching IRQs
+            let irq_lines: Vec<&str> = irq_out.lines()
+                .filter(|l| search_terms.iter().any(|t| l.contains(t.as_str())))
+                .collect();
+            
+            if !irq_lines.is_empty() {
+                 // Check if ALL IRQs are pinned to CPU1
+                 let mut all_optimized = true;
+                 let mut all_found = true;
+                 let mut total = 0;
+                 let mut optimized = 0;
+                 
+                 for line in &irq_lines {
+                     let irq_num = line.trim().split(':').next().unwrap_or("?");
+                     if let Ok(affinity) = std::fs::read_to_string(format!("/proc/irq/{}/smp_affinity", irq_num)) {
+                         total += 1;
+                         let aff = affinity.trim();
+                         // Check if pinned to CPU1 (mask 0x2 in various formats)
+                         let is_cpu1 = aff == "2" || aff == "02" || aff == "00000002" || aff == "000002";
+                         if is_cpu1 {
+                             optimized += 1;
+                         } else {
+                             all_optimized = false;
+                         }
+                     } else {
+                         all_found = false;
+                     }
+                 }
+                 
+                 if total == 0 || !all_found {
+                     format!("{}{}{}", c.dim, t("status.unknown"), c.reset)
+                 } else if all_optimized {
+                     if total > 1 {
+                         format!("{}{}{} {}", c.green, t("status.optimized"), c.reset, tf("status.cpu1_vectors", &[&total]))
+                     } else {
+                         format!("{}{}{} {}", c.green, t("status.optimized"), c.reset, t("status.cpu1"))
+                     }
+                 } else if optimized == 0 {
+                     // No IRQs pinned = default system distribution
+                     format!("{}{}{} {}", c.dim, t("status.default_tag"), c.reset, t("status.system_managed"))
+                 } else {
+                     format!("{}{}{} {}", c.yellow, t("status.partial"), c.reset, tf("status.pinned", &[&optimized, &total]))
+                 }
+            } else {
+                 format!("{}{}{}", c.dim, t("status.not_found"), c.reset)
+            }
+        };
+        writeln!(out, "{}│{}    └─ {:<12}{}", c.blue, c.reset, t("status.irq_pin"), irq_status)?;
+        writeln!(out, "{}│{}", c.blue, c.reset)?;
+    }
+    writeln!(out, "{}└{}", c.blue, c.reset)?;
+    writeln!(out)?;
+
+    // 4. Backend & Governor
+    let backend = BackendTuner::default();
+    writeln!(out, "{}{}{}┌─ {}{}", c.bold, c.blue, c.reset, t("status.governor_backend"), c.reset)?;
+    writeln!(out, "{}│{}  {} {:?}", c.blue, c.reset, t("status.backend"), backend.backend())?;
+    
+    let config = load_config();
+    let gov_status = if service_active { t("status.running") } else { t("status.stopped") };
+    writeln!(out, "{}│{}  {} {}", c.blue, c.reset, t("status.governor"), gov_status)?;
+    let qos_mode = match config.governor.cake_mode {
+        CakeMode::Never => t("status.cake_never"),
+        _ if config.governor.breathing_cake_enabled => t("status.breathing_cake"),
+        _ => t("status.static_cake"),
+    };
+    let qos_suffix = if config.governor.cake_mode == CakeMode::GameOnly { format!(" {}", t("status.cake_game_only")) } else { String::new() };
+    writeln!(out, "{}│{}    ├─ {:<12}{}{}", c.blue, c.reset, t("status.qos_mode"), qos_mode, qos_suffix)?;
+    let pinned = if service_active { game_mode_override().await } else { None };
+    let game_mode = match pinned {
+        Some((forced, minutes)) => {
+            let state = if forced { t("status.game_forced_on") } else { t("status.game_forced_off") };
+            match minutes {
+                Some(m) => format!("{}{}{} ({})", c.yellow, state, c.reset, tf("status.game_forced_left", &[&m])),
+                None => format!("{}{}{}", c.yellow, state, c.reset),
+            }
+        }
+        None if config.governor.game_mode_enabled => tf("status.game_available", &[&config.governor.game_mode_pps_threshold]),
+        None => t("status.disabled").to_string(),
+    };
+    writeln!(out, "{}│{}    ├─ {:<12}{}", c.blue, c.reset, t("status.game_mode"), game_mode)?;
+    writeln!(out, "{}│{}    └─ {:<12}{}", c.blue, c.reset, t("status.band_steer"), if config.governor.band_steering_enabled { t("status.available") } else { t("status.disabled") })?;
+
+    writeln!(out, "{}└{}", c.blue, c.reset)?;
+    writeln!(out)?;
+
+    // 5. Connection Details (NM)
+    if let Ok(nm) = NmClient::new().await {
+        writeln!(out, "{}{}{}┌─ {}{}", c.bold, c.blue, c.reset, t("status.active_connection"), c.reset)?;
+        match nm.get_wireless_devices().await {
+            Ok(devices) => {
+                 let mut found_conn = false;
+                 for device in devices {
+                     if let Some(ap) = device.active_ap {
+                         found_conn = true;
+                         
+                         // Calculate band steering score
+                         let score = ap.score(10, 15); // Default biases: +10 for 5GHz, +15 for 6GHz
+                         
+                         // Channel and width from nl80211; from the frequency alone without `iw`
+                         let iw = IwProbe::new(&device.interface);
+                         let band = match iw.channel() {
+                             Some(ch) => tf("status.channel_width", &[&format!("{:?}", ap.band), &ch.channel, &ch.freq_mhz, &ch.width_mhz]),
+                             None => tf("status.channel", &[&format!("{:?}", ap.band), &freq_to_channel(ap.frequency), &ap.frequency]),
+                         };
+                         
+                         // Signal quality description
+                         let signal_quality = match ap.signal_strength {
+                             s if s >= -50 => format!("{}{}{}", c.green, t("status.excellent"), c.reset),
+                             s if s >= -60 => format!("{}{}{}", c.green, t("status.good"), c.reset),
+                             s if s >= -70 => format!("{}{}{}", c.yellow, t("status.fair"), c.reset),
+                             _ => format!("{}{}{}", c.red, t("status.poor"), c.reset),
+                         };
+                         
+                         writeln!(out, "{}│{}  {}{}{}: {}", c.blue, c.reset, c.bold, device.interface, c.reset, ap.ssid)?;
+                         writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.bssid"), ap.bssid)?;
+                         writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.band"), band)?;
+                         writeln!(out, "{}│{}    ├─ {:<10}{} dBm ({})", c.blue, c.reset, t("status.signal"), ap.signal_strength, signal_quality)?;
+                         if let Some(trend) = signal_trend(&device.interface) {
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.trend"), trend)?;
+                         }
+                         writeln!(out, "{}│{}    ├─ {:<10}{} Mbit/s", c.blue, c.reset, t("status.link"), device.bitrate / 1000)?;
+                         if let Some(uptime) = connection_uptime(&device.interface, &iw) {
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.uptime"), uptime)?;
+                         }
+                         writeln!(out, "{}│{}    ├─ {:<10}{} {}", c.blue, c.reset, t("status.score"), score, t("status.for_band_steering"))?;
+                         if let Some(roams) = roam_history(&device.interface) {
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.roams"), roams)?;
+                         }
+                         writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.gateway"), gateway_rtt(&device.interface))?;
+                         if let Some(dns) = dns_health(&device.interface, metrics.as_ref()) {
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.dns"), dns)?;
+                         }
+                         if let Some(bloat) = bufferbloat_health(&device.interface, metrics.as_ref()) {
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.bloat"), bloat)?;
+                         }
+                         writeln!(out, "{}│{}    └─ {:<10}{}", c.blue, c.reset, t("status.latency"), latency_health(&device.interface, metrics.as_ref()))?;
+                     }
+                 }
+                 if !found_conn {
+                     // Check for ethernet connection instead
+                     let eth_conn = Command::new("nmcli")
+                         .args(["-t", "-f", "NAME,DEVICE,TYPE,STATE", "connection", "show", "--active"])
+                         .output()
+                         .ok()
+                         .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+                         .unwrap_or_default();
+                     
+                     let mut eth_found = false;
+                     for line in eth_conn.lines() {
+                         let parts: Vec<&str> = line.split(':').collect();
+                         if parts.len() >= 4 && parts[2] == "802-3-ethernet" && parts[3] == "activated" {
+                             eth_found = true;
+                             let conn_name = parts[0];
+                             let iface = parts[1];
+                             
+                             // Get ethernet speed
+                             let speed = Command::new("ethtool")
+                                 .arg(iface)
+                                 .output()
+                                 .ok()
+                                 .and_then(|o| {
+                                     let stdout = String::from_utf8_lossy(&o.stdout);
+                                     stdout.lines()
+                                         .find(|l| l.contains("Speed:"))
+                                         .map(|l| l.split(':').nth(1).unwrap_or("").trim().to_string())
+                                 })
+                                 .unwrap_or_else(|| "Unknown".to_string());
+                             
+                             writeln!(out, "{}│{}  {}{}{}: {} (Ethernet)", c.blue, c.reset, c.bold, iface, c.reset, conn_name)?;
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.type"), t("status.wired_ethernet"))?;
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.speed"), speed)?;
+                             writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.gateway"), gateway_rtt(iface))?;
+                             if let Some(dns) = dns_health(iface, metrics.as_ref()) {
+                                 writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.dns"), dns)?;
+                             }
+                             if let Some(bloat) = bufferbloat_health(iface, metrics.as_ref()) {
+                                 writeln!(out, "{}│{}    ├─ {:<10}{}", c.blue, c.reset, t("status.bloat"), bloat)?;
+                             }
+                             if metrics.is_some() {
+                                 writeln!(out, "{}│{}    └─ {:<10}{}", c.blue, c.reset, t("status.latency"), latency_health(iface, metrics.as_ref()))?;
+                             } else {
+                                 writeln!(out, "{}│{}    └─ {:<10}{}{}{} {}", c.blue, c.reset, t("status.latency"), c.green, t("status.ultra_low"), c.reset, t("status.wired"))?;
+                             }
+                         }
+                     }
+                     
+                     if !eth_found {
+                         writeln!(out, "{}│{}  {}", c.blue, c.reset, t("status.no_connection"))?;
+                     }
+                 }
+            }
+            Err(_) => writeln!(out, "{}│{}  {}", c.blue, c.reset, t("status.nm_error"))?,
+        }
+        writeln!(out, "{}└{}", c.blue, c.reset)?;
+    }
+    
+    Ok(())
+}
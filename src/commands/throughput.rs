@@ -0,0 +1,74 @@
+//! `throughput`: iperf3 runs with and without CAKE
+
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::{config, network};
+use crate::error::HifiError;
+
+/// iperf3 with and without CAKE; warn when the shaper cuts more than `max_cut` percent
+pub fn run_throughput(server: &str, port: u16, secs: u64, max_cut: f64, config: &config::structs::Config) -> Result<()> {
+    use crate::network::tc::TcManager;
+    use crate::network::throughput::{self, Direction};
+    use std::process::Command;
+
+    let Some((interface, _)) = network::latency::default_route() else {
+        return Err(HifiError::NotConnected("No default route - connect to a network first".to_string()).into());
+    };
+    let Some(cake_mbit) = TcManager::current_bandwidth(&interface) else {
+        anyhow::bail!("CAKE is not active on {} - run 'sudo hifi-wifi on' first", interface);
+    };
+
+    info!("=== Throughput Test: {} -> {}:{} (CAKE at {}Mbit) ===\n", interface, server, port, cake_mbit);
+
+    // Pause the governor so it doesn't re-shape mid-test; hold CAKE at its current value
+    let was_active = Command::new("systemctl")
+        .args(["is-active", "--quiet", "hifi-wifi.service"])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if was_active {
+        Command::new("systemctl").args(["stop", "hifi-wifi.service"]).output()?;
+    }
+    let mut tc = TcManager::default();
+    tc.seed(cake_mbit);
+
+    let measure = |tc: &mut TcManager| -> Result<[f64; 4]> {
+        tc.apply_cake(&interface)?;
+        info!("Running with CAKE...");
+        let shaped_up = throughput::run_iperf3(server, port, secs, Direction::Upload)?;
+        let shaped_down = throughput::run_iperf3(server, port, secs, Direction::Download)?;
+        tc.remove_cake(&interface)?;
+        info!("Running without CAKE...");
+        let raw_up = throughput::run_iperf3(server, port, secs, Direction::Upload)?;
+        let raw_down = throughput::run_iperf3(server, port, secs, Direction::Download)?;
+        Ok([shaped_up, shaped_down, raw_up, raw_down])
+    };
+    let result = measure(&mut tc);
+
+    // Restore before reporting so a failed run never leaves the link unshaped
+    if was_active {
+        Command::new("systemctl").args(["start", "hifi-wifi.service"]).output()?;
+    } else {
+        tc.apply_cake(&interface)?;
+    }
+    let [shaped_up, shaped_down, raw_up, raw_down] = result?;
+
+    let up_cut = throughput::shaper_cut_pct(shaped_up, raw_up);
+    println!();
+    println!("{:<12}{:>14}{:>14}{:>10}", "", "With CAKE", "Without", "Cut");
+    println!("{:<12}{:>9.1} Mbit{:>9.1} Mbit{:>9.1}%", "Upload", shaped_up, raw_up, up_cut);
+    println!("{:<12}{:>9.1} Mbit{:>9.1} Mbit{:>9.1}%", "Download", shaped_down, raw_down, throughput::shaper_cut_pct(shaped_down, raw_down));
+    println!();
+    println!("CAKE shapes egress only, so the upload figure is the one that reflects cake_overhead_factor.");
+
+    if up_cut > max_cut {
+        let factor = config.governor.cake_overhead_factor;
+        warn!("CAKE is cutting {:.1}% of achievable upload (limit {:.0}%)", up_cut, max_cut);
+        warn!("Consider cake_overhead_factor = {:.2} (currently {:.2}) in /etc/hifi-wifi/config.toml",
+              throughput::suggest_overhead_factor(factor, cake_mbit, raw_up), factor);
+    } else {
+        println!("Shaper overhead is within {:.0}% - cake_overhead_factor looks fine.", max_cut);
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! `usage`: data moved per day and per game-mode session
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::network::usage::{self, Bytes, UsageLog};
+use crate::utils;
+
+/// "1.2 GB", decimal units like most data plans
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn print_totals(title: &str, totals: &BTreeMap<String, Bytes>) {
+    println!("{}:", title);
+    if totals.is_empty() {
+        println!("  nothing recorded");
+    }
+    for (interface, bytes) in totals {
+        println!("  {:<10} down {:>9}  up {:>9}", interface, format_bytes(bytes.rx), format_bytes(bytes.tx));
+    }
+}
+
+/// Today's and this week's totals, then the last `sessions` game-mode sessions
+pub fn run_usage(sessions: usize) -> Result<()> {
+    let c = utils::style::palette();
+    let log = UsageLog::load();
+    let offset = usage::local_offset();
+    let days = usage::last_days(7, offset);
+
+    println!("{}hifi-wifi usage{} {}(the daemon writes every 5 minutes){}", c.bold, c.reset, c.dim, c.reset);
+    print_totals("Today", &log.totals(&days[..1]));
+    print_totals("Last 7 days", &log.totals(&days));
+
+    println!("Game-mode sessions:");
+    if log.sessions.is_empty() {
+        println!("  none yet");
+    }
+    let skip = log.sessions.len().saturating_sub(sessions);
+    for session in log.sessions.iter().skip(skip) {
+        let minutes = session.end.saturating_sub(session.start) / 60;
+        let retrans = session.retransmit_pct()
+            .map(|pct| format!("  TCP retransmits {:.1}%", pct))
+            .unwrap_or_default();
+        println!("  {}  {:>3}h{:02}m  {:<8} down {:>9}  up {:>9}{}",
+                 usage::date_time(session.start, offset), minutes / 60, minutes % 60, session.interface,
+                 format_bytes(session.bytes.rx), format_bytes(session.bytes.tx), retrans);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1_250_000), "1.2 MB");
+        assert_eq!(format_bytes(3_400_000_000), "3.4 GB");
+    }
+}
@@ -3,27 +3,47 @@ use std::fs;
 use std::path::Path;
 use log::{info, warn};
 
-const CONFIG_PATH: &str = "/etc/hifi-wifi/config.toml";
+pub const CONFIG_PATH: &str = "/etc/hifi-wifi/config.toml";
 
-pub fn load_config() -> Config {
-    if Path::new(CONFIG_PATH).exists() {
-        match fs::read_to_string(CONFIG_PATH) {
-            Ok(content) => match toml::from_str(&content) {
-                Ok(config) => {
-                    info!("Loaded configuration from {}", CONFIG_PATH);
-                    return config;
-                }
-                Err(e) => {
-                    warn!("Failed to parse config file: {}. Using defaults.", e);
-                }
-            },
-            Err(e) => {
-                warn!("Failed to read config file: {}. Using defaults.", e);
-            }
+/// What happened while reading the config file
+///
+/// Kept separate from loading so `main` can read the config (which decides the
+/// log format) before the logger exists, then report the outcome afterwards.
+pub enum LoadOutcome {
+    Loaded,
+    Missing,
+    ParseError(String),
+    ReadError(String),
+}
+
+impl LoadOutcome {
+    pub fn log(&self) {
+        match self {
+            LoadOutcome::Loaded => info!("Loaded configuration from {}", CONFIG_PATH),
+            LoadOutcome::Missing => info!("No config file found at {}. Using defaults.", CONFIG_PATH),
+            LoadOutcome::ParseError(e) => warn!("Failed to parse config file: {}. Using defaults.", e),
+            LoadOutcome::ReadError(e) => warn!("Failed to read config file: {}. Using defaults.", e),
         }
-    } else {
-        info!("No config file found at {}. Using defaults.", CONFIG_PATH);
     }
-    
-    Config::default()
+}
+
+/// Read the config file without logging
+pub fn read_config() -> (Config, LoadOutcome) {
+    if !Path::new(CONFIG_PATH).exists() {
+        return (Config::default(), LoadOutcome::Missing);
+    }
+
+    match fs::read_to_string(CONFIG_PATH) {
+        Ok(content) => match toml::from_str(&content) {
+            Ok(config) => (config, LoadOutcome::Loaded),
+            Err(e) => (Config::default(), LoadOutcome::ParseError(e.to_string())),
+        },
+        Err(e) => (Config::default(), LoadOutcome::ReadError(e.to_string())),
+    }
+}
+
+pub fn load_config() -> Config {
+    let (config, outcome) = read_config();
+    outcome.log();
+    config
 }
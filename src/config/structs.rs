@@ -1,6 +1,15 @@
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Deserialize)]
+use crate::network::ipv6::Ipv6Preference;
+use crate::network::lock::LockBand;
+use crate::network::mac_privacy::MacRandomization;
+use crate::network::metered::MeteredMode;
+use crate::network::mtu::MtuFix;
+use crate::network::push::PushFormat;
+use crate::utils::logger::LogFormat;
+
+#[derive(Debug, Default, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub global: GlobalConfig,
@@ -13,32 +22,188 @@ pub struct Config {
     #[serde(default)]
     pub backend: BackendConfig,
     #[serde(default)]
+    pub privacy: PrivacyConfig,
+    #[serde(default)]
     pub governor: GovernorConfig,
-}
-
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            global: GlobalConfig::default(),
-            wifi: WifiConfig::default(),
-            power: PowerConfig::default(),
-            system: SystemConfig::default(),
-            backend: BackendConfig::default(),
-            governor: GovernorConfig::default(),
-        }
-    }
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub mac80211: Mac80211Config,
+    #[serde(default)]
+    pub host: HostConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    /// Time-of-day overrides (`[[schedule]]` entries)
+    #[serde(default)]
+    pub schedule: Vec<ScheduleEntry>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct GlobalConfig {
     /// Tick rate for the governor loop in seconds
     pub tick_rate_secs: u64,
+    /// Output language for CLI messages ("en", "fr"); unset follows the locale
+    #[serde(default)]
+    pub language: Option<String>,
+    /// "client" (handheld receiving a stream) or "host" (PC running Sunshine)
+    #[serde(default)]
+    pub role: Role,
+    /// How long the daemon waits at startup for a managed Wi-Fi interface before applying
+    #[serde(default = "default_startup_wait_secs")]
+    pub startup_wait_secs: u64,
+    /// Started on demand (hifi-wifi.socket): exit after this long idle (0 = keep running)
+    #[serde(default)]
+    pub idle_exit_secs: u64,
+}
+
+fn default_startup_wait_secs() -> u64 {
+    60
+}
+
+/// Which end of a game stream this machine is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    Client,
+    /// Serve streams: prioritize Sunshine's ports, no battery power save
+    Host,
+}
+
+/// Settings for `role = "host"`
+#[derive(Debug, Clone, Deserialize)]
+pub struct HostConfig {
+    /// Sunshine base port (its `port` setting); video/control/audio are +9/+10/+11
+    #[serde(default = "default_sunshine_port")]
+    pub sunshine_port: u16,
+    /// Share of the egress NIC's link speed to shape to (0.0-1.0)
+    #[serde(default = "default_host_overhead_factor")]
+    pub cake_overhead_factor: f64,
+}
+
+fn default_sunshine_port() -> u16 {
+    47989
+}
+
+fn default_host_overhead_factor() -> f64 {
+    0.95
+}
+
+/// One `[[schedule]]` entry: overrides that apply while `when` matches the local time
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ScheduleEntry {
+    /// Shown in logs and `ctl status`
+    #[serde(default)]
+    pub name: String,
+    /// "[days] HH:MM-HH:MM", e.g. "mon-fri 19:00-23:00", "23:00-07:00" (every day)
+    pub when: String,
+    /// Policies to switch off meanwhile (added to `[governor] disabled_policies`)
+    #[serde(default)]
+    pub disabled_policies: Vec<String>,
+    /// "on" or "off" (unset: no opinion)
+    #[serde(default)]
+    pub power_save: Option<String>,
+    /// "on" or "off" to pin game mode (unset: detect from traffic)
+    #[serde(default)]
+    pub game_mode: Option<String>,
+}
+
+/// What the user knows about one mesh node (`[wifi.access_points."aa:bb:cc:dd:ee:ff"]`)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApAnnotation {
+    /// Node is cabled to the router: gets `wired_backhaul_bonus` and no backhaul penalty
+    #[serde(default)]
+    pub wired_backhaul: bool,
+    /// Name shown in roam logs and `ctl status`, e.g. "office"
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+/// Scripts in /etc/hifi-wifi/hooks.d to run at each hook point (file names, run in order)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HooksConfig {
+    /// After `apply` (and the daemon's initial apply)
+    #[serde(default)]
+    pub post_apply: Vec<String>,
+    /// Before `revert` undoes anything
+    #[serde(default)]
+    pub pre_revert: Vec<String>,
+    #[serde(default)]
+    pub on_game_mode_start: Vec<String>,
+    /// After band steering picked another AP
+    #[serde(default)]
+    pub on_roam: Vec<String>,
+    /// After NetworkManager reported a (re)connection
+    #[serde(default)]
+    pub on_reconnect: Vec<String>,
+}
+
+/// Where the daemon sends notable events (`[notify.*]`; all off by default)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NotifyConfig {
+    #[serde(default)]
+    pub mqtt: Option<MqttConfig>,
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+}
+
+/// `[notify.mqtt]`: publish events to a broker (e.g. for Home Assistant)
+#[derive(Debug, Clone, Deserialize)]
+pub struct MqttConfig {
+    /// "host" or "host:port" (default port 1883)
+    pub broker: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Default "hifi-wifi/<hostname>"
+    #[serde(default)]
+    pub topic_prefix: Option<String>,
+    /// Event kinds to publish
+    #[serde(default = "default_mqtt_events")]
+    pub events: Vec<String>,
+}
+
+/// `[notify.webhook]`: POST events as JSON to a URL (Discord, ntfy, Slack, ...)
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Event kinds to post
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<String>,
+}
+
+fn default_webhook_events() -> Vec<String> {
+    ["daemon_restart", "sustained_loss", "reconnect_gave_up"]
+        .into_iter().map(String::from).collect()
+}
+
+fn default_mqtt_events() -> Vec<String> {
+    ["game_mode", "roam", "disconnect", "reconnected", "safe_mode"]
+        .into_iter().map(String::from).collect()
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        Self {
+            sunshine_port: default_sunshine_port(),
+            cake_overhead_factor: default_host_overhead_factor(),
+        }
+    }
 }
 
 impl Default for GlobalConfig {
     fn default() -> Self {
         Self {
             tick_rate_secs: 2, // Per rewrite.md: 2 second tick rate
+            language: None,
+            role: Role::Client,
+            startup_wait_secs: default_startup_wait_secs(),
+            idle_exit_secs: 0,
         }
     }
 }
@@ -57,6 +222,131 @@ pub struct WifiConfig {
     pub band_bias_5ghz: i32,
     /// Band bias for 6GHz (gets +25 - less interference, 160MHz channels, ideal for gaming)
     pub band_bias_6ghz: i32,
+    /// Extra score for 6GHz preferred scanning channels
+    #[serde(default = "default_psc_bonus_6ghz")]
+    pub psc_bonus_6ghz: i32,
+    /// Score per doubling of channel width above 80MHz (160MHz = 1x, 320MHz = 2x)
+    #[serde(default = "default_channel_width_bonus")]
+    pub channel_width_bonus: i32,
+    /// 6GHz signal below which the steep penalty starts (dBm)
+    #[serde(default = "default_marginal_6ghz_dbm")]
+    pub marginal_6ghz_dbm: i32,
+    /// Score lost per dB below marginal_6ghz_dbm
+    #[serde(default = "default_marginal_6ghz_penalty_per_db")]
+    pub marginal_6ghz_penalty_per_db: i32,
+    /// Band steering scores APs on their signal projected this many ticks ahead from
+    /// the recent RSSI trend (0 = score on the current signal only)
+    #[serde(default = "default_roam_trend_ticks")]
+    pub roam_trend_ticks: u32,
+    /// Mesh nodes by BSSID
+    #[serde(default)]
+    pub access_points: HashMap<String, ApAnnotation>,
+    /// Score added for a node annotated `wired_backhaul = true`
+    #[serde(default = "default_wired_backhaul_bonus")]
+    pub wired_backhaul_bonus: i32,
+    /// Most score a node loses when the throughput it has carried falls short of its
+    /// best sibling's (scaled by the shortfall; 0 = ignore observed backhaul)
+    #[serde(default = "default_backhaul_penalty")]
+    pub backhaul_penalty: i32,
+    /// Pin the active Wi-Fi profile to this AP (like `hifi-wifi lock --bssid`)
+    #[serde(default)]
+    pub lock_bssid: Option<String>,
+    /// Pin the active Wi-Fi profile to a band: "2.4ghz" or "5ghz" (like `hifi-wifi lock --band`)
+    #[serde(default)]
+    pub lock_band: Option<LockBand>,
+    /// Stop tuning while NetworkManager reports a captive portal or limited connectivity:
+    /// no CAKE, power save back on, periodic scans (until full connectivity)
+    #[serde(default = "default_true")]
+    pub public_safe_mode: bool,
+    /// Treat links as metered (phone hotspot): "auto" follows NetworkManager, "yes"/"no" override it
+    #[serde(default)]
+    pub metered: MeteredMode,
+    /// Re-activate the last profile when the link drops on its own (monitor mode)
+    #[serde(default = "default_true")]
+    pub auto_reconnect: bool,
+    /// Attempts (with exponential backoff from 5s) before giving up
+    #[serde(default = "default_reconnect_max_attempts")]
+    pub reconnect_max_attempts: u32,
+    /// Signal below which iwd looks for another AP on 2.4GHz (dBm); band steering
+    /// doesn't roam to a 2.4GHz AP weaker than this either
+    #[serde(default = "default_roam_threshold_2g")]
+    pub roam_threshold_2g: i32,
+    /// Same for 5GHz and 6GHz
+    #[serde(default = "default_roam_threshold_5g")]
+    pub roam_threshold_5g: i32,
+    /// Also optimize interfaces hosting a hotspot (AP mode), e.g. to stream to a local client.
+    /// P2P interfaces are always left alone.
+    #[serde(default)]
+    pub optimize_hotspot: bool,
+    /// Ping the gateway and resolve `stream_host` right after (re)connecting
+    #[serde(default = "default_true")]
+    pub prewarm: bool,
+    /// Streaming PC to resolve and ping when pre-warming, and to probe the path MTU against
+    #[serde(default)]
+    pub stream_host: Option<String>,
+    /// On a path MTU black hole during `apply`: "off" (report in doctor), "interface", "mss_clamp"
+    #[serde(default)]
+    pub mtu_fix: MtuFix,
+    /// IPv6 on the active Wi-Fi profiles: "default" (untouched), "prefer", "deprioritize"
+    #[serde(default)]
+    pub ipv6_preference: Ipv6Preference,
+    /// How often to time the link's DNS resolvers (0 = never)
+    #[serde(default = "default_dns_probe_interval_secs")]
+    pub dns_probe_interval_secs: u64,
+    /// Resolvers answering slower than this are flagged in `status`
+    #[serde(default = "default_dns_slow_ms")]
+    pub dns_slow_ms: u32,
+    /// Resolvers to use instead of the ones DHCP hands out (runtime only, empty = leave alone)
+    #[serde(default)]
+    pub dns_servers: Vec<String>,
+}
+
+fn default_psc_bonus_6ghz() -> i32 {
+    5
+}
+
+fn default_channel_width_bonus() -> i32 {
+    3
+}
+
+fn default_marginal_6ghz_dbm() -> i32 {
+    -65
+}
+
+fn default_marginal_6ghz_penalty_per_db() -> i32 {
+    3
+}
+
+fn default_dns_probe_interval_secs() -> u64 {
+    300
+}
+
+fn default_dns_slow_ms() -> u32 {
+    100
+}
+
+fn default_roam_threshold_2g() -> i32 {
+    -75
+}
+
+fn default_roam_threshold_5g() -> i32 {
+    -80
+}
+
+fn default_reconnect_max_attempts() -> u32 {
+    6
+}
+
+fn default_roam_trend_ticks() -> u32 {
+    5
+}
+
+fn default_wired_backhaul_bonus() -> i32 {
+    5
+}
+
+fn default_backhaul_penalty() -> i32 {
+    10
 }
 
 impl Default for WifiConfig {
@@ -69,15 +359,43 @@ impl Default for WifiConfig {
             min_signal_6g_dbm: -70,  // 6GHz: even stricter (higher path loss)
             band_bias_5ghz: 15,  // Per rewrite.md
             band_bias_6ghz: 25,  // Higher than 5GHz - 6GHz has less interference, better for gaming
+            psc_bonus_6ghz: default_psc_bonus_6ghz(),
+            channel_width_bonus: default_channel_width_bonus(),
+            marginal_6ghz_dbm: default_marginal_6ghz_dbm(),
+            marginal_6ghz_penalty_per_db: default_marginal_6ghz_penalty_per_db(),
+            roam_trend_ticks: default_roam_trend_ticks(),
+            access_points: HashMap::new(),
+            wired_backhaul_bonus: default_wired_backhaul_bonus(),
+            backhaul_penalty: default_backhaul_penalty(),
+            lock_bssid: None,
+            public_safe_mode: true,
+            metered: MeteredMode::Auto,
+            lock_band: None,
+            optimize_hotspot: false,
+            prewarm: true,
+            stream_host: None,
+            mtu_fix: MtuFix::Off,
+            ipv6_preference: Ipv6Preference::Default,
+            dns_probe_interval_secs: default_dns_probe_interval_secs(),
+            dns_slow_ms: default_dns_slow_ms(),
+            dns_servers: Vec::new(),
+            auto_reconnect: true,
+            reconnect_max_attempts: default_reconnect_max_attempts(),
+            roam_threshold_2g: default_roam_threshold_2g(),
+            roam_threshold_5g: default_roam_threshold_5g(),
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct PowerConfig {
     #[allow(dead_code)]
     pub enabled: bool,
     pub wlan_power_save: String, // "on", "off", "adaptive"
+    /// Set NetworkManager's per-profile wifi.powersave to "disable" when it would
+    /// re-enable power save on reconnect (original restored on revert)
+    #[serde(default = "default_true")]
+    pub nm_powersave_override: bool,
 }
 
 impl Default for PowerConfig {
@@ -85,15 +403,58 @@ impl Default for PowerConfig {
         Self {
             enabled: true,
             wlan_power_save: "adaptive".to_string(),
+            nm_powersave_override: true,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Sysctl values applied by default (overridable per key in [system.sysctl])
+pub const DEFAULT_SYSCTL: &[(&str, &str)] = &[
+    ("net.ipv4.tcp_congestion_control", "bbr"),
+    ("net.core.rmem_default", "262144"),
+    ("net.core.wmem_default", "262144"),
+    ("net.core.rmem_max", "4194304"),
+    ("net.core.wmem_max", "4194304"),
+    ("net.ipv4.tcp_rmem", "4096 131072 4194304"),
+    ("net.ipv4.tcp_wmem", "4096 65536 4194304"),
+    ("net.ipv4.tcp_fastopen", "3"),
+    ("net.core.netdev_max_backlog", "2000"),
+    ("net.ipv4.tcp_ecn", "1"),
+    ("net.ipv4.tcp_keepalive_time", "60"),
+    ("net.ipv4.tcp_keepalive_intvl", "10"),
+    ("net.ipv4.tcp_keepalive_probes", "6"),
+    ("net.ipv4.tcp_tw_reuse", "1"),
+];
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct SystemConfig {
     pub sysctl_enabled: bool,
     pub irq_affinity_enabled: bool,
     pub driver_tweaks_enabled: bool,
+    /// Per-key overrides of DEFAULT_SYSCTL; an empty value leaves that key alone,
+    /// e.g. `"net.ipv4.tcp_congestion_control" = ""` keeps the distro's choice
+    #[serde(default)]
+    pub sysctl: BTreeMap<String, String>,
+    /// Write TLP/tuned drop-ins that exclude the Wi-Fi device from their control
+    #[serde(default)]
+    pub power_daemon_coordination: bool,
+    /// Enforce the SELinux module / AppArmor profile `install` sets up (default: log only)
+    #[serde(default)]
+    pub lsm_enforce: bool,
+}
+
+impl SystemConfig {
+    /// Effective sysctl settings: defaults merged with overrides, empty values dropped
+    pub fn sysctl_settings(&self) -> BTreeMap<String, String> {
+        let mut settings: BTreeMap<String, String> = DEFAULT_SYSCTL.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        for (key, value) in &self.sysctl {
+            settings.insert(key.clone(), value.clone());
+        }
+        settings.retain(|_, v| !v.trim().is_empty());
+        settings
+    }
 }
 
 impl Default for SystemConfig {
@@ -102,28 +463,62 @@ impl Default for SystemConfig {
             sysctl_enabled: true,
             irq_affinity_enabled: true,
             driver_tweaks_enabled: true,
+            sysctl: BTreeMap::new(),
+            power_daemon_coordination: false,
+            lsm_enforce: false,
         }
     }
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct PrivacyConfig {
+    /// "off", "network" or "full" for both iwd and NetworkManager (unset = leave alone)
+    #[serde(default)]
+    pub mac_randomization: MacRandomization,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BackendConfig {
     pub iwd_periodic_scan_disable: bool,
+    /// wpa_supplicant `bgscan` for the current network ("" = leave NetworkManager's)
+    #[serde(default = "default_wpa_bgscan")]
+    pub wpa_bgscan: String,
+}
+
+fn default_wpa_bgscan() -> String {
+    "simple:30:-70:3600".to_string()
 }
 
 impl Default for BackendConfig {
     fn default() -> Self {
         Self {
             iwd_periodic_scan_disable: true,
+            wpa_bgscan: default_wpa_bgscan(),
         }
     }
 }
 
+/// When the governor keeps a CAKE qdisc on the interface
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CakeMode {
+    /// Shape all the time (default)
+    #[default]
+    Always,
+    /// Install CAKE when game mode starts, restore the default qdisc when it ends
+    GameOnly,
+    /// Never shape
+    Never,
+}
+
 /// Governor-specific settings (the "brain" of hifi-wifi)
 #[derive(Debug, Clone, Deserialize)]
 pub struct GovernorConfig {
     /// Enable dynamic CAKE bandwidth adjustment
     pub breathing_cake_enabled: bool,
+    /// "always", "game_only" or "never"
+    #[serde(default)]
+    pub cake_mode: CakeMode,
     /// Median filter window size (samples)
     pub cake_median_window: usize,
     /// Minimum bandwidth change to trigger CAKE update (Mbit)
@@ -158,12 +553,55 @@ pub struct GovernorConfig {
     
     /// Rolling average window size for CPU monitoring
     pub cpu_avg_window_size: usize,
+
+    /// While a stream is active, police long-running TCP flows (downloads, backups) with nftables
+    #[serde(default)]
+    pub bulk_guard: bool,
+    /// Shared budget for those flows while streaming (Mbit/s, each direction)
+    #[serde(default = "default_bulk_guard_rate_mbit")]
+    pub bulk_guard_rate_mbit: u32,
+    /// A TCP flow counts as bulk once it has moved this much (MB)
+    #[serde(default = "default_bulk_guard_threshold_mb")]
+    pub bulk_guard_threshold_mb: u32,
+
+    /// Remove CAKE from an interface that stops being managed but still exists
+    /// (switched to hotspot mode, or excluded after re-detection)
+    #[serde(default)]
+    pub release_qdisc_on_remove: bool,
+
+    /// Tick policies to skip by name: game_mode, cake, coalescing, power_save, eee, ethernet, band_steering
+    #[serde(default)]
+    pub disabled_policies: Vec<String>,
+
+    /// TX ring size for wired links, capped at the NIC's maximum (0 = leave it alone)
+    #[serde(default = "default_ethernet_tx_ring")]
+    pub ethernet_tx_ring: u32,
+    /// Leave Ethernet pause frames (flow control) on instead of turning them off
+    #[serde(default)]
+    pub ethernet_pause_frames: bool,
+
+    /// Abort background scans this often in game mode (4x less often otherwise; 0 = off)
+    #[serde(default)]
+    pub scan_abort_interval_ms: u64,
+}
+
+fn default_bulk_guard_rate_mbit() -> u32 {
+    5
+}
+
+fn default_bulk_guard_threshold_mb() -> u32 {
+    20
+}
+
+fn default_ethernet_tx_ring() -> u32 {
+    256
 }
 
 impl Default for GovernorConfig {
     fn default() -> Self {
         Self {
             breathing_cake_enabled: true,
+            cake_mode: CakeMode::Always,
             cake_median_window: 3,             // 3 samples = 6 seconds (reduced from 5)
             cake_change_threshold_mbit: 15,    // Reduced from 25 for better responsiveness
             cake_change_threshold_pct: 0.15,   // Reduced from 20% to 15%
@@ -183,7 +621,214 @@ impl Default for GovernorConfig {
             cpu_coalescing_threshold: 0.90,
             
             cpu_avg_window_size: 3,
+
+            bulk_guard: false,
+            bulk_guard_rate_mbit: default_bulk_guard_rate_mbit(),
+            bulk_guard_threshold_mb: default_bulk_guard_threshold_mb(),
+
+            release_qdisc_on_remove: false,
+            disabled_policies: Vec::new(),
+            ethernet_tx_ring: default_ethernet_tx_ring(),
+            ethernet_pause_frames: false,
+            scan_abort_interval_ms: 0,
+        }
+    }
+}
+
+
+/// Logging output settings
+#[derive(Debug, Deserialize)]
+pub struct LoggingConfig {
+    /// "text" (default) or "json" (structured records for journald pipelines)
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Base log level (error, warn, info, debug, trace)
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Per-module levels, e.g. `governor = "debug"`, `band_steering = "warn"`
+    #[serde(default)]
+    pub modules: HashMap<String, String>,
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: default_log_level(),
+            modules: HashMap::new(),
         }
     }
 }
 
+/// Live metrics file for overlays (see network::metrics)
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetricsConfig {
+    /// Monitor gateway latency and write /run/hifi-wifi/{metrics.json,mangohud.txt} each tick
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Host to ping for RTT/jitter/loss (default: the interface's gateway)
+    #[serde(default)]
+    pub probe_target: Option<String>,
+    /// RTT above this counts toward a latency spike (ms)
+    #[serde(default = "default_spike_threshold_ms")]
+    pub spike_threshold_ms: f64,
+    /// Minimum spike length before it is recorded in the event log (ms)
+    #[serde(default = "default_spike_min_duration_ms")]
+    pub spike_min_duration_ms: u64,
+    /// Gateway loss over a full minute that counts as sustained (%, 0 = never): logged
+    /// as a `sustained_loss` event, e.g. for `[notify.webhook]`
+    #[serde(default = "default_sustained_loss_pct")]
+    pub sustained_loss_pct: f64,
+    /// Size cap of the tick history for `hifi-wifi export` (KiB, 0 = don't keep one)
+    #[serde(default = "default_history_max_kb")]
+    pub history_max_kb: u64,
+    /// Push metrics as "influx" line protocol or "statsd" gauges (unset = no push)
+    #[serde(default)]
+    pub push_format: Option<PushFormat>,
+    /// UDP collector to push to ("192.168.1.10:8089")
+    #[serde(default)]
+    pub push_host: Option<String>,
+    #[serde(default = "default_push_interval_secs")]
+    pub push_interval_secs: u64,
+}
+
+fn default_spike_threshold_ms() -> f64 {
+    100.0
+}
+
+fn default_spike_min_duration_ms() -> u64 {
+    500
+}
+
+fn default_sustained_loss_pct() -> f64 {
+    10.0
+}
+
+fn default_history_max_kb() -> u64 {
+    8192
+}
+
+fn default_push_interval_secs() -> u64 {
+    10
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            probe_target: None,
+            spike_threshold_ms: default_spike_threshold_ms(),
+            spike_min_duration_ms: default_spike_min_duration_ms(),
+            sustained_loss_pct: default_sustained_loss_pct(),
+            history_max_kb: default_history_max_kb(),
+            push_format: None,
+            push_host: None,
+            push_interval_secs: default_push_interval_secs(),
+        }
+    }
+}
+
+/// mac80211 TXQ tuning via debugfs (see network::mac80211); unset values are left alone
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mac80211Config {
+    /// Tune the per-phy fq_codel (`aqm`) on drivers that use mac80211 TXQs
+    #[serde(default = "default_true")]
+    pub aqm_enabled: bool,
+    /// Packets queued per phy before fq_codel drops (kernel default 8192)
+    #[serde(default = "default_fq_limit")]
+    pub fq_limit: Option<u64>,
+    /// Bytes a flow may send per round (kernel default 300)
+    #[serde(default = "default_fq_quantum")]
+    pub fq_quantum: Option<u64>,
+    /// CoDel target in microseconds, only on kernels that expose it in `aqm`
+    #[serde(default)]
+    pub codel_target_us: Option<u64>,
+    /// Lower AQL (airtime queue limits) while game mode is active
+    #[serde(default = "default_true")]
+    pub aql_enabled: bool,
+    /// Driver families (quirks category names) that get AQL and airtime fairness tuning
+    #[serde(default = "default_aql_categories")]
+    pub aql_categories: Vec<String>,
+    /// AQL low/high limits during game mode in microseconds (kernel default 5000/12000)
+    #[serde(default = "default_aql_game_low_us")]
+    pub aql_game_low_us: u32,
+    #[serde(default = "default_aql_game_high_us")]
+    pub aql_game_high_us: u32,
+    /// Enable the mac80211 airtime scheduler's TX+RX airtime accounting
+    #[serde(default = "default_true")]
+    pub airtime_fairness: bool,
+    /// Reduce A-MPDU/A-MSDU aggregation during game mode (per-category writes in quirks.toml);
+    /// off by default since it costs throughput
+    #[serde(default)]
+    pub aggregation_game_mode: bool,
+}
+
+fn default_fq_limit() -> Option<u64> {
+    Some(2048)
+}
+
+fn default_fq_quantum() -> Option<u64> {
+    Some(300)
+}
+
+fn default_aql_categories() -> Vec<String> {
+    ["atheros", "ath12k", "mediatek", "mediatek_wifi7"].iter().map(|c| c.to_string()).collect()
+}
+
+fn default_aql_game_low_us() -> u32 {
+    1500
+}
+
+fn default_aql_game_high_us() -> u32 {
+    5000
+}
+
+impl Default for Mac80211Config {
+    fn default() -> Self {
+        Self {
+            aqm_enabled: true,
+            fq_limit: default_fq_limit(),
+            fq_quantum: default_fq_quantum(),
+            codel_target_us: None,
+            aql_enabled: true,
+            aql_categories: default_aql_categories(),
+            aql_game_low_us: default_aql_game_low_us(),
+            aql_game_high_us: default_aql_game_high_us(),
+            airtime_fairness: true,
+            aggregation_game_mode: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sysctl_overrides() {
+        let config: Config = toml::from_str(r#"
+            [system]
+            sysctl_enabled = true
+            irq_affinity_enabled = true
+            driver_tweaks_enabled = true
+
+            [system.sysctl]
+            "net.ipv4.tcp_congestion_control" = ""
+            "net.ipv4.tcp_ecn" = "2"
+            "net.core.somaxconn" = "4096"
+        "#).unwrap();
+        let settings = config.system.sysctl_settings();
+        assert!(!settings.contains_key("net.ipv4.tcp_congestion_control"));
+        assert_eq!(settings["net.ipv4.tcp_ecn"], "2");
+        assert_eq!(settings["net.core.somaxconn"], "4096");
+        assert_eq!(settings["net.core.rmem_max"], "4194304");
+    }
+}
@@ -0,0 +1,83 @@
+//! Failures with a stable exit code
+//!
+//! Commands return `anyhow::Result`; the ones scripts (and the Decky plugin) need to
+//! tell apart are raised as a `HifiError` so `main` can map them to a fixed code.
+//! Anything else exits 1. Codes are part of the CLI contract: add, don't renumber.
+
+use std::fmt;
+
+/// Exit code for invalid command-line usage (BSD `EX_USAGE`)
+pub const EXIT_USAGE: i32 = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HifiError {
+    /// Command needs root (or capabilities the process lacks)
+    NotRoot,
+    /// No Wi-Fi interface to optimize (none present, or only hotspot/P2P ones)
+    NoInterfaces,
+    /// Requested action isn't possible with this adapter/driver
+    UnsupportedHardware(String),
+    /// The systemd service isn't installed
+    ServiceMissing,
+    /// `doctor` found failing checks (count)
+    ChecksFailed(usize),
+    /// No active connection / default route to work with
+    NotConnected(String),
+    /// A required external program isn't installed
+    MissingTool(String),
+    /// The monitor daemon is running (pid) and the command would fight it
+    DaemonRunning(u32),
+}
+
+impl HifiError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HifiError::NotRoot => 2,
+            HifiError::NoInterfaces => 3,
+            HifiError::UnsupportedHardware(_) => 4,
+            HifiError::ServiceMissing => 5,
+            HifiError::ChecksFailed(_) => 6,
+            HifiError::NotConnected(_) => 7,
+            HifiError::MissingTool(_) => 8,
+            HifiError::DaemonRunning(_) => 9,
+        }
+    }
+}
+
+impl fmt::Display for HifiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HifiError::NotRoot => write!(f, "This command must be run as root"),
+            HifiError::NoInterfaces => write!(f, "No Wi-Fi interfaces detected"),
+            HifiError::UnsupportedHardware(what) => write!(f, "Unsupported hardware: {}", what),
+            HifiError::ServiceMissing => write!(f, "hifi-wifi service is not installed (run: sudo hifi-wifi install)"),
+            HifiError::ChecksFailed(n) => write!(f, "{} check(s) failed", n),
+            HifiError::NotConnected(what) => write!(f, "{}", what),
+            HifiError::MissingTool(what) => write!(f, "{}", what),
+            HifiError::DaemonRunning(pid) => write!(f, "hifi-wifi daemon is already running (pid {})", pid),
+        }
+    }
+}
+
+impl std::error::Error for HifiError {}
+
+/// Exit code for an error returned by a command (1 unless it carries a `HifiError`)
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|e| e.downcast_ref::<HifiError>())
+        .map_or(1, HifiError::exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(exit_code(&HifiError::NoInterfaces.into()), 3);
+        let wrapped: anyhow::Result<()> = Err(HifiError::ServiceMissing).context("Failed to turn on");
+        assert_eq!(exit_code(&wrapped.unwrap_err()), 5);
+        assert_eq!(exit_code(&anyhow::anyhow!("something else")), 1);
+    }
+}
@@ -0,0 +1,18 @@
+//! hifi-wifi: Wi-Fi optimizer for Linux streaming handhelds
+//!
+//! The `hifi-wifi` binary is a thin CLI over [`commands`]; other tools (a D-Bus
+//! service, the Decky plugin) can link this crate and call the same entry points.
+
+pub mod commands;
+pub mod config;
+pub mod error;
+pub mod network;
+pub mod system;
+pub mod utils;
+
+pub use config::structs::Config;
+
+/// Apply every optimization once, as `hifi-wifi apply` does (without a driver reload)
+pub fn apply(config: &Config) -> anyhow::Result<()> {
+    commands::apply::run_apply(config, false)
+}
@@ -0,0 +1,179 @@
+//! A/B experiment support
+//!
+//! `hifi-wifi abtest` alternates baseline (service stopped, everything reverted) and
+//! optimized phases while a latency monitor samples the gateway. This module holds the
+//! per-phase results and the comparison; orchestration lives with the other commands.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::network::stats::{summarize, LatencySummary};
+
+/// Which configuration a phase ran under
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Baseline,
+    Optimized,
+}
+
+impl Mode {
+    pub fn other(self) -> Self {
+        match self {
+            Mode::Baseline => Mode::Optimized,
+            Mode::Optimized => Mode::Baseline,
+        }
+    }
+}
+
+/// Samples collected during one phase
+#[derive(Debug, Clone)]
+pub struct PhaseResult {
+    pub mode: Mode,
+    /// RTT samples in ms (None = lost reply)
+    pub samples: Vec<Option<f64>>,
+    pub spikes: usize,
+    pub duration: Duration,
+}
+
+/// Parse durations like "90s", "10m", "1h", "2d" or a bare number of seconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let invalid = || format!("invalid duration '{}'", s);
+    let value: u64 = num.parse().map_err(|_| invalid())?;
+    let factor = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => return Err(format!("invalid duration unit '{}' (use s, m, h or d)", unit)),
+    };
+    value.checked_mul(factor).map(Duration::from_secs).ok_or_else(invalid)
+}
+
+/// Two-sided Mann-Whitney U test (normal approximation with tie correction)
+///
+/// Returns (U for `a`, p-value). Non-parametric because RTT distributions are
+/// heavily right-skewed, which makes a t-test on means misleading.
+pub fn mann_whitney(a: &[f64], b: &[f64]) -> (f64, f64) {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return (0.0, 1.0);
+    }
+
+    let mut all: Vec<(f64, bool)> = a.iter().map(|&v| (v, true))
+        .chain(b.iter().map(|&v| (v, false)))
+        .collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Average ranks across ties
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j + 1 < all.len() && all[j + 1].0 == all[i].0 {
+            j += 1;
+        }
+        let avg_rank = (i + j) as f64 / 2.0 + 1.0;
+        let ties = (j - i + 1) as f64;
+        tie_term += ties.powi(3) - ties;
+        rank_sum_a += all[i..=j].iter().filter(|(_, from_a)| *from_a).count() as f64 * avg_rank;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return (u, 1.0);
+    }
+    let z = (u - mean).abs() / variance.sqrt();
+    (u, (2.0 * (1.0 - normal_cdf(z))).clamp(0.0, 1.0))
+}
+
+/// Standard normal CDF (Abramowitz & Stegun 7.1.26 erf approximation)
+fn normal_cdf(z: f64) -> f64 {
+    let x = z / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    0.5 * (1.0 + if x >= 0.0 { erf } else { -erf })
+}
+
+/// Summary, spike rate and samples for all phases of one mode
+fn collect(phases: &[PhaseResult], mode: Mode) -> (LatencySummary, f64, Vec<f64>) {
+    let samples: Vec<Option<f64>> = phases.iter()
+        .filter(|p| p.mode == mode)
+        .flat_map(|p| p.samples.iter().copied())
+        .collect();
+    let minutes: f64 = phases.iter().filter(|p| p.mode == mode).map(|p| p.duration.as_secs_f64() / 60.0).sum();
+    let spikes: usize = phases.iter().filter(|p| p.mode == mode).map(|p| p.spikes).sum();
+    let spike_rate = if minutes > 0.0 { spikes as f64 / minutes } else { 0.0 };
+    let rtts = samples.iter().filter_map(|r| *r).collect();
+    (summarize(&samples), spike_rate, rtts)
+}
+
+/// Side-by-side comparison table plus significance verdict
+pub fn report(phases: &[PhaseResult]) -> String {
+    let (base, base_spikes, base_rtts) = collect(phases, Mode::Baseline);
+    let (opt, opt_spikes, opt_rtts) = collect(phases, Mode::Optimized);
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<16}{:>12}{:>12}", "", "Baseline", "Optimized");
+    let _ = writeln!(out, "{:<16}{:>12}{:>12}", "Samples", base.samples, opt.samples);
+    for (label, b, o) in [
+        ("RTT p50 (ms)", base.p50_ms, opt.p50_ms),
+        ("RTT p95 (ms)", base.p95_ms, opt.p95_ms),
+        ("RTT p99 (ms)", base.p99_ms, opt.p99_ms),
+        ("Jitter (ms)", base.jitter_ms, opt.jitter_ms),
+        ("Loss (%)", base.loss_pct, opt.loss_pct),
+        ("Spikes / min", base_spikes, opt_spikes),
+    ] {
+        let _ = writeln!(out, "{:<16}{:>12.2}{:>12.2}", label, b, o);
+    }
+    let _ = writeln!(out, "{:<16}{:>12}{:>12}", "Grade", base.grade(), opt.grade());
+    let _ = writeln!(out);
+
+    let (_, p) = mann_whitney(&opt_rtts, &base_rtts);
+    let verdict = if base.samples == 0 || opt.samples == 0 {
+        "Not enough data for a comparison.".to_string()
+    } else if p >= 0.05 {
+        format!("No significant RTT difference (Mann-Whitney p = {:.3}).", p)
+    } else if opt.p50_ms < base.p50_ms {
+        format!("Optimized RTT is significantly LOWER (Mann-Whitney p = {:.4}).", p)
+    } else {
+        format!("Optimized RTT is significantly HIGHER (Mann-Whitney p = {:.4}).", p)
+    };
+    let _ = writeln!(out, "{}", verdict);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_mann_whitney() {
+        let low: Vec<f64> = (0..50).map(|i| 2.0 + (i % 5) as f64 * 0.1).collect();
+        let high: Vec<f64> = (0..50).map(|i| 20.0 + (i % 7) as f64).collect();
+        let (_, p) = mann_whitney(&low, &high);
+        assert!(p < 0.001);
+
+        let (_, p) = mann_whitney(&low, &low);
+        assert!(p > 0.9);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-3);
+    }
+}
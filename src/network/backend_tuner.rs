@@ -1,14 +1,17 @@
 //! Backend tuner for iwd and wpa_supplicant
 //!
-//! Applies optimizations specific to the active Wi-Fi backend.
+//! Applies optimizations specific to the active Wi-Fi backend, and keeps its background
+//! scans down at runtime for the scan-abort task (see `scan_abort`).
 
 use anyhow::Result;
 use log::{info, debug, warn};
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 use std::path::Path;
 use std::process::Command;
 
+use crate::config::structs::Config;
+use crate::network::mac_privacy::MacRandomization;
+
 /// Detected Wi-Fi backend
 #[derive(Debug, Clone, PartialEq)]
 pub enum WifiBackend {
@@ -17,10 +20,282 @@ pub enum WifiBackend {
     Unknown,
 }
 
+const IWD_CONF_PATH: &str = "/etc/iwd/main.conf";
+
+/// Our settings in the iwd config sit between these lines, one block per section
+const BLOCK_BEGIN: &str = "# BEGIN hifi-wifi (managed, removed by revert)";
+const BLOCK_END: &str = "# END hifi-wifi";
+
+/// /etc/iwd/main.conf as versions before the managed blocks wrote it, when there was none
+const LEGACY_IWD_CONF: &str = "[General]
+# Use control port over nl80211 for better performance
+ControlPortOverNL80211=true
+
+# Roaming thresholds (dBm)
+RoamThreshold=-75
+RoamThreshold5G=-80
+
+# Randomize MAC per network for privacy
+AddressRandomization=network
+
+# Enable Management Frame Protection
+ManagementFrameProtection=1
+
+[Scan]
+# Disable periodic scanning when connected (reduces latency spikes)
+DisablePeriodicScan=true
+
+[Rank]
+# Prefer 5GHz and 6GHz bands
+BandModifier2_4GHz=1.0
+BandModifier5GHz=2.0
+BandModifier6GHz=3.0
+";
+/// ...and what they appended to a config without a [Scan] section
+const LEGACY_IWD_SCAN: &str = "\n[Scan]\nDisablePeriodicScan=true\n";
+
+/// `content` with the lines an older version wrote without managed blocks taken out, so
+/// they don't count as the user's. None when there are none (or blocks already).
+fn strip_legacy(content: &str) -> Option<String> {
+    if content.lines().any(|l| l.trim() == BLOCK_BEGIN) {
+        return None;
+    }
+    if let Some(user) = content.strip_suffix(LEGACY_IWD_SCAN) {
+        return Some(user.to_string());
+    }
+    // The whole file was ours; lines the user changed or added since stay theirs
+    let header = LEGACY_IWD_CONF.lines().nth(1)?;
+    if !content.lines().any(|l| l.trim() == header) {
+        return None;
+    }
+    let ours: std::collections::HashSet<&str> = LEGACY_IWD_CONF.lines()
+        .filter(|l| !l.is_empty() && ini_section(l).is_none())
+        .chain(["DisablePeriodicScan=false"])
+        .collect();
+    let kept: Vec<&str> = content.lines().filter(|l| !ours.contains(l.trim())).collect();
+
+    // Sections left empty go, and so do the blank lines that separated what was removed
+    let mut out = String::new();
+    let mut last = "";
+    for (i, line) in kept.iter().enumerate() {
+        let empty_section = ini_section(line).is_some()
+            && kept[i + 1..].iter().take_while(|l| ini_section(l).is_none()).all(|l| l.trim().is_empty());
+        let extra_blank = line.trim().is_empty()
+            && (out.is_empty() || last.trim().is_empty() || ini_section(last).is_some());
+        if !empty_section && !extra_blank {
+            out.push_str(line);
+            out.push('\n');
+            last = line;
+        }
+    }
+    Some(out)
+}
+
+/// `content` without our managed blocks
+fn strip_managed(content: &str) -> String {
+    let mut out = String::new();
+    let mut inside = false;
+    for line in content.lines() {
+        match line.trim() {
+            BLOCK_BEGIN => inside = true,
+            BLOCK_END => inside = false,
+            _ if !inside => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Section and key of an INI line, None for comments and blank lines
+fn ini_key(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.starts_with('#') || line.starts_with(';') {
+        return None;
+    }
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+/// Value of `key` in `section` outside our managed blocks
+fn user_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let base = strip_managed(content);
+    let mut current = "";
+    for line in base.lines() {
+        if let Some(name) = ini_section(line) {
+            current = name;
+        } else if current == section && ini_key(line) == Some(key) {
+            return line.split_once('=').map(|(_, value)| value.trim().to_string());
+        }
+    }
+    None
+}
+
+/// Section name of a `[Section]` header line
+fn ini_section(line: &str) -> Option<&str> {
+    line.trim().strip_prefix('[')?.strip_suffix(']')
+}
+
+/// Put `settings` into `content` as a managed block at the end of each section, replacing
+/// the blocks from last time. Keys the user set outside a block are theirs and left alone.
+fn merge_managed(content: &str, settings: &[(&str, &str, String)]) -> String {
+    let base = strip_managed(content);
+
+    // Keys the user set, per section
+    let mut user_keys = std::collections::HashSet::new();
+    let mut section = "";
+    for line in base.lines() {
+        if let Some(name) = ini_section(line) {
+            section = name;
+        } else if let Some(key) = ini_key(line) {
+            user_keys.insert((section, key));
+        }
+    }
+
+    // Block lines per section, in first-seen order
+    let mut blocks: Vec<(&str, Vec<String>)> = Vec::new();
+    for (section, key, value) in settings {
+        if user_keys.contains(&(*section, *key)) {
+            continue;
+        }
+        let line = format!("{}={}", key, value);
+        match blocks.iter_mut().find(|(name, _)| name == section) {
+            Some((_, lines)) => lines.push(line),
+            None => blocks.push((section, vec![line])),
+        }
+    }
+    /// `header` for sections we add, so revert takes them out again
+    fn take_block(blocks: &mut Vec<(&str, Vec<String>)>, section: &str, header: bool, out: &mut String) {
+        if let Some(i) = blocks.iter().position(|(name, _)| *name == section) {
+            let (_, lines) = blocks.remove(i);
+            out.push_str(BLOCK_BEGIN);
+            out.push('\n');
+            if header {
+                out.push_str(&format!("[{}]\n", section));
+            }
+            for line in lines {
+                out.push_str(&line);
+                out.push('\n');
+            }
+            out.push_str(BLOCK_END);
+            out.push('\n');
+        }
+    }
+
+    // Each block goes after the last line of its section, before trailing blank lines
+    let mut out = String::new();
+    let mut blank = String::new();
+    let mut section = String::new();
+    for line in base.lines() {
+        if line.trim().is_empty() {
+            blank.push('\n');
+            continue;
+        }
+        if let Some(name) = ini_section(line) {
+            take_block(&mut blocks, &section, false, &mut out);
+            section = name.to_string();
+        }
+        out.push_str(&blank);
+        blank.clear();
+        out.push_str(line);
+        out.push('\n');
+    }
+    take_block(&mut blocks, &section, false, &mut out);
+    out.push_str(&blank);
+
+    // Sections the file doesn't have yet
+    while let Some(&(name, _)) = blocks.first() {
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+        take_block(&mut blocks, name, true, &mut out);
+    }
+    out
+}
+
+/// iwd keys that come from our config, with the option: the user's own value outside the
+/// blocks wins over it, so `apply` says so when they disagree
+const CONFIG_KEYS: &[(&str, &str, &str)] = &[
+    ("General", "AddressRandomization", "[privacy] mac_randomization"),
+    ("General", "RoamThreshold", "[wifi] roam_threshold_2g"),
+    ("General", "RoamThreshold5G", "[wifi] roam_threshold_5g"),
+];
+
+/// bgscan set while scans are suppressed: one scan an hour, whatever the signal
+const QUIET_BGSCAN: &str = "\"simple:3600:-100:3600\"";
+
+/// How `suppress_scans` kept an interface's background scans down, to undo it later
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanSuppression {
+    /// iwd runs with DisablePeriodicScan; nothing to undo
+    Iwd,
+    /// The bgscan of wpa_supplicant network `network_id` was replaced (previous value, if any)
+    WpaSupplicant { network_id: String, bgscan: Option<String> },
+}
+
+/// Whether an iwd main.conf has `DisablePeriodicScan=true` in its [Scan] section
+fn iwd_scan_disabled(conf: &str) -> bool {
+    let mut section = "";
+    for line in conf.lines().map(str::trim) {
+        if line.starts_with('[') {
+            section = line;
+        } else if section == "[Scan]" {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == "DisablePeriodicScan" {
+                    return value.trim().eq_ignore_ascii_case("true");
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Network id of the current connection from `wpa_cli status`
+fn wpa_network_id(status: &str) -> Option<String> {
+    status.lines().find_map(|l| l.strip_prefix("id=")).map(|id| id.trim().to_string())
+}
+
+/// Interfaces from `wpa_cli interface` ("Selected interface 'x'" / "Available interfaces:" / names)
+fn wpa_interfaces(output: &str) -> Vec<String> {
+    output.lines()
+        .skip_while(|l| !l.starts_with("Available interfaces"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Set `bgscan` on the network `interface` is connected to; false without a
+/// control socket or a connection
+pub fn set_bgscan(interface: &str, bgscan: &str) -> bool {
+    let Some(network_id) = wpa_cli(interface, &["status"]).and_then(|s| wpa_network_id(&s)) else {
+        return false;
+    };
+    let quoted = format!("\"{}\"", bgscan);
+    wpa_cli(interface, &["set_network", &network_id, "bgscan", &quoted]).is_some_and(|reply| reply == "OK")
+}
+
+/// Run `wpa_cli -i <iface>` with `args`; None if it fails or answers FAIL
+fn wpa_cli(interface: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("wpa_cli").arg("-i").arg(interface).args(args).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !stdout.is_empty() && stdout != "FAIL").then_some(stdout)
+}
+
 /// Tunes the active Wi-Fi backend for optimal performance
 pub struct BackendTuner {
     backend: WifiBackend,
     disable_periodic_scan: bool,
+    /// bgscan for wpa_supplicant's current networks (None = leave as is)
+    bgscan: Option<String>,
+    /// Write the iwd config (`iwd_periodic_scan_disable`)
+    iwd_tuning: bool,
+    /// iwd AddressRandomization (`[privacy] mac_randomization`)
+    mac_randomization: MacRandomization,
+    /// iwd RoamThreshold and RoamThreshold5G (`[wifi] roam_threshold_2g/5g`)
+    roam_thresholds: (i32, i32),
 }
 
 impl BackendTuner {
@@ -31,9 +306,28 @@ impl BackendTuner {
         Self {
             backend,
             disable_periodic_scan,
+            bgscan: None,
+            iwd_tuning: true,
+            mac_randomization: MacRandomization::Default,
+            roam_thresholds: (-75, -80),
         }
     }
 
+    /// Tuner for the `[backend]` and `[privacy]` settings
+    pub fn from_config(config: &Config) -> Self {
+        let mut tuner = Self::new(true).with_bgscan(&config.backend.wpa_bgscan);
+        tuner.iwd_tuning = config.backend.iwd_periodic_scan_disable;
+        tuner.mac_randomization = config.privacy.mac_randomization;
+        tuner.roam_thresholds = (config.wifi.roam_threshold_2g, config.wifi.roam_threshold_5g);
+        tuner
+    }
+
+    /// Set `bgscan` (e.g. "simple:30:-70:3600") on wpa_supplicant's current networks
+    pub fn with_bgscan(mut self, bgscan: &str) -> Self {
+        self.bgscan = (!bgscan.is_empty()).then(|| bgscan.to_string());
+        self
+    }
+
     /// Detect the active Wi-Fi backend
     fn detect_backend() -> WifiBackend {
         // Check if iwd is running
@@ -82,10 +376,29 @@ impl BackendTuner {
         &self.backend
     }
 
+    /// Detect the backend again (NetworkManager's wifi.backend may have been switched);
+    /// true if it changed
+    pub fn redetect(&mut self) -> bool {
+        let backend = Self::detect_backend();
+        if backend == self.backend {
+            return false;
+        }
+        info!(event = "backend_changed", from:? = self.backend, to:? = backend;
+              "Wi-Fi backend changed from {:?} to {:?}", self.backend, backend);
+        self.backend = backend;
+        true
+    }
+
+    /// bgscan to keep on wpa_supplicant's networks, if that's the backend and one is set
+    pub fn bgscan(&self) -> Option<&str> {
+        self.bgscan.as_deref().filter(|_| self.backend == WifiBackend::WpaSupplicant)
+    }
+
     /// Apply backend-specific optimizations
     pub fn apply(&self) -> Result<()> {
         match self.backend {
-            WifiBackend::Iwd => self.tune_iwd(),
+            WifiBackend::Iwd if self.iwd_tuning => self.tune_iwd(),
+            WifiBackend::Iwd => Ok(()),
             WifiBackend::WpaSupplicant => self.tune_wpa_supplicant(),
             WifiBackend::Unknown => {
                 debug!("Unknown backend, skipping tuning");
@@ -94,63 +407,69 @@ impl BackendTuner {
         }
     }
 
-    /// Apply iwd-specific optimizations
+    /// The iwd settings we manage, as (section, key, value)
+    fn iwd_settings(&self) -> Vec<(&'static str, &'static str, String)> {
+        let mut settings = vec![
+            // Control port over nl80211 for better performance
+            ("General", "ControlPortOverNL80211", "true".to_string()),
+            // Roaming thresholds (dBm), shared with band steering
+            ("General", "RoamThreshold", self.roam_thresholds.0.to_string()),
+            ("General", "RoamThreshold5G", self.roam_thresholds.1.to_string()),
+            ("General", "ManagementFrameProtection", "1".to_string()),
+            // Periodic scans while connected cause latency spikes
+            ("Scan", "DisablePeriodicScan", self.disable_periodic_scan.to_string()),
+            // Prefer 5GHz and 6GHz bands
+            ("Rank", "BandModifier2_4GHz", "1.0".to_string()),
+            ("Rank", "BandModifier5GHz", "2.0".to_string()),
+            ("Rank", "BandModifier6GHz", "3.0".to_string()),
+        ];
+        if let Some(value) = self.mac_randomization.iwd_value() {
+            settings.push(("General", "AddressRandomization", value.to_string()));
+        }
+        settings
+    }
+
+    /// Apply iwd-specific optimizations: merge our settings into /etc/iwd/main.conf
     fn tune_iwd(&self) -> Result<()> {
         info!("Applying iwd optimizations...");
 
-        let iwd_conf_dir = Path::new("/etc/iwd");
-        let iwd_conf_path = iwd_conf_dir.join("main.conf");
-
-        // Don't overwrite existing config
-        if iwd_conf_path.exists() {
-            info!("Existing /etc/iwd/main.conf found, checking for updates...");
-            return self.update_iwd_config(&iwd_conf_path);
+        let iwd_conf_path = Path::new(IWD_CONF_PATH);
+        let current = fs::read_to_string(iwd_conf_path).unwrap_or_default();
+        let base = strip_legacy(&current).unwrap_or_else(|| current.clone());
+        if base != current {
+            info!("Moving settings an older hifi-wifi wrote to {} into managed blocks", IWD_CONF_PATH);
+        }
+        let settings = self.iwd_settings();
+        for (section, key, option) in CONFIG_KEYS {
+            let Some((_, _, ours)) = settings.iter().find(|(s, k, _)| s == section && k == key) else { continue };
+            if let Some(theirs) = user_value(&base, section, key).filter(|v| v != ours) {
+                warn!("{} has {}={} outside the hifi-wifi block, which overrides {} ({}); remove that line to use the config",
+                      IWD_CONF_PATH, key, theirs, option, ours);
+            }
+        }
+        let merged = merge_managed(&base, &settings);
+        if merged == current {
+            debug!("{} already up to date", IWD_CONF_PATH);
+            return Ok(());
         }
 
         // Create directory (may fail on read-only filesystem)
-        if let Err(e) = fs::create_dir_all(iwd_conf_dir) {
+        if let Err(e) = iwd_conf_path.parent().map_or(Ok(()), fs::create_dir_all) {
             warn!("Could not create /etc/iwd directory (Read-only filesystem?): {}", e);
             warn!("iwd optimizations will NOT be applied.");
             return Ok(());
         }
 
-        let config = format!(r#"[General]
-# Use control port over nl80211 for better performance
-ControlPortOverNL80211=true
-
-# Roaming thresholds (dBm)
-RoamThreshold=-75
-RoamThreshold5G=-80
-
-# Randomize MAC per network for privacy
-AddressRandomization=network
-
-# Enable Management Frame Protection
-ManagementFrameProtection=1
-
-[Scan]
-# Disable periodic scanning when connected (reduces latency spikes)
-DisablePeriodicScan={}
-
-[Rank]
-# Prefer 5GHz and 6GHz bands
-BandModifier2_4GHz=1.0
-BandModifier5GHz=2.0
-BandModifier6GHz=3.0
-"#, self.disable_periodic_scan);
-
-        match File::create(&iwd_conf_path) {
-            Ok(mut file) => {
-                file.write_all(config.as_bytes())?;
-                info!("Created optimized /etc/iwd/main.conf");
-
-                // Restart iwd to apply changes
+        match fs::write(iwd_conf_path, &merged) {
+            Ok(()) => {
+                info!("Updated hifi-wifi settings in {}", IWD_CONF_PATH);
+                // iwd reads its config at startup only
                 let _ = Command::new("systemctl")
                     .args(["restart", "iwd.service"])
                     .output();
             }
             Err(e) => {
-                warn!("Could not create iwd config (Read-only filesystem?): {}", e);
+                warn!("Could not write iwd config (Read-only filesystem?): {}", e);
                 warn!("iwd optimizations will NOT be applied.");
             }
         }
@@ -158,59 +477,86 @@ BandModifier6GHz=3.0
         Ok(())
     }
 
-    /// Update existing iwd config without full overwrite
-    fn update_iwd_config(&self, path: &Path) -> Result<()> {
-        let content = match fs::read_to_string(path) {
-            Ok(c) => c,
-            Err(e) => {
-                warn!("Could not read iwd config: {}", e);
-                return Ok(());
-            }
+    /// Apply wpa_supplicant optimizations: bgscan on the current networks
+    fn tune_wpa_supplicant(&self) -> Result<()> {
+        let Some(bgscan) = &self.bgscan else {
+            info!("wpa_supplicant backend detected - using NetworkManager defaults");
+            return Ok(());
         };
-        
-        // Check if DisablePeriodicScan is already set
-        if content.contains("DisablePeriodicScan") {
-            debug!("DisablePeriodicScan already configured in iwd");
+        // NetworkManager has no bgscan connection setting, so it goes through the
+        // control interface and lasts until the network is re-added on reconnect
+        // (the service sets it again then)
+        let output = Command::new("wpa_cli").arg("interface").output();
+        let interfaces = output.map(|o| wpa_interfaces(&String::from_utf8_lossy(&o.stdout))).unwrap_or_default();
+        if interfaces.is_empty() {
+            warn!("wpa_supplicant has no control socket (NetworkManager may only use D-Bus) - bgscan not tuned");
             return Ok(());
         }
-
-        // Append Scan section if missing
-        if !content.contains("[Scan]") && self.disable_periodic_scan {
-            match fs::OpenOptions::new().append(true).open(path) {
-                Ok(mut file) => {
-                    let _ = writeln!(file, "\n[Scan]");
-                    let _ = writeln!(file, "DisablePeriodicScan=true");
-                    info!("Added DisablePeriodicScan to existing iwd config");
-                }
-                Err(e) => {
-                    warn!("Could not update iwd config (Read-only filesystem?): {}", e);
-                }
+        for interface in interfaces {
+            if set_bgscan(&interface, bgscan) {
+                info!("Set wpa_supplicant bgscan {} on {}", bgscan, interface);
+            } else {
+                debug!("Could not set bgscan on {} (not connected?)", interface);
             }
         }
-
         Ok(())
     }
 
-    /// Apply wpa_supplicant optimizations (minimal, as NM handles most)
-    fn tune_wpa_supplicant(&self) -> Result<()> {
-        info!("wpa_supplicant backend detected - using NetworkManager defaults");
-        // wpa_supplicant is typically managed by NetworkManager
-        // Most optimizations are handled via nmcli connection settings
-        Ok(())
+    /// Keep background scans on `interface` down through the backend itself: iwd's
+    /// DisablePeriodicScan (read at startup only, so it must already be in main.conf),
+    /// or a near-idle bgscan on wpa_supplicant's current network over its control
+    /// interface. None when neither applies and scans have to be aborted instead.
+    pub fn suppress_scans(&self, interface: &str) -> Option<ScanSuppression> {
+        match self.backend {
+            WifiBackend::Iwd => {
+                let conf = fs::read_to_string(IWD_CONF_PATH).unwrap_or_default();
+                iwd_scan_disabled(&conf).then_some(ScanSuppression::Iwd)
+            }
+            WifiBackend::WpaSupplicant => {
+                // Fails without a control socket (NetworkManager may only use D-Bus)
+                let network_id = wpa_network_id(&wpa_cli(interface, &["status"])?)?;
+                let bgscan = wpa_cli(interface, &["get_network", &network_id, "bgscan"]);
+                wpa_cli(interface, &["set_network", &network_id, "bgscan", QUIET_BGSCAN])
+                    .filter(|reply| reply == "OK")?;
+                Some(ScanSuppression::WpaSupplicant { network_id, bgscan })
+            }
+            WifiBackend::Unknown => None,
+        }
+    }
+
+    /// Undo `suppress_scans`
+    pub fn restore_scans(&self, interface: &str, suppression: &ScanSuppression) {
+        if let ScanSuppression::WpaSupplicant { network_id, bgscan } = suppression {
+            // Without a previous value ours stays until the network is re-added on reconnect
+            let Some(bgscan) = bgscan else { return };
+            if wpa_cli(interface, &["set_network", network_id, "bgscan", bgscan]).is_none() {
+                debug!("Could not restore bgscan on {}", interface);
+            }
+        }
     }
 
-    /// Revert backend tuning
+    /// Revert backend tuning: take our block out of the iwd config, keep everything else
     pub fn revert(&self) -> Result<()> {
         info!("Reverting backend tuning...");
 
-        // Only remove config files we created (check for our marker comment)
-        let iwd_conf = Path::new("/etc/iwd/main.conf");
-        if iwd_conf.exists() {
-            if let Ok(content) = fs::read_to_string(iwd_conf) {
-                if content.contains("ControlPortOverNL80211") {
-                    warn!("Not removing /etc/iwd/main.conf - may contain user customizations");
-                }
+        let iwd_conf_path = Path::new(IWD_CONF_PATH);
+        let Ok(current) = fs::read_to_string(iwd_conf_path) else { return Ok(()) };
+        // An older version's settings have no block around them
+        let stripped = strip_legacy(&current).unwrap_or_else(|| strip_managed(&current));
+        if stripped == current {
+            return Ok(());
+        }
+        let result = if stripped.trim().is_empty() {
+            fs::remove_file(iwd_conf_path)
+        } else {
+            fs::write(iwd_conf_path, &stripped)
+        };
+        match result {
+            Ok(()) => {
+                info!("Removed hifi-wifi settings from {}", IWD_CONF_PATH);
+                let _ = Command::new("systemctl").args(["try-restart", "iwd.service"]).output();
             }
+            Err(e) => warn!("Could not update {}: {}", IWD_CONF_PATH, e),
         }
 
         Ok(())
@@ -222,3 +568,64 @@ impl Default for BackendTuner {
         Self::new(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iwd_merge() {
+        let settings = vec![
+            ("General", "RoamThreshold", "-75".to_string()),
+            ("Scan", "DisablePeriodicScan", "true".to_string()),
+            ("Rank", "BandModifier5GHz", "2.0".to_string()),
+        ];
+        let user = "# my settings\n[General]\nEnableNetworkConfiguration=true\n\n[Rank]\nBandModifier5GHz=1.5\n";
+        let merged = merge_managed(user, &settings);
+        assert_eq!(merged, format!(
+            "# my settings\n[General]\nEnableNetworkConfiguration=true\n{b}\nRoamThreshold=-75\n{e}\n\n\
+             [Rank]\nBandModifier5GHz=1.5\n\n{b}\n[Scan]\nDisablePeriodicScan=true\n{e}\n",
+            b = BLOCK_BEGIN, e = BLOCK_END));
+        assert!(iwd_scan_disabled(&merged));
+        assert_eq!(user_value(&merged, "Rank", "BandModifier5GHz").as_deref(), Some("1.5"));
+        assert_eq!(user_value(&merged, "General", "RoamThreshold"), None);
+
+        // Idempotent, and revert gives back the user's file
+        assert_eq!(merge_managed(&merged, &settings), merged);
+        assert_eq!(strip_managed(&merged).trim_end(), user.trim_end());
+        assert!(strip_managed(&merge_managed("", &settings)).trim().is_empty());
+    }
+
+    #[test]
+    fn test_iwd_legacy_upgrade() {
+        let settings = vec![("General", "RoamThreshold", "-75".to_string())];
+
+        // Untouched file from an older version: all ours, converted to blocks
+        assert_eq!(strip_legacy(LEGACY_IWD_CONF).as_deref(), Some(""));
+        let merged = merge_managed(&strip_legacy(LEGACY_IWD_CONF).unwrap(), &settings);
+        assert!(merged.starts_with(BLOCK_BEGIN) && strip_managed(&merged).is_empty());
+        assert_eq!(strip_legacy(&merged), None);
+
+        // Edited since: the user's lines stay theirs
+        let edited = LEGACY_IWD_CONF.replace("RoamThreshold=-75", "RoamThreshold=-70")
+            .replace("[Rank]", "[Network]\nEnableIPv6=true\n\n[Rank]");
+        assert_eq!(strip_legacy(&edited).as_deref(), Some("[General]\nRoamThreshold=-70\n\n[Network]\nEnableIPv6=true\n\n"));
+
+        // [Scan] appended to the user's own file
+        let appended = format!("[General]\nEnableNetworkConfiguration=true\n{}", LEGACY_IWD_SCAN);
+        assert_eq!(strip_legacy(&appended).as_deref(), Some("[General]\nEnableNetworkConfiguration=true\n"));
+        assert_eq!(strip_legacy("[General]\nRoamThreshold=-75\n"), None);
+    }
+
+    #[test]
+    fn test_scan_config_parsing() {
+        assert!(iwd_scan_disabled("[General]\nRoamThreshold=-75\n\n[Scan]\nDisablePeriodicScan=true\n"));
+        assert!(!iwd_scan_disabled("[Scan]\nDisablePeriodicScan=false\n"));
+        assert!(!iwd_scan_disabled("[General]\nDisablePeriodicScan=true\n"));
+        assert_eq!(wpa_network_id("bssid=aa:bb:cc:dd:ee:ff\nfreq=5180\nssid=home\nid=2\nmode=station\n"),
+                   Some("2".to_string()));
+        assert_eq!(wpa_network_id("wpa_state=DISCONNECTED\n"), None);
+        assert_eq!(wpa_interfaces("Selected interface 'wlan0'\nAvailable interfaces:\nwlan0\np2p-dev-wlan0\n"),
+                   vec!["wlan0".to_string(), "p2p-dev-wlan0".to_string()]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Bufferbloat grade per connection session
+//!
+//! Compares gateway RTT while the link is idle with RTT while it is busy (the latency
+//! monitor keeps probing during downloads), and folds in the share of packets CAKE had
+//! to drop. The result is one A-F letter per session: logged as a `bufferbloat_grade`
+//! event when the session ends so grades can be compared across config changes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::network::tc::CakeStats;
+
+/// RTT samples kept per state (one per tick)
+const MAX_SAMPLES: usize = 900;
+/// Minimum samples in each state before a grade is given
+const MIN_SAMPLES: usize = 5;
+/// Below this the link counts as idle (Mbit/s)
+const IDLE_MBIT: u32 = 1;
+/// Without CAKE, the link counts as loaded above this (Mbit/s)
+const LOADED_MBIT: u32 = 20;
+/// Replies per tick used for one RTT sample (5Hz probing, ~2s ticks)
+const RTT_PER_TICK: usize = 10;
+
+/// Grade of one session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferbloatReport {
+    pub grade: String,
+    /// Median RTT while idle / loaded (ms)
+    pub idle_ms: f64,
+    pub loaded_ms: f64,
+    /// CAKE drops as a share of packets sent this session
+    pub drop_pct: f64,
+    pub loaded_samples: u32,
+}
+
+impl BufferbloatReport {
+    /// Added latency under load (ms)
+    pub fn increase_ms(&self) -> f64 {
+        (self.loaded_ms - self.idle_ms).max(0.0)
+    }
+}
+
+/// Letter for `increase_ms` of added latency under load; heavy CAKE drops cost one letter
+pub fn grade(increase_ms: f64, drop_pct: f64) -> &'static str {
+    const GRADES: [&str; 5] = ["A", "B", "C", "D", "F"];
+    let mut index = match increase_ms {
+        i if i < 10.0 => 0,
+        i if i < 30.0 => 1,
+        i if i < 60.0 => 2,
+        i if i < 200.0 => 3,
+        _ => 4,
+    };
+    if drop_pct >= 2.0 {
+        index += 1;
+    }
+    GRADES[index.min(4)]
+}
+
+/// Idle (false), loaded (true), or neither for a tick with `throughput_mbit` of traffic
+///
+/// Loaded means at least half the shaped bandwidth is in use, which is where a
+/// bloated queue starts adding delay.
+pub fn load_state(throughput_mbit: u32, cake_mbit: Option<u32>) -> Option<bool> {
+    let loaded_mbit = cake_mbit.map_or(LOADED_MBIT, |c| (c / 2).max(IDLE_MBIT + 1));
+    if throughput_mbit < IDLE_MBIT {
+        Some(false)
+    } else if throughput_mbit >= loaded_mbit {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Median of the latest replies from the latency monitor (None if all were lost)
+pub fn recent_rtt(samples: &[Option<f64>]) -> Option<f64> {
+    let recent: VecDeque<f64> = samples.iter().rev().take(RTT_PER_TICK).filter_map(|r| *r).collect();
+    (!recent.is_empty()).then(|| median(&recent))
+}
+
+fn median(samples: &VecDeque<f64>) -> f64 {
+    let mut sorted: Vec<f64> = samples.iter().copied().collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    sorted[sorted.len() / 2]
+}
+
+/// Idle/loaded RTT and CAKE counters since the session began
+#[derive(Debug, Default)]
+pub struct BufferbloatTracker {
+    idle: VecDeque<f64>,
+    loaded: VecDeque<f64>,
+    /// (sent, dropped) when the session started and at the latest tick
+    cake_start: Option<(u64, u64)>,
+    cake_last: Option<(u64, u64)>,
+}
+
+impl BufferbloatTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one RTT sample taken while the link was busy (`loaded`) or not
+    pub fn record_rtt(&mut self, rtt_ms: f64, loaded: bool) {
+        let samples = if loaded { &mut self.loaded } else { &mut self.idle };
+        samples.push_back(rtt_ms);
+        if samples.len() > MAX_SAMPLES {
+            samples.pop_front();
+        }
+    }
+
+    /// Update the CAKE counters; a reset qdisc (counters went down) starts over
+    pub fn record_cake(&mut self, stats: &CakeStats) {
+        let now = (stats.sent_packets, stats.dropped);
+        match self.cake_start {
+            Some(start) if now.0 >= start.0 && now.1 >= start.1 => {}
+            _ => self.cake_start = Some(now),
+        }
+        self.cake_last = Some(now);
+    }
+
+    /// Grade so far (None until both idle and loaded RTT have been seen)
+    pub fn report(&self) -> Option<BufferbloatReport> {
+        if self.idle.len() < MIN_SAMPLES || self.loaded.len() < MIN_SAMPLES {
+            return None;
+        }
+        let idle_ms = median(&self.idle);
+        let loaded_ms = median(&self.loaded);
+        let drop_pct = match (self.cake_start, self.cake_last) {
+            (Some((sent0, dropped0)), Some((sent, dropped))) if sent > sent0 => {
+                (dropped - dropped0) as f64 * 100.0 / (sent - sent0 + dropped - dropped0) as f64
+            }
+            _ => 0.0,
+        };
+        Some(BufferbloatReport {
+            grade: grade((loaded_ms - idle_ms).max(0.0), drop_pct).to_string(),
+            idle_ms,
+            loaded_ms,
+            drop_pct,
+            loaded_samples: self.loaded.len() as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bufferbloat_grade() {
+        assert_eq!(grade(4.0, 0.0), "A");
+        assert_eq!(grade(4.0, 3.0), "B");
+        assert_eq!(grade(45.0, 0.0), "C");
+        assert_eq!(grade(500.0, 5.0), "F");
+        assert_eq!(load_state(0, Some(300)), Some(false));
+        assert_eq!(load_state(100, Some(300)), None);
+        assert_eq!(load_state(160, Some(300)), Some(true));
+        assert_eq!(recent_rtt(&[Some(1.0), None, Some(3.0), Some(2.0)]), Some(2.0));
+
+        let mut tracker = BufferbloatTracker::new();
+        for _ in 0..5 {
+            tracker.record_rtt(4.0, false);
+        }
+        assert!(tracker.report().is_none());
+        for rtt in [20.0, 25.0, 22.0, 90.0, 24.0] {
+            tracker.record_rtt(rtt, true);
+        }
+        tracker.record_cake(&CakeStats { sent_packets: 1000, dropped: 10, ..Default::default() });
+        tracker.record_cake(&CakeStats { sent_packets: 1990, dropped: 20, ..Default::default() });
+        let report = tracker.report().unwrap();
+        assert_eq!(report.loaded_ms, 24.0);
+        assert_eq!(report.increase_ms(), 20.0);
+        assert!((report.drop_pct - 1.0).abs() < 1e-9);
+        assert_eq!(report.grade, "B");
+    }
+}
@@ -0,0 +1,196 @@
+//! DNS resolver latency and runtime resolver override
+//!
+//! A slow or half-broken resolver shows up as a long pause before a stream starts,
+//! which is easy to blame on Wi-Fi. The Governor times a query against each resolver
+//! NetworkManager configured for the link every few minutes and publishes the result
+//! in the metrics file for `status`. With `[wifi] dns_servers` set, those servers are
+//! applied to the active connection at runtime (the saved profile is left alone).
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Name queried for timing (popular enough to be cached by any working resolver)
+const PROBE_NAME: &str = "example.com";
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+const QUERIES_PER_SERVER: usize = 3;
+/// Interfaces whose resolvers we overrode (runtime only, gone on reconnect)
+const OVERRIDE_STATE: &str = "/run/hifi-wifi/dns-override.json";
+
+/// Latency of one resolver
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolverLatency {
+    pub server: String,
+    /// Median of the answered queries (None = no answer at all)
+    pub latency_ms: Option<f64>,
+    /// Slower than `[wifi] dns_slow_ms`
+    pub slow: bool,
+}
+
+/// `IP4.DNS[n]` / `IP6.DNS[n]` values of `nmcli -t -f IP4.DNS,IP6.DNS device show <iface>`
+///
+/// Link-local IPv6 resolvers are skipped: they need a scope id we don't get here.
+pub fn parse_nm_dns(output: &str) -> Vec<IpAddr> {
+    output.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| key.starts_with("IP4.DNS") || key.starts_with("IP6.DNS"))
+        .filter_map(|(_, value)| value.replace("\\:", ":").trim().parse().ok())
+        .filter(|ip: &IpAddr| !matches!(ip, IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80))
+        .collect()
+}
+
+/// Resolvers NetworkManager configured for `interface`
+pub fn configured_servers(interface: &str) -> Vec<IpAddr> {
+    Command::new("nmcli")
+        .args(["-t", "-f", "IP4.DNS,IP6.DNS", "device", "show", interface])
+        .output()
+        .map(|o| parse_nm_dns(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or_default()
+}
+
+/// Recursive A query for `name` with transaction `id`
+pub fn build_query(id: u16, name: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(32);
+    packet.extend_from_slice(&id.to_be_bytes());
+    // RD set; one question, no other records
+    packet.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    // QTYPE A, QCLASS IN
+    packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x01]);
+    packet
+}
+
+/// Whether `reply` answers query `id` (NOERROR or NXDOMAIN; SERVFAIL/REFUSED don't count)
+pub fn is_answer(reply: &[u8], id: u16) -> bool {
+    reply.len() >= 12
+        && reply[0..2] == id.to_be_bytes()
+        && reply[2] & 0x80 != 0
+        && matches!(reply[3] & 0x0f, 0 | 3)
+}
+
+/// Time one query to `server` (None on timeout or error reply)
+fn query_once(server: IpAddr, id: u16) -> Option<Duration> {
+    let bind: SocketAddr = match server {
+        IpAddr::V4(_) => "0.0.0.0:0".parse().ok()?,
+        IpAddr::V6(_) => "[::]:0".parse().ok()?,
+    };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.connect(SocketAddr::new(server, 53)).ok()?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT)).ok()?;
+
+    let start = Instant::now();
+    socket.send(&build_query(id, PROBE_NAME)).ok()?;
+    let mut buf = [0u8; 512];
+    while start.elapsed() < QUERY_TIMEOUT {
+        let len = socket.recv(&mut buf).ok()?;
+        if is_answer(&buf[..len], id) {
+            return Some(start.elapsed());
+        }
+    }
+    None
+}
+
+/// Median latency of a few queries to `server` (blocking, up to ~6s for a dead resolver)
+pub fn measure(server: IpAddr, slow_ms: f64) -> ResolverLatency {
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u16)
+        .unwrap_or(0);
+    let mut answered: Vec<f64> = (0..QUERIES_PER_SERVER)
+        .filter_map(|i| query_once(server, seed.wrapping_add(i as u16)))
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .collect();
+    answered.sort_by(|a, b| a.total_cmp(b));
+    let latency_ms = answered.get(answered.len() / 2).copied();
+    ResolverLatency {
+        server: server.to_string(),
+        latency_ms,
+        slow: latency_ms.is_some_and(|ms| ms > slow_ms),
+    }
+}
+
+/// Measure every resolver configured for `interface`
+pub fn probe(interface: &str, slow_ms: f64) -> Vec<ResolverLatency> {
+    configured_servers(interface).into_iter().map(|server| measure(server, slow_ms)).collect()
+}
+
+/// Parse `[wifi] dns_servers`, warning about entries that aren't IP addresses
+pub fn parse_servers(servers: &[String]) -> Vec<IpAddr> {
+    servers.iter()
+        .filter_map(|s| match s.trim().parse() {
+            Ok(ip) => Some(ip),
+            Err(_) => {
+                warn!("Ignoring dns_servers entry '{}': not an IP address", s);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Remember that `interface` runs with overridden resolvers
+pub fn record_override(interface: &str) -> Result<()> {
+    let mut interfaces = overridden();
+    if !interfaces.iter().any(|i| i == interface) {
+        interfaces.push(interface.to_string());
+    }
+    fs::create_dir_all("/run/hifi-wifi")?;
+    fs::write(OVERRIDE_STATE, serde_json::to_string(&interfaces)?).context("Failed to write DNS override state")?;
+    Ok(())
+}
+
+fn overridden() -> Vec<String> {
+    fs::read_to_string(OVERRIDE_STATE)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+/// Put the profile's own resolvers back on every interface we overrode
+pub fn revert() {
+    for interface in overridden() {
+        let ok = Command::new("nmcli")
+            .args(["device", "reapply", &interface])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        if ok {
+            info!("Restored profile DNS servers on {}", interface);
+        } else {
+            warn!("Could not reapply the profile on {} (disconnected?)", interface);
+        }
+    }
+    let _ = fs::remove_file(OVERRIDE_STATE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nm_dns_and_query() {
+        let out = "IP4.DNS[1]:192.168.1.1\nIP4.DNS[2]:1.1.1.1\nIP6.DNS[1]:fe80\\:\\:1\nIP6.DNS[2]:2606\\:4700\\:4700\\:\\:1111\n";
+        let servers: Vec<String> = parse_nm_dns(out).iter().map(|s| s.to_string()).collect();
+        assert_eq!(servers, vec!["192.168.1.1", "1.1.1.1", "2606:4700:4700::1111"]);
+
+        let query = build_query(0xbeef, "example.com");
+        assert_eq!(&query[0..2], &[0xbe, 0xef]);
+        assert_eq!(&query[12..25], b"\x07example\x03com\x00");
+        assert_eq!(query.len(), 29);
+
+        let mut reply = query.clone();
+        reply[2] |= 0x80;
+        assert!(is_answer(&reply, 0xbeef));
+        assert!(!is_answer(&reply, 0xbeee));
+        assert!(!is_answer(&query, 0xbeef));
+        reply[3] = 0x82; // SERVFAIL
+        assert!(!is_answer(&reply, 0xbeef));
+    }
+}
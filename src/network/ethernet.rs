@@ -0,0 +1,235 @@
+//! Ethernet link tuning
+//!
+//! For a docked Deck or a Sunshine host on a cable, the `ethernet` policy raises the RX
+//! ring to the NIC's maximum (bursts from a stream don't get dropped), keeps the TX ring
+//! short (`ethernet_tx_ring`, less standing queue below CAKE) and turns pause frames off
+//! (a switch pausing the port stalls every packet behind it). It also reports half-duplex
+//! negotiation and a link that keeps going down and up. Original ring and pause settings
+//! are kept in a backup and put back by `revert --qdisc`.
+
+use anyhow::{bail, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const BACKUP_PATH: &str = "/var/lib/hifi-wifi/ethtool.json";
+
+/// `ethtool -g`: hardware maximums and current ring sizes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rings {
+    pub rx_max: u32,
+    pub tx_max: u32,
+    pub rx: u32,
+    pub tx: u32,
+}
+
+/// `ethtool -a`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pause {
+    pub autoneg: bool,
+    pub rx: bool,
+    pub tx: bool,
+}
+
+/// What `ethtool <iface>` says about the negotiated link
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EthLink {
+    pub speed_mbit: Option<u32>,
+    /// None when unknown (no link, or a driver that doesn't say)
+    pub full_duplex: Option<bool>,
+}
+
+fn value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    line.trim().strip_prefix(key).map(str::trim)
+}
+
+pub fn parse_rings(output: &str) -> Option<Rings> {
+    let mut rings = Rings::default();
+    let mut current = false;
+    for line in output.lines() {
+        if line.starts_with("Current hardware settings") {
+            current = true;
+        }
+        let (rx, tx) = if current { (&mut rings.rx, &mut rings.tx) } else { (&mut rings.rx_max, &mut rings.tx_max) };
+        if let Some(v) = value(line, "RX:") {
+            *rx = v.parse().ok()?;
+        } else if let Some(v) = value(line, "TX:") {
+            *tx = v.parse().ok()?;
+        }
+    }
+    (current && rings.rx_max > 0).then_some(rings)
+}
+
+pub fn parse_pause(output: &str) -> Option<Pause> {
+    let flag = |key: &str| output.lines().find_map(|l| value(l, key)).map(|v| v == "on");
+    Some(Pause { autoneg: flag("Autonegotiate:")?, rx: flag("RX:")?, tx: flag("TX:")? })
+}
+
+pub fn parse_link(output: &str) -> EthLink {
+    let field = |key: &str| output.lines().find_map(|l| value(l, key));
+    EthLink {
+        speed_mbit: field("Speed:").and_then(|s| s.trim_end_matches("Mb/s").parse().ok()),
+        full_duplex: field("Duplex:").and_then(|d| match d {
+            "Full" => Some(true),
+            "Half" => Some(false),
+            _ => None,
+        }),
+    }
+}
+
+/// `ethtool` output for one wired interface, fetched lazily and kept for the tick
+#[derive(Debug, Default)]
+pub struct EthtoolProbe {
+    interface: String,
+    link: OnceCell<Option<String>>,
+    rings: OnceCell<Option<String>>,
+    pause: OnceCell<Option<String>>,
+    carrier_changes: OnceCell<Option<u64>>,
+}
+
+impl EthtoolProbe {
+    pub fn new(interface: &str) -> Self {
+        Self { interface: interface.to_string(), ..Default::default() }
+    }
+
+    fn query<'a>(&self, cell: &'a OnceCell<Option<String>>, flag: Option<&str>) -> Option<&'a str> {
+        cell.get_or_init(|| ethtool(flag, &self.interface)).as_deref()
+    }
+
+    pub fn link(&self) -> EthLink {
+        self.query(&self.link, None).map(parse_link).unwrap_or_default()
+    }
+
+    pub fn rings(&self) -> Option<Rings> {
+        self.query(&self.rings, Some("-g")).and_then(parse_rings)
+    }
+
+    pub fn pause(&self) -> Option<Pause> {
+        self.query(&self.pause, Some("-a")).and_then(parse_pause)
+    }
+
+    /// Carrier transitions since the device appeared (a flap is two)
+    pub fn carrier_changes(&self) -> Option<u64> {
+        *self.carrier_changes.get_or_init(|| {
+            let path = Path::new("/sys/class/net").join(&self.interface).join("carrier_changes");
+            fs::read_to_string(path).ok()?.trim().parse().ok()
+        })
+    }
+
+    /// A probe that answers with fixed output instead of running `ethtool`
+    #[cfg(test)]
+    pub fn preset(link: &str, rings: Option<&str>, pause: Option<&str>, carrier_changes: u64) -> Self {
+        let cell = |s: Option<&str>| OnceCell::from(s.map(str::to_string));
+        Self {
+            interface: String::new(),
+            link: cell(Some(link)),
+            rings: cell(rings),
+            pause: cell(pause),
+            carrier_changes: OnceCell::from(Some(carrier_changes)),
+        }
+    }
+}
+
+/// Output of `ethtool [flag] <iface>`, None if it fails
+fn ethtool(flag: Option<&str>, interface: &str) -> Option<String> {
+    let output = Command::new("ethtool").args(flag).arg(interface).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn run(args: &[&str]) -> Result<()> {
+    let output = Command::new("ethtool").args(args).output()?;
+    if !output.status.success() {
+        bail!("ethtool {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+/// Settings as they were before our first change
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Original {
+    rings: Option<(u32, u32)>,
+    pause: Option<Pause>,
+}
+
+type Backup = BTreeMap<String, Original>;
+
+fn load_backup() -> Backup {
+    fs::read_to_string(BACKUP_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup(backup: &Backup) -> Result<()> {
+    if let Some(parent) = Path::new(BACKUP_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(BACKUP_PATH, serde_json::to_string_pretty(backup)?)?;
+    Ok(())
+}
+
+pub fn set_rings(interface: &str, rx: u32, tx: u32) -> Result<()> {
+    if let Some(current) = ethtool(Some("-g"), interface).as_deref().and_then(parse_rings) {
+        let mut backup = load_backup();
+        // First change wins, so repeated ticks keep the driver's original
+        backup.entry(interface.to_string()).or_default().rings.get_or_insert((current.rx, current.tx));
+        save_backup(&backup)?;
+    }
+    run(&["-G", interface, "rx", &rx.to_string(), "tx", &tx.to_string()])
+}
+
+pub fn set_pause(interface: &str, enable: bool) -> Result<()> {
+    if let Some(current) = ethtool(Some("-a"), interface).as_deref().and_then(parse_pause) {
+        let mut backup = load_backup();
+        backup.entry(interface.to_string()).or_default().pause.get_or_insert(current);
+        save_backup(&backup)?;
+    }
+    run(&["-A", interface, "autoneg", on_off(enable), "rx", on_off(enable), "tx", on_off(enable)])
+}
+
+/// Put back the ring and pause settings of every interface we changed
+pub fn revert() {
+    for (interface, original) in load_backup() {
+        if let Some((rx, tx)) = original.rings {
+            match run(&["-G", &interface, "rx", &rx.to_string(), "tx", &tx.to_string()]) {
+                Ok(()) => info!("Restored ring sizes on {} (rx {}, tx {})", interface, rx, tx),
+                Err(e) => warn!("Could not restore ring sizes on {} (unplugged?): {}", interface, e),
+            }
+        }
+        if let Some(p) = original.pause {
+            match run(&["-A", &interface, "autoneg", on_off(p.autoneg), "rx", on_off(p.rx), "tx", on_off(p.tx)]) {
+                Ok(()) => info!("Restored pause frame settings on {}", interface),
+                Err(e) => warn!("Could not restore pause frame settings on {} (unplugged?): {}", interface, e),
+            }
+        }
+    }
+    let _ = fs::remove_file(BACKUP_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ethtool() {
+        let rings = "Ring parameters for eth0:\nPre-set maximums:\nRX:\t\t4096\nRX Mini:\tn/a\nRX Jumbo:\tn/a\nTX:\t\t4096\n\
+                     Current hardware settings:\nRX:\t\t256\nRX Mini:\tn/a\nRX Jumbo:\tn/a\nTX:\t\t1024\n";
+        assert_eq!(parse_rings(rings), Some(Rings { rx_max: 4096, tx_max: 4096, rx: 256, tx: 1024 }));
+        assert_eq!(parse_rings("Ring parameters for eth0:\n"), None);
+
+        let pause = "Pause parameters for eth0:\nAutonegotiate:\ton\nRX:\t\ton\nTX:\t\toff\n";
+        assert_eq!(parse_pause(pause), Some(Pause { autoneg: true, rx: true, tx: false }));
+
+        let link = "Settings for eth0:\n\tSpeed: 100Mb/s\n\tDuplex: Half\n\tAuto-negotiation: on\n\tLink detected: yes\n";
+        assert_eq!(parse_link(link), EthLink { speed_mbit: Some(100), full_duplex: Some(false) });
+        assert_eq!(parse_link("\tSpeed: Unknown!\n\tDuplex: Unknown! (255)\n"), EthLink::default());
+    }
+}
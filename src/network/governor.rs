@@ -1,55 +1,88 @@
 //! The Governor - The "Brain" of hifi-wifi
 //!
-//! Per rewrite.md: Runs the async loop (Tick Rate: 2 seconds) and implements:
+//! Per rewrite.md: Runs the async loop (Tick Rate: 2 seconds) and implements
+//! (the decisions live in `policy`, the Governor executes them):
 //! - Breathing CAKE (Dynamic QoS with asymmetric response)
 //! - CPU Governor (Smart Coalescing)
 //! - Smart Band Steering (with Hysteresis)
 //! - Game Mode Detection (PPS) with CAKE freezing
 //! - Connection Event Handling (inotify-based, per roadmap-beta2.md)
+//! - Interface hotplug (NetworkManager DeviceAdded/DeviceRemoved)
+//! - Reconnect assist after unexpected disconnects, beacon-loss tracking
+//! - SIGUSR1 state dump, SIGUSR2 forced re-optimize, control socket requests
 
 use anyhow::Result;
 use log::{info, debug, warn};
+use std::collections::HashSet;
 use std::time::{Duration, Instant};
-use std::process::Command;
 use std::path::Path;
 use std::sync::mpsc::channel;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::time;
 use notify::{Watcher, RecursiveMode, Config as NotifyConfig, RecommendedWatcher, Event, EventKind};
 
-use crate::config::structs::{GovernorConfig, WifiConfig};
-use crate::network::nm::NmClient;
+use crate::config::structs::{
+    CakeMode, Config, GovernorConfig, HooksConfig, Mac80211Config, MetricsConfig, PowerConfig, ScheduleEntry, SystemConfig, WifiConfig,
+};
+use crate::network::latency;
+use crate::network::link_id::{self, InterfaceMap};
+use crate::network::lock;
+use crate::network::mac80211;
+use crate::network::metered;
+use crate::network::bufferbloat::{self, BufferbloatTracker};
+use crate::network::dns::{self, ResolverLatency};
+use crate::network::ethernet::{self, EthtoolProbe};
+use crate::network::history;
+use crate::network::prewarm;
+use crate::network::probe::IwProbe;
+use crate::network::push::MetricsPusher;
+use crate::network::metrics::{LinkMetrics, MetricsSnapshot, METRICS_DIR};
+use crate::network::nm::{Connectivity, NmClient};
+use crate::network::nm_powersave;
+use crate::network::policy::{self, Action, Policy, TickContext, BAND_STEERING_LOG, LINK_FLAP_WINDOW, WIRED_POLICIES};
+use crate::network::power_save::{PowerSaveController, Source as PowerSaveSource};
+use crate::network::reconnect::{self, ReconnectAssist};
+use crate::network::scan_abort::{LinkActivity, ScanAborter};
+use crate::network::backend_tuner::{self, BackendTuner};
+use crate::network::schedule::Scheduler;
+use crate::network::session::SessionRecorder;
 use crate::network::tc::{TcManager, EthtoolManager};
-use crate::network::stats::PpsMonitor;
-use crate::network::wifi::WifiManager;
+use crate::network::usage::UsageTracker;
+use crate::network::stats::{LatencyMonitor, LatencySummary, PpsMonitor};
+use crate::network::stream_guard::{self, StreamGuard};
+use crate::network::state::PersistentState;
+use crate::network::wifi::{LinkStats, WifiInterface, WifiManager, WifiMode};
+use crate::system::budget::BudgetMonitor;
+use crate::system::sleep;
 use crate::system::cpu::CpuMonitor;
+use crate::system::optimizer::SystemOptimizer;
 use crate::system::power::PowerManager;
+use crate::utils::{control, events};
+use crate::utils::hooks::{self, Hook};
 
 /// Path for connection event signaling (touched by NetworkManager dispatcher)
 const CONNECTION_EVENT_PATH: &str = "/run/hifi-wifi/connection-changed";
-
-/// Band steering candidate tracking for hysteresis
-#[derive(Debug, Default)]
-struct RoamCandidate {
-    bssid: String,
-    score: i32,
-    consecutive_ticks: u32,
-}
+/// How often to check whether NetworkManager switched the Wi-Fi backend
+const BACKEND_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+/// Scan interval while in safe mode
+const SAFE_MODE_SCAN_INTERVAL: Duration = Duration::from_secs(120);
+/// Replies a window needs before its loss counts as sustained (~50s at 5Hz)
+const SUSTAINED_LOSS_MIN_SAMPLES: u32 = 250;
+/// Where SIGUSR1 writes the in-memory state
+pub const STATE_DUMP_PATH: &str = "/run/hifi-wifi/state-dump.json";
+/// `[governor]` options the control socket can change at runtime
+pub const SETTABLE_OPTIONS: &[&str] = &[
+    "cake_mode", "cake_overhead_factor", "game_mode_pps_threshold", "game_mode_cooldown_secs",
+    "game_mode_freeze_cake", "roam_hysteresis_ticks", "cpu_coalescing_threshold",
+    "bulk_guard_rate_mbit", "bulk_guard_threshold_mb", "disabled_policies",
+];
 
 /// Per-interface state
 struct InterfaceState {
     pps_monitor: PpsMonitor,
     tc_manager: TcManager,
-    roam_candidate: Option<RoamCandidate>,
-    game_mode_until: Option<Instant>,
-    coalescing_enabled: bool,
-    coalescing_stable_ticks: u32,
-    pending_coalescing: Option<bool>,
-    power_save_enabled: Option<bool>,
-    power_save_stable_ticks: u32,
-    pending_power_save: Option<bool>,
-    eee_enabled: Option<bool>,
-    eee_stable_ticks: u32,
-    pending_eee: Option<bool>,
+    /// Game mode as of the last tick (to detect entering/leaving it)
+    game_mode_active: bool,
     /// Last known bytes for throughput calculation
     last_rx_bytes: u64,
     last_tx_bytes: u64,
@@ -58,35 +91,64 @@ struct InterfaceState {
     bandwidth_valid: bool,
     /// Last known good bitrate (Kbit/s) - used when current reading is garbage (MCS0 probes)
     last_good_bitrate: Option<u32>,
+    /// BSSID we were associated with on the last tick (None = unknown / reconnected)
+    current_bssid: Option<String>,
+    /// Busiest tick of the current association (bytes/s), the node's demonstrated backhaul
+    session_peak_bps: u64,
+    /// Captive portal or limited connectivity: CAKE off, power save at its default
+    safe_mode: bool,
+    /// Last scan requested in safe mode
+    last_safe_scan: Option<Instant>,
+    /// Metered link (phone hotspot): no gateway pings, rare DNS probes, no pre-warming
+    metered: bool,
+    /// Background gateway RTT monitor (restarted when the probe target changes)
+    latency_monitor: Option<LatencyMonitor>,
+    /// A `sustained_loss` event was logged and loss hasn't recovered since
+    loss_alerted: bool,
+    /// Last `iw` beacon-loss count of the AP link
+    beacon_loss: Option<u64>,
+    /// Traffic over the last tick (bytes/s, rx + tx)
+    throughput_bps: u64,
+    /// Idle vs loaded RTT for the current association
+    bufferbloat: BufferbloatTracker,
+    /// Latest resolver timings, filled in by a background probe
+    dns: std::sync::Arc<std::sync::Mutex<Vec<ResolverLatency>>>,
+    dns_probed: Option<Instant>,
 }
 
 impl InterfaceState {
-    fn new(config: &GovernorConfig) -> Self {
+    fn new(config: &GovernorConfig, host_port: Option<u16>, dry_run: bool) -> Self {
+        let tc_manager = TcManager::new(
+            config.cake_median_window,
+            config.cake_change_threshold_mbit,
+            config.cake_change_threshold_pct,
+            config.cake_hysteresis_up,
+            config.cake_hysteresis_down,
+        ).dry_run(dry_run);
         Self {
             pps_monitor: PpsMonitor::new(),
-            tc_manager: TcManager::new(
-                config.cake_median_window,
-                config.cake_change_threshold_mbit,
-                config.cake_change_threshold_pct,
-                config.cake_hysteresis_up,
-                config.cake_hysteresis_down,
-            ),
-            roam_candidate: None,
-            game_mode_until: None,
-            coalescing_enabled: false,
-            coalescing_stable_ticks: 0,
-            pending_coalescing: None,
-            power_save_enabled: None,
-            power_save_stable_ticks: 0,
-            pending_power_save: None,
-            eee_enabled: None,
-            eee_stable_ticks: 0,
-            pending_eee: None,
+            tc_manager: match host_port {
+                Some(port) => tc_manager.for_host(port),
+                None => tc_manager,
+            },
+            game_mode_active: false,
             last_rx_bytes: 0,
             last_tx_bytes: 0,
             last_stats_time: None,
             bandwidth_valid: false,
             last_good_bitrate: None,
+            current_bssid: None,
+            session_peak_bps: 0,
+            safe_mode: false,
+            last_safe_scan: None,
+            metered: false,
+            latency_monitor: None,
+            loss_alerted: false,
+            beacon_loss: None,
+            throughput_bps: 0,
+            bufferbloat: BufferbloatTracker::new(),
+            dns: Default::default(),
+            dns_probed: None,
         }
     }
 }
@@ -95,29 +157,120 @@ impl InterfaceState {
 pub struct Governor {
     config: GovernorConfig,
     wifi_config: WifiConfig,
+    metrics_config: MetricsConfig,
+    power_config: PowerConfig,
+    system_config: SystemConfig,
+    mac80211_config: Mac80211Config,
+    hooks_config: HooksConfig,
+    /// `[[schedule]]` overrides by time of day
+    schedule: Scheduler,
     nm_client: NmClient,
     cpu_monitor: CpuMonitor,
     power_manager: PowerManager,
+    /// Sunshine base port when running as a streaming host (role = "host")
+    host_port: Option<u16>,
     wifi_manager: WifiManager,
-    interface_states: std::collections::HashMap<String, InterfaceState>,
+    /// Packet rate of each wired link the wired policies run on
+    wired_pps: std::collections::HashMap<String, PpsMonitor>,
+    /// Keyed by device identity, so state follows a rename (see `link_id`)
+    interface_states: InterfaceMap<InterfaceState>,
+    /// Learned per-BSSID state, persisted across restarts
+    learned: PersistentState,
+    reconnect: Option<ReconnectAssist>,
+    /// nftables bulk-flow limits while streaming (bulk_guard = true)
+    stream_guard: Option<StreamGuard>,
+    /// The only writer of Wi-Fi power save
+    power_save: PowerSaveController,
+    /// Enabled tick policies, in run order
+    policies: Vec<Box<dyn Policy>>,
+    /// Log decisions instead of carrying them out (`monitor --dry-run`)
+    dry_run: bool,
+    /// Game mode pinned over the control socket (None = detect from traffic)
+    game_mode_override: Option<GameModeOverride>,
+    /// Interfaces pinned by `hifi-wifi lock`, left out of band steering
+    locked: HashSet<String>,
+    /// TCP keepalives set back to kernel defaults for a metered link
+    keepalives_relaxed: bool,
+    /// Bytes per day and per game-mode session (`hifi-wifi usage`)
+    usage: UsageTracker,
+    /// Quality summaries of game-mode sessions (`hifi-wifi sessions`)
+    sessions: SessionRecorder,
+    /// InfluxDB/statsd push of each tick's metrics (`[metrics] push_format`)
+    pusher: Option<MetricsPusher>,
+    /// Exit after this long without control requests or game mode (socket activation)
+    idle_exit: Option<Duration>,
+    /// Backend scan suppression, with a background `iw scan abort` task as fallback (`scan_abort_interval_ms`)
+    scan_abort: Option<ScanAborter>,
+    /// The daemon's own memory and CPU use against the unit's limits (set up by `run`)
+    budget: Option<BudgetMonitor>,
+    /// `[backend]` tuning, re-applied when NetworkManager switches backends; also keeps
+    /// wpa_supplicant's bgscan across reconnects
+    backend: Option<BackendTuner>,
+    /// Last check for a backend switch
+    backend_checked: Instant,
+}
+
+/// `hifi-wifi game-mode on|off [--for]`
+#[derive(Debug, Clone, Copy)]
+struct GameModeOverride {
+    forced: bool,
+    until: Option<Instant>,
 }
 
 impl Governor {
     /// Create a new Governor with the given configuration
-    pub async fn new(config: GovernorConfig, wifi_config: WifiConfig) -> Result<Self> {
+    pub async fn new(
+        config: GovernorConfig,
+        wifi_config: WifiConfig,
+        metrics_config: MetricsConfig,
+        power_config: PowerConfig,
+        system_config: SystemConfig,
+        mac80211_config: Mac80211Config,
+        hooks_config: HooksConfig,
+    ) -> Result<Self> {
         let nm_client = NmClient::new().await?;
         let cpu_monitor = CpuMonitor::new(config.cpu_avg_window_size);
         let power_manager = PowerManager::new();
-        let wifi_manager = WifiManager::new()?;
+        let wifi_manager = WifiManager::new()?.retain_optimizable(wifi_config.optimize_hotspot);
+        let reconnect = wifi_config.auto_reconnect.then(|| ReconnectAssist::new(wifi_config.reconnect_max_attempts));
+        let stream_guard = config.bulk_guard.then(StreamGuard::new);
+        let policies = policy::build(&config, &wifi_config, &power_config);
+        let pusher = metrics_config.enabled.then(|| MetricsPusher::new(&metrics_config)).flatten();
+        let scan_abort = ScanAborter::start(config.scan_abort_interval_ms);
         
         Ok(Self {
             config,
             wifi_config,
+            metrics_config,
+            power_config,
+            system_config,
+            mac80211_config,
+            hooks_config,
+            schedule: Scheduler::new(&[]),
             nm_client,
             cpu_monitor,
             power_manager,
+            host_port: None,
             wifi_manager,
-            interface_states: std::collections::HashMap::new(),
+            wired_pps: std::collections::HashMap::new(),
+            interface_states: InterfaceMap::new(),
+            learned: PersistentState::load(),
+            reconnect,
+            stream_guard,
+            power_save: PowerSaveController::new(),
+            policies,
+            dry_run: false,
+            game_mode_override: None,
+            locked: lock::locked_interfaces(),
+            keepalives_relaxed: false,
+            usage: UsageTracker::default(),
+            sessions: SessionRecorder::default(),
+            pusher,
+            idle_exit: None,
+            scan_abort,
+            budget: None,
+            backend: None,
+            backend_checked: Instant::now(),
         })
     }
 
@@ -141,9 +294,45 @@ impl Governor {
             }
         };
         
+        // NM device signals for adapters plugged in (or out) mid-session
+        let (hotplug_tx, hotplug_rx) = channel();
+        if let Err(e) = self.nm_client.watch_devices(hotplug_tx).await {
+            warn!("Device hotplug watcher failed (new adapters need a restart): {}", e);
+        }
+
+        self.apply_dns_override().await;
+
+        // SIGUSR1: dump state for debugging, SIGUSR2: re-optimize now
+        let mut sigusr1 = signal(SignalKind::user_defined1())?;
+        let mut sigusr2 = signal(SignalKind::user_defined2())?;
+
+        // Control socket (`hifi-wifi ctl`); a dry run leaves it to the real daemon
+        let (control_tx, mut control_rx) = tokio::sync::mpsc::channel::<control::Pending>(8);
+        if !self.dry_run {
+            match control::listen(control_tx.clone()) {
+                Ok(()) => info!("Control socket listening on {}", control::SOCKET_PATH),
+                Err(e) => warn!("Control socket unavailable: {}", e),
+            }
+        }
+
+        // Suspend/resume from logind
+        let (sleep_tx, mut sleep_rx) = tokio::sync::mpsc::channel::<bool>(4);
+        if let Err(e) = sleep::watch(sleep_tx).await {
+            warn!("Sleep watcher failed (ticks continue through suspend): {}", e);
+        }
+
         let mut interval = time::interval(Duration::from_secs(tick_rate_secs));
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        let mut last_busy = Instant::now();
+        self.budget = Some(BudgetMonitor::new(tick_rate_secs));
         
         loop {
+            // Collapse bursts (one adapter can add several NM devices) into one re-detect
+            if hotplug_rx.try_recv().is_ok() {
+                while hotplug_rx.try_recv().is_ok() {}
+                self.handle_device_change().await;
+            }
+
             // Check for connection events (non-blocking)
             while let Ok(event) = event_rx.try_recv() {
                 if let Ok(Event { kind: EventKind::Create(_) | EventKind::Modify(_), .. }) = event {
@@ -152,12 +341,78 @@ impl Governor {
                 }
             }
             
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = sigusr1.recv() => {
+                    self.dump_state();
+                    continue;
+                }
+                _ = sigusr2.recv() => {
+                    info!("Received SIGUSR2 - re-optimizing");
+                    self.reoptimize().await;
+                    continue;
+                }
+                Some((request, reply)) = control_rx.recv() => {
+                    let _ = reply.send(self.handle_control(request).await);
+                    last_busy = Instant::now();
+                    continue;
+                }
+                Some(true) = sleep_rx.recv() => {
+                    self.sleep(&mut sleep_rx, &mut control_rx).await;
+                    interval.reset();
+                    last_busy = Instant::now();
+                    continue;
+                }
+            }
             
             if let Err(e) = self.tick().await {
                 warn!("Governor tick error: {}", e);
             }
+
+            // Over the CPU budget: tick less often until it recovers
+            if let Some(secs) = self.budget.as_mut().and_then(BudgetMonitor::sample) {
+                let period = Duration::from_secs(secs);
+                interval = time::interval_at(time::Instant::now() + period, period);
+                interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            }
+
+            if let Some(idle) = self.idle_exit {
+                if self.game_mode_override.is_some() || self.interface_states.values().any(|s| s.game_mode_active) {
+                    last_busy = Instant::now();
+                } else if last_busy.elapsed() >= idle {
+                    info!(event = "idle_exit", idle_secs = idle.as_secs();
+                          "Idle for {}s - exiting until the next control request", idle.as_secs());
+                    self.stop();
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Between logind's PrepareForSleep(true) and (false): no ticks and no gateway pings,
+    /// control requests are still answered. Re-optimizes on wake like after a reconnect.
+    async fn sleep(&mut self, sleep_rx: &mut tokio::sync::mpsc::Receiver<bool>,
+                   control_rx: &mut tokio::sync::mpsc::Receiver<control::Pending>) {
+        info!(event = "sleep"; "System going to sleep - pausing");
+        for state in self.interface_states.values_mut() {
+            state.latency_monitor = None;
+        }
+        if let Some(aborter) = &mut self.scan_abort {
+            aborter.update(&[]);
+        }
+        let asleep = Instant::now();
+        loop {
+            tokio::select! {
+                // Woken, or the watcher is gone
+                start = sleep_rx.recv() => if start != Some(true) { break },
+                Some((request, reply)) = control_rx.recv() => {
+                    let _ = reply.send(self.handle_control(request).await);
+                }
+            }
         }
+        info!(event = "wake", slept_secs = asleep.elapsed().as_secs();
+              "Woke after {}s - re-optimizing", asleep.elapsed().as_secs());
+        self.handle_connection_event().await;
     }
 
     /// Setup inotify watcher for connection events
@@ -190,6 +445,10 @@ impl Governor {
     /// Handle a connection event (WiFi reconnect)
     /// Per roadmap-beta2.md: Clear cache, wait for link stability, re-optimize
     async fn handle_connection_event(&mut self) {
+        // The backend may have set up a new network without our scan settings
+        if let Some(aborter) = &mut self.scan_abort {
+            aborter.reset();
+        }
         // Clear all cached bitrates - they're stale after reconnection
         for (interface, state) in &mut self.interface_states {
             if state.last_good_bitrate.is_some() {
@@ -198,529 +457,1158 @@ impl Governor {
             }
             state.last_good_bitrate = None;
             state.bandwidth_valid = false;
+            // DHCP may have handed out different resolvers
+            state.dns_probed = None;
+            // Forget the association so a known BSSID gets warm-started again
+            Self::record_session_peak(&mut self.learned, state);
+            if state.current_bssid.take().is_some() {
+                Self::finish_bufferbloat_session(interface, state, self.dry_run);
+            }
+        }
+        
+        // `lock` reconnects, so this is where a new lock is noticed
+        self.locked = lock::locked_interfaces();
+
+        // A new profile may come with NM's own powersave=3, which would undo ours
+        if self.dry_run {
+            debug!("[DRY-RUN] Skipping NM powersave check");
+        } else if let Err(e) = nm_powersave::deconflict(&self.nm_client, self.power_config.nm_powersave_override).await {
+            debug!("NM powersave check failed: {}", e);
         }
         
         // Wait 1 second for link to stabilize (per legacy dispatcher behavior)
         info!("Waiting 1s for link to stabilize...");
         tokio::time::sleep(Duration::from_secs(1)).await;
+
+        // Before pre-warming, so the stream host is resolved through the chosen servers
+        self.apply_dns_override().await;
+
+        // A reconnect is when a switched backend shows
+        self.check_backend();
+
+        // wpa_supplicant re-adds the network on reconnect, with NetworkManager's bgscan
+        if let Some(bgscan) = self.backend.as_ref().and_then(|b| b.bgscan()) {
+            for ifc in self.wifi_manager.interfaces() {
+                if self.dry_run {
+                    info!("[DRY-RUN] Would set bgscan {} on {}", bgscan, ifc.name);
+                } else if backend_tuner::set_bgscan(&ifc.name, bgscan) {
+                    debug!("Set bgscan {} on {}", bgscan, ifc.name);
+                }
+            }
+        }
+
+        // Resolve the gateway's neighbor entry and the stream host's DNS before the stream needs them
+        if self.wifi_config.prewarm {
+            for ifc in self.wifi_manager.interfaces() {
+                if self.interface_states.get(&ifc.name).is_some_and(|s| s.metered) {
+                    continue;
+                }
+                let interface = ifc.name.clone();
+                let stream_host = self.wifi_config.stream_host.clone();
+                let dry_run = self.dry_run;
+                tokio::task::spawn_blocking(move || prewarm::prime(&interface, stream_host.as_deref(), dry_run));
+            }
+        }
         
         // Force immediate tick to apply fresh optimizations
         if let Err(e) = self.tick().await {
             warn!("Post-reconnect tick error: {}", e);
         }
+        let names: Vec<&str> = self.wifi_manager.interfaces().iter().map(|i| i.name.as_str()).collect();
+        self.run_hook(Hook::Reconnect, vec![("HIFI_WIFI_INTERFACES", names.join(" "))]);
         
         info!("Post-reconnect optimization complete");
     }
 
+    /// Point connected Wi-Fi devices at `[wifi] dns_servers` (no-op when unset)
+    async fn apply_dns_override(&self) {
+        let servers = dns::parse_servers(&self.wifi_config.dns_servers);
+        if servers.is_empty() {
+            return;
+        }
+        let devices = match self.nm_client.get_wireless_devices().await {
+            Ok(devices) => devices,
+            Err(e) => {
+                warn!("DNS override skipped: {}", e);
+                return;
+            }
+        };
+        let managed: Vec<&str> = self.wifi_manager.interfaces().iter().map(|i| i.name.as_str()).collect();
+        for device in devices.iter().filter(|d| d.active_ap.is_some() && managed.contains(&d.interface.as_str())) {
+            if self.dry_run {
+                info!("[DRY-RUN] Would use DNS servers {:?} on {}", self.wifi_config.dns_servers, device.interface);
+                continue;
+            }
+            match self.nm_client.set_runtime_dns(&device.path, &servers).await {
+                Ok(()) => {
+                    info!(event = "dns_override", interface = device.interface.as_str();
+                          "Using DNS servers {:?} on {}", self.wifi_config.dns_servers, device.interface);
+                    if let Err(e) = dns::record_override(&device.interface) {
+                        debug!("{}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to set DNS servers on {}: {}", device.interface, e),
+            }
+        }
+    }
+
+    /// Re-detect interfaces after NetworkManager added or removed a device
+    /// Optimizes new adapters the way `apply` does at startup and drops state for removed ones
+    async fn handle_device_change(&mut self) {
+        // Give udev a moment to finish renaming the new interface
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let wifi_manager = match WifiManager::new_quiet() {
+            Ok(m) => m.retain_optimizable(self.wifi_config.optimize_hotspot),
+            Err(e) => {
+                warn!("Interface re-detection failed: {}", e);
+                return;
+            }
+        };
+
+        let known: Vec<String> = self.wifi_manager.interfaces().iter().map(|i| i.name.clone()).collect();
+        let current: Vec<String> = wifi_manager.interfaces().iter().map(|i| i.name.clone()).collect();
+        let renamed = self.relink_interfaces();
+
+        for ifc in wifi_manager.interfaces().iter().filter(|i| !known.contains(&i.name)) {
+            if !renamed.iter().any(|(_, new)| *new == ifc.name) {
+                info!(event = "interface_added", interface = ifc.name.as_str();
+                      "New interface {} (driver: {}, category: {:?})", ifc.name, ifc.driver, ifc.category);
+                Self::record_event(self.dry_run, "interface_added", Some(&ifc.name), serde_json::json!({ "driver": ifc.driver }));
+                self.interface_states.insert(ifc.name.clone(), InterfaceState::new(&self.config, self.host_port, self.dry_run));
+            }
+            // A re-enumerated adapter lost its driver and IRQ tuning along with the old name
+            self.optimize_device(ifc);
+        }
+
+        for name in known.iter().filter(|n| !current.contains(n) && !renamed.iter().any(|(old, _)| old == *n)) {
+            info!(event = "interface_removed", interface = name.as_str(); "Interface {} removed", name);
+            Self::record_event(self.dry_run, "interface_removed", Some(name), serde_json::json!({}));
+            self.drop_interface(name);
+        }
+
+        self.wifi_manager = wifi_manager;
+    }
+
+    /// Per-device tuning, as `apply` does it (sysctl is global and applied once)
+    fn optimize_device(&self, ifc: &WifiInterface) {
+        if self.dry_run {
+            info!("[DRY-RUN] Would optimize {} (IRQ affinity, driver and mac80211 tuning)", ifc.name);
+            return;
+        }
+        let sys_opt = SystemOptimizer::new(
+            false,
+            self.system_config.irq_affinity_enabled,
+            self.system_config.driver_tweaks_enabled,
+        );
+        if let Err(e) = sys_opt.apply(std::slice::from_ref(ifc)) {
+            warn!("Failed to optimize {}: {}", ifc.name, e);
+        }
+        if ifc.interface_type == crate::network::wifi::InterfaceType::Wifi {
+            if let Err(e) = mac80211::apply(ifc, &self.mac80211_config) {
+                warn!("Failed to tune mac80211 queues on {}: {}", ifc.name, e);
+            }
+        }
+    }
+
+    /// SIGUSR2: everything a reconnect does, then push device tuning, CAKE and
+    /// power save out again even where nothing changed
+    async fn reoptimize(&mut self) {
+        self.handle_connection_event().await;
+        for ifc in self.wifi_manager.interfaces() {
+            self.optimize_device(ifc);
+            if let Err(e) = self.power_save.apply_now(&self.wifi_manager, ifc) {
+                warn!("Failed to re-apply power save on {}: {}", ifc.name, e);
+            }
+        }
+        for (interface, state) in &mut self.interface_states {
+            if state.tc_manager.last_applied().is_some() && Self::cake_wanted(self.config.cake_mode, state) {
+                if let Err(e) = state.tc_manager.apply_cake(interface) {
+                    warn!("Failed to re-apply CAKE on {}: {}", interface, e);
+                }
+            }
+        }
+        info!("Re-optimization complete");
+    }
+
+    /// In-memory state (SIGUSR1 dump, control socket `status`)
+    fn state_json(&self) -> serde_json::Value {
+        let interfaces: serde_json::Map<String, serde_json::Value> = self.interface_states.iter()
+            .map(|(name, state)| {
+                let policies: serde_json::Map<String, serde_json::Value> = self.policies.iter()
+                    .filter_map(|p| p.dump(name).map(|d| (p.name().to_string(), d)))
+                    .collect();
+                (name.clone(), serde_json::json!({
+                    "link_id": self.interface_states.id(name).map(|id| id.to_string()),
+                    "game_mode_active": state.game_mode_active,
+                    "bandwidth_valid": state.bandwidth_valid,
+                    "last_good_bitrate_kbit": state.last_good_bitrate,
+                    "current_bssid": state.current_bssid,
+                    "beacon_loss": state.beacon_loss,
+                    "throughput_bps": state.throughput_bps,
+                    "safe_mode": state.safe_mode,
+                    "metered": state.metered,
+                    "latency_target": state.latency_monitor.as_ref().map(|m| m.target()),
+                    "tc": state.tc_manager.dump(),
+                    "power_save": self.power_save.dump(name),
+                    "policies": policies,
+                }))
+            })
+            .collect();
+        serde_json::json!({
+            "dry_run": self.dry_run,
+            "host_port": self.host_port,
+            "game_mode_override": self.game_mode_override.map(|o| serde_json::json!({
+                "forced": o.forced,
+                "remaining_secs": o.until.map(|until| until.saturating_duration_since(Instant::now()).as_secs()),
+            })),
+            "schedule": self.schedule.effect().active,
+            "locked": self.locked,
+            "policies": self.policies.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            "managed": self.wifi_manager.interfaces().iter().map(|i| i.name.as_str()).collect::<Vec<_>>(),
+            "interfaces": interfaces,
+        })
+    }
+
+    /// SIGUSR1: log the in-memory state and write it to `STATE_DUMP_PATH`
+    fn dump_state(&self) {
+        let dump = self.state_json();
+        info!("State dump (SIGUSR1): {}", dump);
+        match serde_json::to_string_pretty(&dump) {
+            Ok(text) => match std::fs::write(STATE_DUMP_PATH, text) {
+                Ok(()) => info!("State written to {}", STATE_DUMP_PATH),
+                Err(e) => warn!("Failed to write {}: {}", STATE_DUMP_PATH, e),
+            },
+            Err(e) => warn!("Failed to serialize state: {}", e),
+        }
+    }
+
+    /// Answer a control socket request
+    async fn handle_control(&mut self, request: control::Request) -> control::Response {
+        match request {
+            control::Request::Status => control::Response::ok(Some(self.state_json())),
+            control::Request::Reapply => {
+                info!("Re-optimizing (control socket)");
+                self.reoptimize().await;
+                control::Response::ok(None)
+            }
+            control::Request::SetOption { key, value } => match self.set_option(&key, value) {
+                Ok(()) => control::Response::ok(None),
+                Err(e) => control::Response::error(e.to_string()),
+            },
+            control::Request::GameMode { mode, for_secs } => {
+                if !self.policies.iter().any(|p| p.name() == "game_mode") {
+                    return control::Response::error("the game_mode policy is disabled");
+                }
+                info!(event = "game_mode_override", mode:? = mode, for_secs:? = for_secs;
+                      "Game mode override: {:?}{}", mode, for_secs.map(|s| format!(" for {}s", s)).unwrap_or_default());
+                self.game_mode_override = mode.forced().map(|forced| GameModeOverride {
+                    forced,
+                    until: for_secs.map(|secs| Instant::now() + Duration::from_secs(secs)),
+                });
+                control::Response::ok(None)
+            }
+        }
+    }
+
+    /// Rebuild the policies from config plus the active schedule entries (policies read their
+    /// settings when built and pick up from the interface state)
+    fn rebuild_policies(&mut self) {
+        let mut config = self.config.clone();
+        config.disabled_policies.extend(self.schedule.effect().disabled_policies.iter().cloned());
+        self.policies = policy::build(&config, &self.wifi_config, &self.power_config);
+    }
+
+    /// Change a `[governor]` option at runtime (not written back to the config file)
+    fn set_option(&mut self, key: &str, value: serde_json::Value) -> Result<()> {
+        let c = &mut self.config;
+        match key {
+            "cake_mode" => c.cake_mode = serde_json::from_value(value)?,
+            "cake_overhead_factor" => c.cake_overhead_factor = serde_json::from_value(value)?,
+            "game_mode_pps_threshold" => c.game_mode_pps_threshold = serde_json::from_value(value)?,
+            "game_mode_cooldown_secs" => c.game_mode_cooldown_secs = serde_json::from_value(value)?,
+            "game_mode_freeze_cake" => c.game_mode_freeze_cake = serde_json::from_value(value)?,
+            "roam_hysteresis_ticks" => c.roam_hysteresis_ticks = serde_json::from_value(value)?,
+            "cpu_coalescing_threshold" => c.cpu_coalescing_threshold = serde_json::from_value(value)?,
+            "bulk_guard_rate_mbit" => c.bulk_guard_rate_mbit = serde_json::from_value(value)?,
+            "bulk_guard_threshold_mb" => c.bulk_guard_threshold_mb = serde_json::from_value(value)?,
+            "disabled_policies" => c.disabled_policies = serde_json::from_value(value)?,
+            _ => anyhow::bail!("'{}' can't be changed at runtime (settable: {})", key, SETTABLE_OPTIONS.join(", ")),
+        }
+        info!("Option {} changed (control socket)", key);
+        self.rebuild_policies();
+        // A new cake_mode takes effect now rather than on the next bandwidth change
+        if key == "cake_mode" {
+            for (interface, state) in &mut self.interface_states {
+                if Self::cake_wanted(self.config.cake_mode, state) {
+                    let _ = state.tc_manager.apply_cake(interface);
+                } else if state.tc_manager.last_applied().is_some() {
+                    let _ = state.tc_manager.remove_cake(interface);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget an interface; if it still exists (no longer managed), optionally take our qdisc off it
+    fn drop_interface(&mut self, name: &str) {
+        // Dropping the state also stops its latency monitor
+        let Some(mut state) = self.interface_states.remove(name) else { return };
+        self.power_save.forget(name);
+        self.usage.end_session(name);
+        self.sessions.end(name);
+        for policy in &mut self.policies {
+            policy.forget(name);
+        }
+        if self.config.release_qdisc_on_remove && Path::new("/sys/class/net").join(name).exists() {
+            if let Err(e) = state.tc_manager.remove_cake(name) {
+                debug!("Failed to remove CAKE from {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Move state whose interface got a new name (udev rename, USB re-enumeration) over
+    /// to it; returns (old name, new name) for each move
+    fn relink_interfaces(&mut self) -> Vec<(String, String)> {
+        if self.interface_states.keys().all(|name| Path::new("/sys/class/net").join(name).exists()) {
+            return Vec::new();
+        }
+        let renamed = self.interface_states.relink(&link_id::present());
+        for (old, new) in &renamed {
+            info!(event = "interface_renamed", interface = new.as_str(), from = old.as_str();
+                  "Interface {} is now {}, keeping its state", old, new);
+            Self::record_event(self.dry_run, "interface_renamed", Some(new), serde_json::json!({ "from": old }));
+            // Name-keyed helpers start over under the new name
+            self.power_save.forget(old);
+            self.usage.end_session(old);
+            self.sessions.end(old);
+            for policy in &mut self.policies {
+                policy.forget(old);
+            }
+            let Some(state) = self.interface_states.get_mut(new) else { continue };
+            // The monitor pings out of the old name; a re-enumerated device also lost its qdisc
+            state.latency_monitor = None;
+            if state.tc_manager.last_applied().is_some() && Self::cake_wanted(self.config.cake_mode, state) {
+                if let Err(e) = state.tc_manager.apply_cake(new) {
+                    warn!("Failed to re-apply CAKE on {}: {}", new, e);
+                }
+            }
+        }
+        renamed
+    }
+
+    /// Follow renamed interfaces, drop state for ones that no longer exist (unplugged)
+    fn reconcile_interfaces(&mut self) {
+        self.relink_interfaces();
+        let stale: Vec<String> = self.interface_states.keys()
+            .filter(|name| !Path::new("/sys/class/net").join(name).exists())
+            .cloned()
+            .collect();
+        for name in stale {
+            debug!("Dropping state for vanished interface {}", name);
+            self.drop_interface(&name);
+        }
+    }
+
     /// Single tick of the governor loop
     async fn tick(&mut self) -> Result<()> {
+        self.reconcile_interfaces();
+        if self.game_mode_override.is_some_and(|o| o.until.is_some_and(|until| Instant::now() >= until)) {
+            info!("Game mode override expired, back to automatic detection");
+            self.game_mode_override = None;
+        }
+        if self.schedule.update() {
+            self.rebuild_policies();
+        }
+
+        // Power source once per tick; policies hear about a change before they evaluate
+        if let Some(source) = self.power_manager.refresh() {
+            Self::record_event(self.dry_run, "power_source", None, serde_json::json!({ "source": format!("{:?}", source) }));
+            for policy in &mut self.policies {
+                policy.power_source_changed(source);
+            }
+        }
+
         // 1. Sample CPU load
         let cpu_load = self.cpu_monitor.sample();
         debug!("Tick: CPU load {:.1}%", cpu_load * 100.0);
 
         // 2. Get wireless devices from NetworkManager
         let devices = self.nm_client.get_wireless_devices().await?;
+        if let Some(assist) = &mut self.reconnect {
+            assist.update(&self.nm_client, &devices).await;
+        }
         
         // Collect device info we need
         let device_infos: Vec<_> = devices.into_iter()
             .filter(|d| d.state == crate::network::nm::DeviceState::Activated)
-            .map(|d| (d.interface.clone(), d.path.clone(), d.bitrate, d.active_ap.clone()))
+            .map(|d| (d.interface.clone(), d.path.clone(), d.bitrate, d.active_ap.clone(), d.connectivity, d.metered))
             .collect();
 
-        for (interface, path, bitrate, active_ap) in device_infos {
-            info!("Processing interface: {}, active_ap: {:?}, band_steering_enabled: {}", 
+        let mut link_metrics = Vec::new();
+
+        let mut scan_links = Vec::new();
+        for (interface, path, bitrate, active_ap, connectivity, nm_metered) in device_infos {
+            // `iw` output shared by everything below, each command run at most once
+            let iw = IwProbe::new(&interface);
+
+            // NM shows a hotspot as an activated device too; its mode can change at runtime
+            let mode = iw.info().and_then(WifiMode::parse).unwrap_or(WifiMode::Station);
+            if !mode.is_optimizable(self.wifi_config.optimize_hotspot) {
+                debug!("Skipping {} (hotspot/P2P mode)", interface);
+                continue;
+            }
+            debug!("Processing interface: {}, active_ap: {:?}, band_steering_enabled: {}", 
                   interface, active_ap.as_ref().map(|ap| &ap.bssid), self.config.band_steering_enabled);
             
             // Get or create interface state
             if !self.interface_states.contains_key(&interface) {
                 self.interface_states.insert(
                     interface.clone(), 
-                    InterfaceState::new(&self.config)
+                    InterfaceState::new(&self.config, self.host_port, self.dry_run)
                 );
             }
 
-            // 3. Game Mode Detection (PPS) - with CAKE freezing
-            if self.config.game_mode_enabled {
-                let pps_threshold = self.config.game_mode_pps_threshold;
-                let cooldown_secs = self.config.game_mode_cooldown_secs;
-                let freeze_cake = self.config.game_mode_freeze_cake;
-                
-                if let Some(state) = self.interface_states.get_mut(&interface) {
-                    let pps = state.pps_monitor.sample(&interface);
-                    let was_in_game = state.game_mode_until
-                        .map(|until| Instant::now() < until)
-                        .unwrap_or(false);
-                    
-                    if pps > pps_threshold {
-                        let cooldown = Duration::from_secs(cooldown_secs);
-                        state.game_mode_until = Some(Instant::now() + cooldown);
-                        
-                        // Freeze CAKE when entering game mode
-                        if freeze_cake && !was_in_game {
-                            state.tc_manager.enter_game_mode();
-                            info!("Game mode ACTIVATED: {} PPS on {} (CAKE frozen)", pps, interface);
-                        } else {
-                            debug!("Game mode extended: {} PPS on {}", pps, interface);
+            // Hotel/café networks: stand down until the connectivity check passes
+            let restricted = self.wifi_config.public_safe_mode && connectivity.restricted();
+            if self.interface_states.get(&interface).is_some_and(|s| s.safe_mode != restricted) {
+                self.set_safe_mode(&interface, restricted, connectivity);
+            }
+            if restricted {
+                self.safe_mode_scan(&interface, &path).await;
+            }
+
+            // Phone hotspot: keep the daemon's own traffic down
+            let metered = self.wifi_config.metered.resolve(nm_metered);
+            if let Some(state) = self.interface_states.get_mut(&interface).filter(|s| s.metered != metered) {
+                state.metered = metered;
+                info!(event = "metered", interface = interface.as_str(), new = metered;
+                      "{} is {}: {}", interface, if metered { "metered" } else { "no longer metered" },
+                      if metered { "pausing gateway pings, fewer DNS probes" } else { "resuming probes" });
+                if metered {
+                    state.latency_monitor = None;
+                }
+            }
+
+            // (Re)association with a known BSSID: start CAKE at its historical bandwidth
+            // instead of warming up from the conservative default
+            if let (Some(ap), Some(state)) = (&active_ap, self.interface_states.get_mut(&interface)) {
+                if state.current_bssid.as_deref() != Some(ap.bssid.as_str()) {
+                    if state.current_bssid.is_some() {
+                        Self::finish_bufferbloat_session(&interface, state, self.dry_run);
+                        self.sessions.record_roam(&interface);
+                    }
+                    Self::record_session_peak(&mut self.learned, state);
+                    state.current_bssid = Some(ap.bssid.clone());
+                    if let Some(link) = self.learned.link(&ap.bssid) {
+                        if state.last_good_bitrate.is_none() {
+                            state.last_good_bitrate = link.last_good_bitrate_kbit;
                         }
-                    } else if was_in_game {
-                        // Check if cooldown expired
-                        let still_in_game = state.game_mode_until
-                            .map(|until| Instant::now() < until)
-                            .unwrap_or(false);
-                        
-                        if !still_in_game && freeze_cake {
-                            state.tc_manager.exit_game_mode();
-                            info!("Game mode ENDED on {} (CAKE unfrozen)", interface);
+                        let ceiling = self.learned.ceiling(&ap.ssid).map(|c| c.upload_mbit);
+                        let warm_start = link.warm_start_mbit().map(|m| ceiling.map_or(m, |c| m.min(c)));
+                        if let (true, Some(mbit)) = (self.config.breathing_cake_enabled, warm_start) {
+                            info!(event = "cake_warm_start", interface = interface.as_str(), new = mbit, reason = "known_bssid";
+                                  "Known BSSID {} ({}): starting CAKE at learned {}Mbit on {}",
+                                  ap.bssid, link.ssid, mbit, interface);
+                            state.tc_manager.seed(mbit);
+                            if Self::cake_wanted(self.config.cake_mode, state) && state.tc_manager.apply_cake(&interface).is_ok() {
+                                state.bandwidth_valid = true;
+                            }
                         }
                     }
                 }
             }
 
-            // 4. Breathing CAKE (Dynamic QoS) with throughput monitoring
-            if self.config.breathing_cake_enabled {
-                // Get bitrate from BOTH sources and average for stability
-                let nm_bitrate = bitrate;  // Already in Kbit/s from NetworkManager
-                let iw_bitrate = Self::get_bitrate_from_iw(&interface).unwrap_or(0);
-                
-                // Average both sources if both valid, otherwise use whichever is valid
-                // Reject readings below 20Mbit (lowered for Steam Deck compatibility)
-                // WiFi 4 HT20 MCS7 = 65Mbit, but some devices report lower during idle
-                let min_valid_kbit = 20_000;  // 20 Mbit minimum
-                
-                let nm_valid = nm_bitrate >= min_valid_kbit;
-                let iw_valid = iw_bitrate >= min_valid_kbit;
-                
-                // Debug logging on first tick or when both invalid
-                if !nm_valid && !iw_valid {
-                    debug!("CAKE bitrate check on {}: NM={} Kbit (valid:{}), iw={} Kbit (valid:{})",
-                           interface, nm_bitrate, nm_valid, iw_bitrate, iw_valid);
+            // Beacon loss: the AP went silent for a while, usually right before a disconnect
+            let beacon_loss = iw.station_dump().and_then(reconnect::parse_beacon_loss);
+            if let (Some(count), Some(state)) = (beacon_loss, self.interface_states.get_mut(&interface)) {
+                if let Some(prev) = state.beacon_loss.filter(|&prev| count > prev) {
+                    warn!(event = "beacon_loss", interface = interface.as_str(), old = prev, new = count;
+                          "Beacon loss on {} ({} new, {} total)", interface, count - prev, count);
+                    Self::record_event(self.dry_run, "beacon_loss", Some(&interface), serde_json::json!({ "new": count - prev, "total": count }));
                 }
-                
-                let effective_bitrate = match (nm_valid, iw_valid) {
-                    (true, true) => (nm_bitrate + iw_bitrate) / 2,  // Average both
-                    (true, false) => nm_bitrate,
-                    (false, true) => iw_bitrate,
-                    (false, false) => 0,  // Both invalid - will use last known good
-                };
-                
-                if let Some(state) = self.interface_states.get_mut(&interface) {
-                    // Update throughput estimate from actual traffic
-                    Self::update_throughput_estimate(state, &interface);
-                    
-                    if effective_bitrate > 0 {
-                        // Store as last known good bitrate
-                        state.last_good_bitrate = Some(effective_bitrate);
-                        
-                        // Convert Kbit to Mbit and scale using overhead factor (default 0.85)
-                        let bitrate_mbit = effective_bitrate / 1000;
-                        let scaled_mbit = (bitrate_mbit as f64 * self.config.cake_overhead_factor) as u32;
-                        
-                        debug!("CAKE: NM={}Kbit, iw={}Kbit, effective={}Kbit, scaled={}Mbit",
-                               nm_bitrate, iw_bitrate, effective_bitrate, scaled_mbit);
-                        
-                        if state.tc_manager.update_bandwidth(scaled_mbit) {
-                            let _ = state.tc_manager.apply_cake(&interface);
-                        }
-                        state.bandwidth_valid = true;
-                    } else if let Some(last_good) = state.last_good_bitrate {
-                        // Both sources invalid BUT we have a last known good value - use it
-                        // This handles MCS0 probe frames during idle periods
-                        let bitrate_mbit = last_good / 1000;
-                        let scaled_mbit = (bitrate_mbit as f64 * self.config.cake_overhead_factor) as u32;
-                        
-                        debug!("CAKE: Invalid readings (NM={}, iw={}), using last known good {}Kbit -> {}Mbit",
-                               nm_bitrate, iw_bitrate, last_good, scaled_mbit);
-                        
-                        if state.tc_manager.update_bandwidth(scaled_mbit) {
-                            let _ = state.tc_manager.apply_cake(&interface);
-                        }
-                        state.bandwidth_valid = true;
-                    } else {
-                        // No current OR historical valid bitrate
-                        // Use a conservative default of 100Mbit (safe for most WiFi 5/6 networks)
-                        // This ensures CAKE is enabled even when bitrate detection fails
-                        let default_mbit = 100;
-                        let scaled_mbit = (default_mbit as f64 * self.config.cake_overhead_factor) as u32;
-                        
-                        if !state.bandwidth_valid {
-                            info!("CAKE: No bitrate detected (NM={}, iw={}), using conservative default {}Mbit on {}",
-                                  nm_bitrate, iw_bitrate, default_mbit, interface);
-                        }
-                        
-                        if state.tc_manager.update_bandwidth(scaled_mbit) {
-                            let _ = state.tc_manager.apply_cake(&interface);
-                        }
-                        state.bandwidth_valid = true;
-                    }
+                state.beacon_loss = Some(count);
+            }
+
+            // Traffic since the last tick (CAKE reality check, bufferbloat load detection)
+            let Some(state) = self.interface_states.get_mut(&interface) else { continue };
+            let (rx_bytes, tx_bytes) = Self::update_throughput_estimate(state, &interface);
+            self.usage.add(&interface, rx_bytes, tx_bytes);
+            if let Some(ap) = &active_ap {
+                self.sessions.record_signal(&interface, ap.signal_strength);
+            }
+            let pps = state.pps_monitor.sample(&interface);
+            let (last_good_bitrate_kbit, bandwidth_valid, in_game) =
+                (state.last_good_bitrate, state.bandwidth_valid, state.game_mode_active);
+
+            // 3-6. Policies: game mode, breathing CAKE, coalescing, power save, EEE, band steering
+            let wifi_ifc = self.wifi_manager.interfaces().iter().find(|i| i.name == interface);
+            let ethernet = wifi_ifc.is_some_and(|i| i.interface_type == crate::network::wifi::InterfaceType::Ethernet);
+            let mlo_capable = wifi_ifc.is_some_and(|i| i.category.supports_mlo());
+            let steering = !self.locked.contains(&interface) && self.policies.iter().any(|p| p.name() == "band_steering");
+            let access_points = match &active_ap {
+                Some(_) if steering => self.nm_client.get_access_points(&path).await
+                    .map_err(|e| debug!(target: BAND_STEERING_LOG, "Band steering: Failed to get APs: {}", e))
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            // -100 is `iw` failing to parse, 0 not associated
+            let station_signal_dbm = match (wifi_ifc, &active_ap) {
+                (Some(_), Some(_)) if steering && !ethernet => iw.link()
+                    .map(|link| LinkStats::parse_iw_link(link).signal_dbm)
+                    .filter(|dbm| *dbm > -100 && *dbm < 0),
+                _ => None,
+            };
+            let backhaul_mbit = match &active_ap {
+                Some(current) => access_points.iter()
+                    .filter(|ap| ap.ssid == current.ssid)
+                    .filter_map(|ap| Some((ap.bssid.clone(), self.learned.link(&ap.bssid)?.backhaul_mbit()?)))
+                    .collect(),
+                None => std::collections::HashMap::new(),
+            };
+            let mut ctx = TickContext {
+                interface: &interface,
+                ethernet,
+                mlo_capable,
+                pps,
+                cpu_load,
+                battery_power_save: self.battery_power_save(),
+                host: self.host_port.is_some(),
+                in_game,
+                game_mode_override: self.game_mode_override.map(|o| o.forced).or(self.schedule.effect().game_mode),
+                schedule_power_save: self.schedule.effect().power_save,
+                nm_bitrate_kbit: bitrate,
+                last_good_bitrate_kbit,
+                bandwidth_valid,
+                ceiling_mbit: active_ap.as_ref()
+                    .and_then(|ap| self.learned.ceiling(&ap.ssid))
+                    .map(|c| c.upload_mbit),
+                current_ap: active_ap.as_ref(),
+                station_signal_dbm,
+                access_points: &access_points,
+                backhaul_mbit,
+                iw,
+                ethtool: EthtoolProbe::new(&interface),
+            };
+            let mut policies = std::mem::take(&mut self.policies);
+            for policy in &mut policies {
+                for action in policy.evaluate(&ctx) {
+                    self.execute(&mut ctx, &path, action).await;
+                }
+            }
+            self.policies = policies;
+            if !ethernet && self.interface_states.get(&interface).is_some_and(|s| !s.safe_mode) {
+                scan_links.push(LinkActivity {
+                    interface: interface.clone(),
+                    in_game: ctx.in_game,
+                    power_save: self.power_save.applied(&interface) == Some(true),
+                    pps,
+                });
+            }
+            // Power save requests are in; the controller applies the winner once it's stable
+            if let Some(wifi_ifc) = self.wifi_manager.interfaces().iter().find(|i| i.name == interface) {
+                if let Err(e) = self.power_save.tick(&self.wifi_manager, wifi_ifc) {
+                    debug!("Power save update on {} failed: {}", interface, e);
                 }
             }
 
-            // 5. CPU Governor (Smart Coalescing) - with hysteresis to prevent jitter
-            if self.config.cpu_coalescing_enabled {
-                let threshold = self.config.cpu_coalescing_threshold;
-                let on_battery = self.power_manager.should_enable_power_save();
-                
+            // Gateway latency monitor -> spike events + overlay metrics file
+            if self.metrics_config.enabled {
                 if let Some(state) = self.interface_states.get_mut(&interface) {
-                    let in_game = state.game_mode_until
-                        .map(|until| Instant::now() < until)
-                        .unwrap_or(false);
-                    
-                    let high_cpu = cpu_load > threshold;
-                    let should_coalesce = if in_game && high_cpu {
-                        true
-                    } else if in_game {
-                        false
-                    } else {
-                        true // Idle or battery
-                    };
-
-                    // Hysteresis: require 2 stable ticks before changing coalescing state
-                    if should_coalesce != state.coalescing_enabled {
-                        if state.pending_coalescing == Some(should_coalesce) {
-                            state.coalescing_stable_ticks += 1;
-                        } else {
-                            state.pending_coalescing = Some(should_coalesce);
-                            state.coalescing_stable_ticks = 1;
+                    let target = self.metrics_config.probe_target.clone()
+                        .or_else(|| latency::default_gateway(&interface));
+                    if let Some(target) = target.filter(|_| !state.metered) {
+                        if state.latency_monitor.as_ref().map(|m| m.target()) != Some(target.as_str()) {
+                            state.latency_monitor = LatencyMonitor::start(
+                                &interface,
+                                &target,
+                                self.metrics_config.spike_threshold_ms,
+                                Duration::from_millis(self.metrics_config.spike_min_duration_ms),
+                            ).map_err(|e| debug!("Latency monitor unavailable on {}: {}", interface, e)).ok();
                         }
-                        
-                        // Apply after 2 stable ticks (4 seconds)
-                        if state.coalescing_stable_ticks >= 2 {
-                            if should_coalesce {
-                                let _ = EthtoolManager::enable_coalescing(&interface);
-                                debug!("Coalescing ENABLED on {} (game:{}, cpu:{:.0}%, battery:{})",
-                                       interface, in_game, cpu_load * 100.0, on_battery);
-                            } else {
-                                let _ = EthtoolManager::disable_coalescing(&interface);
-                                debug!("Coalescing DISABLED on {} (game:{}, cpu:{:.0}%)",
-                                       interface, in_game, cpu_load * 100.0);
-                            }
-                            state.coalescing_enabled = should_coalesce;
-                            state.pending_coalescing = None;
-                            state.coalescing_stable_ticks = 0;
+                    }
+
+                    let mut summary = None;
+                    if let Some(monitor) = &state.latency_monitor {
+                        for spike in monitor.take_spikes() {
+                            info!(event = "latency_spike", interface = interface.as_str(),
+                                  duration_ms = spike.duration_ms, lost = spike.lost;
+                                  "Latency spike on {}: {}ms above {}ms (peak {}, {} lost)",
+                                  interface, spike.duration_ms, self.metrics_config.spike_threshold_ms,
+                                  spike.peak_ms.map(|p| format!("{:.0}ms", p)).unwrap_or("n/a".to_string()), spike.lost);
+                            Self::record_event(self.dry_run, "latency_spike", Some(&interface), serde_json::json!({
+                                "target": monitor.target(),
+                                "duration_ms": spike.duration_ms,
+                                "peak_ms": spike.peak_ms,
+                                "lost": spike.lost,
+                                "bssid": active_ap.as_ref().map(|ap| ap.bssid.as_str()),
+                            }));
                         }
-                    } else {
-                        // State matches, reset pending
-                        state.pending_coalescing = None;
-                        state.coalescing_stable_ticks = 0;
+                        summary = Some(monitor.summary()).filter(|s| s.samples > 0);
+                        self.sessions.record_latency(&interface, &monitor.samples());
                     }
+
+                    if let Some(s) = &summary {
+                        Self::check_sustained_loss(&interface, state, s, self.metrics_config.sustained_loss_pct, self.dry_run);
+                    }
+
+                    // Bufferbloat: RTT of this tick, filed as idle or loaded by the traffic seen
+                    let cake_mbit = state.tc_manager.last_applied();
+                    let cake_stats = cake_mbit.and_then(|_| TcManager::stats(&interface));
+                    if let Some(stats) = &cake_stats {
+                        state.bufferbloat.record_cake(stats);
+                    }
+                    let throughput_mbit = (state.throughput_bps * 8 / 1_000_000) as u32;
+                    let latest_rtt = state.latency_monitor.as_ref()
+                        .and_then(|m| bufferbloat::recent_rtt(&m.samples()));
+                    if let (Some(rtt), Some(loaded)) = (latest_rtt, bufferbloat::load_state(throughput_mbit, cake_mbit)) {
+                        state.bufferbloat.record_rtt(rtt, loaded);
+                    }
+
+                    // DNS: time the link's resolvers every few minutes, off the tick
+                    let factor = if state.metered { metered::PROBE_INTERVAL_FACTOR } else { 1 };
+                    let dns_interval = Duration::from_secs(self.wifi_config.dns_probe_interval_secs) * factor;
+                    if !dns_interval.is_zero() && state.dns_probed.is_none_or(|t| t.elapsed() >= dns_interval) {
+                        state.dns_probed = Some(Instant::now());
+                        let results = std::sync::Arc::clone(&state.dns);
+                        let interface = interface.clone();
+                        let slow_ms = self.wifi_config.dns_slow_ms;
+                        tokio::task::spawn_blocking(move || Self::probe_dns(&interface, slow_ms, &results));
+                    }
+                    let dns = state.dns.lock().map(|d| d.clone()).unwrap_or_default();
+
+                    link_metrics.push(LinkMetrics {
+                        interface: interface.clone(),
+                        latency: summary,
+                        signal_dbm: active_ap.as_ref().map(|ap| ap.signal_strength),
+                        cake_mbit,
+                        cake_stats,
+                        bufferbloat: state.bufferbloat.report(),
+                        dns,
+                        aqm: mac80211::read_aqm(&interface).unwrap_or_default(),
+                        throughput_mbit,
+                        game_mode: state.game_mode_active,
+                    });
                 }
             }
+        }
 
-            // 5b. Power Save Management (Adaptive) - with hysteresis to prevent flapping
-            // FIXED: Also disable power save during ANY network activity, not just game mode
-            {
-                let base_should_enable = self.power_manager.should_enable_power_save();
-                
-                if let Some(state) = self.interface_states.get_mut(&interface) {
-                    // Check for active network usage (PPS > 50 = meaningful traffic)
-                    let pps = state.pps_monitor.sample(&interface);
-                    let has_network_activity = pps > 50;
-                    
-                    let in_game = state.game_mode_until
-                        .map(|until| Instant::now() < until)
-                        .unwrap_or(false);
-                    
-                    // Disable power save if:
-                    // 1. On AC power, OR
-                    // 2. Game mode active, OR  
-                    // 3. Any significant network activity (>50 PPS)
-                    let should_enable = base_should_enable && !in_game && !has_network_activity;
-                    
-                    // Hysteresis: require 3 stable ticks before changing power save
-                    // This prevents AC/battery flapping from causing jitter
-                    if state.power_save_enabled != Some(should_enable) {
-                        if state.pending_power_save == Some(should_enable) {
-                            state.power_save_stable_ticks += 1;
-                        } else {
-                            state.pending_power_save = Some(should_enable);
-                            state.power_save_stable_ticks = 1;
-                        }
-                        
-                        // Apply after 3 stable ticks (6 seconds) to avoid brief AC disconnects
-                        if state.power_save_stable_ticks >= 3 {
-                            let wifi_interfaces = self.wifi_manager.interfaces();
-                            if let Some(wifi_ifc) = wifi_interfaces.iter().find(|i| i.name == interface) {
-                                if should_enable {
-                                    if let Ok(_) = self.wifi_manager.enable_power_save(wifi_ifc) {
-                                        info!("Power save ENABLED on {} (battery, idle)", interface);
-                                        state.power_save_enabled = Some(true);
-                                    }
-                                } else {
-                                    if let Ok(_) = self.wifi_manager.disable_power_save(wifi_ifc) {
-                                        let reason = if !base_should_enable { "AC power" }
-                                            else if in_game { "game mode" }
-                                            else { "network activity" };
-                                        info!("Power save DISABLED on {} ({})", interface, reason);
-                                        state.power_save_enabled = Some(false);
-                                    }
-                                }
-                            }
-                            state.pending_power_save = None;
-                            state.power_save_stable_ticks = 0;
-                        }
-                    } else {
-                        // State matches, reset pending
-                        state.pending_power_save = None;
-                        state.power_save_stable_ticks = 0;
-                    }
+        self.tick_wired(cpu_load).await;
+
+        if self.backend_checked.elapsed() >= BACKEND_CHECK_INTERVAL {
+            self.check_backend();
+        }
+        if let Some(aborter) = &mut self.scan_abort {
+            aborter.update(&scan_links);
+        }
+
+        // Keepalives are system-wide: kernel defaults while any managed link is metered
+        let any_metered = self.interface_states.values().any(|s| s.metered);
+        if any_metered != self.keepalives_relaxed && self.system_config.sysctl_enabled && !self.dry_run {
+            metered::set_keepalives(any_metered, &self.system_config.sysctl_settings());
+            self.keepalives_relaxed = any_metered;
+        }
+
+        if self.usage.flush_due() && !self.dry_run {
+            self.usage.flush();
+        }
+        // Learned state survives a crash or an OOM kill, not just a clean stop
+        if !self.dry_run {
+            if let Err(e) = self.learned.save_if_due() {
+                warn!("Failed to persist learned state: {}", e);
+            }
+        }
+
+        // 7. Bulk-flow limits follow the stream (any link in game mode, or Sunshine's ports in use)
+        if let Some(guard) = &mut self.stream_guard {
+            let streaming = self.interface_states.values().any(|s| s.game_mode_active)
+                || stream_guard::sunshine_flow_active(self.host_port.unwrap_or(stream_guard::DEFAULT_SUNSHINE_PORT));
+            guard.update(streaming, self.config.bulk_guard_rate_mbit, self.config.bulk_guard_threshold_mb);
+        }
+
+        // (a dry run leaves the overlay file to a running daemon)
+        if self.metrics_config.enabled && !self.dry_run {
+            let mut snapshot = MetricsSnapshot::new(link_metrics);
+            snapshot.daemon = self.budget.as_ref().map(|b| b.usage().clone());
+            if let Err(e) = snapshot.write(Path::new(METRICS_DIR)) {
+                debug!("Failed to write metrics file: {}", e);
+            }
+            if self.metrics_config.history_max_kb > 0 {
+                if let Err(e) = history::append(&snapshot, self.metrics_config.history_max_kb * 1024) {
+                    debug!("Failed to write {}: {}", history::HISTORY_PATH, e);
+                }
+            }
+            if let Some(pusher) = &mut self.pusher {
+                pusher.push(&snapshot);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wired links aren't NetworkManager Wi-Fi devices, so they get their own pass that
+    /// runs only `WIRED_POLICIES` (EEE, ring sizes, pause frames, duplex and flaps)
+    async fn tick_wired(&mut self, cpu_load: f64) {
+        let wired: Vec<String> = self.wifi_manager.interfaces().iter()
+            .filter(|i| i.interface_type == crate::network::wifi::InterfaceType::Ethernet)
+            .map(|i| i.name.clone())
+            .collect();
+        let policies = &mut self.policies;
+        self.wired_pps.retain(|name, _| {
+            let keep = wired.contains(name);
+            if !keep {
+                for policy in policies.iter_mut() {
+                    policy.forget(name);
                 }
             }
+            keep
+        });
 
-            // 5c. Energy Efficient Ethernet (EEE) Management - Adaptive based on power source
-            // EEE causes 50-200us wakeup latency on ethernet, so disable for gaming/streaming
-            {
-                let base_should_enable = self.power_manager.should_enable_power_save();
-                
-                if let Some(state) = self.interface_states.get_mut(&interface) {
-                    let wifi_interfaces = self.wifi_manager.interfaces();
-                    if let Some(ifc) = wifi_interfaces.iter().find(|i| i.name == interface) {
-                        // Only manage EEE for ethernet interfaces
-                        if ifc.interface_type == crate::network::wifi::InterfaceType::Ethernet {
-                            let pps = state.pps_monitor.sample(&interface);
-                            let has_network_activity = pps > 50;
-                            
-                            let in_game = state.game_mode_until
-                                .map(|until| Instant::now() < until)
-                                .unwrap_or(false);
-                            
-                            // Enable EEE only on battery AND idle (no game, no network activity)
-                            // Otherwise disable for minimum latency
-                            let should_enable = base_should_enable && !in_game && !has_network_activity;
-                            
-                            // Hysteresis: require 3 stable ticks before changing EEE
-                            if state.eee_enabled != Some(should_enable) {
-                                if state.pending_eee == Some(should_enable) {
-                                    state.eee_stable_ticks += 1;
-                                } else {
-                                    state.pending_eee = Some(should_enable);
-                                    state.eee_stable_ticks = 1;
-                                }
-                                
-                                // Apply after 3 stable ticks (6 seconds)
-                                if state.eee_stable_ticks >= 3 {
-                                    if should_enable {
-                                        if let Ok(_) = EthtoolManager::enable_eee(&interface) {
-                                            info!("EEE ENABLED on {} (battery, idle)", interface);
-                                            state.eee_enabled = Some(true);
-                                        }
-                                    } else {
-                                        if let Ok(_) = EthtoolManager::disable_eee(&interface) {
-                                            let reason = if !base_should_enable { "AC power" }
-                                                else if in_game { "game mode" }
-                                                else { "network activity" };
-                                            info!("EEE DISABLED on {} ({})", interface, reason);
-                                            state.eee_enabled = Some(false);
-                                        }
-                                    }
-                                    state.pending_eee = None;
-                                    state.eee_stable_ticks = 0;
-                                }
-                            } else {
-                                // State matches, reset pending
-                                state.pending_eee = None;
-                                state.eee_stable_ticks = 0;
-                            }
-                        }
-                    }
+        let in_game = self.game_mode_override.map(|o| o.forced)
+            .unwrap_or_else(|| self.interface_states.values().any(|s| s.game_mode_active));
+        for interface in wired {
+            let connected = self.wifi_manager.interfaces().iter()
+                .find(|i| i.name == interface)
+                .is_some_and(|i| self.wifi_manager.is_interface_connected(i));
+            if !connected {
+                continue;
+            }
+            let pps = self.wired_pps.entry(interface.clone()).or_default().sample(&interface);
+            let mut ctx = TickContext {
+                interface: &interface,
+                ethernet: true,
+                mlo_capable: false,
+                pps,
+                cpu_load,
+                battery_power_save: self.battery_power_save(),
+                host: self.host_port.is_some(),
+                in_game,
+                game_mode_override: self.game_mode_override.map(|o| o.forced).or(self.schedule.effect().game_mode),
+                schedule_power_save: self.schedule.effect().power_save,
+                nm_bitrate_kbit: 0,
+                last_good_bitrate_kbit: None,
+                bandwidth_valid: false,
+                ceiling_mbit: None,
+                current_ap: None,
+                station_signal_dbm: None,
+                access_points: &[],
+                backhaul_mbit: std::collections::HashMap::new(),
+                iw: IwProbe::default(),
+                ethtool: EthtoolProbe::new(&interface),
+            };
+            let mut policies = std::mem::take(&mut self.policies);
+            for policy in policies.iter_mut().filter(|p| WIRED_POLICIES.contains(&p.name())) {
+                for action in policy.evaluate(&ctx) {
+                    self.execute(&mut ctx, "", action).await;
                 }
             }
+            self.policies = policies;
+        }
+    }
 
-            // 6. Smart Band Steering
-            if self.config.band_steering_enabled {
-                if let Some(current_ap) = &active_ap {
-                    let hysteresis_ticks = self.config.roam_hysteresis_ticks;
-                    
-                    info!("Band steering: Checking for better AP (current: {} on {:?}, score: {})", 
-                           current_ap.bssid, current_ap.band, 
-                           current_ap.score(self.wifi_config.band_bias_5ghz, self.wifi_config.band_bias_6ghz));
-                    
-                    // Get all visible APs
-                    match self.nm_client.get_access_points(&path).await {
-                        Ok(access_points) => {
-                            info!("Band steering: Found {} visible APs (current SSID: '{}')", access_points.len(), current_ap.ssid);
-                            info!("Band steering: access_points is_empty={}, len={}", access_points.is_empty(), access_points.len());
-                            
-                            if access_points.is_empty() {
-                                info!("Band steering: No APs returned from NetworkManager");
-                                continue;
-                            }
-                            
-                            let bias_5 = self.wifi_config.band_bias_5ghz;
-                            let bias_6 = self.wifi_config.band_bias_6ghz;
-                            let min_2g = self.wifi_config.min_signal_2g_dbm;
-                            let min_5g = self.wifi_config.min_signal_5g_dbm;
-                            let min_6g = self.wifi_config.min_signal_6g_dbm;
-
-                            let current_score = current_ap.score(bias_5, bias_6);
-                            
-                            // First, log all APs to see what we have
-                            info!("Band steering: About to list {} APs...", access_points.len());
-                            for i in 0..access_points.len() {
-                                let ap = &access_points[i];
-                                info!("  [{}] AP: {} ({}), band={:?}, signal={}dBm, rate={}Mbps", 
-                                       i, ap.bssid, ap.ssid, ap.band, ap.signal_strength, ap.max_bitrate / 1000);
-                            }
-                            info!("Band steering: Done listing APs");
-                            
-                            // Find best AP with same SSID and usable signal for its band
-                            let best = access_points.iter()
-                                .filter(|ap| {
-                                    let same_ssid = ap.ssid == current_ap.ssid;
-                                    let different_bssid = ap.bssid != current_ap.bssid;
-                                    let signal_ok = ap.signal_usable(min_2g, min_5g, min_6g);
-                                    
-                                    info!("  AP {}: ssid={} (same={}), band={:?}, signal={}dBm (ok={}), max_rate={}Mbps, score={}", 
-                                           ap.bssid, ap.ssid, same_ssid, ap.band, ap.signal_strength, signal_ok,
-                                           ap.max_bitrate / 1000, ap.score(bias_5, bias_6));
-                                    
-                                    same_ssid && different_bssid && signal_ok
-                                })
-                                .max_by_key(|ap| ap.score(bias_5, bias_6));
-
-                        if let Some(state) = self.interface_states.get_mut(&interface) {
-                            if let Some(best_candidate) = best {
-                                let candidate_score = best_candidate.score(bias_5, bias_6);
-                                
-                                if candidate_score > current_score {
-                                    // Update hysteresis
-                                    let should_trigger = if let Some(ref mut roam) = state.roam_candidate {
-                                        if roam.bssid == best_candidate.bssid {
-                                            roam.consecutive_ticks += 1;
-                                            roam.score = candidate_score;
-                                        } else {
-                                            *roam = RoamCandidate {
-                                                bssid: best_candidate.bssid.clone(),
-                                                score: candidate_score,
-                                                consecutive_ticks: 1,
-                                            };
-                                        }
-                                        roam.consecutive_ticks >= hysteresis_ticks
-                                    } else {
-                                        state.roam_candidate = Some(RoamCandidate {
-                                            bssid: best_candidate.bssid.clone(),
-                                            score: candidate_score,
-                                            consecutive_ticks: 1,
-                                        });
-                                        false
-                                    };
-
-                                    if should_trigger {
-                                        info!("Band steering: {} -> {} (score: {} -> {}, band: {:?} -> {:?})",
-                                              current_ap.bssid, best_candidate.bssid, 
-                                              current_score, candidate_score,
-                                              current_ap.band, best_candidate.band);
-                                        
-                                        // Clear cached bitrate - after roaming it will be stale
-                                        state.last_good_bitrate = None;
-                                        state.bandwidth_valid = false;
-                                        
-                                        // Request scan to hint firmware/driver about better AP
-                                        let _ = self.nm_client.request_scan(&path).await;
-                                        state.roam_candidate = None;
-                                    }
-                                } else {
-                                    state.roam_candidate = None;
-                                }
-                            } else {
-                                state.roam_candidate = None;
+    /// Append to the event log; a dry run only logs, the event log belongs to the live daemon
+    fn record_event(dry_run: bool, kind: &str, interface: Option<&str>, data: serde_json::Value) {
+        if !dry_run {
+            events::record(kind, interface, data);
+        }
+    }
+
+    /// Carry out a policy's action; `ctx` is updated so later policies see the result
+    async fn execute(&mut self, ctx: &mut TickContext<'_>, path: &str, action: Action) {
+        let interface = ctx.interface;
+        match action {
+            Action::GameMode { active, pps } => {
+                ctx.in_game = active;
+                let freeze_cake = self.config.game_mode_freeze_cake;
+                let cake_mode = self.config.cake_mode;
+                if let Some(state) = self.interface_states.get_mut(interface) {
+                    if active {
+                        // game_only: shape just for the session, at the bandwidth tracked meanwhile
+                        if cake_mode == CakeMode::GameOnly {
+                            if let Err(e) = state.tc_manager.apply_cake(interface) {
+                                warn!("Failed to install CAKE for game mode on {}: {}", interface, e);
                             }
                         }
+                        // Freeze CAKE when entering game mode
+                        if freeze_cake {
+                            state.tc_manager.enter_game_mode();
+                        }
+                        info!(event = "game_mode", interface = interface, old = false, new = true, pps = pps;
+                              "Game mode ACTIVATED: {} PPS on {}{}", pps, interface, if freeze_cake { " (CAKE frozen)" } else { "" });
+                    } else {
+                        if freeze_cake {
+                            state.tc_manager.exit_game_mode();
                         }
-                        Err(e) => {
-                            debug!("Band steering: Failed to get APs: {}", e);
+                        if cake_mode == CakeMode::GameOnly {
+                            let _ = state.tc_manager.remove_cake(interface);
                         }
+                        info!(event = "game_mode", interface = interface, old = true, new = false, reason = "cooldown";
+                              "Game mode ENDED on {}{}", interface, if freeze_cake { " (CAKE unfrozen)" } else { "" });
                     }
+                    state.game_mode_active = active;
+                }
+                Self::record_event(self.dry_run, "game_mode", Some(interface), serde_json::json!({ "active": active, "pps": pps }));
+                if active {
+                    self.usage.start_session(interface);
+                    self.sessions.start(interface);
+                } else {
+                    self.usage.end_session(interface);
+                    self.sessions.end(interface);
+                }
+                // Low-latency AQL / aggregation presets follow game mode
+                if self.dry_run {
+                    info!("[DRY-RUN] Would switch game-mode queue settings {} on {}", if active { "on" } else { "off" }, interface);
+                } else if let Some(ifc) = self.wifi_manager.interfaces().iter().find(|i| i.name == interface) {
+                    if let Err(e) = mac80211::set_game_mode(ifc, active, &self.mac80211_config) {
+                        warn!("Failed to switch game-mode queue settings on {}: {}", interface, e);
+                    }
+                }
+                if active {
+                    self.run_hook(Hook::GameModeStart, vec![
+                        ("HIFI_WIFI_INTERFACE", interface.to_string()),
+                        ("HIFI_WIFI_PPS", pps.to_string()),
+                    ]);
                 }
             }
-        }
+            Action::CakeBandwidth { mbit, link_kbit } => {
+                let cake_mode = self.config.cake_mode;
+                let Some(state) = self.interface_states.get_mut(interface) else { return };
+                let applied_before = state.tc_manager.last_applied();
+                if link_kbit.is_some() {
+                    state.last_good_bitrate = link_kbit;
+                }
+                // (in game_only mode the bandwidth is tracked all along but only applied in game)
+                if state.tc_manager.update_bandwidth(mbit) && Self::cake_wanted(cake_mode, state) {
+                    let _ = state.tc_manager.apply_cake(interface);
+                }
+                state.bandwidth_valid = true;
+                ctx.last_good_bitrate_kbit = state.last_good_bitrate;
+                ctx.bandwidth_valid = true;
 
-        Ok(())
+                // Remember newly applied bandwidth for this link
+                let applied = state.tc_manager.last_applied();
+                if applied_before.is_some() && applied != applied_before {
+                    self.sessions.record_cake_change(interface);
+                }
+                if let (Some(ap), Some(applied)) = (ctx.current_ap, applied) {
+                    if applied_before != Some(applied) {
+                        self.learned.record_bandwidth(&ap.bssid, &ap.ssid, applied, state.last_good_bitrate);
+                    }
+                }
+            }
+            Action::Coalescing { enable } if self.dry_run => {
+                info!("[DRY-RUN] Would {} interrupt coalescing on {}", if enable { "enable" } else { "disable" }, interface);
+            }
+            Action::Coalescing { enable } => {
+                let _ = if enable {
+                    EthtoolManager::enable_coalescing(interface)
+                } else {
+                    EthtoolManager::disable_coalescing(interface)
+                };
+            }
+            Action::PowerSave { source, request: Some((enable, reason)) } => {
+                self.power_save.request(interface, source, enable, reason);
+            }
+            Action::PowerSave { source, request: None } => self.power_save.withdraw(interface, source),
+            Action::Eee { enable, reason } if self.dry_run => {
+                info!(event = "eee", interface = interface, new = enable, reason = reason, dry_run = true;
+                      "[DRY-RUN] EEE would be {} on {} ({})", if enable { "ENABLED" } else { "DISABLED" }, interface, reason);
+            }
+            Action::Eee { enable, reason } => {
+                let result = if enable { EthtoolManager::enable_eee(interface) } else { EthtoolManager::disable_eee(interface) };
+                if result.is_ok() {
+                    info!(event = "eee", interface = interface, new = enable, reason = reason;
+                          "EEE {} on {} ({})", if enable { "ENABLED" } else { "DISABLED" }, interface, reason);
+                }
+            }
+            Action::RoamDamped { from, to, hold_secs } => {
+                info!(target: BAND_STEERING_LOG, event = "roam_damped", interface = interface, old = from.as_str(), new = to.as_str();
+                      "Band steering: holding off {} -> {} for {}s ({} and {} keep trading places)", from, to, hold_secs, from, to);
+                Self::record_event(self.dry_run, "roam_damped", Some(interface), serde_json::json!({ "from": from, "to": to, "hold_secs": hold_secs }));
+            }
+            Action::Rings { rx, tx } if self.dry_run => {
+                info!("[DRY-RUN] Would set ring sizes on {} to rx {}, tx {}", interface, rx, tx);
+            }
+            Action::Rings { rx, tx } => match ethernet::set_rings(interface, rx, tx) {
+                Ok(()) => info!(event = "ethernet_rings", interface = interface, rx = rx, tx = tx;
+                                "Ring sizes on {} set to rx {}, tx {}", interface, rx, tx),
+                Err(e) => debug!("Failed to set ring sizes on {}: {}", interface, e),
+            },
+            Action::PauseFrames { enable } if self.dry_run => {
+                info!("[DRY-RUN] Would turn pause frames {} on {}", if enable { "on" } else { "off" }, interface);
+            }
+            Action::PauseFrames { enable } => match ethernet::set_pause(interface, enable) {
+                Ok(()) => info!(event = "pause_frames", interface = interface, new = enable;
+                                "Pause frames {} on {}", if enable { "on" } else { "off" }, interface),
+                Err(e) => debug!("Failed to change pause frames on {}: {}", interface, e),
+            },
+            Action::HalfDuplex { speed_mbit } => {
+                warn!(event = "half_duplex", interface = interface, speed_mbit:? = speed_mbit;
+                      "{} negotiated HALF duplex{} - check the cable and the switch port", interface,
+                      speed_mbit.map(|s| format!(" at {} Mbit/s", s)).unwrap_or_default());
+                Self::record_event(self.dry_run, "half_duplex", Some(interface), serde_json::json!({ "speed_mbit": speed_mbit }));
+            }
+            Action::LinkFlaps { flaps } => {
+                warn!(event = "link_flaps", interface = interface, flaps = flaps;
+                      "{} went down and up {} times in {} minutes - check the cable, dock or switch port",
+                      interface, flaps, LINK_FLAP_WINDOW.as_secs() / 60);
+                Self::record_event(self.dry_run, "link_flaps", Some(interface), serde_json::json!({ "flaps": flaps }));
+            }
+            Action::Roam { from, to } => {
+                Self::record_event(self.dry_run, "roam", Some(interface), serde_json::json!({ "from": from, "to": to }));
+                self.run_hook(Hook::Roam, vec![
+                    ("HIFI_WIFI_INTERFACE", interface.to_string()),
+                    ("HIFI_WIFI_FROM_BSSID", from),
+                    ("HIFI_WIFI_TO_BSSID", to),
+                ]);
+                // Cached bitrate is stale after roaming
+                if let Some(state) = self.interface_states.get_mut(interface) {
+                    state.last_good_bitrate = None;
+                    state.bandwidth_valid = false;
+                }
+                ctx.last_good_bitrate_kbit = None;
+                ctx.bandwidth_valid = false;
+                // Request scan to hint firmware/driver about better AP
+                if self.dry_run {
+                    info!("[DRY-RUN] Would request a scan on {}", interface);
+                } else {
+                    if let Some(aborter) = &mut self.scan_abort {
+                        aborter.hold_off(interface);
+                    }
+                    let _ = self.nm_client.request_scan(path).await;
+                }
+            }
+        }
     }
 
     /// Stop the governor and clean up
     pub fn stop(&mut self) {
         info!("Governor stopping, cleaning up...");
         
-        for (interface, state) in &self.interface_states {
+        if self.dry_run {
+            info!("[DRY-RUN] Would remove CAKE and persist learned state");
+            return;
+        }
+        control::remove();
+        if self.keepalives_relaxed {
+            metered::set_keepalives(false, &self.system_config.sysctl_settings());
+        }
+        self.usage.end_all();
+        self.usage.flush();
+        self.sessions.end_all();
+        for state in self.interface_states.values_mut() {
+            Self::record_session_peak(&mut self.learned, state);
+        }
+        if let Err(e) = self.learned.save() {
+            warn!("Failed to persist learned state: {}", e);
+        }
+        MetricsSnapshot::clear(Path::new(METRICS_DIR));
+        if let Some(guard) = &mut self.stream_guard {
+            guard.remove();
+        }
+        
+        for (interface, state) in &mut self.interface_states {
+            Self::finish_bufferbloat_session(interface, state, self.dry_run);
             let _ = state.tc_manager.remove_cake(interface);
+            // Don't leave the game-mode AQL preset behind
+            if state.game_mode_active {
+                if let Some(ifc) = self.wifi_manager.interfaces().iter().find(|i| &i.name == interface) {
+                    let _ = mac80211::set_game_mode(ifc, false, &self.mac80211_config);
+                }
+            }
         }
     }
 
-    /// Fallback: Get bitrate from `iw` when NetworkManager reports 0
-    fn get_bitrate_from_iw(interface: &str) -> Option<u32> {
-        let output = Command::new("iw")
-            .args(["dev", interface, "link"])
-            .output()
-            .ok()?;
-        
-        if !output.status.success() {
-            return None;
+    /// Run as a streaming host: host CAKE profile, no battery-driven power saving
+    pub fn with_host(mut self, sunshine_port: u16) -> Self {
+        info!("Streaming host role: prioritizing Sunshine ports from {}", sunshine_port);
+        self.host_port = Some(sunshine_port);
+        self
+    }
+
+    /// Apply `[[schedule]]` overrides by time of day
+    pub fn with_schedule(mut self, entries: &[ScheduleEntry]) -> Self {
+        if !entries.is_empty() {
+            info!("{} schedule entr{} configured", entries.len(), if entries.len() == 1 { "y" } else { "ies" });
         }
-        
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        
-        // Parse bitrate from iw output - multiple formats supported:
-        // "tx bitrate: 866.7 MBit/s ..."
-        // "	tx bitrate: 866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2"
-        // Steam Deck ath11k may report: "tx bitrate: 1201.0 MBit/s 80MHz HE-MCS 11 HE-NSS 2 HE-GI 0 HE-DCM 0"
-        for line in stdout.lines() {
-            let line_lower = line.to_lowercase();
-            if line_lower.contains("tx bitrate:") || line_lower.contains("bitrate:") {
-                // Extract the number - look for pattern like "866.7 MBit/s" or "1201.0 Mbit/s"
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    // Look for "bitrate:" followed by a number
-                    if part.to_lowercase().contains("bitrate:") {
-                        // Next part should be the number
-                        if i + 1 < parts.len() {
-                            if let Ok(mbit) = parts[i + 1].parse::<f64>() {
-                                // Convert to Kbit for consistency with NM
-                                debug!("iw fallback: {}Mbit on {}", mbit, interface);
-                                return Some((mbit * 1000.0) as u32);
-                            }
-                        }
-                    }
-                    // Also try matching "NNN.N" followed by "MBit" in case format varies
-                    if i + 1 < parts.len() && parts[i + 1].to_lowercase().contains("mbit") {
-                        if let Ok(mbit) = part.parse::<f64>() {
-                            debug!("iw fallback (alt format): {}Mbit on {}", mbit, interface);
-                            return Some((mbit * 1000.0) as u32);
-                        }
-                    }
-                }
+        self.schedule = Scheduler::new(entries);
+        self
+    }
+
+    /// Exit cleanly after `secs` without control requests or game mode (0 = never);
+    /// systemd starts the daemon again on the next connection to the socket
+    pub fn with_idle_exit(mut self, secs: u64) -> Self {
+        if secs > 0 {
+            info!("Started on demand: exiting after {}s idle", secs);
+            self.idle_exit = Some(Duration::from_secs(secs));
+        }
+        self
+    }
+
+    /// Keep the `[backend]` tuning in place while the daemon runs, across backend switches
+    pub fn with_backend(mut self, config: &Config) -> Self {
+        self.backend = Some(BackendTuner::from_config(config));
+        self
+    }
+
+    /// Re-detect the Wi-Fi backend and tune the new one if NetworkManager switched
+    fn check_backend(&mut self) {
+        self.backend_checked = Instant::now();
+        if let Some(aborter) = &mut self.scan_abort {
+            aborter.redetect();
+        }
+        let Some(tuner) = &mut self.backend else { return };
+        if !tuner.redetect() {
+            return;
+        }
+        if self.dry_run {
+            info!("[DRY-RUN] Would apply {:?} tuning", tuner.backend());
+        } else if let Err(e) = tuner.apply() {
+            warn!("Failed to tune {:?}: {}", tuner.backend(), e);
+        }
+    }
+
+    /// Decide as usual but only log what would change; nothing on the system is touched
+    pub fn with_dry_run(mut self) -> Self {
+        info!("[DRY-RUN] Decisions are logged, no changes are made (auto-reconnect and bulk-flow limits are off)");
+        self.dry_run = true;
+        self.reconnect = None;
+        self.stream_guard = None;
+        self.power_save = PowerSaveController::new().dry_run(true);
+        self.sessions = SessionRecorder::default().dry_run(true);
+        self.scan_abort = None;
+        self
+    }
+
+    /// Run a user hook in the background (logged only in a dry run)
+    fn run_hook(&self, hook: Hook, env: Vec<(&'static str, String)>) {
+        if self.dry_run {
+            if !hook.scripts(&self.hooks_config).is_empty() {
+                info!("[DRY-RUN] Would run {} hook(s): {}", hook.name(), hook.scripts(&self.hooks_config).join(", "));
             }
+            return;
         }
-        
-        // Final fallback: try to get signal from iw station dump
-        // Some drivers (ath11k) may report better data this way
-        let station_output = Command::new("iw")
-            .args(["dev", interface, "station", "dump"])
-            .output()
-            .ok()?;
-        
-        if station_output.status.success() {
-            let station_out = String::from_utf8_lossy(&station_output.stdout);
-            for line in station_out.lines() {
-                let line_lower = line.to_lowercase();
-                if line_lower.contains("tx bitrate:") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    for (i, part) in parts.iter().enumerate() {
-                        if part.to_lowercase().contains("bitrate:") && i + 1 < parts.len() {
-                            if let Ok(mbit) = parts[i + 1].parse::<f64>() {
-                                debug!("iw station dump fallback: {}Mbit on {}", mbit, interface);
-                                return Some((mbit * 1000.0) as u32);
-                            }
-                        }
-                    }
-                }
+        hooks::spawn(&self.hooks_config, hook, env);
+    }
+
+    /// Battery-driven power saving applies (never for a streaming host)
+    fn battery_power_save(&self) -> bool {
+        self.host_port.is_none() && self.power_manager.should_enable_power_save()
+    }
+
+    /// Whether CAKE should be on the interface right now
+    fn cake_wanted(mode: CakeMode, state: &InterfaceState) -> bool {
+        if state.safe_mode {
+            return false;
+        }
+        match mode {
+            CakeMode::Always => true,
+            CakeMode::GameOnly => state.game_mode_active,
+            CakeMode::Never => false,
+        }
+    }
+
+    /// Time the resolvers of `interface` and publish the result (blocking)
+    fn probe_dns(interface: &str, slow_ms: u32, results: &std::sync::Mutex<Vec<ResolverLatency>>) {
+        let probed = dns::probe(interface, slow_ms as f64);
+        for resolver in probed.iter().filter(|r| r.slow || r.latency_ms.is_none()) {
+            warn!(event = "dns_slow", interface = interface;
+                  "DNS server {} on {} is {}", resolver.server, interface,
+                  resolver.latency_ms.map(|ms| format!("slow ({:.0}ms)", ms)).unwrap_or("not answering".to_string()));
+        }
+        if let Ok(mut r) = results.lock() {
+            *r = probed;
+        }
+    }
+
+    /// Log the grade of the association that just ended and start a fresh one
+    /// Enter or leave safe mode: shaping against the PHY rate and our power save choices
+    /// only make a portal or half-working network flakier
+    fn set_safe_mode(&mut self, interface: &str, on: bool, connectivity: Connectivity) {
+        let Some(state) = self.interface_states.get_mut(interface) else { return };
+        state.safe_mode = on;
+        state.last_safe_scan = None;
+        if on {
+            warn!(event = "safe_mode", interface = interface, new = true, connectivity:? = connectivity;
+                  "{:?} connectivity on {} (captive portal or public network): safe mode, no CAKE, default power save",
+                  connectivity, interface);
+            if state.tc_manager.last_applied().is_some() {
+                let _ = state.tc_manager.remove_cake(interface);
+            }
+            self.power_save.request(interface, PowerSaveSource::SafeMode, true, "safe mode");
+        } else {
+            info!(event = "safe_mode", interface = interface, new = false;
+                  "Full connectivity on {}: leaving safe mode", interface);
+            if Self::cake_wanted(self.config.cake_mode, state) {
+                let _ = state.tc_manager.apply_cake(interface);
             }
+            self.power_save.withdraw(interface, PowerSaveSource::SafeMode);
         }
-        
-        None
+        Self::record_event(self.dry_run, "safe_mode", Some(interface),
+                           serde_json::json!({ "active": on, "connectivity": format!("{:?}", connectivity) }));
+    }
+
+    /// Scan now and then in safe mode, standing in for the periodic scans iwd's
+    /// `DisablePeriodicScan` turned off
+    async fn safe_mode_scan(&mut self, interface: &str, path: &str) {
+        let Some(state) = self.interface_states.get_mut(interface) else { return };
+        if state.last_safe_scan.is_some_and(|at| at.elapsed() < SAFE_MODE_SCAN_INTERVAL) {
+            return;
+        }
+        state.last_safe_scan = Some(Instant::now());
+        if self.dry_run {
+            info!("[DRY-RUN] Would request a scan on {} (safe mode)", interface);
+        } else if let Err(e) = self.nm_client.request_scan(path).await {
+            debug!("Safe mode scan on {} failed: {}", interface, e);
+        }
+    }
+
+    /// File the association's throughput peak under its BSSID
+    fn record_session_peak(learned: &mut PersistentState, state: &mut InterfaceState) {
+        let peak_bps = std::mem::take(&mut state.session_peak_bps);
+        if let Some(bssid) = &state.current_bssid {
+            learned.record_throughput_peak(bssid, (peak_bps * 8 / 1_000_000) as u32);
+        }
+    }
+
+    /// Log `sustained_loss` once when a whole window loses too much, again only after it recovered
+    fn check_sustained_loss(interface: &str, state: &mut InterfaceState, summary: &LatencySummary, threshold_pct: f64, dry_run: bool) {
+        if threshold_pct <= 0.0 {
+            return;
+        }
+        if !state.loss_alerted && summary.samples >= SUSTAINED_LOSS_MIN_SAMPLES && summary.loss_pct >= threshold_pct {
+            state.loss_alerted = true;
+            warn!(event = "sustained_loss", interface = interface, loss_pct = summary.loss_pct;
+                  "Sustained packet loss on {}: {:.0}% of gateway pings lost over the last minute", interface, summary.loss_pct);
+            Self::record_event(dry_run, "sustained_loss", Some(interface), serde_json::json!({
+                "loss_pct": summary.loss_pct,
+                "p95_ms": summary.p95_ms,
+                "samples": summary.samples,
+            }));
+        } else if state.loss_alerted && summary.loss_pct < threshold_pct / 2.0 {
+            state.loss_alerted = false;
+            info!(event = "sustained_loss_cleared", interface = interface; "Packet loss on {} back to {:.1}%", interface, summary.loss_pct);
+        }
+    }
+
+    fn finish_bufferbloat_session(interface: &str, state: &mut InterfaceState, dry_run: bool) {
+        if let Some(report) = state.bufferbloat.report() {
+            info!(event = "bufferbloat_grade", interface = interface, grade = report.grade.as_str();
+                  "Bufferbloat grade {} on {} (+{:.0}ms under load, {:.1}% CAKE drops)",
+                  report.grade, interface, report.increase_ms(), report.drop_pct);
+            Self::record_event(dry_run, "bufferbloat_grade", Some(interface), serde_json::json!({
+                "grade": report.grade,
+                "idle_ms": report.idle_ms,
+                "loaded_ms": report.loaded_ms,
+                "drop_pct": report.drop_pct,
+                "bssid": state.current_bssid,
+            }));
+        }
+        state.bufferbloat = BufferbloatTracker::new();
     }
 
     /// Update throughput estimate from /sys/class/net statistics
-    fn update_throughput_estimate(state: &mut InterfaceState, interface: &str) {
+    /// Returns the bytes received and sent since the last tick
+    fn update_throughput_estimate(state: &mut InterfaceState, interface: &str) -> (u64, u64) {
         let rx_path = format!("/sys/class/net/{}/statistics/rx_bytes", interface);
         let tx_path = format!("/sys/class/net/{}/statistics/tx_bytes", interface);
         
@@ -734,14 +1622,19 @@ impl Governor {
             .unwrap_or(0);
         
         let now = Instant::now();
+        let mut deltas = (0, 0);
         
         if let Some(last_time) = state.last_stats_time {
             let elapsed = now.duration_since(last_time).as_secs_f64();
+            // Counters restart from zero when the interface is recreated
+            let rx_delta = rx_bytes.saturating_sub(state.last_rx_bytes);
+            let tx_delta = tx_bytes.saturating_sub(state.last_tx_bytes);
+            deltas = (rx_delta, tx_delta);
             if elapsed > 0.5 {
-                let rx_delta = rx_bytes.saturating_sub(state.last_rx_bytes);
-                let tx_delta = tx_bytes.saturating_sub(state.last_tx_bytes);
                 let total_bytes = rx_delta + tx_delta;
                 let bytes_per_sec = (total_bytes as f64 / elapsed) as u64;
+                state.throughput_bps = bytes_per_sec;
+                state.session_peak_bps = state.session_peak_bps.max(bytes_per_sec);
                 
                 // Only update if there's meaningful traffic (>100KB/s)
                 if bytes_per_sec > 100_000 {
@@ -753,5 +1646,6 @@ impl Governor {
         state.last_rx_bytes = rx_bytes;
         state.last_tx_bytes = tx_bytes;
         state.last_stats_time = Some(now);
+        deltas
     }
 }
@@ -0,0 +1,132 @@
+//! Tick-level metric history
+//!
+//! With `[metrics]` enabled, every tick's link metrics are also appended as one flat
+//! JSON line per interface to /var/lib/hifi-wifi/history.jsonl for `hifi-wifi export`.
+//! The file is rotated to history.jsonl.1 at half of `history_max_kb`, so the two
+//! together stay under the cap.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::network::metrics::MetricsSnapshot;
+
+pub const HISTORY_PATH: &str = "/var/lib/hifi-wifi/history.jsonl";
+
+/// One interface at one tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Row {
+    /// Unix time in seconds
+    pub ts: u64,
+    pub interface: String,
+    pub rtt_p50_ms: Option<f64>,
+    pub rtt_p95_ms: Option<f64>,
+    pub jitter_ms: Option<f64>,
+    pub loss_pct: Option<f64>,
+    pub signal_dbm: Option<i32>,
+    pub cake_mbit: Option<u32>,
+    pub throughput_mbit: u32,
+    pub game_mode: bool,
+}
+
+impl Row {
+    pub const CSV_HEADER: &'static str =
+        "ts,interface,rtt_p50_ms,rtt_p95_ms,jitter_ms,loss_pct,signal_dbm,cake_mbit,throughput_mbit,game_mode";
+
+    pub fn to_csv(&self) -> String {
+        fn opt<T: ToString>(v: Option<T>) -> String {
+            v.map(|v| v.to_string()).unwrap_or_default()
+        }
+        let round = |v: Option<f64>| v.map(|v| (v * 10.0).round() / 10.0);
+        format!("{},{},{},{},{},{},{},{},{},{}",
+                self.ts, self.interface, opt(round(self.rtt_p50_ms)), opt(round(self.rtt_p95_ms)),
+                opt(round(self.jitter_ms)), opt(round(self.loss_pct)), opt(self.signal_dbm),
+                opt(self.cake_mbit), self.throughput_mbit, self.game_mode)
+    }
+}
+
+/// One row per link of a snapshot
+pub fn rows(snapshot: &MetricsSnapshot) -> Vec<Row> {
+    snapshot.links.iter().map(|l| Row {
+        ts: snapshot.timestamp,
+        interface: l.interface.clone(),
+        rtt_p50_ms: l.latency.as_ref().map(|s| s.p50_ms),
+        rtt_p95_ms: l.latency.as_ref().map(|s| s.p95_ms),
+        jitter_ms: l.latency.as_ref().map(|s| s.jitter_ms),
+        loss_pct: l.latency.as_ref().map(|s| s.loss_pct),
+        signal_dbm: l.signal_dbm,
+        cake_mbit: l.cake_mbit,
+        throughput_mbit: l.throughput_mbit,
+        game_mode: l.game_mode,
+    }).collect()
+}
+
+/// Append a snapshot's rows, rotating first if the file passed half of `max_bytes`
+pub fn append(snapshot: &MetricsSnapshot, max_bytes: u64) -> Result<()> {
+    append_to(Path::new(HISTORY_PATH), &rows(snapshot), max_bytes)
+}
+
+fn append_to(path: &Path, rows: &[Row], max_bytes: u64) -> Result<()> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::metadata(path).map(|m| m.len() > max_bytes / 2).unwrap_or(false) {
+        fs::rename(path, path.with_extension("jsonl.1"))?;
+    }
+    let mut lines = String::new();
+    for row in rows {
+        lines.push_str(&serde_json::to_string(row)?);
+        lines.push('\n');
+    }
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+/// Rows from `since` (Unix time) on, oldest first
+pub fn read_since(since: u64) -> Vec<Row> {
+    read_from(Path::new(HISTORY_PATH), since)
+}
+
+fn read_from(path: &Path, since: u64) -> Vec<Row> {
+    let rotated = fs::read_to_string(path.with_extension("jsonl.1")).unwrap_or_default();
+    let current = fs::read_to_string(path).unwrap_or_default();
+    rotated.lines()
+        .chain(current.lines())
+        .filter_map(|line| serde_json::from_str::<Row>(line).ok())
+        .filter(|row| row.ts >= since)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::metrics::LinkMetrics;
+    use crate::network::stats::LatencySummary;
+
+    #[test]
+    fn test_append_rotate_and_read() {
+        let dir = std::env::temp_dir().join(format!("hifi-wifi-history-{}", std::process::id()));
+        let path = dir.join("history.jsonl");
+        let mut snapshot = MetricsSnapshot::new(vec![LinkMetrics {
+            interface: "wlan0".to_string(),
+            latency: Some(LatencySummary { p50_ms: 3.44, jitter_ms: 1.0, ..Default::default() }),
+            signal_dbm: Some(-52),
+            ..Default::default()
+        }]);
+        for ts in [100, 200, 300] {
+            snapshot.timestamp = ts;
+            // Small cap: every append after the first rotates
+            append_to(&path, &rows(&snapshot), 200).unwrap();
+        }
+        assert!(path.with_extension("jsonl.1").exists());
+        let read = read_from(&path, 200);
+        assert_eq!(read.iter().map(|r| r.ts).collect::<Vec<_>>(), vec![200, 300]);
+        assert_eq!(read[0].to_csv(), "200,wlan0,3.4,0,1,0,-52,,0,false");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
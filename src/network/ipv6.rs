@@ -0,0 +1,207 @@
+//! IPv6 path health and per-connection preference
+//!
+//! A router that hands out IPv6 but can't route it makes every new connection wait
+//! for happy-eyeballs to give up on IPv6 first. `doctor` probes both families, `status`
+//! shows the gateway RTT of each, and `[wifi] ipv6_preference` adjusts the active
+//! Wi-Fi profiles in NetworkManager (originals are restored by `revert --backend`).
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::network::latency;
+
+const BACKUP_PATH: &str = "/var/lib/hifi-wifi/nm-ipv6.json";
+/// Public IPv6 resolver used as the "internet" reachability target
+const INTERNET_V6: &str = "2606:4700:4700::1111";
+
+/// How the active Wi-Fi profiles treat IPv6
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ipv6Preference {
+    /// Leave the profiles alone
+    #[default]
+    Default,
+    /// Ask IPv6 DNS servers first
+    Prefer,
+    /// Keep only link-local IPv6 so applications connect over IPv4 right away
+    Deprioritize,
+}
+
+impl Ipv6Preference {
+    /// NM settings for this preference
+    pub fn settings(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Ipv6Preference::Default => &[],
+            Ipv6Preference::Prefer => &[("ipv6.dns-priority", "50"), ("ipv4.dns-priority", "100")],
+            Ipv6Preference::Deprioritize => &[("ipv6.method", "link-local")],
+        }
+    }
+}
+
+/// IPv6 default gateway of an interface (usually link-local)
+pub fn default_gateway6(interface: &str) -> Option<String> {
+    let output = Command::new("ip")
+        .args(["-6", "route", "show", "default", "dev", interface])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut words = stdout.split_whitespace();
+    words.find(|w| *w == "via")?;
+    words.next().map(str::to_string)
+}
+
+/// Best of two pings to `target` through `interface` (ms), None if both were lost
+pub fn ping_rtt(interface: &str, target: &str, v6: bool) -> Option<f64> {
+    let family = if v6 { "-6" } else { "-4" };
+    let output = Command::new("ping")
+        .args([family, "-n", "-c", "2", "-i", "0.2", "-W", "1", "-I", interface, target])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| latency::parse_reply_line(l).flatten())
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// Gateway RTT per family: (IPv4, IPv6); None where there is no route or no reply
+pub fn gateway_rtts(interface: &str) -> (Option<f64>, Option<f64>) {
+    let v4 = latency::default_gateway(interface).and_then(|gw| ping_rtt(interface, &gw, false));
+    let v6 = default_gateway6(interface).and_then(|gw| ping_rtt(interface, &gw, true));
+    (v4, v6)
+}
+
+/// IPv6 state of one interface for `doctor`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ipv6Health {
+    /// No IPv6 default route: IPv4 only, nothing to stall on
+    NoRoute,
+    /// Route present but the gateway doesn't answer
+    GatewayDown,
+    /// Gateway answers, the internet doesn't (the happy-eyeballs stall case)
+    NoInternet { gateway_ms: f64 },
+    Ok { gateway_ms: f64, internet_ms: f64 },
+}
+
+pub fn probe(interface: &str) -> Ipv6Health {
+    let Some(gateway) = default_gateway6(interface) else {
+        return Ipv6Health::NoRoute;
+    };
+    let Some(gateway_ms) = ping_rtt(interface, &gateway, true) else {
+        return Ipv6Health::GatewayDown;
+    };
+    match ping_rtt(interface, INTERNET_V6, true) {
+        Some(internet_ms) => Ipv6Health::Ok { gateway_ms, internet_ms },
+        None => Ipv6Health::NoInternet { gateway_ms },
+    }
+}
+
+/// `UUID:TYPE:DEVICE` lines of `nmcli -t connection show --active` -> Wi-Fi (uuid, device)
+pub fn parse_active_wifi(output: &str) -> Vec<(String, String)> {
+    output.lines()
+        .filter_map(|line| {
+            let mut parts = line.split(':');
+            let (uuid, kind, device) = (parts.next()?, parts.next()?, parts.next()?);
+            (kind == "802-11-wireless").then(|| (uuid.to_string(), device.to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn nmcli_get(uuid: &str, setting: &str) -> Option<String> {
+    let output = Command::new("nmcli").args(["-g", setting, "connection", "show", uuid]).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub(crate) fn nmcli_modify(uuid: &str, settings: &[(String, String)]) -> Result<()> {
+    let mut args = vec!["connection".to_string(), "modify".to_string(), uuid.to_string()];
+    for (key, value) in settings {
+        args.push(key.clone());
+        args.push(value.clone());
+    }
+    let output = Command::new("nmcli").args(&args).output()?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+type Backup = BTreeMap<String, BTreeMap<String, String>>;
+
+fn load_backup() -> Backup {
+    fs::read_to_string(BACKUP_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup(backup: &Backup) -> Result<()> {
+    if let Some(parent) = Path::new(BACKUP_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = format!("{}.tmp", BACKUP_PATH);
+    fs::write(&tmp, serde_json::to_string_pretty(backup)?)?;
+    fs::rename(&tmp, BACKUP_PATH)?;
+    Ok(())
+}
+
+/// Apply `preference` to every active Wi-Fi profile and reapply it to the device
+pub fn apply(preference: Ipv6Preference) -> Result<()> {
+    if preference == Ipv6Preference::Default {
+        return Ok(());
+    }
+    let output = Command::new("nmcli")
+        .args(["-t", "-f", "UUID,TYPE,DEVICE", "connection", "show", "--active"])
+        .output()?;
+    let mut backup = load_backup();
+    for (uuid, device) in parse_active_wifi(&String::from_utf8_lossy(&output.stdout)) {
+        let changes: Vec<(String, String, String)> = preference.settings().iter()
+            .filter_map(|(key, value)| {
+                let current = nmcli_get(&uuid, key)?;
+                (current != *value).then(|| (key.to_string(), value.to_string(), current))
+            })
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        // First change wins, so repeated applies keep the user's original
+        let original = backup.entry(uuid.clone()).or_default();
+        for (key, _, current) in &changes {
+            original.entry(key.clone()).or_insert_with(|| current.clone());
+        }
+        save_backup(&backup)?;
+        let settings: Vec<(String, String)> = changes.iter().map(|(k, v, _)| (k.clone(), v.clone())).collect();
+        nmcli_modify(&uuid, &settings)?;
+        let _ = Command::new("nmcli").args(["device", "reapply", &device]).output();
+        info!("IPv6 preference {:?} applied to connection {} on {}", preference, uuid, device);
+    }
+    Ok(())
+}
+
+/// Put back the original IPv6/DNS settings of every profile we changed
+pub fn revert() {
+    for (uuid, settings) in load_backup() {
+        let settings: Vec<(String, String)> = settings.into_iter().collect();
+        match nmcli_modify(&uuid, &settings) {
+            Ok(()) => info!("Restored IPv6 settings on connection {}", uuid),
+            Err(e) => warn!("Could not restore IPv6 settings on connection {} (profile removed?): {}", uuid, e),
+        }
+    }
+    let _ = fs::remove_file(BACKUP_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_active_wifi() {
+        let out = "0b1c-uuid:802-11-wireless:wlan0\n7f2e-uuid:802-3-ethernet:eth0\nlo-uuid:loopback:lo\n";
+        assert_eq!(parse_active_wifi(out), vec![("0b1c-uuid".to_string(), "wlan0".to_string())]);
+        assert!(Ipv6Preference::Default.settings().is_empty());
+        assert_eq!(Ipv6Preference::Deprioritize.settings(), &[("ipv6.method", "link-local")]);
+    }
+}
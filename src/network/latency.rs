@@ -0,0 +1,58 @@
+//! Helpers for latency probing with `ping`
+//!
+//! The probe target defaults to the interface's default gateway so the
+//! figures reflect the Wi-Fi hop rather than the wider internet.
+
+use std::process::Command;
+
+/// Default gateway for an interface (from `ip route show default dev <iface>`)
+pub fn default_gateway(interface: &str) -> Option<String> {
+    let output = Command::new("ip")
+        .args(["route", "show", "default", "dev", interface])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut words = stdout.split_whitespace();
+    words.find(|w| *w == "via")?;
+    words.next().map(str::to_string)
+}
+
+/// Interface and gateway of the system default route
+pub fn default_route() -> Option<(String, String)> {
+    let output = Command::new("ip")
+        .args(["route", "show", "default"])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let words: Vec<&str> = stdout.lines().next()?.split_whitespace().collect();
+    let after = |key: &str| words.iter().position(|w| *w == key).and_then(|i| words.get(i + 1));
+    Some((after("dev")?.to_string(), after("via")?.to_string()))
+}
+
+/// Parse one line of `ping -n -O` output
+///
+/// Returns Some(Some(rtt)) for a reply, Some(None) for a missed reply
+/// ("no answer yet"), None for anything else (banner, errors).
+pub fn parse_reply_line(line: &str) -> Option<Option<f64>> {
+    if line.starts_with("no answer yet") {
+        return Some(None);
+    }
+    let rtt = line.split_whitespace()
+        .find_map(|w| w.strip_prefix("time="))?
+        .parse::<f64>()
+        .ok()?;
+    Some(Some(rtt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply_line() {
+        assert_eq!(parse_reply_line("64 bytes from 192.168.1.1: icmp_seq=4 ttl=64 time=3.21 ms"), Some(Some(3.21)));
+        assert_eq!(parse_reply_line("no answer yet for icmp_seq=5"), Some(None));
+        assert_eq!(parse_reply_line("PING 192.168.1.1 (192.168.1.1) from 192.168.1.20 wlan0: 56(84) bytes of data."), None);
+        assert_eq!(parse_reply_line("ping: sendmsg: Network is unreachable"), None);
+    }
+}
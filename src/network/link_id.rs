@@ -0,0 +1,197 @@
+//! Interface identity
+//!
+//! udev's predictable naming can rename a device after the daemon first saw it, and a USB
+//! adapter that re-enumerates comes back with a new ifindex and often a new name. The
+//! Governor keys per-interface state by `LinkId` (the permanent MAC where the device has
+//! one, else the ifindex) and keeps the name as display metadata, so `relink` can move the
+//! state over when the old name disappears and the same device shows up under another.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const SYS_NET: &str = "/sys/class/net";
+/// `addr_assign_type` of a burned-in address (NET_ADDR_PERM)
+const ADDR_PERMANENT: &str = "0";
+
+/// What stays the same when a device is renamed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LinkId {
+    /// Permanent MAC address (the wiphy address for Wi-Fi, which MAC randomization leaves alone)
+    Mac(String),
+    /// Kernel ifindex: survives a rename, not a re-enumeration
+    Index(u32),
+    /// The device is gone or has no sysfs entry (dry runs, tests)
+    Name(String),
+}
+
+impl LinkId {
+    /// Identity of the device currently called `name`
+    pub fn of(name: &str) -> Self {
+        let dir = Path::new(SYS_NET).join(name);
+        let read = |file: &str| fs::read_to_string(dir.join(file)).ok().map(|s| s.trim().to_string());
+        let permanent = read("phy80211/macaddress").or_else(|| {
+            (read("addr_assign_type").as_deref() == Some(ADDR_PERMANENT)).then(|| read("address")).flatten()
+        });
+        if let Some(mac) = permanent.filter(|m| !m.is_empty() && m != "00:00:00:00:00:00") {
+            return LinkId::Mac(mac);
+        }
+        match read("ifindex").and_then(|i| i.parse().ok()) {
+            Some(index) => LinkId::Index(index),
+            None => LinkId::Name(name.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LinkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkId::Mac(mac) => write!(f, "mac {}", mac),
+            LinkId::Index(index) => write!(f, "ifindex {}", index),
+            LinkId::Name(name) => write!(f, "name {}", name),
+        }
+    }
+}
+
+/// Every interface in /sys/class/net with its identity
+pub fn present() -> Vec<(String, LinkId)> {
+    let Ok(entries) = fs::read_dir(SYS_NET) else { return Vec::new() };
+    entries.flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .map(|name| {
+            let id = LinkId::of(&name);
+            (name, id)
+        })
+        .collect()
+}
+
+struct Entry<T> {
+    id: LinkId,
+    name: String,
+    value: T,
+}
+
+/// Per-interface values keyed by `LinkId`, looked up by the current name
+pub struct InterfaceMap<T> {
+    entries: Vec<Entry<T>>,
+}
+
+impl<T> Default for InterfaceMap<T> {
+    fn default() -> Self {
+        Self { entries: Vec::new() }
+    }
+}
+
+impl<T> InterfaceMap<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.name == name)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries.iter().find(|e| e.name == name).map(|e| &e.value)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.entries.iter_mut().find(|e| e.name == name).map(|e| &mut e.value)
+    }
+
+    /// Identity the value under `name` is kept by
+    pub fn id(&self, name: &str) -> Option<&LinkId> {
+        self.entries.iter().find(|e| e.name == name).map(|e| &e.id)
+    }
+
+    /// Insert under the identity of the device now called `name`
+    pub fn insert(&mut self, name: String, value: T) {
+        let id = LinkId::of(&name);
+        self.insert_with_id(id, name, value);
+    }
+
+    fn insert_with_id(&mut self, id: LinkId, name: String, value: T) {
+        self.entries.retain(|e| e.name != name && e.id != id);
+        self.entries.push(Entry { id, name, value });
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<T> {
+        let index = self.position(name)?;
+        Some(self.entries.remove(index).value)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|e| &e.name)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|e| &e.value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.entries.iter_mut().map(|e| &mut e.value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &T)> {
+        self.entries.iter().map(|e| (&e.name, &e.value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut T)> {
+        self.entries.iter_mut().map(|e| (&e.name, &mut e.value))
+    }
+
+    /// Follow renames: an entry whose name is no longer in `present` moves to the name
+    /// its device has now. Returns (old name, new name) for each move.
+    pub fn relink(&mut self, present: &[(String, LinkId)]) -> Vec<(String, String)> {
+        let mut renamed = Vec::new();
+        for i in 0..self.entries.len() {
+            if present.iter().any(|(name, _)| *name == self.entries[i].name) {
+                continue;
+            }
+            let Some((name, _)) = present.iter()
+                .find(|(name, id)| *id == self.entries[i].id && !self.contains_key(name)) else { continue };
+            renamed.push((std::mem::replace(&mut self.entries[i].name, name.clone()), name.clone()));
+        }
+        renamed
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut InterfaceMap<T> {
+    type Item = (&'a String, &'a mut T);
+    type IntoIter = Box<dyn Iterator<Item = Self::Item> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relink() {
+        let mac = |m: &str| LinkId::Mac(m.to_string());
+        let mut map = InterfaceMap::new();
+        map.insert_with_id(mac("aa:aa"), "wlan0".to_string(), 1);
+        map.insert_with_id(LinkId::Index(7), "wlan1".to_string(), 2);
+
+        // wlan0 renamed by udev, wlan1's adapter unplugged
+        let present = vec![
+            ("lo".to_string(), LinkId::Index(1)),
+            ("wlp2s0".to_string(), mac("aa:aa")),
+        ];
+        assert_eq!(map.relink(&present), vec![("wlan0".to_string(), "wlp2s0".to_string())]);
+        assert_eq!(map.get("wlp2s0"), Some(&1));
+        assert!(!map.contains_key("wlan0"));
+        assert_eq!(map.get("wlan1"), Some(&2));
+
+        // Nothing moves while the old name still exists
+        assert!(map.relink(&[("wlp2s0".to_string(), mac("aa:aa"))]).is_empty());
+        assert_eq!(map.keys().count(), 2);
+    }
+}
@@ -0,0 +1,209 @@
+//! BSSID and band locks
+//!
+//! `hifi-wifi lock --bssid <mac>` / `--band 5ghz` (or `[wifi] lock_bssid` / `lock_band`)
+//! pins the active Wi-Fi profile through NetworkManager's `802-11-wireless.bssid` and
+//! `802-11-wireless.band`, e.g. to hold one mesh node through a match. The Governor
+//! leaves locked interfaces out of band steering. `unlock` and `revert --backend` put the
+//! profiles' original values back.
+
+use anyhow::{bail, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::network::ipv6::{nmcli_get, nmcli_modify, parse_active_wifi};
+
+/// Locked profiles, keyed by connection UUID
+const LOCK_PATH: &str = "/var/lib/hifi-wifi/nm-lock.json";
+
+const NM_BSSID: &str = "802-11-wireless.bssid";
+const NM_BAND: &str = "802-11-wireless.band";
+
+/// Band a profile can be pinned to (NetworkManager has no 6GHz value; lock a 6GHz AP's BSSID instead)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum LockBand {
+    #[serde(rename = "2.4ghz")]
+    #[value(name = "2.4ghz")]
+    Band2_4GHz,
+    #[serde(rename = "5ghz")]
+    #[value(name = "5ghz")]
+    Band5GHz,
+}
+
+impl LockBand {
+    fn nm_value(self) -> &'static str {
+        match self {
+            LockBand::Band2_4GHz => "bg",
+            LockBand::Band5GHz => "a",
+        }
+    }
+}
+
+/// One locked profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Locked {
+    pub device: String,
+    pub bssid: Option<String>,
+    pub band: Option<LockBand>,
+    /// Profile values from before the lock
+    original: BTreeMap<String, String>,
+}
+
+pub fn load() -> BTreeMap<String, Locked> {
+    fs::read_to_string(LOCK_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save(locks: &BTreeMap<String, Locked>) -> Result<()> {
+    if locks.is_empty() {
+        let _ = fs::remove_file(LOCK_PATH);
+        return Ok(());
+    }
+    if let Some(parent) = Path::new(LOCK_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = format!("{}.tmp", LOCK_PATH);
+    fs::write(&tmp, serde_json::to_string_pretty(locks)?)?;
+    fs::rename(&tmp, LOCK_PATH)?;
+    Ok(())
+}
+
+/// Interfaces whose profile is locked (band steering skips them)
+pub fn locked_interfaces() -> HashSet<String> {
+    load().into_values().map(|l| l.device).collect()
+}
+
+/// `aa:bb:cc:dd:ee:ff`
+pub fn valid_bssid(bssid: &str) -> bool {
+    let parts: Vec<&str> = bssid.split(':').collect();
+    parts.len() == 6 && parts.iter().all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// `nmcli -g` escapes the colons in a BSSID
+fn nm_value(uuid: &str, key: &str) -> Option<String> {
+    nmcli_get(uuid, key).map(|v| v.replace("\\:", ":"))
+}
+
+/// Reactivate a profile so changed 802-11-wireless settings take effect (drops the link briefly)
+pub(crate) fn reactivate(uuid: &str, device: &str) {
+    let ok = Command::new("nmcli")
+        .args(["connection", "up", "uuid", uuid, "ifname", device])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if !ok {
+        warn!("Could not reactivate connection {} on {}; the change applies on the next connect", uuid, device);
+    }
+}
+
+/// Active Wi-Fi profiles as (uuid, device), on `interface` only if given
+fn active_wifi(interface: Option<&str>) -> Result<Vec<(String, String)>> {
+    let output = Command::new("nmcli")
+        .args(["-t", "-f", "UUID,TYPE,DEVICE", "connection", "show", "--active"])
+        .output()?;
+    Ok(parse_active_wifi(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .filter(|(_, device)| interface.is_none_or(|i| i == device))
+        .collect())
+}
+
+/// Pin the active Wi-Fi profile on `interface` (every Wi-Fi interface when None) to a
+/// BSSID and/or band; returns the locked devices
+pub fn lock(interface: Option<&str>, bssid: Option<&str>, band: Option<LockBand>) -> Result<Vec<String>> {
+    if let Some(bssid) = bssid.filter(|b| !valid_bssid(b)) {
+        bail!("'{}' is not a BSSID (expected aa:bb:cc:dd:ee:ff)", bssid);
+    }
+    let bssid = bssid.map(str::to_uppercase);
+    let wanted: Vec<(&str, String)> = bssid.iter().map(|b| (NM_BSSID, b.clone()))
+        .chain(band.map(|b| (NM_BAND, b.nm_value().to_string())))
+        .collect();
+
+    let active = active_wifi(interface)?;
+    if active.is_empty() {
+        bail!("No active Wi-Fi connection{}", interface.map(|i| format!(" on {}", i)).unwrap_or_default());
+    }
+    let mut locks = load();
+    let mut devices = Vec::new();
+    for (uuid, device) in active {
+        let changes: Vec<(String, String, String)> = wanted.iter()
+            .filter_map(|(key, value)| {
+                let current = nm_value(&uuid, key)?;
+                (!current.eq_ignore_ascii_case(value)).then(|| (key.to_string(), value.clone(), current))
+            })
+            .collect();
+        let entry = locks.entry(uuid.clone()).or_insert_with(|| Locked {
+            device: device.clone(),
+            bssid: None,
+            band: None,
+            original: BTreeMap::new(),
+        });
+        entry.device = device.clone();
+        entry.bssid = bssid.clone().or(entry.bssid.take());
+        entry.band = band.or(entry.band);
+        // First change wins, so re-locking keeps the user's original
+        for (key, _, current) in &changes {
+            entry.original.entry(key.clone()).or_insert_with(|| current.clone());
+        }
+        save(&locks)?;
+        if !changes.is_empty() {
+            let settings: Vec<(String, String)> = changes.into_iter().map(|(k, v, _)| (k, v)).collect();
+            nmcli_modify(&uuid, &settings)?;
+            reactivate(&uuid, &device);
+        }
+        info!(event = "lock", interface = device.as_str(), bssid:? = bssid, band:? = band;
+              "Locked connection {} on {} (bssid: {:?}, band: {:?})", uuid, device, bssid, band);
+        devices.push(device);
+    }
+    Ok(devices)
+}
+
+/// Put back the original BSSID/band of every locked profile (on `interface` only if
+/// given); returns the unlocked devices
+pub fn unlock(interface: Option<&str>) -> Result<Vec<String>> {
+    let mut locks = load();
+    let uuids: Vec<String> = locks.iter()
+        .filter(|(_, l)| interface.is_none_or(|i| i == l.device))
+        .map(|(uuid, _)| uuid.clone())
+        .collect();
+    let mut devices = Vec::new();
+    for uuid in uuids {
+        let Some(locked) = locks.remove(&uuid) else { continue };
+        let settings: Vec<(String, String)> = locked.original.into_iter().collect();
+        if !settings.is_empty() {
+            match nmcli_modify(&uuid, &settings) {
+                Ok(()) => reactivate(&uuid, &locked.device),
+                Err(e) => warn!("Could not unlock connection {} (profile removed?): {}", uuid, e),
+            }
+        }
+        info!(event = "unlock", interface = locked.device.as_str(); "Unlocked connection {} on {}", uuid, locked.device);
+        devices.push(locked.device);
+    }
+    save(&locks)?;
+    Ok(devices)
+}
+
+/// `revert --backend`: drop every lock
+pub fn revert() {
+    if let Err(e) = unlock(None) {
+        warn!("Could not remove BSSID/band locks: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_bssid() {
+        assert!(valid_bssid("aa:BB:cc:00:11:22"));
+        assert!(!valid_bssid("aa:bb:cc:00:11"));
+        assert!(!valid_bssid("aa-bb-cc-00-11-22"));
+        assert!(!valid_bssid("aa:bb:cc:00:11:2g"));
+        assert_eq!(LockBand::Band5GHz.nm_value(), "a");
+    }
+}
@@ -0,0 +1,339 @@
+//! mac80211 queue tuning through debugfs
+//!
+//! For drivers that use mac80211 TXQs, the per-phy fq_codel (`aqm`) sits below the
+//! root qdisc and often decides Wi-Fi latency more than CAKE does. The knobs live in
+//! /sys/kernel/debug/ieee80211/<phy>/ and only exist when debugfs is mounted and the
+//! driver supports them, so every setter treats a missing file as "not supported".
+//!
+//! AQL (airtime queue limits) bounds how much airtime each station may have queued
+//! in the driver/firmware; the game-mode preset lowers it on ath11k/mt76 by default.
+//! Aggregation limits during game mode come from the quirks table (opt-in).
+
+use anyhow::{Context, Result};
+use log::{info, warn, debug};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::structs::Mac80211Config;
+use crate::network::wifi::{DriverCategory, WifiInterface};
+use crate::system::quirks;
+
+pub const DEBUGFS_ROOT: &str = "/sys/kernel/debug/ieee80211";
+
+/// Original values, restored on revert
+const BACKUP_PATH: &str = "/var/lib/hifi-wifi/mac80211-backup.json";
+
+/// debugfs directory of the phy behind a Wi-Fi interface
+pub fn phy_dir(iface: &str) -> Option<PathBuf> {
+    let phy = fs::read_to_string(format!("/sys/class/net/{}/phy80211/name", iface)).ok()?;
+    let dir = Path::new(DEBUGFS_ROOT).join(phy.trim());
+    dir.is_dir().then_some(dir)
+}
+
+/// One row of the `aqm` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AqmParam {
+    pub name: String,
+    pub value: u64,
+    pub writable: bool,
+}
+
+/// Parse `access name value` rows, e.g. `RW fq_limit 8192`
+pub fn parse_aqm(content: &str) -> Vec<AqmParam> {
+    content.lines()
+        .filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            let access = cols.next()?;
+            let name = cols.next()?;
+            let value = cols.next()?.parse().ok()?;
+            Some(AqmParam { name: name.to_string(), value, writable: access.contains('W') })
+        })
+        .collect()
+}
+
+/// Current aqm parameters, None if the driver has no mac80211 TXQs (or we can't read debugfs)
+pub fn read_aqm(iface: &str) -> Option<Vec<AqmParam>> {
+    let content = fs::read_to_string(phy_dir(iface)?.join("aqm")).ok()?;
+    Some(parse_aqm(&content))
+}
+
+fn load_backup() -> BTreeMap<String, String> {
+    fs::read_to_string(BACKUP_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup(backup: &BTreeMap<String, String>) {
+    let write = || -> Result<()> {
+        if backup.is_empty() {
+            let _ = fs::remove_file(BACKUP_PATH);
+            return Ok(());
+        }
+        if let Some(parent) = Path::new(BACKUP_PATH).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = format!("{}.tmp", BACKUP_PATH);
+        fs::write(&tmp, serde_json::to_string_pretty(backup)?)?;
+        fs::rename(&tmp, BACKUP_PATH)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        warn!("Could not record original mac80211 setting: {}", e);
+    }
+}
+
+/// Remember what to write back to `file` to undo a change (first change wins)
+fn record_original(iface: &str, file: &str, param: &str, restore: &str) {
+    let mut backup = load_backup();
+    let key = format!("{}|{}|{}", iface, file, param);
+    if backup.contains_key(&key) {
+        return;
+    }
+    backup.insert(key, restore.to_string());
+    save_backup(&backup);
+}
+
+/// Remove and return the recorded originals of one file
+fn take_originals(iface: &str, file: &str) -> Vec<String> {
+    let mut backup = load_backup();
+    let prefix = format!("{}|{}|", iface, file);
+    let keys: Vec<String> = backup.keys().filter(|k| k.starts_with(&prefix)).cloned().collect();
+    let restores = keys.iter().filter_map(|k| backup.remove(k)).collect();
+    if !keys.is_empty() {
+        save_backup(&backup);
+    }
+    restores
+}
+
+fn write_debugfs(dir: &Path, file: &str, content: &str) -> Result<()> {
+    fs::write(dir.join(file), content).with_context(|| format!("Failed to write '{}' to {}", content, file))
+}
+
+/// Apply the always-on tuning (fq_codel limits, airtime fairness) to an interface
+pub fn apply(ifc: &WifiInterface, config: &Mac80211Config) -> Result<()> {
+    apply_aqm(&ifc.name, config)?;
+    if config.airtime_fairness && aql_driver(&ifc.category, config) {
+        apply_airtime_fairness(&ifc.name)?;
+    }
+    Ok(())
+}
+
+/// Apply the configured fq_codel limits to the interface's phy
+fn apply_aqm(iface: &str, config: &Mac80211Config) -> Result<()> {
+    if !config.aqm_enabled {
+        return Ok(());
+    }
+    let Some(dir) = phy_dir(iface) else {
+        debug!("No mac80211 debugfs for {} (debugfs not mounted?)", iface);
+        return Ok(());
+    };
+    let Ok(content) = fs::read_to_string(dir.join("aqm")) else {
+        debug!("{} has no mac80211 aqm (driver without TXQs)", iface);
+        return Ok(());
+    };
+    let params = parse_aqm(&content);
+
+    let wanted = [
+        ("fq_limit", config.fq_limit),
+        ("fq_quantum", config.fq_quantum),
+        ("codel_target", config.codel_target_us),
+    ];
+    for (name, value) in wanted {
+        let Some(value) = value else { continue };
+        match params.iter().find(|p| p.name == name) {
+            Some(p) if !p.writable => debug!("aqm {} is read-only on {}", name, iface),
+            Some(p) if p.value == value => {}
+            Some(p) => {
+                record_original(iface, "aqm", name, &format!("{} {}", name, p.value));
+                write_debugfs(&dir, "aqm", &format!("{} {}", name, value))?;
+                info!(event = "aqm", interface = iface, param = name, old = p.value, new = value;
+                      "mac80211 {} on {}: {} -> {}", name, iface, p.value, value);
+            }
+            None => debug!("Kernel does not expose aqm {} on {}", name, iface),
+        }
+    }
+    Ok(())
+}
+
+/// mac80211 airtime scheduler flags: account TX and RX airtime
+const AIRTIME_TX_RX: u32 = 0x3;
+
+/// Let the airtime scheduler share air time between stations by TX+RX use
+fn apply_airtime_fairness(iface: &str) -> Result<()> {
+    let Some(dir) = phy_dir(iface) else { return Ok(()) };
+    let Ok(current) = fs::read_to_string(dir.join("airtime_flags")) else {
+        debug!("{} has no airtime scheduler", iface);
+        return Ok(());
+    };
+    let current = current.trim();
+    if current.parse::<u32>().ok() == Some(AIRTIME_TX_RX) {
+        return Ok(());
+    }
+    record_original(iface, "airtime_flags", "flags", current);
+    write_debugfs(&dir, "airtime_flags", &AIRTIME_TX_RX.to_string())?;
+    info!("Airtime fairness enabled on {} (flags {} -> {})", iface, current, AIRTIME_TX_RX);
+    Ok(())
+}
+
+/// Access categories in `aql_txq_limit` order (the index is what the file takes on write)
+const AQL_ACS: [&str; 4] = ["VO", "VI", "BE", "BK"];
+
+/// `(ac index, low us, high us)` rows of `aql_txq_limit`
+pub fn parse_aql_limits(content: &str) -> Vec<(usize, u32, u32)> {
+    content.lines()
+        .filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            let name = cols.next()?;
+            let ac = AQL_ACS.iter().position(|a| *a == name)?;
+            let low = cols.next()?.parse().ok()?;
+            let high = cols.next()?.parse().ok()?;
+            Some((ac, low, high))
+        })
+        .collect()
+}
+
+/// Whether AQL/airtime tuning is enabled for this driver family
+fn aql_driver(category: &DriverCategory, config: &Mac80211Config) -> bool {
+    config.aql_categories.iter().any(|c| c == quirks::category_key(category))
+}
+
+/// Swap AQL limits to the low-latency preset while gaming, and back afterwards
+fn set_aql_game_mode(ifc: &WifiInterface, active: bool, config: &Mac80211Config) -> Result<()> {
+    if !config.aql_enabled || !aql_driver(&ifc.category, config) {
+        return Ok(());
+    }
+    let Some(dir) = phy_dir(&ifc.name) else { return Ok(()) };
+
+    if !active {
+        let restores = take_originals(&ifc.name, "aql_txq_limit");
+        for restore in &restores {
+            write_debugfs(&dir, "aql_txq_limit", restore)?;
+        }
+        if !restores.is_empty() {
+            info!(event = "aql", interface = ifc.name.as_str(), new = "default";
+                  "AQL limits restored on {}", ifc.name);
+        }
+        return Ok(());
+    }
+
+    let Ok(content) = fs::read_to_string(dir.join("aql_txq_limit")) else {
+        debug!("{} does not expose AQL limits", ifc.name);
+        return Ok(());
+    };
+    for (ac, low, high) in parse_aql_limits(&content) {
+        record_original(&ifc.name, "aql_txq_limit", AQL_ACS[ac], &format!("{} {} {}", ac, low, high));
+        write_debugfs(&dir, "aql_txq_limit", &format!("{} {} {}", ac, config.aql_game_low_us, config.aql_game_high_us))?;
+    }
+    info!(event = "aql", interface = ifc.name.as_str(), new = "game";
+          "AQL limits lowered on {} ({}/{}us) for game mode", ifc.name, config.aql_game_low_us, config.aql_game_high_us);
+    Ok(())
+}
+
+/// MAC of the AP we're associated with, from the netdev's debugfs station list
+fn station_mac(dir: &Path, iface: &str) -> Option<String> {
+    fs::read_dir(dir.join(format!("netdev:{}/stations", iface)))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .next()
+}
+
+/// Apply (or undo) the per-category game-mode debugfs writes from the quirks table
+fn set_aggregation_game_mode(ifc: &WifiInterface, active: bool, config: &Mac80211Config) -> Result<()> {
+    if !config.aggregation_game_mode {
+        return Ok(());
+    }
+    let writes = quirks::get().game_mode_writes(&ifc.category);
+    if writes.is_empty() {
+        return Ok(());
+    }
+    let Some(dir) = phy_dir(&ifc.name) else { return Ok(()) };
+    let station = station_mac(&dir, &ifc.name);
+
+    let mut changed = 0;
+    for write in writes {
+        if write.path.contains("{station}") && station.is_none() {
+            debug!("No station on {}; skipping {}", ifc.name, write.path);
+            continue;
+        }
+        let path = write.path
+            .replace("{iface}", &ifc.name)
+            .replace("{station}", station.as_deref().unwrap_or_default());
+
+        if active {
+            let restore = match &write.restore {
+                Some(r) => r.clone(),
+                None => match fs::read_to_string(dir.join(&path)) {
+                    Ok(v) => v.trim().to_string(),
+                    Err(_) => {
+                        debug!("{} not available on {}", path, ifc.name);
+                        continue;
+                    }
+                },
+            };
+            record_original(&ifc.name, &path, "game_mode", &restore);
+            write_debugfs(&dir, &path, &write.game)?;
+            changed += 1;
+        } else {
+            for restore in take_originals(&ifc.name, &path) {
+                // The station may be gone by now (roam/disconnect); nothing to restore then
+                if write_debugfs(&dir, &path, &restore).is_ok() {
+                    changed += 1;
+                }
+            }
+        }
+    }
+    if changed > 0 {
+        info!(event = "aggregation", interface = ifc.name.as_str(), new = if active { "reduced" } else { "full" };
+              "{} aggregation on {} ({} setting(s))", if active { "Reduced" } else { "Restored full" }, ifc.name, changed);
+    }
+    Ok(())
+}
+
+/// Switch every game-mode preset (AQL, aggregation) on or off
+pub fn set_game_mode(ifc: &WifiInterface, active: bool, config: &Mac80211Config) -> Result<()> {
+    set_aql_game_mode(ifc, active, config)?;
+    set_aggregation_game_mode(ifc, active, config)
+}
+
+/// Write back every recorded original value
+pub fn revert() {
+    let backup = load_backup();
+    let mut restored = 0;
+    for (key, restore) in &backup {
+        let mut parts = key.splitn(3, '|');
+        let (Some(iface), Some(file)) = (parts.next(), parts.next()) else { continue };
+        // Interfaces that are gone (or reloaded drivers) already run kernel defaults
+        let Some(dir) = phy_dir(iface) else { continue };
+        match write_debugfs(&dir, file, restore) {
+            Ok(()) => restored += 1,
+            Err(e) => warn!("Could not restore {} on {}: {}", file, iface, e),
+        }
+    }
+    let _ = fs::remove_file(BACKUP_PATH);
+    if restored > 0 {
+        info!("Restored {} mac80211 setting(s)", restored);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aqm() {
+        let content = "access name value\nR fq_flows_cnt 4096\nR fq_backlog 0\nRW fq_memory_limit 4194304\nRW fq_limit 8192\nRW fq_quantum 300\n";
+        let params = parse_aqm(content);
+        assert_eq!(params.len(), 5);
+        assert_eq!(params[0], AqmParam { name: "fq_flows_cnt".into(), value: 4096, writable: false });
+        assert_eq!(params[3], AqmParam { name: "fq_limit".into(), value: 8192, writable: true });
+
+        let aql = "AC\tAQL limit low\tAQL limit high\nVO\t5000\t12000\nVI\t5000\t12000\nBE\t5000\t12000\nBK\t5000\t12000\n";
+        let limits = parse_aql_limits(aql);
+        assert_eq!(limits.len(), 4);
+        assert_eq!(limits[2], (2, 5000, 12000));
+    }
+}
@@ -0,0 +1,123 @@
+//! MAC address randomization
+//!
+//! A per-network random MAC breaks router rules keyed on the device's address, such as
+//! a QoS priority or a port forward for Moonlight. `[privacy] mac_randomization` sets one
+//! policy for iwd (`AddressRandomization`, merged with the rest of our iwd settings) and
+//! for the active Wi-Fi profiles in NetworkManager (`wifi.cloned-mac-address`, originals
+//! restored by `revert --backend`). Left unset, neither is touched.
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::network::ipv6::{nmcli_get, nmcli_modify, parse_active_wifi};
+use crate::network::lock::reactivate;
+
+const BACKUP_PATH: &str = "/var/lib/hifi-wifi/nm-mac.json";
+const NM_CLONED_MAC: &str = "802-11-wireless.cloned-mac-address";
+
+/// Which MAC address the Wi-Fi interface presents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacRandomization {
+    /// Leave iwd and NetworkManager as they are
+    #[default]
+    Default,
+    /// The hardware address everywhere
+    Off,
+    /// A fixed random address per network
+    Network,
+    /// A new random address (per connection on NetworkManager, per start on iwd)
+    Full,
+}
+
+impl MacRandomization {
+    /// iwd `[General] AddressRandomization`
+    pub fn iwd_value(&self) -> Option<&'static str> {
+        match self {
+            MacRandomization::Default => None,
+            MacRandomization::Off => Some("disabled"),
+            MacRandomization::Network => Some("network"),
+            MacRandomization::Full => Some("once"),
+        }
+    }
+
+    /// NetworkManager `wifi.cloned-mac-address`
+    pub fn nm_value(&self) -> Option<&'static str> {
+        match self {
+            MacRandomization::Default => None,
+            MacRandomization::Off => Some("permanent"),
+            MacRandomization::Network => Some("stable"),
+            MacRandomization::Full => Some("random"),
+        }
+    }
+}
+
+type Backup = BTreeMap<String, String>;
+
+fn load_backup() -> Backup {
+    fs::read_to_string(BACKUP_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup(backup: &Backup) -> Result<()> {
+    if let Some(parent) = Path::new(BACKUP_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(BACKUP_PATH, serde_json::to_string_pretty(backup)?)?;
+    Ok(())
+}
+
+/// Apply `policy` to every active Wi-Fi profile; a changed profile reconnects once
+pub fn apply(policy: MacRandomization) -> Result<()> {
+    let Some(value) = policy.nm_value() else { return Ok(()) };
+    let output = Command::new("nmcli")
+        .args(["-t", "-f", "UUID,TYPE,DEVICE", "connection", "show", "--active"])
+        .output()?;
+    let mut backup = load_backup();
+    for (uuid, device) in parse_active_wifi(&String::from_utf8_lossy(&output.stdout)) {
+        let Some(current) = nmcli_get(&uuid, NM_CLONED_MAC) else { continue };
+        if current == value {
+            continue;
+        }
+        // First change wins, so repeated applies keep the user's original
+        backup.entry(uuid.clone()).or_insert(current);
+        save_backup(&backup)?;
+        nmcli_modify(&uuid, &[(NM_CLONED_MAC.to_string(), value.to_string())])?;
+        reactivate(&uuid, &device);
+        info!(event = "mac_randomization", interface = device.as_str(), policy:? = policy;
+              "MAC randomization {:?} applied to connection {} on {}", policy, uuid, device);
+    }
+    Ok(())
+}
+
+/// Put back the original cloned MAC setting of every profile we changed
+pub fn revert() {
+    for (uuid, original) in load_backup() {
+        match nmcli_modify(&uuid, &[(NM_CLONED_MAC.to_string(), original)]) {
+            Ok(()) => info!("Restored MAC address setting on connection {}", uuid),
+            Err(e) => warn!("Could not restore MAC address setting on connection {} (profile removed?): {}", uuid, e),
+        }
+    }
+    let _ = fs::remove_file(BACKUP_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_values() {
+        let policy: MacRandomization = serde_json::from_str("\"off\"").unwrap();
+        assert_eq!(policy.iwd_value(), Some("disabled"));
+        assert_eq!(policy.nm_value(), Some("permanent"));
+        assert_eq!(MacRandomization::Full.iwd_value(), Some("once"));
+        assert_eq!(MacRandomization::Default.nm_value(), None);
+    }
+}
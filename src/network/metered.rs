@@ -0,0 +1,76 @@
+//! Metered links (phone hotspots)
+//!
+//! NetworkManager flags a connection metered when its profile says so or when it
+//! guesses it (Android tethering advertises it in the beacon). On such a link the
+//! daemon stops its constant gateway pings, probes DNS less often, skips pre-warming
+//! and puts TCP keepalives back to the kernel defaults so idle sockets don't keep
+//! waking the phone. `[wifi] metered = "yes"/"no"` overrides the detection.
+
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// DNS probes run this many times less often on a metered link
+pub const PROBE_INTERVAL_FACTOR: u32 = 6;
+
+/// Kernel defaults of the keepalive keys DEFAULT_SYSCTL shortens
+const KEEPALIVE_DEFAULTS: &[(&str, &str)] = &[
+    ("net.ipv4.tcp_keepalive_time", "7200"),
+    ("net.ipv4.tcp_keepalive_intvl", "75"),
+    ("net.ipv4.tcp_keepalive_probes", "9"),
+];
+
+/// `[wifi] metered`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeteredMode {
+    /// Follow NetworkManager
+    #[default]
+    Auto,
+    Yes,
+    No,
+}
+
+impl MeteredMode {
+    /// Whether to treat a link as metered, given NM's `Metered` device property
+    pub fn resolve(self, nm_metered: u32) -> bool {
+        match self {
+            // 1 = yes, 3 = guess-yes
+            MeteredMode::Auto => matches!(nm_metered, 1 | 3),
+            MeteredMode::Yes => true,
+            MeteredMode::No => false,
+        }
+    }
+}
+
+fn proc_path(key: &str) -> String {
+    format!("/proc/sys/{}", key.replace('.', "/"))
+}
+
+/// Kernel keepalives while a metered link is up, the configured ones otherwise;
+/// keys the user took out of `[system.sysctl]` are left alone
+pub fn set_keepalives(metered: bool, configured: &BTreeMap<String, String>) {
+    for (key, default) in KEEPALIVE_DEFAULTS {
+        let Some(ours) = configured.get(*key) else { continue };
+        let value = if metered { default } else { ours.as_str() };
+        if let Err(e) = fs::write(proc_path(key), value) {
+            warn!("Failed to set {} = {}: {}", key, value, e);
+        }
+    }
+    info!("TCP keepalives {}", if metered { "back to kernel defaults (metered link)" } else { "restored" });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert!(MeteredMode::Auto.resolve(3));
+        assert!(!MeteredMode::Auto.resolve(4));
+        assert!(!MeteredMode::No.resolve(1));
+        assert!(MeteredMode::Yes.resolve(0));
+        assert_eq!(proc_path("net.ipv4.tcp_keepalive_time"), "/proc/sys/net/ipv4/tcp_keepalive_time");
+    }
+}
@@ -0,0 +1,160 @@
+//! Live link metrics for overlays (MangoHud)
+//!
+//! The governor rewrites two small files in /run/hifi-wifi every tick:
+//! - `metrics.json`: full snapshot for scripts and tooling
+//! - `mangohud.txt`: one pre-formatted line, shown with
+//!   `exec=cat /run/hifi-wifi/mangohud.txt` in MangoHud.conf
+//!
+//! Both are written via tmp + rename so readers never see a partial file.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::network::bufferbloat::BufferbloatReport;
+use crate::network::dns::ResolverLatency;
+use crate::network::mac80211::AqmParam;
+use crate::network::stats::LatencySummary;
+use crate::network::tc::CakeStats;
+use crate::system::budget::DaemonUsage;
+
+pub const METRICS_DIR: &str = "/run/hifi-wifi";
+const METRICS_JSON: &str = "metrics.json";
+const MANGOHUD_TXT: &str = "mangohud.txt";
+
+/// Health of one connected interface at the latest tick
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkMetrics {
+    pub interface: String,
+    /// Gateway RTT over the last minute (None until the monitor has samples)
+    pub latency: Option<LatencySummary>,
+    pub signal_dbm: Option<i32>,
+    pub cake_mbit: Option<u32>,
+    /// Drops, ECN marks, backlog and per-tin delays of the CAKE qdisc
+    #[serde(default)]
+    pub cake_stats: Option<CakeStats>,
+    /// Bufferbloat grade of the current association so far
+    #[serde(default)]
+    pub bufferbloat: Option<BufferbloatReport>,
+    /// Latest timing of the link's DNS resolvers
+    #[serde(default)]
+    pub dns: Vec<ResolverLatency>,
+    /// mac80211 fq_codel parameters (debugfs is root-only, so `status` reads them here)
+    #[serde(default)]
+    pub aqm: Vec<AqmParam>,
+    /// Traffic over the last tick (rx + tx)
+    #[serde(default)]
+    pub throughput_mbit: u32,
+    pub game_mode: bool,
+}
+
+/// All links at one tick
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// Unix time in seconds, lets readers detect a stalled daemon
+    pub timestamp: u64,
+    pub links: Vec<LinkMetrics>,
+    /// The daemon's own memory and CPU use
+    #[serde(default)]
+    pub daemon: Option<DaemonUsage>,
+}
+
+impl MetricsSnapshot {
+    pub fn new(links: Vec<LinkMetrics>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self { timestamp, links, daemon: None }
+    }
+
+    /// Write both metrics files to `dir`
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        write_atomic(&dir.join(METRICS_JSON), &serde_json::to_string(self)?)?;
+        write_atomic(&dir.join(MANGOHUD_TXT), &format!("{}\n", self.overlay_line()))?;
+        Ok(())
+    }
+
+    /// Read the snapshot last written to `dir` (None if missing or unparsable)
+    pub fn read(dir: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(dir.join(METRICS_JSON)).ok()?).ok()
+    }
+
+    /// Remove the metrics files so overlays don't show stale numbers
+    pub fn clear(dir: &Path) {
+        let _ = fs::remove_file(dir.join(METRICS_JSON));
+        let _ = fs::remove_file(dir.join(MANGOHUD_TXT));
+    }
+
+    /// Compact one-line summary, e.g. `wlan0 RTT 3ms ±1 loss 0% -52dBm CAKE 340M [GAME]`
+    pub fn overlay_line(&self) -> String {
+        if self.links.is_empty() {
+            return "hifi-wifi: no link".to_string();
+        }
+        self.links.iter().map(|l| {
+            let mut line = l.interface.clone();
+            match &l.latency {
+                Some(lat) => line.push_str(&format!(" RTT {:.0}ms ±{:.0} loss {:.0}%", lat.p50_ms, lat.jitter_ms, lat.loss_pct)),
+                None => line.push_str(" RTT --"),
+            }
+            if let Some(signal) = l.signal_dbm {
+                line.push_str(&format!(" {}dBm", signal));
+            }
+            if let Some(mbit) = l.cake_mbit {
+                line.push_str(&format!(" CAKE {}M", mbit));
+            }
+            if l.game_mode {
+                line.push_str(" [GAME]");
+            }
+            line
+        }).collect::<Vec<_>>().join(" | ")
+    }
+}
+
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_line() {
+        let snapshot = MetricsSnapshot::new(vec![LinkMetrics {
+            interface: "wlan0".to_string(),
+            latency: Some(LatencySummary { p50_ms: 3.4, jitter_ms: 1.2, ..Default::default() }),
+            signal_dbm: Some(-52),
+            cake_mbit: Some(340),
+            cake_stats: None,
+            bufferbloat: None,
+            dns: Vec::new(),
+            aqm: Vec::new(),
+            throughput_mbit: 0,
+            game_mode: true,
+        }]);
+        assert_eq!(snapshot.overlay_line(), "wlan0 RTT 3ms ±1 loss 0% -52dBm CAKE 340M [GAME]");
+
+        let unprobed = MetricsSnapshot::new(vec![LinkMetrics { interface: "eth0".to_string(), ..Default::default() }]);
+        assert_eq!(unprobed.overlay_line(), "eth0 RTT --");
+        assert_eq!(MetricsSnapshot::new(vec![]).overlay_line(), "hifi-wifi: no link");
+    }
+
+    #[test]
+    fn test_write_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("hifi-wifi-metrics-{}", std::process::id()));
+        let snapshot = MetricsSnapshot::new(vec![LinkMetrics { interface: "wlan0".to_string(), ..Default::default() }]);
+        snapshot.write(&dir).unwrap();
+        let read = MetricsSnapshot::read(&dir).unwrap();
+        assert_eq!(read.links[0].interface, "wlan0");
+        MetricsSnapshot::clear(&dir);
+        assert!(!dir.join(MANGOHUD_TXT).exists());
+        let _ = fs::remove_dir(&dir);
+    }
+}
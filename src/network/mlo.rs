@@ -0,0 +1,144 @@
+//! Wi-Fi 7 multi-link operation (MLO)
+//!
+//! An MLO association spreads traffic over several links (e.g. 5GHz + 6GHz) while
+//! NetworkManager and `iw link` report a single bitrate. Links and their channels
+//! come from `iw dev <iface> info` (nl80211 MLD links); per-link TX rates from
+//! `iw dev <iface> station dump` on kernels that report link stats.
+
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::network::nm::WifiBand;
+use crate::network::probe::IwProbe;
+
+/// One affiliated link of an MLO association
+#[derive(Debug, Clone, PartialEq)]
+pub struct MloLink {
+    pub link_id: u8,
+    pub freq_mhz: u32,
+    pub width_mhz: Option<u32>,
+    pub tx_bitrate_mbit: Option<f64>,
+}
+
+impl MloLink {
+    pub fn band(&self) -> WifiBand {
+        WifiBand::from_frequency(self.freq_mhz)
+    }
+}
+
+/// Parse the `MLD with links:` section of `iw dev <iface> info`
+pub fn parse_info_links(output: &str) -> Vec<MloLink> {
+    let link_re = Regex::new(r"- link ID\s+(\d+)").unwrap();
+    let chan_re = Regex::new(r"channel \d+ \((\d+) MHz\)(?:, width: (\d+) MHz)?").unwrap();
+
+    let mut links: Vec<MloLink> = Vec::new();
+    for line in output.lines() {
+        if let Some(caps) = link_re.captures(line) {
+            links.push(MloLink {
+                link_id: caps[1].parse().unwrap_or(0),
+                freq_mhz: 0,
+                width_mhz: None,
+                tx_bitrate_mbit: None,
+            });
+        } else if let (Some(caps), Some(link)) = (chan_re.captures(line), links.last_mut()) {
+            if link.freq_mhz == 0 {
+                link.freq_mhz = caps[1].parse().unwrap_or(0);
+                link.width_mhz = caps.get(2).and_then(|w| w.as_str().parse().ok());
+            }
+        }
+    }
+    links.retain(|l| l.freq_mhz > 0);
+    links
+}
+
+/// Per-link `tx bitrate` from a station dump with link sections (`link ID 0` / `Link 0:`)
+pub fn parse_link_bitrates(output: &str) -> HashMap<u8, f64> {
+    let section_re = Regex::new(r"(?i)^\s*link(?: id)?:?\s+(\d+)\b").unwrap();
+    let mut rates = HashMap::new();
+    let mut current = None;
+    for line in output.lines() {
+        if let Some(caps) = section_re.captures(line) {
+            current = caps[1].parse::<u8>().ok();
+        } else if let (Some(id), Some(rest)) = (current, line.trim().strip_prefix("tx bitrate:")) {
+            if let Some(mbit) = rest.split_whitespace().next().and_then(|v| v.parse().ok()) {
+                rates.insert(id, mbit);
+            }
+        }
+    }
+    rates
+}
+
+/// MLO links of the interface; empty for single-link associations
+pub fn links(interface: &str) -> Vec<MloLink> {
+    links_from(&IwProbe::new(interface))
+}
+
+/// `links` from this tick's `iw` output
+pub fn links_from(iw: &IwProbe) -> Vec<MloLink> {
+    let Some(info) = iw.info() else { return Vec::new() };
+    let mut links = parse_info_links(info);
+    if links.len() < 2 {
+        return Vec::new();
+    }
+    if let Some(dump) = iw.station_dump() {
+        let rates = parse_link_bitrates(dump);
+        for link in &mut links {
+            link.tx_bitrate_mbit = rates.get(&link.link_id).copied();
+        }
+    }
+    links
+}
+
+/// Combined TX rate of all links in Kbit/s (None unless every link reports one)
+pub fn aggregate_bitrate_kbit(links: &[MloLink]) -> Option<u32> {
+    if links.len() < 2 {
+        return None;
+    }
+    let total: Option<f64> = links.iter().map(|l| l.tx_bitrate_mbit).sum();
+    total.map(|mbit| (mbit * 1000.0) as u32)
+}
+
+/// Short status text, e.g. `link0 6GHz/320MHz 2882Mbit`
+pub fn describe(link: &MloLink) -> String {
+    let band = match link.band() {
+        WifiBand::Band2_4GHz => "2.4GHz",
+        WifiBand::Band5GHz => "5GHz",
+        WifiBand::Band6GHz => "6GHz",
+        WifiBand::Unknown => "?",
+    };
+    let mut text = format!("link{} {}", link.link_id, band);
+    if let Some(width) = link.width_mhz {
+        text.push_str(&format!("/{}MHz", width));
+    }
+    if let Some(mbit) = link.tx_bitrate_mbit {
+        text.push_str(&format!(" {:.0}Mbit", mbit));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mlo_links() {
+        let info = "Interface wlan0\n\tifindex 3\n\ttype managed\n\tMLD with links:\n\t - link ID  0 link addr 02:00:00:00:00:01\n\t   channel 37 (6135 MHz), width: 320 MHz, center1: 6105 MHz\n\t - link ID  2 link addr 02:00:00:00:00:02\n\t   channel 36 (5180 MHz), width: 80 MHz, center1: 5210 MHz\n\ttxpower 22.00 dBm\n";
+        let mut links = parse_info_links(info);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].freq_mhz, 6135);
+        assert_eq!(links[0].width_mhz, Some(320));
+        assert_eq!(links[1].link_id, 2);
+        assert_eq!(links[1].band(), WifiBand::Band5GHz);
+
+        let dump = "Station aa:bb:cc:dd:ee:ff (on wlan0)\n\tlink ID 0\n\t\ttx bitrate:\t2882.4 MBit/s 320MHz EHT-MCS 11\n\tlink ID 2\n\t\ttx bitrate:\t1200.9 MBit/s 80MHz HE-MCS 11\n";
+        let rates = parse_link_bitrates(dump);
+        for link in &mut links {
+            link.tx_bitrate_mbit = rates.get(&link.link_id).copied();
+        }
+        assert_eq!(aggregate_bitrate_kbit(&links), Some(4_083_300));
+        assert_eq!(describe(&links[0]), "link0 6GHz/320MHz 2882Mbit");
+
+        // Single-link (non-MLO) association
+        assert!(parse_info_links("Interface wlan0\n\tchannel 36 (5180 MHz), width: 80 MHz\n").is_empty());
+    }
+}
@@ -4,3 +4,33 @@ pub mod nm;
 pub mod tc;
 pub mod stats;
 pub mod governor;
+pub mod state;
+pub mod latency;
+pub mod metrics;
+pub mod abtest;
+pub mod throughput;
+pub mod speedtest;
+pub mod nm_powersave;
+pub mod power_save;
+pub mod policy;
+pub mod schedule;
+pub mod mac80211;
+pub mod reconnect;
+pub mod mlo;
+pub mod bufferbloat;
+pub mod prewarm;
+pub mod mtu;
+pub mod ipv6;
+pub mod dns;
+pub mod lock;
+pub mod metered;
+pub mod stream_guard;
+pub mod usage;
+pub mod session;
+pub mod history;
+pub mod push;
+pub mod probe;
+pub mod scan_abort;
+pub mod mac_privacy;
+pub mod link_id;
+pub mod ethernet;
@@ -0,0 +1,240 @@
+//! Path MTU probing and black-hole workarounds
+//!
+//! Mesh and powerline backhauls often carry less than 1500 bytes and some drop the
+//! ICMP "fragmentation needed" replies, so oversized packets vanish instead of being
+//! resent smaller (a PMTU black hole). The probe pings with DF set and binary-searches
+//! the largest size that gets through. When a black hole is found, `apply` can lower
+//! the interface MTU or clamp TCP MSS with nftables; both are undone by `revert`.
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::network::latency;
+
+const BACKUP_PATH: &str = "/var/lib/hifi-wifi/mtu-backup.json";
+const NFT_TABLE: &str = "hifi_wifi_mss";
+/// IPv4 + ICMP header bytes on top of the ping payload
+const ICMP_OVERHEAD: u32 = 28;
+/// Smallest MTU worth probing (IPv6 minimum)
+const MIN_MTU: u32 = 1280;
+
+/// What `apply` does about a detected black hole
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MtuFix {
+    /// Only report (doctor)
+    #[default]
+    Off,
+    /// Lower the interface MTU to the path MTU
+    Interface,
+    /// Clamp TCP MSS on outgoing SYNs (nftables)
+    MssClamp,
+}
+
+/// Result of probing one interface
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtuReport {
+    pub interface: String,
+    pub target: String,
+    pub interface_mtu: u32,
+    /// Largest packet that made it (None if even the minimum failed)
+    pub path_mtu: Option<u32>,
+    /// MTU the kernel learned for the target from ICMP, if any
+    pub route_mtu: Option<u32>,
+}
+
+impl MtuReport {
+    /// Path is narrower than the interface and the kernel was never told
+    pub fn black_hole(&self) -> bool {
+        match self.path_mtu {
+            Some(path) => path < self.interface_mtu && self.route_mtu.is_none_or(|r| r > path),
+            None => false,
+        }
+    }
+}
+
+/// `mtu N` from `ip route get <addr>` (present once a PMTU exception is cached)
+pub fn parse_route_mtu(output: &str) -> Option<u32> {
+    let words: Vec<&str> = output.split_whitespace().collect();
+    words.iter().position(|w| *w == "mtu").and_then(|i| words.get(i + 1)).and_then(|v| v.parse().ok())
+}
+
+/// Largest size in `low..=high` for which `fits` holds, assuming it holds at `low`
+pub fn search_mtu(mut low: u32, mut high: u32, mut fits: impl FnMut(u32) -> bool) -> u32 {
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
+pub fn interface_mtu(interface: &str) -> Option<u32> {
+    fs::read_to_string(format!("/sys/class/net/{}/mtu", interface)).ok()?.trim().parse().ok()
+}
+
+fn route_mtu(target: &str) -> Option<u32> {
+    let output = Command::new("ip").args(["route", "get", target]).output().ok()?;
+    parse_route_mtu(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// One DF-flagged ping of `mtu` bytes
+fn fits(interface: &str, target: &str, mtu: u32) -> bool {
+    let size = (mtu - ICMP_OVERHEAD).to_string();
+    // Two tries so a single lost reply isn't mistaken for a size limit
+    (0..2).any(|_| {
+        Command::new("ping")
+            .args(["-n", "-q", "-c", "1", "-W", "1", "-M", "do", "-s", &size, "-I", interface, target])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Probe the path MTU from `interface` to `target` (stream host, else the gateway)
+pub fn probe(interface: &str, target: Option<&str>) -> Option<MtuReport> {
+    let target = match target {
+        Some(t) => t.to_string(),
+        None => latency::default_gateway(interface)?,
+    };
+    let interface_mtu = interface_mtu(interface)?;
+    let path_mtu = if fits(interface, &target, interface_mtu) {
+        Some(interface_mtu)
+    } else if fits(interface, &target, MIN_MTU) {
+        Some(search_mtu(MIN_MTU, interface_mtu - 1, |mtu| fits(interface, &target, mtu)))
+    } else {
+        None
+    };
+    Some(MtuReport {
+        interface: interface.to_string(),
+        route_mtu: route_mtu(&target),
+        target,
+        interface_mtu,
+        path_mtu,
+    })
+}
+
+fn load_backup() -> BTreeMap<String, u32> {
+    fs::read_to_string(BACKUP_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup(backup: &BTreeMap<String, u32>) -> Result<()> {
+    if let Some(parent) = Path::new(BACKUP_PATH).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = format!("{}.tmp", BACKUP_PATH);
+    fs::write(&tmp, serde_json::to_string_pretty(backup)?)?;
+    fs::rename(&tmp, BACKUP_PATH)?;
+    Ok(())
+}
+
+fn set_interface_mtu(interface: &str, mtu: u32) -> Result<()> {
+    let output = Command::new("ip")
+        .args(["link", "set", "dev", interface, "mtu", &mtu.to_string()])
+        .output()
+        .context("Failed to run ip link")?;
+    if !output.status.success() {
+        anyhow::bail!("ip link set mtu: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn nft(script: &str) -> Result<()> {
+    use std::io::Write;
+    let mut child = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to run nft")?;
+    child.stdin.take().context("nft stdin")?.write_all(script.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("nft: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Work around a black hole found by `probe`
+pub fn apply_fix(report: &MtuReport, fix: MtuFix) -> Result<()> {
+    let Some(path_mtu) = report.path_mtu.filter(|_| report.black_hole()) else {
+        return Ok(());
+    };
+    match fix {
+        MtuFix::Off => {}
+        MtuFix::Interface => {
+            let mut backup = load_backup();
+            backup.entry(report.interface.clone()).or_insert(report.interface_mtu);
+            save_backup(&backup)?;
+            set_interface_mtu(&report.interface, path_mtu)?;
+            info!(event = "mtu", interface = report.interface.as_str(), old = report.interface_mtu, new = path_mtu;
+                  "MTU black hole towards {}: lowered {} MTU {} -> {}", report.target, report.interface, report.interface_mtu, path_mtu);
+        }
+        MtuFix::MssClamp => {
+            // MSS = MTU - 40 (IPv4 + TCP headers); recreated from scratch so re-runs don't stack rules
+            let mss = path_mtu - 40;
+            let _ = nft(&format!("delete table inet {}\n", NFT_TABLE));
+            nft(&format!(
+                "table inet {t} {{\n  chain output {{\n    type filter hook output priority mangle;\n    oifname \"{i}\" tcp flags syn tcp option maxseg size set {m}\n  }}\n}}\n",
+                t = NFT_TABLE, i = report.interface, m = mss,
+            ))?;
+            info!(event = "mtu", interface = report.interface.as_str(), new = mss;
+                  "MTU black hole towards {}: clamping TCP MSS to {} on {}", report.target, mss, report.interface);
+        }
+    }
+    Ok(())
+}
+
+/// Restore interface MTUs and drop the MSS clamp table
+pub fn revert() {
+    for (interface, mtu) in load_backup() {
+        if Path::new("/sys/class/net").join(&interface).exists() {
+            match set_interface_mtu(&interface, mtu) {
+                Ok(()) => info!("Restored MTU {} on {}", mtu, interface),
+                Err(e) => warn!("Could not restore MTU on {}: {}", interface, e),
+            }
+        }
+    }
+    let _ = fs::remove_file(BACKUP_PATH);
+    if nft(&format!("delete table inet {}\n", NFT_TABLE)).is_ok() {
+        info!("Removed MSS clamp");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_and_black_hole() {
+        assert_eq!(search_mtu(1280, 1499, |mtu| mtu <= 1420), 1420);
+        assert_eq!(search_mtu(1280, 1499, |_| false), 1280);
+        assert_eq!(parse_route_mtu("192.168.1.20 dev wlan0 src 192.168.1.5 uid 0 \n    cache expires 590sec mtu 1400 \n"), Some(1400));
+        assert_eq!(parse_route_mtu("192.168.1.20 dev wlan0 src 192.168.1.5 uid 0 \n    cache \n"), None);
+
+        let mut report = MtuReport {
+            interface: "wlan0".to_string(),
+            target: "192.168.1.20".to_string(),
+            interface_mtu: 1500,
+            path_mtu: Some(1420),
+            route_mtu: None,
+        };
+        assert!(report.black_hole());
+        // The kernel learned it via ICMP: PMTU discovery works, nothing to fix
+        report.route_mtu = Some(1420);
+        assert!(!report.black_hole());
+        report.path_mtu = Some(1500);
+        assert!(!report.black_hole());
+    }
+}
@@ -5,6 +5,7 @@
 
 use anyhow::{Context, Result};
 use log::{info, debug};
+use futures_lite::StreamExt;
 use std::collections::HashMap;
 use zbus::{Connection, proxy};
 
@@ -41,8 +42,28 @@ pub struct AccessPoint {
     pub signal_strength: i32, // dBm (typically -30 to -90)
     #[allow(dead_code)]
     pub max_bitrate: u32,     // Kbit/s
+    /// Channel width from the scan IEs in MHz (0 = unknown, NetworkManager < 1.46)
+    pub bandwidth_mhz: u32,
 }
 
+/// AP scoring knobs beyond the flat band bias (from [wifi] in the config)
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreWeights {
+    pub bias_5ghz: i32,
+    pub bias_6ghz: i32,
+    /// Bonus for 6GHz preferred scanning channels
+    pub psc_bonus_6ghz: i32,
+    /// Bonus per doubling of channel width above 80MHz
+    pub channel_width_bonus: i32,
+    /// 6GHz signal below which the penalty starts (dBm)
+    pub marginal_6ghz_dbm: i32,
+    /// Penalty points per dB below `marginal_6ghz_dbm`
+    pub marginal_6ghz_penalty_per_db: i32,
+}
+
+/// Furthest a signal trend can move a projected RSSI (dB), so one noisy sample can't dominate
+pub const MAX_TREND_DB: i32 = 10;
+
 impl AccessPoint {
     /// Calculate roaming score for band steering
     /// 
@@ -69,6 +90,35 @@ impl AccessPoint {
         self.signal_strength + band_bias + throughput_bonus
     }
     
+    /// 6GHz preferred scanning channel (5, 21, 37, ... every 16th, where clients scan first)
+    pub fn is_psc(&self) -> bool {
+        self.band == WifiBand::Band6GHz
+            && self.frequency >= 5955
+            && ((self.frequency - 5950) / 5) % 16 == 5
+    }
+
+    /// `score` refined for 6GHz PSC channels, channel width and 6GHz's steep range cliff
+    ///
+    /// 6GHz throughput collapses a few dB past where 5GHz would still be fine, so a
+    /// 6GHz AP loses its bias quickly once the signal is marginal.
+    pub fn refined_score(&self, weights: &ScoreWeights) -> i32 {
+        let mut score = self.score(weights.bias_5ghz, weights.bias_6ghz);
+        if self.band == WifiBand::Band6GHz {
+            if self.is_psc() {
+                score += weights.psc_bonus_6ghz;
+            }
+            if self.signal_strength < weights.marginal_6ghz_dbm {
+                score -= (weights.marginal_6ghz_dbm - self.signal_strength) * weights.marginal_6ghz_penalty_per_db;
+            }
+        }
+        score += match self.bandwidth_mhz {
+            320.. => 2 * weights.channel_width_bonus,
+            160..=319 => weights.channel_width_bonus,
+            _ => 0,
+        };
+        score
+    }
+
     /// Check if signal is usable for the given band
     /// 5GHz/6GHz need stronger signals due to higher path loss
     pub fn signal_usable(&self, min_2g: i32, min_5g: i32, min_6g: i32) -> bool {
@@ -79,6 +129,15 @@ impl AccessPoint {
             WifiBand::Unknown => false,
         }
     }
+
+    /// Copy with the signal moved `ticks` ahead along its trend (dB per tick), within ±MAX_TREND_DB
+    pub fn projected(&self, db_per_tick: f64, ticks: u32) -> AccessPoint {
+        let delta = (db_per_tick * ticks as f64).round() as i32;
+        AccessPoint {
+            signal_strength: self.signal_strength + delta.clamp(-MAX_TREND_DB, MAX_TREND_DB),
+            ..self.clone()
+        }
+    }
 }
 
 /// NetworkManager device state
@@ -114,6 +173,47 @@ impl From<u32> for DeviceState {
     }
 }
 
+/// NetworkManager's connectivity check result for a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Connectivity {
+    Unknown,
+    None,
+    /// Behind a captive portal (hotel, café login page)
+    Portal,
+    /// Connected, but the internet isn't reachable
+    Limited,
+    Full,
+}
+
+impl From<u32> for Connectivity {
+    fn from(state: u32) -> Self {
+        match state {
+            1 => Connectivity::None,
+            2 => Connectivity::Portal,
+            3 => Connectivity::Limited,
+            4 => Connectivity::Full,
+            _ => Connectivity::Unknown,
+        }
+    }
+}
+
+impl Connectivity {
+    /// Portal or limited: a network the daemon shouldn't tune for
+    pub fn restricted(self) -> bool {
+        matches!(self, Connectivity::Portal | Connectivity::Limited)
+    }
+}
+
+/// `802-11-wireless.powersave` of the connection profile active on a device
+#[derive(Debug, Clone)]
+pub struct ConnectionPowersave {
+    pub interface: String,
+    pub uuid: String,
+    pub id: String,
+    /// 0 = use global default, 1 = ignore, 2 = disable, 3 = enable
+    pub value: u32,
+}
+
 /// Wireless device info from NetworkManager
 #[derive(Debug, Clone)]
 pub struct WirelessDevice {
@@ -122,6 +222,10 @@ pub struct WirelessDevice {
     pub state: DeviceState,
     pub bitrate: u32,         // Current bitrate in Kbit/s
     pub active_ap: Option<AccessPoint>,
+    /// Better of the IPv4 and IPv6 connectivity checks
+    pub connectivity: Connectivity,
+    /// NM's `Metered` property (1 = yes, 3 = guess-yes, 0 = unknown)
+    pub metered: u32,
 }
 
 // NetworkManager D-Bus proxy for the main interface
@@ -136,6 +240,19 @@ trait NetworkManager {
     
     #[zbus(property)]
     fn version(&self) -> zbus::Result<String>;
+
+    fn activate_connection(
+        &self,
+        connection: &zbus::zvariant::ObjectPath<'_>,
+        device: &zbus::zvariant::ObjectPath<'_>,
+        specific_object: &zbus::zvariant::ObjectPath<'_>,
+    ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    #[zbus(signal)]
+    fn device_added(&self, device_path: zbus::zvariant::OwnedObjectPath) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn device_removed(&self, device_path: zbus::zvariant::OwnedObjectPath) -> zbus::Result<()>;
 }
 
 // Device proxy
@@ -152,6 +269,53 @@ trait NmDevice {
     
     #[zbus(property)]
     fn state(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn active_connection(&self) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn state_reason(&self) -> zbus::Result<(u32, u32)>;
+
+    #[zbus(property)]
+    fn metered(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn ip4_connectivity(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn ip6_connectivity(&self) -> zbus::Result<u32>;
+
+    fn get_applied_connection(&self, flags: u32) -> zbus::Result<(ConnectionSettings, u64)>;
+
+    fn reapply(&self, connection: ConnectionSettings, version_id: u64, flags: u32) -> zbus::Result<()>;
+}
+
+/// Settings dict as NetworkManager sends it (setting name -> property -> value)
+type ConnectionSettings = HashMap<String, HashMap<String, zbus::zvariant::OwnedValue>>;
+
+// Active connection proxy
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait NmActiveConnection {
+    #[zbus(property)]
+    fn connection(&self) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn uuid(&self) -> zbus::Result<String>;
+}
+
+// Connection settings proxy
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait NmSettingsConnection {
+    fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, zbus::zvariant::OwnedValue>>>;
 }
 
 // Wireless device proxy
@@ -192,6 +356,9 @@ trait NmAccessPoint {
     
     #[zbus(property)]
     fn max_bitrate(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn bandwidth(&self) -> zbus::Result<u32>;
 }
 
 /// NetworkManager D-Bus Client
@@ -214,6 +381,26 @@ impl NmClient {
         Ok(Self { connection })
     }
 
+    /// Send on `tx` whenever NetworkManager adds or removes a device (e.g. USB hotplug)
+    pub async fn watch_devices(&self, tx: std::sync::mpsc::Sender<()>) -> Result<()> {
+        let nm = NetworkManagerProxy::new(&self.connection).await?;
+        let mut added = nm.receive_device_added().await?;
+        let mut removed = nm.receive_device_removed().await?;
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(_) = added.next() => {}
+                    Some(_) = removed.next() => {}
+                    else => break,
+                }
+                if tx.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
     /// Get all wireless devices
     pub async fn get_wireless_devices(&self) -> Result<Vec<WirelessDevice>> {
         let nm = NetworkManagerProxy::new(&self.connection).await?;
@@ -258,12 +445,18 @@ impl NmClient {
                 _ => None,
             };
             
+            // NM < 1.16 has no per-device check
+            let connectivity = Connectivity::from(device.ip4_connectivity().await.unwrap_or(0))
+                .max(Connectivity::from(device.ip6_connectivity().await.unwrap_or(0)));
+
             wireless_devices.push(WirelessDevice {
                 path: path.to_string(),
                 interface,
                 state,
                 bitrate,
                 active_ap,
+                connectivity,
+                metered: device.metered().await.unwrap_or(0),
             });
         }
         
@@ -283,6 +476,7 @@ impl NmClient {
         let frequency = ap.frequency().await.unwrap_or(0);
         let strength = ap.strength().await.unwrap_or(0);
         let max_bitrate = ap.max_bitrate().await.unwrap_or(0);
+        let bandwidth_mhz = ap.bandwidth().await.unwrap_or(0);
         
         // Convert strength (0-100) to approximate dBm
         let signal_dbm = Self::strength_to_dbm(strength);
@@ -295,6 +489,7 @@ impl NmClient {
             band: WifiBand::from_frequency(frequency),
             signal_strength: signal_dbm,
             max_bitrate,
+            bandwidth_mhz,
         })
     }
 
@@ -331,6 +526,122 @@ impl NmClient {
         Ok(())
     }
 
+    /// Settings path of the profile active on a device (None when nothing is active)
+    pub async fn active_profile(&self, device_path: &str) -> Result<Option<String>> {
+        let proxy = NmDeviceProxy::builder(&self.connection)
+            .path(device_path)?
+            .build()
+            .await?;
+        let active_path = match proxy.active_connection().await {
+            Ok(p) if p.as_str() != "/" => p,
+            _ => return Ok(None),
+        };
+        let active = NmActiveConnectionProxy::builder(&self.connection)
+            .path(active_path.as_ref())?
+            .build()
+            .await?;
+        Ok(Some(active.connection().await?.to_string()))
+    }
+
+    /// Reason code NetworkManager gave for the device's last state change
+    pub async fn device_state_reason(&self, device_path: &str) -> Result<u32> {
+        let proxy = NmDeviceProxy::builder(&self.connection)
+            .path(device_path)?
+            .build()
+            .await?;
+        Ok(proxy.state_reason().await?.1)
+    }
+
+    /// Activate a saved profile on a device (what picking it in the network list does)
+    pub async fn activate_connection(&self, profile_path: &str, device_path: &str) -> Result<()> {
+        let nm = NetworkManagerProxy::new(&self.connection).await?;
+        nm.activate_connection(
+            &zbus::zvariant::ObjectPath::try_from(profile_path)?,
+            &zbus::zvariant::ObjectPath::try_from(device_path)?,
+            &zbus::zvariant::ObjectPath::try_from("/")?,
+        ).await?;
+        Ok(())
+    }
+
+    /// Use `servers` as the device's only resolvers until it reconnects
+    ///
+    /// Goes through Device.Reapply, so the saved profile is untouched and a reconnect
+    /// (or `nmcli device reapply`) brings the profile's resolvers back.
+    pub async fn set_runtime_dns(&self, device_path: &str, servers: &[std::net::IpAddr]) -> Result<()> {
+        use zbus::zvariant::{OwnedValue, Value};
+        let device = NmDeviceProxy::builder(&self.connection)
+            .path(device_path)?
+            .build()
+            .await?;
+        let (mut settings, version_id) = device.get_applied_connection(0).await?;
+
+        // Legacy array encodings: ipv4 as network-order u32s, ipv6 as 16-byte arrays
+        let v4: Vec<u32> = servers.iter()
+            .filter_map(|ip| match ip {
+                std::net::IpAddr::V4(v4) => Some(u32::from_ne_bytes(v4.octets())),
+                _ => None,
+            })
+            .collect();
+        let v6: Vec<Vec<u8>> = servers.iter()
+            .filter_map(|ip| match ip {
+                std::net::IpAddr::V6(v6) => Some(v6.octets().to_vec()),
+                _ => None,
+            })
+            .collect();
+        let entries: [(&str, OwnedValue); 2] = [
+            ("ipv4", OwnedValue::try_from(Value::from(v4))?),
+            ("ipv6", OwnedValue::try_from(Value::from(v6))?),
+        ];
+        for (family, dns) in entries {
+            let Some(ip) = settings.get_mut(family) else { continue };
+            // Newer NM prefers the string form when present; drop it so ours is used
+            ip.remove("dns-data");
+            ip.insert("dns".to_string(), dns);
+            ip.insert("ignore-auto-dns".to_string(), OwnedValue::from(true));
+        }
+        device.reapply(settings, version_id, 0).await?;
+        Ok(())
+    }
+
+    /// Powersave setting of the profile active on each connected Wi-Fi device
+    pub async fn wifi_powersave_settings(&self) -> Result<Vec<ConnectionPowersave>> {
+        let mut result = Vec::new();
+        for device in self.get_wireless_devices().await? {
+            let proxy = NmDeviceProxy::builder(&self.connection)
+                .path(device.path.as_str())?
+                .build()
+                .await?;
+            let active_path = match proxy.active_connection().await {
+                Ok(p) if p.as_str() != "/" => p,
+                _ => continue,
+            };
+            let active = NmActiveConnectionProxy::builder(&self.connection)
+                .path(active_path.as_ref())?
+                .build()
+                .await?;
+            let settings = NmSettingsConnectionProxy::builder(&self.connection)
+                .path(active.connection().await?)?
+                .build()
+                .await?
+                .get_settings()
+                .await?;
+
+            // Property is omitted from the settings dict when left at its default (0)
+            let value = settings.get("802-11-wireless")
+                .and_then(|wireless| wireless.get("powersave"))
+                .and_then(|v| u32::try_from(v).ok())
+                .unwrap_or(0);
+
+            result.push(ConnectionPowersave {
+                interface: device.interface,
+                uuid: active.uuid().await.unwrap_or_default(),
+                id: active.id().await.unwrap_or_default(),
+                value,
+            });
+        }
+        Ok(result)
+    }
+
     /// Check if interface is virtual (per rewrite.md: ignore docker, veth, virbr, tun, tap)
     fn is_virtual_interface(name: &str) -> bool {
         name.starts_with("docker") ||
@@ -362,6 +673,15 @@ mod tests {
         assert_eq!(WifiBand::from_frequency(900), WifiBand::Unknown);
     }
     
+    #[test]
+    fn test_connectivity() {
+        assert!(Connectivity::from(2).restricted());
+        assert!(!Connectivity::from(4).restricted());
+        // IPv6 full wins over an IPv4 portal
+        assert_eq!(Connectivity::from(2).max(Connectivity::from(4)), Connectivity::Full);
+        assert!(!Connectivity::from(0).max(Connectivity::from(0)).restricted());
+    }
+
     #[test]
     fn test_access_point_scoring() {
         let ap = AccessPoint {
@@ -372,6 +692,7 @@ mod tests {
             band: WifiBand::Band5GHz,
             signal_strength: -60,
             max_bitrate: 300000, // 300 Mbps - gives 5 points throughput bonus
+            bandwidth_mhz: 0,
         };
         
         // Score = RSSI (-60) + band_bias (15) + throughput_bonus (5) = -40
@@ -391,6 +712,7 @@ mod tests {
             band: WifiBand::Band6GHz,
             signal_strength: -71, // Too weak for 6GHz threshold
             max_bitrate: 600000,
+            bandwidth_mhz: 0,
         };
         assert!(!weak_ap.signal_usable(-75, -72, -70)); // -71 fails 6GHz threshold of -70
     }
@@ -406,6 +728,7 @@ mod tests {
             band: WifiBand::Band2_4GHz,
             signal_strength: -50,
             max_bitrate: 54000, // 54 Mbps - gives 0 points
+            bandwidth_mhz: 0,
         };
         assert_eq!(slow_ap.score(15, 25), -50); // No band bias, no throughput bonus
         
@@ -418,8 +741,44 @@ mod tests {
             band: WifiBand::Band6GHz,
             signal_strength: -55,
             max_bitrate: 1200000, // 1.2 Gbps - caps at 10 points
+            bandwidth_mhz: 0,
         };
         // Score = -55 + 25 (6GHz bias) + 10 (throughput cap) = -20
         assert_eq!(fast_ap.score(15, 25), -20);
     }
+
+    #[test]
+    fn test_refined_score_6ghz() {
+        let weights = ScoreWeights {
+            bias_5ghz: 15,
+            bias_6ghz: 25,
+            psc_bonus_6ghz: 5,
+            channel_width_bonus: 3,
+            marginal_6ghz_dbm: -65,
+            marginal_6ghz_penalty_per_db: 3,
+        };
+        let ap = |frequency, band, signal_strength, bandwidth_mhz| AccessPoint {
+            path: "/".to_string(),
+            ssid: "Test".to_string(),
+            bssid: "00:11:22:33:44:77".to_string(),
+            frequency,
+            band,
+            signal_strength,
+            max_bitrate: 0,
+            bandwidth_mhz,
+        };
+
+        // Channel 37 (6135 MHz) is PSC, channel 33 (6115 MHz) is not
+        let psc = ap(6135, WifiBand::Band6GHz, -60, 160);
+        assert!(psc.is_psc());
+        assert!(!ap(6115, WifiBand::Band6GHz, -60, 160).is_psc());
+        // -60 + 25 + 5 (PSC) + 3 (160MHz)
+        assert_eq!(psc.refined_score(&weights), -27);
+
+        // Marginal 6GHz: -69 is 4dB past -65 -> -12, now loses to a -60dBm 5GHz AP
+        let marginal = ap(6135, WifiBand::Band6GHz, -69, 80);
+        let five = ap(5180, WifiBand::Band5GHz, -60, 80);
+        assert_eq!(marginal.refined_score(&weights), -69 + 25 + 5 - 12);
+        assert!(five.refined_score(&weights) > marginal.refined_score(&weights));
+    }
 }
@@ -0,0 +1,166 @@
+//! NetworkManager wifi.powersave de-confliction
+//!
+//! NM applies the profile's `802-11-wireless.powersave` on every (re)connect. With an
+//! effective value of 3 (enable) it silently undoes our `iw set power_save off`. When
+//! allowed by config we set the profile to 2 (disable) and remember the original so
+//! `revert` can put it back. The Governor still toggles power save at runtime via iw.
+
+use anyhow::Result;
+use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::network::nm::NmClient;
+
+/// Original per-profile values, keyed by connection UUID
+const BACKUP_PATH: &str = "/var/lib/hifi-wifi/nm-powersave.json";
+
+/// NM config locations that may set the global `wifi.powersave` default, in NM's
+/// read order (later overrides earlier); directories are read in filename order
+const NM_CONF_SOURCES: &[&str] = &[
+    "/usr/lib/NetworkManager/conf.d",
+    "/run/NetworkManager/conf.d",
+    "/etc/NetworkManager/NetworkManager.conf",
+    "/etc/NetworkManager/conf.d",
+];
+
+const POWERSAVE_DEFAULT: u32 = 0;
+const POWERSAVE_DISABLE: u32 = 2;
+const POWERSAVE_ENABLE: u32 = 3;
+
+/// Global `wifi.powersave` default from NetworkManager config, if any file sets one
+pub fn global_default() -> Option<u32> {
+    let mut files = Vec::new();
+    for source in NM_CONF_SOURCES {
+        match fs::read_dir(source) {
+            Ok(entries) => {
+                let mut paths: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+                paths.sort();
+                files.extend(paths);
+            }
+            Err(_) => files.push(Path::new(source).to_path_buf()),
+        }
+    }
+    // Later files override earlier ones
+    files.iter()
+        .rev()
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .find_map(|content| parse_powersave_conf(&content))
+}
+
+/// Last `wifi.powersave = N` in an NM config file
+fn parse_powersave_conf(content: &str) -> Option<u32> {
+    content.lines()
+        .rev()
+        .map(str::trim)
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .filter(|(key, _)| key.trim() == "wifi.powersave")
+        .find_map(|(_, value)| value.trim().parse().ok())
+}
+
+/// Value NM will actually apply for a profile setting
+fn effective(profile_value: u32, global: Option<u32>) -> u32 {
+    if profile_value == POWERSAVE_DEFAULT {
+        global.unwrap_or(POWERSAVE_DEFAULT)
+    } else {
+        profile_value
+    }
+}
+
+/// Whether NM will turn power save back on when this profile (re)connects
+pub fn re_enables(profile_value: u32, global: Option<u32>) -> bool {
+    effective(profile_value, global) == POWERSAVE_ENABLE
+}
+
+/// Check active Wi-Fi profiles; disable NM-managed powersave where it would fight us
+pub async fn deconflict(nm: &NmClient, override_profile: bool) -> Result<()> {
+    let global = global_default();
+    for conn in nm.wifi_powersave_settings().await? {
+        debug!("NM powersave for '{}' on {}: profile={}, effective={}",
+               conn.id, conn.interface, conn.value, effective(conn.value, global));
+        if !re_enables(conn.value, global) {
+            continue;
+        }
+
+        if !override_profile {
+            warn!("NetworkManager profile '{}' enables Wi-Fi power save on every reconnect (wifi.powersave=3); \
+                   set [power] nm_powersave_override = true to let hifi-wifi manage it", conn.id);
+            continue;
+        }
+
+        let output = Command::new("nmcli")
+            .args(["connection", "modify", &conn.uuid, "802-11-wireless.powersave", &POWERSAVE_DISABLE.to_string()])
+            .output()?;
+        if output.status.success() {
+            record_original(&conn.uuid, conn.value);
+            info!("Set NetworkManager powersave to 'disable' on '{}' (was {}) so reconnects don't re-enable it",
+                  conn.id, conn.value);
+        } else {
+            warn!("Failed to update NM profile '{}': {}", conn.id, String::from_utf8_lossy(&output.stderr).trim());
+        }
+    }
+    Ok(())
+}
+
+fn load_backup() -> BTreeMap<String, u32> {
+    fs::read_to_string(BACKUP_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+/// Remember a profile's original value (first change only)
+fn record_original(uuid: &str, value: u32) {
+    let mut backup = load_backup();
+    if backup.contains_key(uuid) {
+        return;
+    }
+    backup.insert(uuid.to_string(), value);
+    let write = || -> Result<()> {
+        if let Some(parent) = Path::new(BACKUP_PATH).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(BACKUP_PATH, serde_json::to_string_pretty(&backup)?)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        warn!("Could not record original NM powersave value: {}", e);
+    }
+}
+
+/// Restore every profile we changed to its original powersave value
+pub fn revert() {
+    let backup = load_backup();
+    for (uuid, value) in &backup {
+        let ok = Command::new("nmcli")
+            .args(["connection", "modify", uuid, "802-11-wireless.powersave", &value.to_string()])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        if ok {
+            info!("Restored NetworkManager powersave={} on connection {}", value, uuid);
+        } else {
+            warn!("Could not restore NM powersave on connection {} (profile removed?)", uuid);
+        }
+    }
+    let _ = fs::remove_file(BACKUP_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_powersave_conf_and_effective() {
+        let conf = "[connection]\n# wifi.powersave = 2\nwifi.powersave = 3\n";
+        assert_eq!(parse_powersave_conf(conf), Some(3));
+        assert_eq!(parse_powersave_conf("[main]\nplugins=keyfile\n"), None);
+
+        assert_eq!(effective(0, Some(3)), 3);
+        assert_eq!(effective(2, Some(3)), 2);
+        assert_eq!(effective(0, None), 0);
+    }
+}
@@ -0,0 +1,989 @@
+//! Tick policies
+//!
+//! Each Governor feature (game mode, breathing CAKE, coalescing, power save, EEE, Ethernet
+//! link tuning, band steering) is a `Policy`: it looks at a `TickContext` for one interface and returns
+//! the `Action`s it wants, keeping whatever per-interface state it needs. The Governor
+//! builds the context, runs the enabled policies in order and executes their actions,
+//! so a policy never touches the system itself. Policies are named so
+//! `[governor] disabled_policies` can switch them off individually.
+
+use log::{debug, trace};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::config::structs::{ApAnnotation, CakeMode, GovernorConfig, PowerConfig, WifiConfig};
+use crate::network::ethernet::{EthtoolProbe, Rings};
+use crate::network::mlo;
+use crate::network::nm::{AccessPoint, ScoreWeights};
+use crate::network::probe::IwProbe;
+use crate::network::power_save::Source as PowerSaveSource;
+use crate::system::power::PowerSource;
+
+/// Log target for band steering, filterable as `band_steering` in [logging.modules]
+pub const BAND_STEERING_LOG: &str = "hifi_wifi::band_steering";
+
+/// Policy names, in the order they run
+pub const POLICY_NAMES: &[&str] = &["game_mode", "cake", "coalescing", "power_save", "eee", "ethernet", "band_steering"];
+/// The policies the Governor also runs on wired links
+pub const WIRED_POLICIES: &[&str] = &["eee", "ethernet"];
+
+/// Packets/s above which a link counts as busy (keeps power save and EEE off)
+const ACTIVITY_PPS: u64 = 50;
+
+/// What policies see of one interface on one tick
+#[derive(Debug)]
+pub struct TickContext<'a> {
+    pub interface: &'a str,
+    /// Wired interface (EEE applies, power save doesn't)
+    pub ethernet: bool,
+    pub mlo_capable: bool,
+    /// Smoothed packets/s, sampled once per tick
+    pub pps: u64,
+    pub cpu_load: f64,
+    /// On battery, and not a streaming host
+    pub battery_power_save: bool,
+    pub host: bool,
+    /// Game mode as of now (updated as soon as the game-mode policy's actions run)
+    pub in_game: bool,
+    /// Game mode forced on/off over the control socket or by the schedule (None = detect from traffic)
+    pub game_mode_override: Option<bool>,
+    /// Power save wanted by an active `[[schedule]]` entry
+    pub schedule_power_save: Option<bool>,
+    /// Link rate from NetworkManager (Kbit/s)
+    pub nm_bitrate_kbit: u32,
+    /// Last plausible link rate seen on this association (Kbit/s)
+    pub last_good_bitrate_kbit: Option<u32>,
+    /// Whether CAKE has had a bandwidth on this association yet
+    pub bandwidth_valid: bool,
+    /// Calibrated upload ceiling for the current SSID (Mbit/s)
+    pub ceiling_mbit: Option<u32>,
+    pub current_ap: Option<&'a AccessPoint>,
+    /// RSSI of the current association from the station (only fetched when band steering runs)
+    pub station_signal_dbm: Option<i32>,
+    /// Visible APs (only fetched when band steering runs)
+    pub access_points: &'a [AccessPoint],
+    /// Throughput each visible node of the current SSID has carried (Mbit/s, band steering only)
+    pub backhaul_mbit: HashMap<String, u32>,
+    /// This tick's `iw` output for the interface
+    pub iw: IwProbe,
+    /// This tick's `ethtool` output (wired links)
+    pub ethtool: EthtoolProbe,
+}
+
+/// Something a policy wants done; the Governor's executor carries it out
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Game mode started (true) or ended (false)
+    GameMode { active: bool, pps: u64 },
+    /// New CAKE bandwidth (Mbit/s) through TcManager's smoothing; `link_kbit` is the
+    /// plausible link rate it came from, None when reusing the last one or the default
+    CakeBandwidth { mbit: u32, link_kbit: Option<u32> },
+    Coalescing { enable: bool },
+    /// File (Some) or withdraw (None) a power save request
+    PowerSave { source: PowerSaveSource, request: Option<(bool, &'static str)> },
+    Eee { enable: bool, reason: &'static str },
+    /// Hint the driver towards a better AP of the same SSID
+    Roam { from: String, to: String },
+    /// A roam was held back because the pair keeps flapping
+    RoamDamped { from: String, to: String, hold_secs: u64 },
+    /// New RX/TX ring sizes for a wired NIC
+    Rings { rx: u32, tx: u32 },
+    /// Ethernet flow control on or off
+    PauseFrames { enable: bool },
+    /// The link negotiated half duplex (bad cable or a forced port on the other end)
+    HalfDuplex { speed_mbit: Option<u32> },
+    /// The link went down and up this many times within `LINK_FLAP_WINDOW`
+    LinkFlaps { flaps: usize },
+}
+
+pub trait Policy: Send {
+    fn name(&self) -> &'static str;
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action>;
+    /// Drop per-interface state (the interface went away)
+    fn forget(&mut self, _interface: &str) {}
+    /// Per-interface state for the daemon's SIGUSR1 dump (None if it keeps none)
+    fn dump(&self, _interface: &str) -> Option<serde_json::Value> {
+        None
+    }
+    /// The power source changed (called before the tick's `evaluate`)
+    fn power_source_changed(&mut self, _source: PowerSource) {}
+}
+
+/// Hysteresis for an on/off decision: a change must hold `needed` ticks
+#[derive(Debug, Default, Serialize)]
+struct Debounce {
+    current: Option<bool>,
+    pending: Option<(bool, u32)>,
+}
+
+impl Debounce {
+    /// Some(wanted) when it is time to switch
+    fn update(&mut self, wanted: bool, needed: u32) -> Option<bool> {
+        if self.current == Some(wanted) {
+            self.pending = None;
+            return None;
+        }
+        let ticks = match self.pending {
+            Some((value, ticks)) if value == wanted => ticks + 1,
+            _ => 1,
+        };
+        if ticks >= needed {
+            self.current = Some(wanted);
+            self.pending = None;
+            Some(wanted)
+        } else {
+            self.pending = Some((wanted, ticks));
+            None
+        }
+    }
+}
+
+/// Game mode: sustained packet rate above a threshold, held for a cooldown
+pub struct GameModePolicy {
+    pps_threshold: u64,
+    cooldown: Duration,
+    until: HashMap<String, Instant>,
+}
+
+impl GameModePolicy {
+    pub fn new(config: &GovernorConfig) -> Self {
+        Self {
+            pps_threshold: config.game_mode_pps_threshold,
+            cooldown: Duration::from_secs(config.game_mode_cooldown_secs),
+            until: HashMap::new(),
+        }
+    }
+}
+
+impl Policy for GameModePolicy {
+    fn name(&self) -> &'static str {
+        "game_mode"
+    }
+
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action> {
+        let now = Instant::now();
+        // A forced session still runs the cooldown when handed back to detection
+        if ctx.game_mode_override == Some(true) || ctx.pps > self.pps_threshold {
+            self.until.insert(ctx.interface.to_string(), now + self.cooldown);
+        }
+        if ctx.game_mode_override == Some(false) {
+            self.until.remove(ctx.interface);
+        }
+        let in_game = self.until.get(ctx.interface).is_some_and(|until| now < *until);
+        if in_game != ctx.in_game {
+            vec![Action::GameMode { active: in_game, pps: ctx.pps }]
+        } else {
+            if in_game && ctx.pps > self.pps_threshold {
+                debug!("Game mode extended: {} PPS on {}", ctx.pps, ctx.interface);
+            }
+            Vec::new()
+        }
+    }
+
+    fn forget(&mut self, interface: &str) {
+        self.until.remove(interface);
+    }
+
+    fn dump(&self, interface: &str) -> Option<serde_json::Value> {
+        let remaining = self.until.get(interface).map(|until| until.saturating_duration_since(Instant::now()).as_secs());
+        Some(serde_json::json!({ "cooldown_secs": remaining }))
+    }
+}
+
+/// Readings below this are idle probes (MCS0), not the link rate; 20Mbit keeps
+/// Steam Deck's low idle reports usable
+const MIN_VALID_KBIT: u32 = 20_000;
+/// Shaping rate when no link rate was ever seen (safe for most Wi-Fi 5/6 networks)
+const DEFAULT_LINK_MBIT: u32 = 100;
+
+/// Link rate to shape for: both sources averaged when plausible, else whichever is
+pub fn effective_bitrate_kbit(nm_kbit: u32, iw_kbit: u32) -> u32 {
+    match (nm_kbit >= MIN_VALID_KBIT, iw_kbit >= MIN_VALID_KBIT) {
+        (true, true) => (nm_kbit + iw_kbit) / 2,
+        (true, false) => nm_kbit,
+        (false, true) => iw_kbit,
+        (false, false) => 0,
+    }
+}
+
+/// Breathing CAKE: shape at a share of the link rate, never above the calibrated ceiling
+pub struct CakePolicy {
+    overhead_factor: f64,
+}
+
+impl CakePolicy {
+    pub fn new(config: &GovernorConfig) -> Self {
+        Self { overhead_factor: config.cake_overhead_factor }
+    }
+
+    fn scaled(&self, link_mbit: u32, ceiling: Option<u32>) -> u32 {
+        let mbit = (link_mbit as f64 * self.overhead_factor) as u32;
+        ceiling.map_or(mbit, |c| mbit.min(c))
+    }
+}
+
+impl Policy for CakePolicy {
+    fn name(&self) -> &'static str {
+        "cake"
+    }
+
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action> {
+        let nm_bitrate = ctx.nm_bitrate_kbit;
+        let iw_bitrate = bitrate_from_iw(ctx.interface, &ctx.iw).unwrap_or(0);
+        if nm_bitrate < MIN_VALID_KBIT && iw_bitrate < MIN_VALID_KBIT {
+            debug!("CAKE bitrate check on {}: NM={} Kbit, iw={} Kbit (both below {} Kbit)",
+                   ctx.interface, nm_bitrate, iw_bitrate, MIN_VALID_KBIT);
+        }
+        let mut effective = effective_bitrate_kbit(nm_bitrate, iw_bitrate);
+
+        // MLO: NM and `iw link` only see one link; shape for the combined rate instead
+        if ctx.mlo_capable {
+            if let Some(aggregate) = mlo::aggregate_bitrate_kbit(&mlo::links_from(&ctx.iw)).filter(|&a| a >= MIN_VALID_KBIT) {
+                debug!("CAKE: MLO aggregate {}Kbit on {} (single-link readings NM={}, iw={})",
+                       aggregate, ctx.interface, nm_bitrate, iw_bitrate);
+                effective = aggregate;
+            }
+        }
+
+        let action = if effective > 0 {
+            let mbit = self.scaled(effective / 1000, ctx.ceiling_mbit);
+            debug!("CAKE: NM={}Kbit, iw={}Kbit, effective={}Kbit, scaled={}Mbit", nm_bitrate, iw_bitrate, effective, mbit);
+            Action::CakeBandwidth { mbit, link_kbit: Some(effective) }
+        } else if let Some(last_good) = ctx.last_good_bitrate_kbit {
+            // Both sources invalid (MCS0 probes while idle): keep the last known good rate
+            let mbit = self.scaled(last_good / 1000, ctx.ceiling_mbit);
+            debug!("CAKE: Invalid readings (NM={}, iw={}), using last known good {}Kbit -> {}Mbit",
+                   nm_bitrate, iw_bitrate, last_good, mbit);
+            Action::CakeBandwidth { mbit, link_kbit: None }
+        } else {
+            if !ctx.bandwidth_valid {
+                log::info!("CAKE: No bitrate detected (NM={}, iw={}), using conservative default {}Mbit on {}",
+                           nm_bitrate, iw_bitrate, DEFAULT_LINK_MBIT, ctx.interface);
+            }
+            Action::CakeBandwidth { mbit: self.scaled(DEFAULT_LINK_MBIT, ctx.ceiling_mbit), link_kbit: None }
+        };
+        vec![action]
+    }
+}
+
+/// Fallback: Get bitrate (Kbit/s) from `iw` when NetworkManager reports 0
+fn bitrate_from_iw(interface: &str, iw: &IwProbe) -> Option<u32> {
+    let stdout = iw.link()?;
+
+    // Parse bitrate from iw output - multiple formats supported:
+    // "tx bitrate: 866.7 MBit/s ..."
+    // "	tx bitrate: 866.7 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 2"
+    // Steam Deck ath11k may report: "tx bitrate: 1201.0 MBit/s 80MHz HE-MCS 11 HE-NSS 2 HE-GI 0 HE-DCM 0"
+    for line in stdout.lines() {
+        let line_lower = line.to_lowercase();
+        if line_lower.contains("tx bitrate:") || line_lower.contains("bitrate:") {
+            // Extract the number - look for pattern like "866.7 MBit/s" or "1201.0 Mbit/s"
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            for (i, part) in parts.iter().enumerate() {
+                // Look for "bitrate:" followed by a number
+                if part.to_lowercase().contains("bitrate:") {
+                    // Next part should be the number
+                    if i + 1 < parts.len() {
+                        if let Ok(mbit) = parts[i + 1].parse::<f64>() {
+                            // Convert to Kbit for consistency with NM
+                            debug!("iw fallback: {}Mbit on {}", mbit, interface);
+                            return Some((mbit * 1000.0) as u32);
+                        }
+                    }
+                }
+                // Also try matching "NNN.N" followed by "MBit" in case format varies
+                if i + 1 < parts.len() && parts[i + 1].to_lowercase().contains("mbit") {
+                    if let Ok(mbit) = part.parse::<f64>() {
+                        debug!("iw fallback (alt format): {}Mbit on {}", mbit, interface);
+                        return Some((mbit * 1000.0) as u32);
+                    }
+                }
+            }
+        }
+    }
+
+    // Final fallback: try to get signal from iw station dump
+    // Some drivers (ath11k) may report better data this way
+    if let Some(station_out) = iw.station_dump() {
+        for line in station_out.lines() {
+            let line_lower = line.to_lowercase();
+            if line_lower.contains("tx bitrate:") {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                for (i, part) in parts.iter().enumerate() {
+                    if part.to_lowercase().contains("bitrate:") && i + 1 < parts.len() {
+                        if let Ok(mbit) = parts[i + 1].parse::<f64>() {
+                            debug!("iw station dump fallback: {}Mbit on {}", mbit, interface);
+                            return Some((mbit * 1000.0) as u32);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Interrupt coalescing: off in game mode unless the CPU is saturated, on otherwise
+pub struct CoalescingPolicy {
+    threshold: f64,
+    state: HashMap<String, Debounce>,
+}
+
+impl CoalescingPolicy {
+    pub fn new(config: &GovernorConfig) -> Self {
+        Self { threshold: config.cpu_coalescing_threshold, state: HashMap::new() }
+    }
+}
+
+impl Policy for CoalescingPolicy {
+    fn name(&self) -> &'static str {
+        "coalescing"
+    }
+
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action> {
+        let should_coalesce = !ctx.in_game || ctx.cpu_load > self.threshold;
+        // Starts out as "off" so the first tick only acts if coalescing is wanted
+        let debounce = self.state.entry(ctx.interface.to_string())
+            .or_insert_with(|| Debounce { current: Some(false), pending: None });
+        // 2 stable ticks (4 seconds) before switching
+        match debounce.update(should_coalesce, 2) {
+            Some(enable) => {
+                debug!("Coalescing {} on {} (game:{}, cpu:{:.0}%, battery:{})",
+                       if enable { "ENABLED" } else { "DISABLED" }, ctx.interface,
+                       ctx.in_game, ctx.cpu_load * 100.0, ctx.battery_power_save);
+                vec![Action::Coalescing { enable }]
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn forget(&mut self, interface: &str) {
+        self.state.remove(interface);
+    }
+
+    fn dump(&self, interface: &str) -> Option<serde_json::Value> {
+        self.state.get(interface).and_then(|d| serde_json::to_value(d).ok())
+    }
+}
+
+/// Why power saving (power save, EEE) should be off, if it should
+fn power_saving_veto(ctx: &TickContext) -> Option<&'static str> {
+    if ctx.host {
+        Some("streaming host")
+    } else if !ctx.battery_power_save {
+        Some("AC power")
+    } else if ctx.in_game {
+        Some("game mode")
+    } else if ctx.pps > ACTIVITY_PPS {
+        Some("network activity")
+    } else {
+        None
+    }
+}
+
+/// Wi-Fi power save requests; `PowerSaveController` arbitrates and debounces them
+pub struct PowerSavePolicy {
+    /// `[power] wlan_power_save` when it forces "on"/"off"
+    forced: Option<bool>,
+}
+
+impl PowerSavePolicy {
+    pub fn new(config: &PowerConfig) -> Self {
+        let forced = match config.wlan_power_save.as_str() {
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => None,
+        };
+        Self { forced }
+    }
+}
+
+impl Policy for PowerSavePolicy {
+    fn name(&self) -> &'static str {
+        "power_save"
+    }
+
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action> {
+        if ctx.ethernet {
+            return Vec::new();
+        }
+        let adaptive = match power_saving_veto(ctx) {
+            // Game mode has its own, higher-priority request
+            Some("game mode") | None => (true, "battery, idle"),
+            Some(reason) => (false, reason),
+        };
+        vec![
+            Action::PowerSave { source: PowerSaveSource::Config, request: self.forced.map(|on| (on, "config")) },
+            Action::PowerSave { source: PowerSaveSource::GameMode, request: ctx.in_game.then_some((false, "game mode")) },
+            Action::PowerSave { source: PowerSaveSource::Schedule, request: ctx.schedule_power_save.map(|on| (on, "schedule")) },
+            Action::PowerSave { source: PowerSaveSource::Adaptive, request: Some(adaptive) },
+        ]
+    }
+}
+
+/// Energy Efficient Ethernet: its 50-200us wakeups hurt streaming, so only on battery and idle
+#[derive(Default)]
+pub struct EeePolicy {
+    state: HashMap<String, Debounce>,
+    /// Interfaces that switch on their next tick: plugging in or out isn't noise
+    immediate: HashSet<String>,
+}
+
+impl Policy for EeePolicy {
+    fn name(&self) -> &'static str {
+        "eee"
+    }
+
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action> {
+        if !ctx.ethernet {
+            return Vec::new();
+        }
+        let veto = power_saving_veto(ctx);
+        // 3 stable ticks (6 seconds) before switching, unless the power source just changed
+        let needed = if self.immediate.remove(ctx.interface) { 1 } else { 3 };
+        match self.state.entry(ctx.interface.to_string()).or_default().update(veto.is_none(), needed) {
+            Some(enable) => vec![Action::Eee { enable, reason: veto.unwrap_or("battery, idle") }],
+            None => Vec::new(),
+        }
+    }
+
+    fn forget(&mut self, interface: &str) {
+        self.state.remove(interface);
+        self.immediate.remove(interface);
+    }
+
+    fn dump(&self, interface: &str) -> Option<serde_json::Value> {
+        self.state.get(interface).and_then(|d| serde_json::to_value(d).ok())
+    }
+
+    fn power_source_changed(&mut self, _source: PowerSource) {
+        self.immediate = self.state.keys().cloned().collect();
+    }
+}
+
+/// Link flaps are counted over this window...
+pub const LINK_FLAP_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// ...and reported from this many on
+const LINK_FLAP_ALERT: usize = 3;
+/// Carrier changes this soon after retuning are the NIC resetting, not flaps
+const ETHERNET_SETTLE: Duration = Duration::from_secs(15);
+
+/// RX ring to the maximum, TX ring to `tx_ring` (0 = as is); None if already there
+fn ring_target(rings: &Rings, tx_ring: u32) -> Option<(u32, u32)> {
+    let tx = if tx_ring == 0 { rings.tx } else { tx_ring.min(rings.tx_max) };
+    let target = (rings.rx_max.max(rings.rx), tx);
+    (target != (rings.rx, rings.tx)).then_some(target)
+}
+
+#[derive(Debug, Default)]
+struct WiredLink {
+    /// Rings and pause frames checked since the link last came up
+    tuned: bool,
+    carrier_changes: Option<u64>,
+    flaps: VecDeque<Instant>,
+    half_duplex: bool,
+    settle_until: Option<Instant>,
+}
+
+/// Wired links: ring sizes and pause frames once per link-up, half duplex and flap reports
+pub struct EthernetPolicy {
+    tx_ring: u32,
+    pause_frames: bool,
+    links: HashMap<String, WiredLink>,
+}
+
+impl EthernetPolicy {
+    pub fn new(config: &GovernorConfig) -> Self {
+        Self { tx_ring: config.ethernet_tx_ring, pause_frames: config.ethernet_pause_frames, links: HashMap::new() }
+    }
+}
+
+impl Policy for EthernetPolicy {
+    fn name(&self) -> &'static str {
+        "ethernet"
+    }
+
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action> {
+        if !ctx.ethernet {
+            return Vec::new();
+        }
+        let now = Instant::now();
+        let link = self.links.entry(ctx.interface.to_string()).or_default();
+        let mut actions = Vec::new();
+
+        if let Some(count) = ctx.ethtool.carrier_changes() {
+            let settling = link.settle_until.is_some_and(|until| now < until);
+            match link.carrier_changes {
+                Some(last) if count > last && !settling => {
+                    link.flaps.extend(std::iter::repeat_n(now, (count - last).div_ceil(2) as usize));
+                    // Came back up: the driver may have reset rings and renegotiated pause
+                    link.tuned = false;
+                }
+                _ => {}
+            }
+            link.carrier_changes = Some(count);
+        }
+        link.flaps.retain(|t| now.duration_since(*t) < LINK_FLAP_WINDOW);
+        if link.flaps.len() >= LINK_FLAP_ALERT {
+            actions.push(Action::LinkFlaps { flaps: link.flaps.len() });
+            link.flaps.clear();
+        }
+
+        let eth = ctx.ethtool.link();
+        let half = eth.full_duplex == Some(false);
+        if half && !link.half_duplex {
+            actions.push(Action::HalfDuplex { speed_mbit: eth.speed_mbit });
+        }
+        link.half_duplex = half;
+
+        if !link.tuned {
+            link.tuned = true;
+            let reported = actions.len();
+            if let Some((rx, tx)) = ctx.ethtool.rings().and_then(|r| ring_target(&r, self.tx_ring)) {
+                actions.push(Action::Rings { rx, tx });
+            }
+            if !self.pause_frames && ctx.ethtool.pause().is_some_and(|p| p.autoneg || p.rx || p.tx) {
+                actions.push(Action::PauseFrames { enable: false });
+            }
+            if actions.len() > reported {
+                link.settle_until = Some(now + ETHERNET_SETTLE);
+            }
+        }
+        actions
+    }
+
+    fn forget(&mut self, interface: &str) {
+        self.links.remove(interface);
+    }
+
+    fn dump(&self, interface: &str) -> Option<serde_json::Value> {
+        self.links.get(interface).map(|l| serde_json::json!({
+            "tuned": l.tuned,
+            "carrier_changes": l.carrier_changes,
+            "recent_flaps": l.flaps.len(),
+            "half_duplex": l.half_duplex,
+        }))
+    }
+}
+
+/// Band steering candidate tracking for hysteresis
+#[derive(Debug, Default, Serialize)]
+struct RoamCandidate {
+    bssid: String,
+    score: i32,
+    consecutive_ticks: u32,
+    /// Flap damping already reported for this candidate
+    damped: bool,
+}
+
+/// Roams between the same two APs within this window count as flapping
+const FLAP_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Hold-off after the second switch within the window, doubling with each further one
+const FLAP_BASE_BACKOFF: Duration = Duration::from_secs(2 * 60);
+const FLAP_MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// How long to wait after the last A<->B roam, given how many the window holds
+fn flap_backoff(pair_roams: usize) -> Duration {
+    match pair_roams {
+        0 | 1 => Duration::ZERO,
+        n => FLAP_BASE_BACKOFF.saturating_mul(1 << (n - 2).min(16)).min(FLAP_MAX_BACKOFF),
+    }
+}
+
+/// RSSI samples kept per BSSID for the trend
+const TREND_SAMPLES: usize = 6;
+
+/// Least-squares slope of one-per-tick samples in dB per tick (None below 3 samples)
+fn signal_slope(samples: &VecDeque<i32>) -> Option<f64> {
+    if samples.len() < 3 {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = samples.iter().map(|&s| s as f64).sum::<f64>() / n;
+    let (num, den) = samples.iter().enumerate().fold((0.0, 0.0), |(num, den), (i, &s)| {
+        let dx = i as f64 - mean_x;
+        (num + dx * (s as f64 - mean_y), den + dx * dx)
+    });
+    Some(num / den)
+}
+
+#[derive(Debug)]
+struct RoamRecord {
+    at: Instant,
+    from: String,
+    to: String,
+}
+
+impl RoamRecord {
+    fn between(&self, a: &str, b: &str) -> bool {
+        (self.from == a && self.to == b) || (self.from == b && self.to == a)
+    }
+}
+
+/// Smart band steering: roam to a clearly better AP of the same SSID after it stays better
+pub struct BandSteeringPolicy {
+    hysteresis_ticks: u32,
+    weights: ScoreWeights,
+    min_signal: (i32, i32, i32),
+    candidates: HashMap<String, RoamCandidate>,
+    /// Recent roams per interface (within FLAP_WINDOW)
+    history: HashMap<String, VecDeque<RoamRecord>>,
+    /// Ticks ahead APs are scored at (`[wifi] roam_trend_ticks`, 0 = no prediction)
+    trend_ticks: u32,
+    /// Recent RSSI per interface and BSSID of the current SSID
+    rssi: HashMap<String, HashMap<String, VecDeque<i32>>>,
+    /// `[wifi.access_points]`, keyed by upper-case BSSID
+    nodes: HashMap<String, ApAnnotation>,
+    wired_backhaul_bonus: i32,
+    backhaul_penalty: i32,
+}
+
+impl BandSteeringPolicy {
+    pub fn new(config: &GovernorConfig, wifi: &WifiConfig) -> Self {
+        Self {
+            hysteresis_ticks: config.roam_hysteresis_ticks,
+            weights: ScoreWeights {
+                bias_5ghz: wifi.band_bias_5ghz,
+                bias_6ghz: wifi.band_bias_6ghz,
+                psc_bonus_6ghz: wifi.psc_bonus_6ghz,
+                channel_width_bonus: wifi.channel_width_bonus,
+                marginal_6ghz_dbm: wifi.marginal_6ghz_dbm,
+                marginal_6ghz_penalty_per_db: wifi.marginal_6ghz_penalty_per_db,
+            },
+            // iwd roams away from an AP below its RoamThreshold, so don't steer onto one
+            min_signal: (wifi.min_signal_2g_dbm.max(wifi.roam_threshold_2g),
+                         wifi.min_signal_5g_dbm.max(wifi.roam_threshold_5g),
+                         wifi.min_signal_6g_dbm.max(wifi.roam_threshold_5g)),
+            candidates: HashMap::new(),
+            history: HashMap::new(),
+            trend_ticks: wifi.roam_trend_ticks,
+            rssi: HashMap::new(),
+            nodes: wifi.access_points.iter().map(|(bssid, node)| (bssid.to_uppercase(), node.clone())).collect(),
+            wired_backhaul_bonus: wifi.wired_backhaul_bonus,
+            backhaul_penalty: wifi.backhaul_penalty,
+        }
+    }
+
+    /// BSSID with its `location`, if annotated
+    fn label(&self, bssid: &str) -> String {
+        match self.nodes.get(&bssid.to_uppercase()).and_then(|n| n.location.as_deref()) {
+            Some(location) => format!("{} ({})", bssid, location),
+            None => bssid.to_string(),
+        }
+    }
+
+    /// Mesh nodes share one SSID but not one backhaul: favor nodes annotated as wired, and
+    /// mark down a node that has carried far less traffic than its best sibling
+    fn mesh_adjustment(&self, ap: &AccessPoint, backhaul_mbit: &HashMap<String, u32>) -> i32 {
+        if self.nodes.get(&ap.bssid.to_uppercase()).is_some_and(|n| n.wired_backhaul) {
+            return self.wired_backhaul_bonus;
+        }
+        let (Some(&mbit), Some(&best)) = (backhaul_mbit.get(&ap.bssid), backhaul_mbit.values().max()) else {
+            return 0;
+        };
+        let shortfall = best.saturating_sub(mbit) as f64 / best.max(1) as f64;
+        -(shortfall * self.backhaul_penalty as f64).round() as i32
+    }
+
+    /// Add this tick's RSSI for the current AP and every visible AP of its SSID
+    fn record_signals(&mut self, interface: &str, current: &AccessPoint, visible: &[AccessPoint]) {
+        if self.trend_ticks == 0 {
+            return;
+        }
+        let seen: Vec<(&str, i32)> = std::iter::once(current)
+            .chain(visible.iter().filter(|ap| ap.ssid == current.ssid && ap.bssid != current.bssid))
+            .map(|ap| (ap.bssid.as_str(), ap.signal_strength))
+            .collect();
+        let samples = self.rssi.entry(interface.to_string()).or_default();
+        samples.retain(|bssid, _| seen.iter().any(|(b, _)| b == bssid));
+        for (bssid, signal) in seen {
+            let history = samples.entry(bssid.to_string()).or_default();
+            if history.len() == TREND_SAMPLES {
+                history.pop_front();
+            }
+            history.push_back(signal);
+        }
+    }
+
+    /// RSSI trend of a BSSID in dB per tick
+    fn trend(&self, interface: &str, bssid: &str) -> Option<f64> {
+        signal_slope(self.rssi.get(interface)?.get(bssid)?)
+    }
+
+    /// The AP as it will likely look `trend_ticks` from now
+    fn project(&self, interface: &str, ap: &AccessPoint) -> AccessPoint {
+        match self.trend(interface, &ap.bssid) {
+            Some(slope) if self.trend_ticks > 0 => ap.projected(slope, self.trend_ticks),
+            _ => ap.clone(),
+        }
+    }
+
+    /// Time left before roaming between `a` and `b` is allowed again
+    fn hold_off(&mut self, interface: &str, a: &str, b: &str, now: Instant) -> Duration {
+        let Some(history) = self.history.get_mut(interface) else { return Duration::ZERO };
+        while history.front().is_some_and(|r| now.duration_since(r.at) > FLAP_WINDOW) {
+            history.pop_front();
+        }
+        let pair: Vec<&RoamRecord> = history.iter().filter(|r| r.between(a, b)).collect();
+        match pair.last() {
+            Some(last) => flap_backoff(pair.len()).saturating_sub(now.duration_since(last.at)),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+impl Policy for BandSteeringPolicy {
+    fn name(&self) -> &'static str {
+        "band_steering"
+    }
+
+    fn evaluate(&mut self, ctx: &TickContext) -> Vec<Action> {
+        let Some(current_ap) = ctx.current_ap else { return Vec::new() };
+        debug!(target: BAND_STEERING_LOG, "Band steering: Found {} visible APs (current SSID: '{}')",
+               ctx.access_points.len(), current_ap.ssid);
+        if ctx.access_points.is_empty() {
+            return Vec::new();
+        }
+
+        // The station's RSSI is fresher than the last scan's; score everything on where
+        // its signal is heading, so a collapsing AP loses and a fading current one is left early
+        let mut current_ap = current_ap.clone();
+        if let Some(signal) = ctx.station_signal_dbm {
+            current_ap.signal_strength = signal;
+        }
+        self.record_signals(ctx.interface, &current_ap, ctx.access_points);
+        let current_ap = self.project(ctx.interface, &current_ap);
+        let weights = &self.weights;
+        let current_score = current_ap.refined_score(weights) + self.mesh_adjustment(&current_ap, &ctx.backhaul_mbit);
+        debug!(target: BAND_STEERING_LOG, "Band steering: Checking for better AP (current: {} on {:?}, projected {}dBm, trend {:+.1}dB/tick, score: {})",
+               current_ap.bssid, current_ap.band, current_ap.signal_strength,
+               self.trend(ctx.interface, &current_ap.bssid).unwrap_or(0.0), current_score);
+
+        // Full AP listing is very chatty - trace level only
+        for (i, ap) in ctx.access_points.iter().enumerate() {
+            trace!(target: BAND_STEERING_LOG, "  [{}] AP: {} ({}), band={:?}, signal={}dBm, rate={}Mbps",
+                   i, ap.bssid, ap.ssid, ap.band, ap.signal_strength, ap.max_bitrate / 1000);
+        }
+
+        // Best AP with the same SSID and usable signal for its band
+        let (min_2g, min_5g, min_6g) = self.min_signal;
+        let best = ctx.access_points.iter()
+            .filter(|ap| ap.ssid == current_ap.ssid && ap.bssid != current_ap.bssid)
+            .map(|ap| self.project(ctx.interface, ap))
+            .filter(|ap| ap.signal_usable(min_2g, min_5g, min_6g))
+            .map(|ap| {
+                let score = ap.refined_score(weights) + self.mesh_adjustment(&ap, &ctx.backhaul_mbit);
+                (ap, score)
+            })
+            .max_by_key(|(_, score)| *score)
+            .filter(|(_, score)| *score > current_score);
+
+        let Some((best, score)) = best else {
+            self.candidates.remove(ctx.interface);
+            return Vec::new();
+        };
+        let candidate = self.candidates.entry(ctx.interface.to_string()).or_default();
+        if candidate.bssid == best.bssid {
+            candidate.consecutive_ticks += 1;
+        } else {
+            *candidate = RoamCandidate { bssid: best.bssid.clone(), score, consecutive_ticks: 1, damped: false };
+        }
+        candidate.score = score;
+        if candidate.consecutive_ticks < self.hysteresis_ticks {
+            return Vec::new();
+        }
+
+        // Near-equal APs can beat each other in turn; back off exponentially on a flapping pair
+        let now = Instant::now();
+        let hold = self.hold_off(ctx.interface, &current_ap.bssid, &best.bssid, now);
+        if !hold.is_zero() {
+            let Some(candidate) = self.candidates.get_mut(ctx.interface) else { return Vec::new() };
+            if std::mem::replace(&mut candidate.damped, true) {
+                return Vec::new();
+            }
+            return vec![Action::RoamDamped { from: current_ap.bssid.clone(), to: best.bssid.clone(), hold_secs: hold.as_secs() }];
+        }
+
+        log::info!(target: BAND_STEERING_LOG, event = "roam", interface = ctx.interface,
+                   old = current_ap.bssid.as_str(), new = best.bssid.as_str();
+                   "Band steering: {} -> {} (score: {} -> {}, band: {:?} -> {:?})",
+                   self.label(&current_ap.bssid), self.label(&best.bssid), current_score, score, current_ap.band, best.band);
+        self.candidates.remove(ctx.interface);
+        self.history.entry(ctx.interface.to_string()).or_default()
+            .push_back(RoamRecord { at: now, from: current_ap.bssid.clone(), to: best.bssid.clone() });
+        vec![Action::Roam { from: current_ap.bssid.clone(), to: best.bssid.clone() }]
+    }
+
+    fn forget(&mut self, interface: &str) {
+        self.candidates.remove(interface);
+        self.history.remove(interface);
+        self.rssi.remove(interface);
+    }
+
+    fn dump(&self, interface: &str) -> Option<serde_json::Value> {
+        let history: Vec<serde_json::Value> = self.history.get(interface).into_iter().flatten()
+            .map(|r| serde_json::json!({ "from": r.from, "to": r.to, "secs_ago": r.at.elapsed().as_secs() }))
+            .collect();
+        let trends: serde_json::Map<String, serde_json::Value> = self.rssi.get(interface).into_iter().flatten()
+            .map(|(bssid, samples)| (bssid.clone(), serde_json::json!({ "rssi": samples, "db_per_tick": signal_slope(samples) })))
+            .collect();
+        Some(serde_json::json!({
+            "roam_candidate": self.candidates.get(interface),
+            "roam_history": history,
+            "signal_trends": trends,
+        }))
+    }
+}
+
+/// The enabled policies, in run order
+///
+/// A policy runs if its `[governor]` switch is on (where it has one) and its name isn't
+/// in `disabled_policies`.
+pub fn build(config: &GovernorConfig, wifi: &WifiConfig, power: &PowerConfig) -> Vec<Box<dyn Policy>> {
+    for name in config.disabled_policies.iter().filter(|n| !POLICY_NAMES.contains(&n.as_str())) {
+        log::warn!("Unknown policy '{}' in disabled_policies (known: {})", name, POLICY_NAMES.join(", "));
+    }
+    let candidates: Vec<(bool, Box<dyn Policy>)> = vec![
+        (config.game_mode_enabled, Box::new(GameModePolicy::new(config))),
+        (config.breathing_cake_enabled && config.cake_mode != CakeMode::Never, Box::new(CakePolicy::new(config))),
+        (config.cpu_coalescing_enabled, Box::new(CoalescingPolicy::new(config))),
+        (true, Box::new(PowerSavePolicy::new(power))),
+        (true, Box::new(EeePolicy::default())),
+        (true, Box::new(EthernetPolicy::new(config))),
+        (config.band_steering_enabled, Box::new(BandSteeringPolicy::new(config, wifi))),
+    ];
+    candidates.into_iter()
+        .filter(|(enabled, policy)| *enabled && !config.disabled_policies.iter().any(|n| n == policy.name()))
+        .map(|(_, policy)| policy)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(pps: u64, in_game: bool, battery: bool) -> TickContext<'static> {
+        TickContext {
+            interface: "eth0",
+            ethernet: true,
+            mlo_capable: false,
+            pps,
+            cpu_load: 0.2,
+            battery_power_save: battery,
+            host: false,
+            in_game,
+            game_mode_override: None,
+            schedule_power_save: None,
+            nm_bitrate_kbit: 0,
+            last_good_bitrate_kbit: None,
+            bandwidth_valid: false,
+            ceiling_mbit: None,
+            current_ap: None,
+            station_signal_dbm: None,
+            access_points: &[],
+            backhaul_mbit: HashMap::new(),
+            iw: IwProbe::default(),
+            ethtool: EthtoolProbe::default(),
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        assert_eq!(effective_bitrate_kbit(400_000, 600_000), 500_000);
+        assert_eq!(effective_bitrate_kbit(6_500, 300_000), 300_000);
+        assert_eq!(effective_bitrate_kbit(6_500, 0), 0);
+        let iw = IwProbe::preset(Some("Connected to aa:bb:cc:dd:ee:ff (on wlan0)\n\ttx bitrate: 1201.0 MBit/s 80MHz HE-MCS 11\n"), None, None);
+        assert_eq!(bitrate_from_iw("wlan0", &iw), Some(1_201_000));
+        let iw = IwProbe::preset(Some("Not connected.\n"), Some("Station aa:bb (on wlan0)\n\ttx bitrate:\t866.7 MBit/s\n"), None);
+        assert_eq!(bitrate_from_iw("wlan0", &iw), Some(866_700));
+
+        let config = GovernorConfig::default();
+        let mut game = GameModePolicy::new(&config);
+        assert_eq!(game.evaluate(&ctx(500, false, false)), vec![Action::GameMode { active: true, pps: 500 }]);
+        assert!(game.evaluate(&ctx(10, true, false)).is_empty(), "cooldown keeps game mode on");
+
+        // EEE: battery and idle for 3 ticks turns it on; game mode vetoes it again
+        let mut eee = EeePolicy::default();
+        assert!(eee.evaluate(&ctx(0, false, true)).is_empty());
+        assert!(eee.evaluate(&ctx(0, false, true)).is_empty());
+        assert_eq!(eee.evaluate(&ctx(0, false, true)), vec![Action::Eee { enable: true, reason: "battery, idle" }]);
+        for _ in 0..2 {
+            assert!(eee.evaluate(&ctx(0, true, true)).is_empty());
+        }
+        assert_eq!(eee.evaluate(&ctx(0, true, true)), vec![Action::Eee { enable: false, reason: "game mode" }]);
+        // Unplugging switches on the next tick
+        eee.power_source_changed(PowerSource::Battery);
+        assert_eq!(eee.evaluate(&ctx(0, false, true)), vec![Action::Eee { enable: true, reason: "battery, idle" }]);
+
+        // Flap damping: free for the first switch, then 2, 4, 8... minutes, capped at an hour
+        assert_eq!(flap_backoff(1), Duration::ZERO);
+        assert_eq!(flap_backoff(2), Duration::from_secs(120));
+        assert_eq!(flap_backoff(4), Duration::from_secs(480));
+        assert_eq!(flap_backoff(40), FLAP_MAX_BACKOFF);
+        let mut steering = BandSteeringPolicy::new(&config, &WifiConfig::default());
+        let now = Instant::now();
+        steering.history.insert("wlan0".into(), VecDeque::from([
+            RoamRecord { at: now, from: "aa".into(), to: "bb".into() },
+            RoamRecord { at: now, from: "bb".into(), to: "aa".into() },
+        ]));
+        assert_eq!(steering.hold_off("wlan0", "aa", "bb", now), Duration::from_secs(120));
+        assert_eq!(steering.hold_off("wlan0", "aa", "cc", now), Duration::ZERO);
+
+        // Signal trend: a steady 2dB/tick fade projects 5 ticks ahead, capped at 10dB
+        assert_eq!(signal_slope(&VecDeque::from([-60, -62])), None);
+        assert_eq!(signal_slope(&VecDeque::from([-60, -62, -64, -66])), Some(-2.0));
+        let ap = AccessPoint {
+            path: "/".into(), ssid: "Home".into(), bssid: "aa".into(), frequency: 5180,
+            band: crate::network::nm::WifiBand::Band5GHz, signal_strength: -60, max_bitrate: 0, bandwidth_mhz: 0,
+        };
+        assert_eq!(ap.projected(-1.0, 5).signal_strength, -65);
+        assert_eq!(ap.projected(-4.0, 5).signal_strength, -70);
+
+        // Mesh: a node that has carried half of what its sibling has loses half the penalty
+        let mut wifi = WifiConfig::default();
+        wifi.access_points.insert("cc".into(), ApAnnotation { wired_backhaul: true, location: Some("office".into()) });
+        let steering = BandSteeringPolicy::new(&config, &wifi);
+        let backhaul = HashMap::from([("aa".to_string(), 50), ("bb".to_string(), 100)]);
+        assert_eq!(steering.mesh_adjustment(&ap, &backhaul), -5);
+        assert_eq!(steering.mesh_adjustment(&AccessPoint { bssid: "CC".into(), ..ap.clone() }, &backhaul), 5);
+        assert_eq!(steering.mesh_adjustment(&ap, &HashMap::new()), 0);
+        assert_eq!(steering.label("CC"), "CC (office)");
+
+        // Candidates must clear iwd's roam threshold as well as the band minimum
+        wifi.roam_threshold_5g = -65;
+        assert_eq!(BandSteeringPolicy::new(&config, &wifi).min_signal, (-75, -65, -65));
+
+        let mut disabled = config.clone();
+        disabled.disabled_policies = vec!["eee".to_string(), "band_steering".to_string()];
+        let names: Vec<&str> = build(&disabled, &WifiConfig::default(), &PowerConfig::default())
+            .iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["game_mode", "cake", "coalescing", "power_save", "ethernet"]);
+    }
+
+    #[test]
+    fn test_ethernet_policy() {
+        let rings = "Pre-set maximums:\nRX:\t4096\nTX:\t4096\nCurrent hardware settings:\nRX:\t256\nTX:\t1024\n";
+        let pause = "Autonegotiate:\ton\nRX:\ton\nTX:\ton\n";
+        let probe = |duplex: &str, carrier| EthtoolProbe::preset(&format!("\tSpeed: 1000Mb/s\n\tDuplex: {}\n", duplex),
+                                                                 Some(rings), Some(pause), carrier);
+        let mut policy = EthernetPolicy::new(&GovernorConfig::default());
+        let mut tick = ctx(0, false, false);
+        tick.ethtool = probe("Full", 2);
+        assert_eq!(policy.evaluate(&tick), vec![Action::Rings { rx: 4096, tx: 256 }, Action::PauseFrames { enable: false }]);
+        // The NIC resetting after the change isn't a flap
+        tick.ethtool = probe("Full", 4);
+        assert!(policy.evaluate(&tick).is_empty());
+
+        // Three down/up cycles once settled, and a half-duplex renegotiation
+        policy.links.get_mut("eth0").unwrap().settle_until = None;
+        tick.ethtool = probe("Half", 10);
+        let actions = policy.evaluate(&tick);
+        assert_eq!(actions[..2], [Action::LinkFlaps { flaps: 3 }, Action::HalfDuplex { speed_mbit: Some(1000) }]);
+        tick.ethtool = probe("Half", 10);
+        assert!(!policy.evaluate(&tick).contains(&Action::HalfDuplex { speed_mbit: Some(1000) }), "reported once");
+        assert_eq!(ring_target(&Rings { rx_max: 4096, tx_max: 128, rx: 4096, tx: 128 }, 256), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Single writer for Wi-Fi power save
+//!
+//! `apply`, `revert`, game mode and the Governor's adaptive logic all have an opinion
+//! on power save. They file requests here instead of calling `iw` themselves; the
+//! highest-priority request wins (config override > safe mode > game mode > schedule >
+//! adaptive), and in the daemon a change only goes out once it has held for a few
+//! ticks, so competing inputs can't make it flap.
+
+use anyhow::Result;
+use log::info;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::network::wifi::{WifiInterface, WifiManager};
+
+/// Ticks a new decision must hold before the daemon applies it (6s at the default rate)
+const STABLE_TICKS: u32 = 3;
+
+/// Who is asking, highest priority first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    /// `[power] wlan_power_save = "on"/"off"`, or `revert` restoring the default
+    Config,
+    /// Captive portal / limited network: back to the default
+    SafeMode,
+    GameMode,
+    /// A `[[schedule]]` entry's `power_save`
+    Schedule,
+    /// Battery vs AC, network activity, streaming host
+    Adaptive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Request {
+    pub enable: bool,
+    pub reason: &'static str,
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    requests: BTreeMap<Source, Request>,
+    applied: Option<bool>,
+    /// Decision waiting out the hysteresis, and for how many ticks it has held
+    pending: Option<(bool, u32)>,
+}
+
+/// Power save arbitration for every interface
+#[derive(Debug, Default)]
+pub struct PowerSaveController {
+    entries: HashMap<String, Entry>,
+    /// Log decisions instead of writing them (`--dry-run`)
+    dry_run: bool,
+}
+
+impl PowerSaveController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Arbitrate as usual but only log what would be written
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn request(&mut self, interface: &str, source: Source, enable: bool, reason: &'static str) {
+        self.entries.entry(interface.to_string()).or_default()
+            .requests.insert(source, Request { enable, reason });
+    }
+
+    pub fn withdraw(&mut self, interface: &str, source: Source) {
+        if let Some(entry) = self.entries.get_mut(interface) {
+            entry.requests.remove(&source);
+        }
+    }
+
+    /// The winning request
+    pub fn decision(&self, interface: &str) -> Option<(Source, Request)> {
+        self.entries.get(interface)?
+            .requests.iter().next()
+            .map(|(source, request)| (*source, *request))
+    }
+
+    /// Last state written to the interface
+    pub fn applied(&self, interface: &str) -> Option<bool> {
+        self.entries.get(interface)?.applied
+    }
+
+    /// Decision and applied state for the daemon's SIGUSR1 dump
+    pub fn dump(&self, interface: &str) -> serde_json::Value {
+        let decision = self.decision(interface);
+        serde_json::json!({
+            "source": decision.map(|(source, _)| format!("{:?}", source)),
+            "wanted": decision.map(|(_, request)| request.enable),
+            "reason": decision.map(|(_, request)| request.reason),
+            "applied": self.applied(interface),
+            "pending": self.entries.get(interface).and_then(|e| e.pending),
+        })
+    }
+
+    /// Drop everything known about an interface (it went away)
+    pub fn forget(&mut self, interface: &str) {
+        self.entries.remove(interface);
+    }
+
+    /// Advance the hysteresis by one tick; the request to write once it has held long enough
+    fn due(&mut self, interface: &str) -> Option<Request> {
+        let (_, request) = self.decision(interface)?;
+        let entry = self.entries.get_mut(interface)?;
+        if entry.applied == Some(request.enable) {
+            entry.pending = None;
+            return None;
+        }
+        let ticks = match entry.pending {
+            Some((enable, ticks)) if enable == request.enable => ticks + 1,
+            _ => 1,
+        };
+        if ticks >= STABLE_TICKS {
+            entry.pending = None;
+            Some(request)
+        } else {
+            entry.pending = Some((request.enable, ticks));
+            None
+        }
+    }
+
+    /// Daemon tick: apply the winning request once it has been stable
+    pub fn tick(&mut self, wifi: &WifiManager, ifc: &WifiInterface) -> Result<()> {
+        match self.due(&ifc.name) {
+            Some(request) => self.write(wifi, ifc, request),
+            None => Ok(()),
+        }
+    }
+
+    /// One-shot commands: apply the winning request right away
+    pub fn apply_now(&mut self, wifi: &WifiManager, ifc: &WifiInterface) -> Result<()> {
+        match self.decision(&ifc.name) {
+            Some((_, request)) => self.write(wifi, ifc, request),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&mut self, wifi: &WifiManager, ifc: &WifiInterface, request: Request) -> Result<()> {
+        let state = if request.enable { "ENABLED" } else { "DISABLED" };
+        if self.dry_run {
+            info!(event = "power_save", interface = ifc.name.as_str(), new = request.enable, reason = request.reason, dry_run = true;
+                  "[DRY-RUN] Power save would be {} on {} ({})", state, ifc.name, request.reason);
+        } else {
+            if request.enable {
+                wifi.enable_power_save(ifc)?;
+            } else {
+                wifi.disable_power_save(ifc)?;
+            }
+            info!(event = "power_save", interface = ifc.name.as_str(), new = request.enable, reason = request.reason;
+                  "Power save {} on {} ({})", state, ifc.name, request.reason);
+        }
+        let entry = self.entries.entry(ifc.name.clone()).or_default();
+        entry.applied = Some(request.enable);
+        entry.pending = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_and_hysteresis() {
+        let mut ctl = PowerSaveController::new();
+        ctl.request("wlan0", Source::Adaptive, true, "battery, idle");
+        ctl.request("wlan0", Source::GameMode, false, "game mode");
+        assert_eq!(ctl.decision("wlan0").unwrap().0, Source::GameMode);
+        ctl.request("wlan0", Source::Config, true, "config");
+        assert_eq!(ctl.decision("wlan0").unwrap(), (Source::Config, Request { enable: true, reason: "config" }));
+
+        ctl.withdraw("wlan0", Source::Config);
+        assert_eq!(ctl.due("wlan0"), None);
+        assert_eq!(ctl.due("wlan0"), None);
+        // Game mode ending resets the count before the third tick
+        ctl.withdraw("wlan0", Source::GameMode);
+        assert_eq!(ctl.due("wlan0"), None);
+        assert_eq!(ctl.due("wlan0"), None);
+        assert_eq!(ctl.due("wlan0").map(|r| r.enable), Some(true));
+        assert_eq!(ctl.decision("eth0"), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Connection pre-warming after (re)connect
+//!
+//! The first packets of a stream after a reconnect or resume wait on ARP/neighbor
+//! discovery for the gateway and a cold DNS cache. Pinging the gateway and resolving
+//! the streaming host right away does that work before the stream needs it.
+
+use log::{debug, info};
+use std::process::Command;
+use std::time::Instant;
+
+use crate::network::latency;
+use crate::utils::events;
+
+/// NUD state from `ip neigh show <addr> dev <iface>` (e.g. REACHABLE, STALE)
+pub fn parse_neigh_state(output: &str) -> Option<String> {
+    output.lines().next()?.split_whitespace().last().map(str::to_string)
+}
+
+/// First address from `getent ahosts <host>`
+pub fn parse_getent(output: &str) -> Option<String> {
+    output.split_whitespace().next().map(str::to_string)
+}
+
+/// Ping `target` through `interface` a few times; true if any reply came back
+fn ping(interface: &str, target: &str) -> bool {
+    Command::new("ping")
+        .args(["-n", "-q", "-c", "3", "-i", "0.2", "-W", "1", "-I", interface, target])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+fn neigh_state(interface: &str, addr: &str) -> Option<String> {
+    let output = Command::new("ip").args(["neigh", "show", addr, "dev", interface]).output().ok()?;
+    parse_neigh_state(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn resolve(host: &str) -> Option<String> {
+    let output = Command::new("getent").args(["ahosts", host]).output().ok()?;
+    parse_getent(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Prime the gateway neighbor entry and the streaming host's DNS/path (blocking, a few seconds);
+/// a dry run logs the result but keeps it out of the event log
+pub fn prime(interface: &str, stream_host: Option<&str>, dry_run: bool) {
+    let start = Instant::now();
+    let Some(gateway) = latency::default_gateway(interface) else {
+        debug!("Pre-warm skipped on {}: no default gateway", interface);
+        return;
+    };
+
+    let gateway_ok = ping(interface, &gateway);
+    let neighbor = neigh_state(interface, &gateway);
+
+    let host_addr = stream_host.and_then(|host| {
+        let addr = resolve(host);
+        if let Some(addr) = &addr {
+            ping(interface, addr);
+        }
+        addr
+    });
+
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    info!(event = "prewarm", interface = interface;
+          "Pre-warmed {}: gateway {} {} ({}){}", interface, gateway,
+          if gateway_ok { "answered" } else { "silent" },
+          neighbor.as_deref().unwrap_or("no neighbor entry"),
+          stream_host.map(|h| format!(", {} -> {}", h, host_addr.as_deref().unwrap_or("unresolved"))).unwrap_or_default());
+    if dry_run {
+        return;
+    }
+    events::record("prewarm", Some(interface), serde_json::json!({
+        "gateway": gateway,
+        "gateway_ok": gateway_ok,
+        "neighbor": neighbor,
+        "stream_host": stream_host,
+        "stream_host_addr": host_addr,
+        "elapsed_ms": elapsed_ms,
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_neigh_and_getent() {
+        assert_eq!(parse_neigh_state("192.168.1.1 lladdr aa:bb:cc:dd:ee:ff REACHABLE\n").as_deref(), Some("REACHABLE"));
+        assert_eq!(parse_neigh_state(""), None);
+        let getent = "192.168.1.20    STREAM gaming-pc.lan\n192.168.1.20    DGRAM\n";
+        assert_eq!(parse_getent(getent).as_deref(), Some("192.168.1.20"));
+        assert_eq!(parse_getent(""), None);
+    }
+}
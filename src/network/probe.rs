@@ -0,0 +1,123 @@
+//! Per-tick `iw` queries
+//!
+//! Beacon-loss tracking, band steering, the CAKE bitrate fallback and MLO link rates
+//! all read `iw dev <iface> link`, `station dump` or `info`. The Governor makes one
+//! `IwProbe` per interface per tick and hands it to all of them: each command runs at
+//! most once, on first use, and its output is shared.
+
+use std::cell::OnceCell;
+use std::process::Command;
+
+/// Output of `iw` with `args`, None if it fails
+pub fn iw(args: &[&str]) -> Option<String> {
+    let output = Command::new("iw").args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Operating channel of an interface, as nl80211 reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelInfo {
+    pub channel: u32,
+    pub freq_mhz: u32,
+    /// 20, 40, 80, 160 or 320 (80+80 reads as 160)
+    pub width_mhz: u32,
+    /// Center of the whole channel (differs from `freq_mhz` above 20MHz)
+    pub center_mhz: Option<u32>,
+}
+
+/// The `channel 37 (6135 MHz), width: 160 MHz, center1: 6185 MHz` line of `iw dev <iface> info`
+pub fn parse_channel(info: &str) -> Option<ChannelInfo> {
+    let line = info.lines().map(str::trim).find(|l| l.starts_with("channel "))?;
+    let mut words = line.split_whitespace();
+    words.next();
+    let channel = words.next()?.parse().ok()?;
+    let freq_mhz = words.next()?.trim_start_matches('(').parse().ok()?;
+    let field = |name: &str| {
+        line.split(", ").find_map(|part| part.strip_prefix(name))
+            .and_then(|v| v.split_whitespace().next())
+            .map(str::to_string)
+    };
+    let width_mhz = match field("width: ")?.as_str() {
+        "80+80" => 160,
+        w => w.parse().ok()?,
+    };
+    let center_mhz = field("center1: ").and_then(|c| c.parse().ok());
+    Some(ChannelInfo { channel, freq_mhz, width_mhz, center_mhz })
+}
+
+/// `connected time: 5231 seconds` of the AP entry in `iw dev <iface> station dump`
+pub fn parse_connected_secs(station_dump: &str) -> Option<u64> {
+    station_dump.lines()
+        .find_map(|l| l.trim().strip_prefix("connected time:"))
+        .and_then(|v| v.split_whitespace().next()?.parse().ok())
+}
+
+/// `iw` output for one interface, fetched lazily and kept for the tick
+#[derive(Debug, Default)]
+pub struct IwProbe {
+    interface: String,
+    link: OnceCell<Option<String>>,
+    station_dump: OnceCell<Option<String>>,
+    info: OnceCell<Option<String>>,
+}
+
+impl IwProbe {
+    pub fn new(interface: &str) -> Self {
+        Self { interface: interface.to_string(), ..Default::default() }
+    }
+
+    fn query<'a>(&self, cell: &'a OnceCell<Option<String>>, command: &[&str]) -> Option<&'a str> {
+        cell.get_or_init(|| {
+            let mut args = vec!["dev", self.interface.as_str()];
+            args.extend_from_slice(command);
+            iw(&args)
+        }).as_deref()
+    }
+
+    /// `iw dev <iface> link`
+    pub fn link(&self) -> Option<&str> {
+        self.query(&self.link, &["link"])
+    }
+
+    /// `iw dev <iface> station dump`
+    pub fn station_dump(&self) -> Option<&str> {
+        self.query(&self.station_dump, &["station", "dump"])
+    }
+
+    /// `iw dev <iface> info`
+    pub fn info(&self) -> Option<&str> {
+        self.query(&self.info, &["info"])
+    }
+
+    /// Channel, width and center frequency from `info`
+    pub fn channel(&self) -> Option<ChannelInfo> {
+        parse_channel(self.info()?)
+    }
+
+    /// How long the interface has been associated with its AP
+    pub fn connected_secs(&self) -> Option<u64> {
+        parse_connected_secs(self.station_dump()?)
+    }
+
+    /// A probe that answers with fixed output instead of running `iw`
+    #[cfg(test)]
+    pub fn preset(link: Option<&str>, station_dump: Option<&str>, info: Option<&str>) -> Self {
+        let cell = |s: Option<&str>| OnceCell::from(s.map(str::to_string));
+        Self { interface: String::new(), link: cell(link), station_dump: cell(station_dump), info: cell(info) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_channel() {
+        let info = "Interface wlan0\n\tifindex 3\n\ttype managed\n\tchannel 37 (6135 MHz), width: 160 MHz, center1: 6185 MHz\n\ttxpower 22.00 dBm\n";
+        assert_eq!(parse_channel(info), Some(ChannelInfo { channel: 37, freq_mhz: 6135, width_mhz: 160, center_mhz: Some(6185) }));
+        let info = "\tchannel 6 (2437 MHz), width: 20 MHz (no HT), center1: 2437 MHz\n";
+        assert_eq!(parse_channel(info).map(|c| (c.channel, c.width_mhz)), Some((6, 20)));
+        assert_eq!(parse_channel("Interface wlan0\n\ttype managed\n"), None);
+        assert_eq!(parse_connected_secs("Station aa:bb (on wlan0)\n\tinactive time:\t20 ms\n\tconnected time:\t5231 seconds\n"), Some(5231));
+    }
+}
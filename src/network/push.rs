@@ -0,0 +1,156 @@
+//! Metrics push (InfluxDB line protocol / statsd over UDP)
+//!
+//! For long-term history in Grafana without a listener on the handheld: every
+//! `push_interval_secs` the latest tick's link metrics are sent as one UDP datagram to
+//! `push_host`, either as InfluxDB line protocol (InfluxDB 1.x UDP input, Telegraf
+//! `socket_listener`) or as statsd gauges. UDP never blocks the tick; a collector
+//! that is down just misses the points.
+
+use log::{debug, info};
+use serde::Deserialize;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::config::structs::MetricsConfig;
+use crate::network::history::{self, Row};
+use crate::network::metrics::MetricsSnapshot;
+use crate::utils::notify;
+
+/// Measurement name / statsd prefix
+const MEASUREMENT: &str = "hifi_wifi";
+
+/// `[metrics] push_format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushFormat {
+    Influx,
+    Statsd,
+}
+
+/// Numeric fields of a row as (name, value, integer)
+fn fields(row: &Row) -> Vec<(&'static str, f64, bool)> {
+    [
+        ("rtt_p50_ms", row.rtt_p50_ms, false),
+        ("rtt_p95_ms", row.rtt_p95_ms, false),
+        ("jitter_ms", row.jitter_ms, false),
+        ("loss_pct", row.loss_pct, false),
+        ("signal_dbm", row.signal_dbm.map(f64::from), true),
+        ("cake_mbit", row.cake_mbit.map(f64::from), true),
+        ("throughput_mbit", Some(row.throughput_mbit as f64), true),
+        ("game_mode", Some(if row.game_mode { 1.0 } else { 0.0 }), true),
+    ]
+    .into_iter()
+    .filter_map(|(name, value, int)| Some((name, value?, int)))
+    .collect()
+}
+
+/// Escape a tag value (commas, spaces and equals signs)
+fn influx_tag(value: &str) -> String {
+    value.replace(',', "\\,").replace(' ', "\\ ").replace('=', "\\=")
+}
+
+/// `hifi_wifi,host=deck,interface=wlan0 rtt_p50_ms=3.4,...,signal_dbm=-52i <ns>`
+fn influx_lines(rows: &[Row], host: &str) -> String {
+    rows.iter().map(|row| {
+        let fields: Vec<String> = fields(row).into_iter()
+            .map(|(name, value, int)| if int { format!("{}={}i", name, value as i64) } else { format!("{}={}", name, value) })
+            .collect();
+        format!("{},host={},interface={} {} {}\n", MEASUREMENT, influx_tag(host), influx_tag(&row.interface),
+                fields.join(","), row.ts * 1_000_000_000)
+    }).collect()
+}
+
+/// `hifi_wifi.deck.wlan0.rtt_p50_ms:3.4|g`
+fn statsd_lines(rows: &[Row], host: &str) -> String {
+    let clean = |s: &str| s.replace(['.', ':', '|', ' '], "_");
+    rows.iter().flat_map(|row| {
+        fields(row).into_iter().map(move |(name, value, _)| {
+            format!("{}.{}.{}.{}:{}|g\n", MEASUREMENT, clean(host), clean(&row.interface), name, value)
+        })
+    }).collect()
+}
+
+/// Sends the governor's snapshots to the configured collector
+pub struct MetricsPusher {
+    socket: UdpSocket,
+    target: String,
+    /// Resolved once, again after a send error (the collector's address may change)
+    addr: Option<SocketAddr>,
+    format: PushFormat,
+    hostname: String,
+    interval: Duration,
+    last_push: Option<Instant>,
+}
+
+impl MetricsPusher {
+    /// None unless both `push_format` and `push_host` are set
+    pub fn new(config: &MetricsConfig) -> Option<Self> {
+        let (format, target) = (config.push_format?, config.push_host.clone()?);
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .and_then(|s| s.set_nonblocking(true).map(|_| s))
+            .map_err(|e| debug!("Metrics push disabled, no UDP socket: {}", e))
+            .ok()?;
+        let hostname = notify::hostname();
+        info!("Pushing metrics to {} ({:?}) every {}s", target, format, config.push_interval_secs);
+        Some(Self {
+            socket,
+            target,
+            addr: None,
+            format,
+            hostname,
+            interval: Duration::from_secs(config.push_interval_secs.max(1)),
+            last_push: None,
+        })
+    }
+
+    pub fn push(&mut self, snapshot: &MetricsSnapshot) {
+        if self.last_push.is_some_and(|t| t.elapsed() < self.interval) {
+            return;
+        }
+        self.last_push = Some(Instant::now());
+        let rows = history::rows(snapshot);
+        if rows.is_empty() {
+            return;
+        }
+        let payload = match self.format {
+            PushFormat::Influx => influx_lines(&rows, &self.hostname),
+            PushFormat::Statsd => statsd_lines(&rows, &self.hostname),
+        };
+        if self.addr.is_none() {
+            self.addr = self.target.to_socket_addrs().ok().and_then(|mut a| a.next());
+        }
+        let Some(addr) = self.addr else {
+            debug!("Metrics push: can't resolve {}", self.target);
+            return;
+        };
+        if let Err(e) = self.socket.send_to(payload.as_bytes(), addr) {
+            debug!("Metrics push to {} failed: {}", self.target, e);
+            self.addr = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_formats() {
+        let rows = vec![Row {
+            ts: 1_792_189_800,
+            interface: "wlan0".to_string(),
+            rtt_p50_ms: Some(3.5),
+            rtt_p95_ms: None,
+            jitter_ms: None,
+            loss_pct: Some(0.0),
+            signal_dbm: Some(-52),
+            cake_mbit: None,
+            throughput_mbit: 12,
+            game_mode: true,
+        }];
+        assert_eq!(influx_lines(&rows, "steam deck"),
+                   "hifi_wifi,host=steam\\ deck,interface=wlan0 rtt_p50_ms=3.5,loss_pct=0,signal_dbm=-52i,\
+                    throughput_mbit=12i,game_mode=1i 1792189800000000000\n");
+        assert_eq!(statsd_lines(&rows, "deck.lan").lines().next(), Some("hifi_wifi.deck_lan.wlan0.rtt_p50_ms:3.5|g"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Reconnect assist and link-loss tracking
+//!
+//! When a Wi-Fi device drops to Disconnected/Failed without the user asking for it,
+//! re-activate the last profile it had (what opening the network list and picking it
+//! does) with exponential backoff. Every disconnect, attempt and recovery goes to the
+//! event log, as do beacon-loss counter increases.
+
+use log::{info, warn, debug};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::network::nm::{DeviceState, NmClient, WirelessDevice};
+use crate::utils::events;
+
+/// First retry delay; doubled per attempt up to MAX_BACKOFF
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+/// NMDeviceStateReason values where reconnecting would be wrong or pointless:
+/// no secrets, device removed, sleeping, profile deleted, user requested
+const NO_RETRY_REASONS: &[u32] = &[7, 36, 37, 38, 39];
+
+/// Delay before attempt number `attempt` (1-based)
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF.saturating_mul(1 << attempt.saturating_sub(1).min(16)).min(MAX_BACKOFF)
+}
+
+struct PendingReconnect {
+    device_path: String,
+    profile: String,
+    since: Instant,
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+/// Watches NM device states across governor ticks
+pub struct ReconnectAssist {
+    max_attempts: u32,
+    states: HashMap<String, DeviceState>,
+    /// Last profile each interface was activated with
+    profiles: HashMap<String, String>,
+    pending: HashMap<String, PendingReconnect>,
+}
+
+impl ReconnectAssist {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            states: HashMap::new(),
+            profiles: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Feed the current device list (called once per tick)
+    pub async fn update(&mut self, nm: &NmClient, devices: &[WirelessDevice]) {
+        for dev in devices {
+            let prev = self.states.insert(dev.interface.clone(), dev.state);
+            match dev.state {
+                DeviceState::Activated if prev != Some(DeviceState::Activated) => {
+                    self.on_activated(nm, dev).await;
+                }
+                DeviceState::Disconnected | DeviceState::Failed => {
+                    if matches!(prev, Some(DeviceState::Activated | DeviceState::Deactivating)) {
+                        self.on_dropped(nm, dev).await;
+                    }
+                    self.maybe_retry(nm, dev).await;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn on_activated(&mut self, nm: &NmClient, dev: &WirelessDevice) {
+        if let Some(pending) = self.pending.remove(&dev.interface) {
+            let downtime = pending.since.elapsed().as_secs();
+            info!(event = "reconnected", interface = dev.interface.as_str(), attempts = pending.attempts;
+                  "{} reconnected after {}s ({} assisted attempt(s))", dev.interface, downtime, pending.attempts);
+            events::record("reconnected", Some(&dev.interface),
+                           serde_json::json!({ "downtime_secs": downtime, "attempts": pending.attempts }));
+        }
+        match nm.active_profile(&dev.path).await {
+            Ok(Some(profile)) => {
+                self.profiles.insert(dev.interface.clone(), profile);
+            }
+            Ok(None) => {}
+            Err(e) => debug!("Could not read active profile of {}: {}", dev.interface, e),
+        }
+    }
+
+    async fn on_dropped(&mut self, nm: &NmClient, dev: &WirelessDevice) {
+        let reason = nm.device_state_reason(&dev.path).await.unwrap_or(0);
+        warn!(event = "disconnect", interface = dev.interface.as_str(), reason = reason;
+              "{} dropped to {:?} (NM reason {})", dev.interface, dev.state, reason);
+        events::record("disconnect", Some(&dev.interface),
+                       serde_json::json!({ "state": format!("{:?}", dev.state), "reason": reason }));
+
+        if NO_RETRY_REASONS.contains(&reason) {
+            debug!("Not assisting reconnect on {} (reason {})", dev.interface, reason);
+            return;
+        }
+        let Some(profile) = self.profiles.get(&dev.interface) else { return };
+        let now = Instant::now();
+        self.pending.entry(dev.interface.clone()).or_insert(PendingReconnect {
+            device_path: dev.path.clone(),
+            profile: profile.clone(),
+            since: now,
+            attempts: 0,
+            next_attempt: now + backoff(1),
+        });
+    }
+
+    async fn maybe_retry(&mut self, nm: &NmClient, dev: &WirelessDevice) {
+        let max_attempts = self.max_attempts;
+        let Some(pending) = self.pending.get_mut(&dev.interface) else { return };
+        if Instant::now() < pending.next_attempt {
+            return;
+        }
+        if pending.attempts >= max_attempts {
+            warn!("Giving up reconnecting {} after {} attempts", dev.interface, pending.attempts);
+            events::record("reconnect_gave_up", Some(&dev.interface),
+                           serde_json::json!({ "attempts": pending.attempts }));
+            self.pending.remove(&dev.interface);
+            return;
+        }
+
+        pending.attempts += 1;
+        pending.next_attempt = Instant::now() + backoff(pending.attempts + 1);
+        let result = nm.activate_connection(&pending.profile, &pending.device_path).await;
+        info!(event = "reconnect_attempt", interface = dev.interface.as_str(), attempt = pending.attempts;
+              "Reconnect attempt {} on {}{}", pending.attempts, dev.interface,
+              result.as_ref().err().map(|e| format!(" failed: {}", e)).unwrap_or_default());
+        events::record("reconnect_attempt", Some(&dev.interface), serde_json::json!({
+            "attempt": pending.attempts,
+            "error": result.err().map(|e| e.to_string()),
+        }));
+    }
+}
+
+/// `beacon loss:` counter from `iw station dump` (the AP entry in station mode)
+pub fn parse_beacon_loss(output: &str) -> Option<u64> {
+    output.lines()
+        .find_map(|line| line.trim().strip_prefix("beacon loss:"))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_beacon_loss() {
+        assert_eq!(backoff(1), Duration::from_secs(5));
+        assert_eq!(backoff(3), Duration::from_secs(20));
+        assert_eq!(backoff(10), MAX_BACKOFF);
+
+        let dump = "Station aa:bb:cc:dd:ee:ff (on wlan0)\n\tinactive time:\t20 ms\n\tbeacon loss:\t3\n\trx drop misc:\t0\n";
+        assert_eq!(parse_beacon_loss(dump), Some(3));
+        assert_eq!(parse_beacon_loss("Station aa:bb (on wlan0)\n"), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Scan abort
+//!
+//! A background scan takes the radio off-channel for tens of milliseconds at a time,
+//! which a stream sees as latency spikes. With `[governor] scan_abort_interval_ms` set,
+//! a background task runs `iw dev <iface> scan abort` that often while game mode is on,
+//! a quarter as often otherwise, and not at all while every link is idle in power save.
+//! Scans the daemon requests itself (band steering, safe mode) are left alone for 30s,
+//! and interfaces in safe mode are never touched.
+//!
+//! Aborting is the fallback: while a link is in game mode the backend's own mechanism is
+//! tried first (iwd's DisablePeriodicScan, wpa_supplicant's bgscan, see
+//! `BackendTuner::suppress_scans`), and only interfaces where that isn't available get the
+//! abort loop. Game mode ending hands the backend its previous setting back, so roaming
+//! scans run normally the rest of the time.
+
+use log::{debug, info};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::watch;
+
+use crate::network::backend_tuner::{BackendTuner, ScanSuppression};
+
+/// Outside game mode the interval is this many times longer
+const IDLE_FACTOR: u64 = 4;
+/// Below this many packets/s a link counts as idle
+const IDLE_PPS: u64 = 5;
+/// How long a scan the daemon asked for is left to finish
+const HOLD_OFF: Duration = Duration::from_secs(30);
+
+/// One interface as the Governor saw it this tick
+pub struct LinkActivity {
+    pub interface: String,
+    pub in_game: bool,
+    pub power_save: bool,
+    pub pps: u64,
+}
+
+/// How often to abort scans for this tick's links (None = paused)
+fn interval(base_ms: u64, links: &[LinkActivity]) -> Option<Duration> {
+    if links.is_empty() || links.iter().all(|l| l.power_save && !l.in_game && l.pps < IDLE_PPS) {
+        return None;
+    }
+    let factor = if links.iter().any(|l| l.in_game) { 1 } else { IDLE_FACTOR };
+    Some(Duration::from_millis(base_ms * factor))
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Plan {
+    interval: Option<Duration>,
+    interfaces: Vec<String>,
+}
+
+/// Handle to the background task; dropping it stops the task
+pub struct ScanAborter {
+    base_ms: u64,
+    tx: watch::Sender<Plan>,
+    held: HashMap<String, Instant>,
+    tuner: BackendTuner,
+    /// Per interface in game mode: what the backend did, None where it couldn't and we
+    /// abort instead
+    suppressed: HashMap<String, Option<ScanSuppression>>,
+}
+
+impl ScanAborter {
+    /// None when `interval_ms` is 0 (off)
+    pub fn start(interval_ms: u64) -> Option<Self> {
+        if interval_ms == 0 {
+            return None;
+        }
+        info!("Aborting background scans every {}ms in game mode, {}ms otherwise", interval_ms, interval_ms * IDLE_FACTOR);
+        let (tx, mut rx) = watch::channel(Plan::default());
+        tokio::spawn(async move {
+            loop {
+                let plan = rx.borrow_and_update().clone();
+                let Some(period) = plan.interval else {
+                    if rx.changed().await.is_err() {
+                        return;
+                    }
+                    continue;
+                };
+                for interface in &plan.interfaces {
+                    // Fails harmlessly when no scan is running
+                    if let Err(e) = Command::new("iw").args(["dev", interface, "scan", "abort"]).output().await {
+                        debug!("iw scan abort on {} failed: {}", interface, e);
+                    }
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(period) => {}
+                    changed = rx.changed() => if changed.is_err() { return },
+                }
+            }
+        });
+        Some(Self { base_ms: interval_ms, tx, held: HashMap::new(), tuner: BackendTuner::new(true), suppressed: HashMap::new() })
+    }
+
+    /// Leave `interface`'s scans alone for a while (the daemon just asked for one)
+    pub fn hold_off(&mut self, interface: &str) {
+        self.held.insert(interface.to_string(), Instant::now() + HOLD_OFF);
+        self.tx.send_modify(|plan| plan.interfaces.retain(|i| i != interface));
+    }
+
+    /// Hand interfaces the backend quietened back to it, e.g. after a reconnect
+    /// replaced the network; the next `update` suppresses them again
+    pub fn reset(&mut self) {
+        for (interface, suppression) in self.suppressed.drain() {
+            if let Some(suppression) = suppression {
+                self.tuner.restore_scans(&interface, &suppression);
+            }
+        }
+    }
+
+    /// Follow a switch of the Wi-Fi backend: hand everything back and start over
+    pub fn redetect(&mut self) {
+        if self.tuner.redetect() {
+            self.reset();
+        }
+    }
+
+    /// New tick: which links to abort scans on, and how often
+    pub fn update(&mut self, links: &[LinkActivity]) {
+        let now = Instant::now();
+        self.held.retain(|_, until| *until > now);
+
+        // Links that left game mode or went away (safe mode, unplugged) get their backend
+        // settings back
+        let done: Vec<String> = self.suppressed.keys()
+            .filter(|i| !links.iter().any(|l| &l.interface == *i && l.in_game))
+            .cloned()
+            .collect();
+        for interface in done {
+            if let Some(Some(suppression)) = self.suppressed.remove(&interface) {
+                info!("Background scans on {} handed back to the backend", interface);
+                self.tuner.restore_scans(&interface, &suppression);
+            }
+        }
+        for link in links.iter().filter(|l| l.in_game) {
+            if !self.suppressed.contains_key(&link.interface) {
+                let suppression = self.tuner.suppress_scans(&link.interface);
+                match &suppression {
+                    Some(how) => info!("Background scans on {} suppressed by the backend ({:?})", link.interface, how),
+                    None => debug!("No backend scan control on {}, aborting scans instead", link.interface),
+                }
+                self.suppressed.insert(link.interface.clone(), suppression);
+            }
+        }
+
+        let plan = Plan {
+            interval: interval(self.base_ms, links),
+            interfaces: links.iter()
+                .map(|l| l.interface.clone())
+                .filter(|i| !self.held.contains_key(i) && !matches!(self.suppressed.get(i), Some(Some(_))))
+                .collect(),
+        };
+        self.tx.send_if_modified(|current| {
+            let changed = *current != plan;
+            if changed {
+                debug!("Scan abort: {:?} on {:?}", plan.interval, plan.interfaces);
+                *current = plan;
+            }
+            changed
+        });
+    }
+}
+
+impl Drop for ScanAborter {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval() {
+        let link = |in_game, power_save, pps| LinkActivity { interface: "wlan0".to_string(), in_game, power_save, pps };
+        assert_eq!(interval(500, &[link(true, false, 900)]), Some(Duration::from_millis(500)));
+        assert_eq!(interval(500, &[link(false, false, 0)]), Some(Duration::from_millis(2000)));
+        assert_eq!(interval(500, &[link(false, true, 2)]), None);
+        assert_eq!(interval(500, &[link(false, true, 2), link(true, false, 300)]), Some(Duration::from_millis(500)));
+        assert_eq!(interval(500, &[]), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Time-of-day schedule
+//!
+//! `[[schedule]]` entries switch policies off, pin power save or pin game mode while
+//! their `when` window ("mon-fri 19:00-23:00") matches the local time. The Governor
+//! asks once a minute; windows may run past midnight.
+
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config::structs::ScheduleEntry;
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When an entry applies: days (0 = Monday) and minutes since midnight, end exclusive
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    days: [bool; 7],
+    start: u16,
+    end: u16,
+}
+
+fn parse_day(s: &str) -> Result<usize> {
+    DAYS.iter().position(|d| s.eq_ignore_ascii_case(d))
+        .with_context(|| format!("unknown day '{}' (use mon..sun)", s))
+}
+
+fn parse_time(s: &str) -> Result<u16> {
+    let (h, m) = s.split_once(':').with_context(|| format!("'{}' is not HH:MM", s))?;
+    let (h, m): (u16, u16) = (h.parse()?, m.parse()?);
+    if h > 24 || m > 59 || (h == 24 && m > 0) {
+        bail!("'{}' is not a time of day", s);
+    }
+    Ok(h * 60 + m)
+}
+
+impl Window {
+    /// "[days] HH:MM-HH:MM"; days are "*", "mon-fri", "sat,sun" or a mix ("mon,wed-fri")
+    pub fn parse(when: &str) -> Result<Self> {
+        let mut parts = when.split_whitespace();
+        let (days_spec, range) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(range), None, None) => ("*", range),
+            (Some(days), Some(range), None) => (days, range),
+            _ => bail!("expected \"[days] HH:MM-HH:MM\", got '{}'", when),
+        };
+        let mut days = [false; 7];
+        for part in days_spec.split(',') {
+            if part == "*" {
+                days = [true; 7];
+                continue;
+            }
+            match part.split_once('-') {
+                Some((from, to)) => {
+                    let (from, to) = (parse_day(from)?, parse_day(to)?);
+                    // "fri-mon" wraps over the weekend
+                    let mut day = from;
+                    loop {
+                        days[day] = true;
+                        if day == to {
+                            break;
+                        }
+                        day = (day + 1) % 7;
+                    }
+                }
+                None => days[parse_day(part)?] = true,
+            }
+        }
+        let (start, end) = range.split_once('-').with_context(|| format!("'{}' is not HH:MM-HH:MM", range))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end {
+            bail!("'{}' is an empty window", range);
+        }
+        Ok(Self { days, start, end })
+    }
+
+    /// Whether `minute` (since midnight) on `day` (0 = Monday) is inside the window;
+    /// the part of an overnight window after midnight belongs to the day it started
+    pub fn contains(&self, day: usize, minute: u16) -> bool {
+        if self.start < self.end {
+            self.days[day] && (self.start..self.end).contains(&minute)
+        } else {
+            (self.days[day] && minute >= self.start) || (self.days[(day + 6) % 7] && minute < self.end)
+        }
+    }
+}
+
+/// Local weekday (0 = Monday) and minute of the day
+fn local_now() -> Option<(usize, u16)> {
+    let output = Command::new("date").arg("+%u %H:%M").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let (day, time) = text.trim().split_once(' ')?;
+    let day: usize = day.parse().ok()?;
+    Some((day.checked_sub(1)?, parse_time(time).ok()?))
+}
+
+fn on_off(value: &Option<String>) -> Option<bool> {
+    match value.as_deref() {
+        Some("on") => Some(true),
+        Some("off") => Some(false),
+        _ => None,
+    }
+}
+
+/// What the entries active right now ask for (the first entry wins for power save and game mode)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Effect {
+    pub active: Vec<String>,
+    pub disabled_policies: Vec<String>,
+    pub power_save: Option<bool>,
+    pub game_mode: Option<bool>,
+}
+
+pub struct Scheduler {
+    entries: Vec<(ScheduleEntry, Window)>,
+    effect: Effect,
+    checked: Option<Instant>,
+}
+
+impl Scheduler {
+    /// Entries with an invalid `when` are logged and ignored
+    pub fn new(entries: &[ScheduleEntry]) -> Self {
+        let entries = entries.iter().enumerate().filter_map(|(i, entry)| {
+            match Window::parse(&entry.when) {
+                Ok(window) => {
+                    let mut entry = entry.clone();
+                    if entry.name.is_empty() {
+                        entry.name = format!("schedule #{}", i + 1);
+                    }
+                    Some((entry, window))
+                }
+                Err(e) => {
+                    warn!("Ignoring [[schedule]] entry {}: {}", i + 1, e);
+                    None
+                }
+            }
+        }).collect();
+        Self { entries, effect: Effect::default(), checked: None }
+    }
+
+    pub fn effect(&self) -> &Effect {
+        &self.effect
+    }
+
+    fn evaluate(&self, day: usize, minute: u16) -> Effect {
+        let mut effect = Effect::default();
+        for (entry, _) in self.entries.iter().filter(|(_, w)| w.contains(day, minute)) {
+            effect.active.push(entry.name.clone());
+            effect.disabled_policies.extend(entry.disabled_policies.iter().cloned());
+            effect.power_save = effect.power_save.or(on_off(&entry.power_save));
+            effect.game_mode = effect.game_mode.or(on_off(&entry.game_mode));
+        }
+        effect
+    }
+
+    /// Re-check the clock (at most once a minute); true if the effect changed
+    pub fn update(&mut self) -> bool {
+        if self.entries.is_empty() || self.checked.is_some_and(|t| t.elapsed() < CHECK_INTERVAL) {
+            return false;
+        }
+        self.checked = Some(Instant::now());
+        let Some((day, minute)) = local_now() else { return false };
+        let effect = self.evaluate(day, minute);
+        if effect == self.effect {
+            return false;
+        }
+        info!(event = "schedule", old:? = self.effect.active, new:? = effect.active;
+              "Schedule: {}", if effect.active.is_empty() { "no entry active".to_string() } else { format!("{} active", effect.active.join(", ")) });
+        self.effect = effect;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows() {
+        let evening = Window::parse("mon-fri 19:00-23:00").unwrap();
+        assert!(evening.contains(0, 19 * 60));
+        assert!(!evening.contains(0, 23 * 60));
+        assert!(!evening.contains(5, 20 * 60));
+
+        // Friday night runs into Saturday morning, Sunday night doesn't
+        let night = Window::parse("fri,sat 23:30-07:00").unwrap();
+        assert!(night.contains(4, 23 * 60 + 45));
+        assert!(night.contains(5, 6 * 60));
+        assert!(!night.contains(0, 6 * 60));
+        assert_eq!(Window::parse("fri-mon 10:00-11:00").unwrap().days, [true, false, false, false, true, true, true]);
+
+        assert!(Window::parse("25:00-26:00").is_err());
+        assert!(Window::parse("someday 10:00-11:00").is_err());
+        assert!(Window::parse("10:00-10:00").is_err());
+
+        let entries = vec![
+            ScheduleEntry { name: "night".into(), when: "23:00-07:00".into(), disabled_policies: vec!["band_steering".into()],
+                            power_save: Some("on".into()), game_mode: None },
+            ScheduleEntry { when: "bogus".into(), ..Default::default() },
+        ];
+        let scheduler = Scheduler::new(&entries);
+        assert_eq!(scheduler.entries.len(), 1);
+        let effect = scheduler.evaluate(2, 2 * 60);
+        assert_eq!(effect.active, vec!["night"]);
+        assert_eq!(effect.power_save, Some(true));
+        assert_eq!(scheduler.evaluate(2, 12 * 60), Effect::default());
+    }
+}
@@ -0,0 +1,236 @@
+//! Game-mode session summaries
+//!
+//! While an interface is in game mode the Governor feeds this the gateway RTTs of
+//! each tick, roams, CAKE bandwidth changes and the AP signal. When the session ends
+//! it is boiled down to one record with a 0-100 quality score and appended to
+//! /var/lib/hifi-wifi/sessions.json, which `hifi-wifi sessions` lists.
+
+use anyhow::Result;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use crate::network::usage::unix_now;
+
+pub const SESSIONS_PATH: &str = "/var/lib/hifi-wifi/sessions.json";
+
+/// Summaries kept
+const MAX_SUMMARIES: usize = 200;
+/// Latency monitor replies per tick (5Hz probing, ~2s ticks)
+const SAMPLES_PER_TICK: usize = 10;
+/// Sessions shorter than this aren't worth a record
+const MIN_DURATION_SECS: u64 = 60;
+
+/// One finished game-mode session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Summary {
+    pub interface: String,
+    /// Unix time
+    pub start: u64,
+    pub end: u64,
+    /// None when the latency monitor wasn't running
+    pub avg_rtt_ms: Option<f64>,
+    pub jitter_ms: Option<f64>,
+    pub loss_pct: Option<f64>,
+    pub roams: u32,
+    pub cake_changes: u32,
+    /// Weakest and strongest AP signal seen (dBm)
+    pub signal_min_dbm: Option<i32>,
+    pub signal_max_dbm: Option<i32>,
+    /// 0 (unplayable) to 100 (clean)
+    pub score: u32,
+}
+
+impl Summary {
+    pub fn duration_secs(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Letter grade of the score, same scale as the latency grade in `status`
+    pub fn grade(&self) -> &'static str {
+        match self.score {
+            90.. => "A",
+            75..=89 => "B",
+            60..=74 => "C",
+            40..=59 => "D",
+            _ => "F",
+        }
+    }
+}
+
+/// Penalties for RTT above 20ms, jitter, loss and roams mid-session
+fn score(avg_rtt_ms: Option<f64>, jitter_ms: Option<f64>, loss_pct: Option<f64>, roams: u32) -> u32 {
+    let penalty = avg_rtt_ms.map_or(0.0, |r| ((r - 20.0).max(0.0) * 0.5).min(40.0))
+        + jitter_ms.map_or(0.0, |j| (j * 2.0).min(20.0))
+        + loss_pct.map_or(0.0, |l| (l * 10.0).min(30.0))
+        + (roams as f64 * 5.0).min(10.0);
+    (100.0 - penalty).round().max(0.0) as u32
+}
+
+/// What `sessions.json` holds, oldest first
+pub fn load() -> VecDeque<Summary> {
+    fs::read_to_string(SESSIONS_PATH)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn append(summary: Summary) -> Result<()> {
+    let mut summaries = load();
+    summaries.push_back(summary);
+    while summaries.len() > MAX_SUMMARIES {
+        summaries.pop_front();
+    }
+    let path = Path::new(SESSIONS_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(&summaries)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct OpenSession {
+    start: u64,
+    rtt_sum: f64,
+    replies: u32,
+    lost: u32,
+    jitter_sum: f64,
+    jitter_n: u32,
+    roams: u32,
+    cake_changes: u32,
+    signal_min: Option<i32>,
+    signal_max: Option<i32>,
+}
+
+impl OpenSession {
+    fn record_latency(&mut self, recent: &[Option<f64>]) {
+        let rtts: Vec<f64> = recent.iter().filter_map(|r| *r).collect();
+        self.rtt_sum += rtts.iter().sum::<f64>();
+        self.replies += rtts.len() as u32;
+        self.lost += (recent.len() - rtts.len()) as u32;
+        for w in rtts.windows(2) {
+            self.jitter_sum += (w[1] - w[0]).abs();
+            self.jitter_n += 1;
+        }
+    }
+
+    fn finish(self, interface: &str, end: u64) -> Summary {
+        let total = self.replies + self.lost;
+        let avg_rtt_ms = (self.replies > 0).then(|| self.rtt_sum / self.replies as f64);
+        let jitter_ms = (self.jitter_n > 0).then(|| self.jitter_sum / self.jitter_n as f64);
+        let loss_pct = (total > 0).then(|| self.lost as f64 * 100.0 / total as f64);
+        Summary {
+            interface: interface.to_string(),
+            start: self.start,
+            end,
+            avg_rtt_ms,
+            jitter_ms,
+            loss_pct,
+            roams: self.roams,
+            cake_changes: self.cake_changes,
+            signal_min_dbm: self.signal_min,
+            signal_max_dbm: self.signal_max,
+            score: score(avg_rtt_ms, jitter_ms, loss_pct, self.roams),
+        }
+    }
+}
+
+/// The daemon's side: one open session per interface in game mode
+#[derive(Debug, Default)]
+pub struct SessionRecorder {
+    open: HashMap<String, OpenSession>,
+    dry_run: bool,
+}
+
+impl SessionRecorder {
+    /// Log summaries without writing them
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn start(&mut self, interface: &str) {
+        self.open.insert(interface.to_string(), OpenSession { start: unix_now(), ..Default::default() });
+    }
+
+    /// This tick's latency monitor replies (the newest `SAMPLES_PER_TICK` are used)
+    pub fn record_latency(&mut self, interface: &str, samples: &[Option<f64>]) {
+        if let Some(session) = self.open.get_mut(interface) {
+            session.record_latency(&samples[samples.len().saturating_sub(SAMPLES_PER_TICK)..]);
+        }
+    }
+
+    pub fn record_signal(&mut self, interface: &str, dbm: i32) {
+        if let Some(session) = self.open.get_mut(interface) {
+            session.signal_min = Some(session.signal_min.map_or(dbm, |m| m.min(dbm)));
+            session.signal_max = Some(session.signal_max.map_or(dbm, |m| m.max(dbm)));
+        }
+    }
+
+    pub fn record_roam(&mut self, interface: &str) {
+        if let Some(session) = self.open.get_mut(interface) {
+            session.roams += 1;
+        }
+    }
+
+    pub fn record_cake_change(&mut self, interface: &str) {
+        if let Some(session) = self.open.get_mut(interface) {
+            session.cake_changes += 1;
+        }
+    }
+
+    /// Close the interface's session and write its summary
+    pub fn end(&mut self, interface: &str) {
+        let Some(open) = self.open.remove(interface) else { return };
+        let summary = open.finish(interface, unix_now());
+        if summary.duration_secs() < MIN_DURATION_SECS {
+            return;
+        }
+        info!(event = "session_summary", interface = interface, score = summary.score, roams = summary.roams;
+              "Game-mode session on {} lasted {}min: quality {} ({})",
+              interface, summary.duration_secs() / 60, summary.score, summary.grade());
+        if self.dry_run {
+            return;
+        }
+        if let Err(e) = append(summary) {
+            debug!("Failed to write {}: {}", SESSIONS_PATH, e);
+        }
+    }
+
+    /// End every open session (shutdown)
+    pub fn end_all(&mut self) {
+        let interfaces: Vec<String> = self.open.keys().cloned().collect();
+        for interface in interfaces {
+            self.end(&interface);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut open = OpenSession { start: 1000, ..Default::default() };
+        open.record_latency(&[Some(10.0), Some(14.0), None, Some(12.0)]);
+        open.record_latency(&[Some(12.0), Some(12.0), Some(12.0), Some(12.0)]);
+        open.roams = 1;
+        let summary = open.finish("wlan0", 1600);
+        assert_eq!(summary.duration_secs(), 600);
+        assert_eq!(summary.avg_rtt_ms, Some(12.0));
+        assert_eq!(summary.jitter_ms, Some(1.2));
+        assert_eq!(summary.loss_pct, Some(12.5));
+        // 2.4 jitter + 30 (capped) loss + 5 for the roam
+        assert_eq!(summary.score, 63);
+        assert_eq!(summary.grade(), "C");
+
+        assert_eq!(score(Some(15.0), Some(0.5), Some(0.0), 0), 99);
+        assert_eq!(score(None, None, None, 0), 100);
+    }
+}
@@ -0,0 +1,121 @@
+//! Internet bandwidth test for `hifi-wifi calibrate`
+//!
+//! Wraps whichever speedtest client is installed (Ookla `speedtest`, sivel's
+//! `speedtest-cli` or `librespeed-cli`); each picks a nearby server itself.
+
+use anyhow::{Context, Result};
+use log::debug;
+use std::io::ErrorKind;
+use std::process::Command;
+
+use crate::error::HifiError;
+use crate::network::state::Ceiling;
+
+/// Units the client reports rates in
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    /// Ookla: {"download": {"bandwidth": <bytes/s>}, ...}
+    Ookla,
+    /// speedtest-cli: {"download": <bits/s>, ...}
+    SpeedtestCli,
+    /// librespeed-cli: [{"download": <Mbit/s>, ...}]
+    Librespeed,
+}
+
+/// Fraction of measured capacity used as the CAKE ceiling, so the bottleneck
+/// queue stays in CAKE rather than in the modem/ISP
+pub const CEILING_HEADROOM: f64 = 0.95;
+
+/// Clients to try, in order of preference
+const CLIENTS: &[(&str, &[&str], Format)] = &[
+    ("speedtest", &["--format=json", "--accept-license", "--accept-gdpr"], Format::Ookla),
+    ("speedtest-cli", &["--json"], Format::SpeedtestCli),
+    ("speedtest", &["--json"], Format::SpeedtestCli),
+    ("librespeed-cli", &["--json"], Format::Librespeed),
+];
+
+/// Measured internet capacity
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedResult {
+    pub download_mbit: f64,
+    pub upload_mbit: f64,
+    pub server: String,
+}
+
+impl SpeedResult {
+    /// Ceiling to store for this measurement
+    pub fn ceiling(&self, measured_at: u64) -> Ceiling {
+        Ceiling {
+            upload_mbit: ((self.upload_mbit * CEILING_HEADROOM) as u32).max(1),
+            download_mbit: ((self.download_mbit * CEILING_HEADROOM) as u32).max(1),
+            measured_at,
+        }
+    }
+}
+
+/// Run the first available speedtest client
+pub fn run() -> Result<SpeedResult> {
+    let mut found_any = false;
+    for (program, args, format) in CLIENTS {
+        let output = match Command::new(program).args(*args).output() {
+            Ok(o) => o,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to run {}", program)),
+        };
+        found_any = true;
+        match parse(&String::from_utf8_lossy(&output.stdout), *format) {
+            Ok(result) => return Ok(result),
+            Err(e) => debug!("{} {:?} unusable: {}", program, args, e),
+        }
+    }
+    if found_any {
+        anyhow::bail!("Speedtest failed - check connectivity");
+    }
+    Err(HifiError::MissingTool("No speedtest client found (install speedtest, speedtest-cli or librespeed-cli), or use --server with an iperf3 server".to_string()).into())
+}
+
+fn parse(json: &str, format: Format) -> Result<SpeedResult> {
+    let value: serde_json::Value = serde_json::from_str(json).context("no JSON output")?;
+    let num = |v: &serde_json::Value, ptr: &str| v.pointer(ptr).and_then(|x| x.as_f64()).with_context(|| format!("missing {}", ptr));
+    let text = |v: &serde_json::Value, ptr: &str| v.pointer(ptr).and_then(|x| x.as_str()).unwrap_or("unknown").to_string();
+
+    let result = match format {
+        Format::Ookla => SpeedResult {
+            download_mbit: num(&value, "/download/bandwidth")? * 8.0 / 1_000_000.0,
+            upload_mbit: num(&value, "/upload/bandwidth")? * 8.0 / 1_000_000.0,
+            server: format!("{} ({})", text(&value, "/server/name"), text(&value, "/server/location")),
+        },
+        Format::SpeedtestCli => SpeedResult {
+            download_mbit: num(&value, "/download")? / 1_000_000.0,
+            upload_mbit: num(&value, "/upload")? / 1_000_000.0,
+            server: format!("{} ({})", text(&value, "/server/sponsor"), text(&value, "/server/name")),
+        },
+        Format::Librespeed => SpeedResult {
+            download_mbit: num(&value, "/0/download")?,
+            upload_mbit: num(&value, "/0/upload")?,
+            server: text(&value, "/0/server/name"),
+        },
+    };
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formats() {
+        let ookla = r#"{"type":"result","download":{"bandwidth":62500000},"upload":{"bandwidth":5000000},"server":{"name":"ISP","location":"Berlin"}}"#;
+        assert_eq!(parse(ookla, Format::Ookla).unwrap(), SpeedResult {
+            download_mbit: 500.0, upload_mbit: 40.0, server: "ISP (Berlin)".to_string(),
+        });
+
+        let cli = r#"{"download": 500000000.0, "upload": 40000000.0, "server": {"sponsor": "ISP", "name": "Berlin"}}"#;
+        assert_eq!(parse(cli, Format::SpeedtestCli).unwrap().upload_mbit, 40.0);
+
+        let libre = r#"[{"download": 500.0, "upload": 40.0, "server": {"name": "Frankfurt"}}]"#;
+        assert_eq!(parse(libre, Format::Librespeed).unwrap().server, "Frankfurt");
+
+        assert!(parse(cli, Format::Ookla).is_err());
+    }
+}
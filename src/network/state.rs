@@ -0,0 +1,269 @@
+//! Persistent Governor State
+//!
+//! Learned per-link values (applied CAKE bandwidth, last good bitrate) are written to
+//! /var/lib/hifi-wifi/state.json at most every few minutes while they change and on
+//! shutdown, and reloaded at startup, so CAKE starts at a known-good value instead of
+//! re-learning from scratch after every restart (or crash).
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Location of the persisted state (survives SteamOS updates)
+pub const STATE_PATH: &str = "/var/lib/hifi-wifi/state.json";
+
+/// Changes are written out at most this often while the daemon runs
+const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Maximum bandwidth samples kept per SSID / BSSID
+const SSID_HISTORY_LEN: usize = 50;
+const BSSID_HISTORY_LEN: usize = 50;
+
+/// Percentile of a BSSID's history used to warm-start CAKE on reconnect
+const WARM_START_PERCENTILE: f64 = 0.85;
+
+/// Per-association throughput peaks kept per BSSID
+const PEAK_HISTORY_LEN: usize = 20;
+/// Peaks below this say more about demand than about the node (Mbit/s)
+const MIN_LOADED_PEAK_MBIT: u32 = 10;
+/// Loaded associations needed before a backhaul estimate is trusted
+const MIN_PEAK_SESSIONS: usize = 3;
+
+/// Learned values for a single BSSID
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkState {
+    pub ssid: String,
+    /// Last CAKE bandwidth applied on this link (Mbit)
+    pub last_bandwidth_mbit: Option<u32>,
+    /// Last known good bitrate (Kbit/s)
+    pub last_good_bitrate_kbit: Option<u32>,
+    /// Applied CAKE bandwidth history for this BSSID (Mbit, oldest first)
+    #[serde(default)]
+    pub bandwidth_history: VecDeque<u32>,
+    /// Busiest tick of each loaded association (Mbit/s, oldest first)
+    #[serde(default)]
+    pub throughput_peaks_mbit: VecDeque<u32>,
+}
+
+impl LinkState {
+    /// Bandwidth to start CAKE at when (re)associating with this BSSID:
+    /// the 85th percentile of its history, or the last applied value
+    pub fn warm_start_mbit(&self) -> Option<u32> {
+        if self.bandwidth_history.is_empty() {
+            return self.last_bandwidth_mbit;
+        }
+        let mut sorted: Vec<u32> = self.bandwidth_history.iter().copied().collect();
+        sorted.sort_unstable();
+        let idx = ((sorted.len() - 1) as f64 * WARM_START_PERCENTILE).round() as usize;
+        Some(sorted[idx.min(sorted.len() - 1)])
+    }
+
+    /// Throughput this node has demonstrated it can carry (Mbit/s), a lower bound on its
+    /// backhaul; None until enough loaded associations were seen
+    pub fn backhaul_mbit(&self) -> Option<u32> {
+        if self.throughput_peaks_mbit.len() < MIN_PEAK_SESSIONS {
+            return None;
+        }
+        self.throughput_peaks_mbit.iter().copied().max()
+    }
+}
+
+/// Calibrated capacity for an SSID (written by `hifi-wifi calibrate`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ceiling {
+    /// Upper bound for egress CAKE (Mbit)
+    pub upload_mbit: u32,
+    /// Measured download capacity with the same headroom (Mbit)
+    pub download_mbit: u32,
+    /// Unix time of the calibration run
+    pub measured_at: u64,
+}
+
+/// Everything the Governor remembers across restarts
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PersistentState {
+    /// Per-BSSID learned state
+    #[serde(default)]
+    pub links: HashMap<String, LinkState>,
+    /// Applied CAKE bandwidth history per SSID (Mbit, oldest first)
+    #[serde(default)]
+    pub ssid_history: HashMap<String, VecDeque<u32>>,
+    /// Calibrated CAKE ceilings per SSID
+    #[serde(default)]
+    pub ceilings: HashMap<String, Ceiling>,
+    /// Changed since the last save
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(skip)]
+    saved_at: Option<Instant>,
+}
+
+impl PersistentState {
+    /// Load state from disk, falling back to empty state on any error
+    pub fn load() -> Self {
+        Self::load_from(Path::new(STATE_PATH))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        if !path.exists() {
+            debug!("No persisted state at {}", path.display());
+            return Self::default();
+        }
+
+        match fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<Self>(&content) {
+                Ok(state) => {
+                    info!("Loaded learned state for {} link(s) from {}", state.links.len(), path.display());
+                    state
+                }
+                Err(e) => {
+                    warn!("Ignoring unreadable state file {}: {}", path.display(), e);
+                    Self::default()
+                }
+            },
+            Err(e) => {
+                warn!("Failed to read state file {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Write state to disk (atomic rename so a crash never leaves a torn file)
+    pub fn save(&self) -> Result<()> {
+        self.save_to(Path::new(STATE_PATH))
+    }
+
+    /// Save if something changed and the last save is `SAVE_INTERVAL` old
+    pub fn save_if_due(&mut self) -> Result<()> {
+        self.save_if_due_to(Path::new(STATE_PATH))
+    }
+
+    fn save_if_due_to(&mut self, path: &Path) -> Result<()> {
+        if !self.dirty || self.saved_at.is_some_and(|t| t.elapsed() < SAVE_INTERVAL) {
+            return Ok(());
+        }
+        self.save_to(path)?;
+        self.dirty = false;
+        self.saved_at = Some(Instant::now());
+        Ok(())
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+        debug!("Saved learned state to {}", path.display());
+        Ok(())
+    }
+
+    /// Learned state for a BSSID, if we've seen it before
+    pub fn link(&self, bssid: &str) -> Option<&LinkState> {
+        self.links.get(bssid)
+    }
+
+    /// Calibrated ceiling for an SSID, if `calibrate` has been run on it
+    pub fn ceiling(&self, ssid: &str) -> Option<&Ceiling> {
+        self.ceilings.get(ssid)
+    }
+
+    /// Store a calibrated ceiling for an SSID (replaces any previous one)
+    pub fn set_ceiling(&mut self, ssid: &str, ceiling: Ceiling) {
+        self.ceilings.insert(ssid.to_string(), ceiling);
+        self.dirty = true;
+    }
+
+    /// Record a newly applied CAKE bandwidth for a link
+    pub fn record_bandwidth(&mut self, bssid: &str, ssid: &str, mbit: u32, good_bitrate: Option<u32>) {
+        self.dirty = true;
+        let link = self.links.entry(bssid.to_string()).or_default();
+        link.ssid = ssid.to_string();
+        link.last_bandwidth_mbit = Some(mbit);
+        if good_bitrate.is_some() {
+            link.last_good_bitrate_kbit = good_bitrate;
+        }
+        link.bandwidth_history.push_back(mbit);
+        while link.bandwidth_history.len() > BSSID_HISTORY_LEN {
+            link.bandwidth_history.pop_front();
+        }
+
+        let history = self.ssid_history.entry(ssid.to_string()).or_default();
+        history.push_back(mbit);
+        while history.len() > SSID_HISTORY_LEN {
+            history.pop_front();
+        }
+    }
+
+    /// Record the busiest tick of a finished association (ignored if the link was mostly idle)
+    pub fn record_throughput_peak(&mut self, bssid: &str, mbit: u32) {
+        if mbit < MIN_LOADED_PEAK_MBIT {
+            return;
+        }
+        self.dirty = true;
+        let peaks = &mut self.links.entry(bssid.to_string()).or_default().throughput_peaks_mbit;
+        peaks.push_back(mbit);
+        while peaks.len() > PEAK_HISTORY_LEN {
+            peaks.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_roundtrip() {
+        let mut state = PersistentState::default();
+        for mbit in 0..(SSID_HISTORY_LEN as u32 + 10) {
+            state.record_bandwidth("aa:bb:cc:dd:ee:ff", "Home", mbit, Some(866_000));
+        }
+
+        let link = state.link("aa:bb:cc:dd:ee:ff").unwrap();
+        assert_eq!(link.last_bandwidth_mbit, Some(SSID_HISTORY_LEN as u32 + 9));
+        assert_eq!(link.last_good_bitrate_kbit, Some(866_000));
+        assert_eq!(state.ssid_history["Home"].len(), SSID_HISTORY_LEN);
+
+        let dir = std::env::temp_dir().join(format!("hifi-wifi-state-{}", std::process::id()));
+        let path = dir.join("state.json");
+        state.set_ceiling("Home", Ceiling { upload_mbit: 38, download_mbit: 475, measured_at: 1 });
+        state.save_if_due_to(&path).unwrap();
+        assert!(!state.dirty);
+        state.record_bandwidth("aa:bb:cc:dd:ee:ff", "Home", 60, None);
+        state.save_if_due_to(&path).unwrap();
+        assert!(state.dirty, "throttled until SAVE_INTERVAL has passed");
+        let loaded = PersistentState::load_from(&path);
+        assert_eq!(loaded.link("aa:bb:cc:dd:ee:ff").unwrap().ssid, "Home");
+        assert_eq!(loaded.ceiling("Home").unwrap().upload_mbit, 38);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_warm_start_percentile() {
+        let mut link = LinkState { last_bandwidth_mbit: Some(42), ..Default::default() };
+        assert_eq!(link.warm_start_mbit(), Some(42)); // No history yet
+
+        // 100..=300 in steps of 10: 85th percentile = 270
+        link.bandwidth_history = (10..=30).map(|v| v * 10).collect();
+        assert_eq!(link.warm_start_mbit(), Some(270));
+    }
+
+    #[test]
+    fn test_backhaul_estimate() {
+        let mut state = PersistentState::default();
+        for mbit in [4, 80, 120] {
+            state.record_throughput_peak("aa", mbit);
+        }
+        // The idle association doesn't count, so two loaded ones aren't enough yet
+        assert_eq!(state.link("aa").unwrap().backhaul_mbit(), None);
+        state.record_throughput_peak("aa", 95);
+        assert_eq!(state.link("aa").unwrap().backhaul_mbit(), Some(120));
+    }
+}
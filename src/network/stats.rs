@@ -3,10 +3,21 @@
 //! Reads /sys/class/net/<iface>/statistics for PPS (packets per second) calculation.
 //! Per rewrite.md: Game Mode detection via PPS threshold > 200.
 //! Uses EMA smoothing to prevent game mode flapping from brief PPS spikes.
+//!
+//! Also hosts the gateway latency monitor (RTT percentiles, jitter, loss and
+//! spike detection) behind the `status` latency grade and the metrics file.
 
+use anyhow::{anyhow, Result};
 use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fs;
-use std::time::Instant;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::network::latency;
 
 /// Network statistics from sysfs
 #[derive(Debug, Clone, Default)]
@@ -104,3 +115,272 @@ impl Default for PpsMonitor {
         Self::new()
     }
 }
+
+/// How long RTT samples are kept for percentiles
+const LATENCY_WINDOW: Duration = Duration::from_secs(60);
+/// How long completed spikes count toward the health grade
+const SPIKE_WINDOW: Duration = Duration::from_secs(600);
+
+/// A period where RTT stayed above the spike threshold (or replies were lost)
+#[derive(Debug, Clone)]
+pub struct LatencySpike {
+    pub duration_ms: u64,
+    /// Worst RTT seen during the spike (None if every reply was lost)
+    pub peak_ms: Option<f64>,
+    pub lost: u32,
+}
+
+/// RTT percentiles and derived figures over the recent window
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    /// Mean absolute difference between consecutive RTTs
+    pub jitter_ms: f64,
+    pub loss_pct: f64,
+    /// Spikes completed in the last 10 minutes
+    pub recent_spikes: u32,
+    pub samples: u32,
+}
+
+impl LatencySummary {
+    /// Letter grade for `status`: A (clean) to F (unusable for streaming)
+    pub fn grade(&self) -> &'static str {
+        let score = match self.p95_ms {
+            p if p < 20.0 => 0,
+            p if p < 50.0 => 1,
+            p if p < 100.0 => 2,
+            p if p < 200.0 => 3,
+            _ => 4,
+        } + match self.loss_pct {
+            l if l < 1.0 => 0,
+            l if l < 3.0 => 1,
+            l if l < 10.0 => 2,
+            _ => 4,
+        } + match self.recent_spikes {
+            0 => 0,
+            1..=2 => 1,
+            _ => 2,
+        };
+        ["A", "B", "C", "D", "F"][score.min(4)]
+    }
+}
+
+/// Sample buffer plus spike tracking, fed by the ping reader thread
+#[derive(Debug)]
+struct LatencyWindow {
+    samples: VecDeque<(Instant, Option<f64>)>,
+    retention: Duration,
+    spike_threshold_ms: f64,
+    spike_min_duration: Duration,
+    spike_start: Option<Instant>,
+    spike_peak: Option<f64>,
+    spike_lost: u32,
+    completed: Vec<LatencySpike>,
+    recent_spikes: VecDeque<Instant>,
+}
+
+impl LatencyWindow {
+    fn new(retention: Duration, spike_threshold_ms: f64, spike_min_duration: Duration) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            retention,
+            spike_threshold_ms,
+            spike_min_duration,
+            spike_start: None,
+            spike_peak: None,
+            spike_lost: 0,
+            completed: Vec::new(),
+            recent_spikes: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, now: Instant, rtt: Option<f64>) {
+        self.samples.push_back((now, rtt));
+        while self.samples.front().is_some_and(|(t, _)| now.duration_since(*t) > self.retention) {
+            self.samples.pop_front();
+        }
+
+        // Lost replies count as spike samples - that's exactly the frame-drop case
+        if rtt.is_none_or(|r| r > self.spike_threshold_ms) {
+            self.spike_start.get_or_insert(now);
+            if let Some(r) = rtt {
+                self.spike_peak = Some(self.spike_peak.map_or(r, |p| p.max(r)));
+            } else {
+                self.spike_lost += 1;
+            }
+        } else if let Some(start) = self.spike_start.take() {
+            let duration = now.duration_since(start);
+            if duration >= self.spike_min_duration {
+                self.completed.push(LatencySpike {
+                    duration_ms: duration.as_millis() as u64,
+                    peak_ms: self.spike_peak,
+                    lost: self.spike_lost,
+                });
+                self.recent_spikes.push_back(now);
+            }
+            self.spike_peak = None;
+            self.spike_lost = 0;
+        }
+        while self.recent_spikes.front().is_some_and(|t| now.duration_since(*t) > SPIKE_WINDOW) {
+            self.recent_spikes.pop_front();
+        }
+    }
+
+    fn summary(&self) -> LatencySummary {
+        let samples: Vec<Option<f64>> = self.samples.iter().map(|(_, r)| *r).collect();
+        LatencySummary {
+            recent_spikes: self.recent_spikes.len() as u32,
+            ..summarize(&samples)
+        }
+    }
+}
+
+/// Percentiles, jitter and loss over a run of samples (None = lost reply)
+pub fn summarize(samples: &[Option<f64>]) -> LatencySummary {
+    let rtts: Vec<f64> = samples.iter().filter_map(|r| *r).collect();
+    let total = samples.len();
+    if total == 0 {
+        return LatencySummary::default();
+    }
+
+    let mut sorted = rtts.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let pct = |p: f64| {
+        if sorted.is_empty() {
+            0.0
+        } else {
+            // Nearest-rank percentile
+            sorted[((sorted.len() as f64 * p).ceil() as usize).saturating_sub(1)]
+        }
+    };
+    let jitter_ms = if rtts.len() > 1 {
+        rtts.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (rtts.len() - 1) as f64
+    } else {
+        0.0
+    };
+
+    LatencySummary {
+        p50_ms: pct(0.50),
+        p95_ms: pct(0.95),
+        p99_ms: pct(0.99),
+        jitter_ms,
+        loss_pct: (total - rtts.len()) as f64 * 100.0 / total as f64,
+        recent_spikes: 0,
+        samples: total as u32,
+    }
+}
+
+/// Continuous gateway RTT monitor
+///
+/// Runs `ping -O` at 5Hz in the background; a reader thread feeds every reply (or
+/// missed reply) into a 60s window. The ping process is killed on drop.
+pub struct LatencyMonitor {
+    target: String,
+    child: Child,
+    window: Arc<Mutex<LatencyWindow>>,
+}
+
+impl LatencyMonitor {
+    /// Start probing `target` through `interface`
+    pub fn start(interface: &str, target: &str, spike_threshold_ms: f64, spike_min_duration: Duration) -> Result<Self> {
+        Self::start_with_retention(interface, target, LATENCY_WINDOW, spike_threshold_ms, spike_min_duration)
+    }
+
+    /// Like `start`, but keep samples for `retention` instead of the default minute
+    pub fn start_with_retention(
+        interface: &str,
+        target: &str,
+        retention: Duration,
+        spike_threshold_ms: f64,
+        spike_min_duration: Duration,
+    ) -> Result<Self> {
+        let mut child = Command::new("ping")
+            .args(["-n", "-O", "-i", "0.2", "-W", "1", "-I", interface, target])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("ping stdout unavailable"))?;
+
+        let window = Arc::new(Mutex::new(LatencyWindow::new(retention, spike_threshold_ms, spike_min_duration)));
+        let reader_window = Arc::clone(&window);
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if let Some(rtt) = latency::parse_reply_line(&line) {
+                    if let Ok(mut w) = reader_window.lock() {
+                        w.push(Instant::now(), rtt);
+                    }
+                }
+            }
+        });
+
+        debug!("Latency monitor started: {} via {}", target, interface);
+        Ok(Self { target: target.to_string(), child, window })
+    }
+
+    /// Host being probed
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Percentiles/jitter/loss over the last minute
+    pub fn summary(&self) -> LatencySummary {
+        self.window.lock().map(|w| w.summary()).unwrap_or_default()
+    }
+
+    /// Raw samples currently retained (None = lost reply), oldest first
+    pub fn samples(&self) -> Vec<Option<f64>> {
+        self.window.lock().map(|w| w.samples.iter().map(|(_, r)| *r).collect()).unwrap_or_default()
+    }
+
+    /// Spikes completed since the last call
+    pub fn take_spikes(&self) -> Vec<LatencySpike> {
+        self.window.lock().map(|mut w| std::mem::take(&mut w.completed)).unwrap_or_default()
+    }
+}
+
+impl Drop for LatencyMonitor {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_window_spikes_and_summary() {
+        let mut w = LatencyWindow::new(LATENCY_WINDOW, 100.0, Duration::from_millis(500));
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        // Short blip (400ms) is not a spike, 600ms with a lost reply is
+        w.push(ms(0), Some(5.0));
+        w.push(ms(200), Some(150.0));
+        w.push(ms(400), Some(5.0));
+        w.push(ms(600), Some(180.0));
+        w.push(ms(800), None);
+        w.push(ms(1000), Some(250.0));
+        w.push(ms(1200), Some(5.0));
+
+        let spikes = std::mem::take(&mut w.completed);
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].duration_ms, 600);
+        assert_eq!(spikes[0].peak_ms, Some(250.0));
+        assert_eq!(spikes[0].lost, 1);
+
+        let s = w.summary();
+        assert_eq!(s.samples, 7);
+        assert!((s.loss_pct - 100.0 / 7.0).abs() < 1e-9);
+        assert_eq!(s.p50_ms, 5.0);
+        assert_eq!(s.p99_ms, 250.0);
+        assert_eq!(s.recent_spikes, 1);
+        assert_eq!(s.grade(), "F");
+
+        let clean = LatencySummary { p95_ms: 8.0, samples: 300, ..Default::default() };
+        assert_eq!(clean.grade(), "A");
+    }
+}
@@ -0,0 +1,172 @@
+//! Bulk-flow limiting while a stream is running
+//!
+//! CAKE shares the link fairly between flows, which on a slow 2.4GHz link still lets a
+//! Steam download or a backup on the same machine take most of the airtime from a game
+//! stream. While a stream is active (game mode, or a Sunshine/Moonlight flow on the
+//! streaming ports) this installs an nftables table that polices every long-running TCP
+//! flow off loopback — one that has already moved `bulk_guard_threshold_mb` — to a shared
+//! `bulk_guard_rate_mbit` budget. The table is removed as soon as the stream ends.
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::utils::events;
+
+const TABLE: &str = "hifi_wifi_guard";
+/// Conntrack byte counters (needed by `ct bytes`) are off by default on some kernels
+const CONNTRACK_ACCT: &str = "/proc/sys/net/netfilter/nf_conntrack_acct";
+/// Sunshine's default base port (Moonlight uses the same)
+pub const DEFAULT_SUNSHINE_PORT: u16 = 47989;
+
+/// Video, control and audio UDP ports of a Sunshine stream
+pub fn stream_ports(base: u16) -> std::ops::RangeInclusive<u16> {
+    base + 9..=base + 11
+}
+
+/// (local port, peer port) of each socket in `ss -Hun` output
+pub fn parse_ss_ports(output: &str) -> Vec<(u16, u16)> {
+    let port = |addr: &str| addr.rsplit(':').next().and_then(|p| p.parse().ok());
+    output.lines()
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            // State Recv-Q Send-Q Local Peer
+            let (local, peer) = (cols.get(3)?, cols.get(4)?);
+            Some((port(local)?, port(peer).unwrap_or(0)))
+        })
+        .collect()
+}
+
+/// Whether a connected UDP socket talks on Sunshine's stream ports (either end)
+///
+/// Unconnected sockets don't count: an idle Sunshine keeps its ports bound.
+pub fn sunshine_flow_active(base: u16) -> bool {
+    let ports = stream_ports(base);
+    Command::new("ss")
+        .args(["-Hun"])
+        .output()
+        .map(|o| parse_ss_ports(&String::from_utf8_lossy(&o.stdout)).iter()
+            .any(|(local, peer)| *peer != 0 && (ports.contains(local) || ports.contains(peer))))
+        .unwrap_or(false)
+}
+
+/// nftables script for the guard table
+pub fn ruleset(rate_mbit: u32, threshold_mb: u32) -> String {
+    // Mbit/s -> kbytes/s; a quarter second of burst keeps TCP from collapsing
+    let kbytes = (rate_mbit.max(1) * 125).to_string();
+    let burst = (rate_mbit.max(1) * 125 / 4).max(16);
+    let threshold = threshold_mb as u64 * 1_000_000;
+    format!(
+        "table inet {t} {{\n\
+        \x20 limit bulk_in {{ rate over {k} kbytes/second burst {b} kbytes }}\n\
+        \x20 limit bulk_out {{ rate over {k} kbytes/second burst {b} kbytes }}\n\
+        \x20 chain input {{\n\
+        \x20   type filter hook input priority filter; policy accept;\n\
+        \x20   iifname != \"lo\" meta l4proto tcp ct bytes > {th} limit name \"bulk_in\" drop\n\
+        \x20 }}\n\
+        \x20 chain output {{\n\
+        \x20   type filter hook output priority filter; policy accept;\n\
+        \x20   oifname != \"lo\" meta l4proto tcp ct bytes > {th} limit name \"bulk_out\" drop\n\
+        \x20 }}\n\
+        }}\n",
+        t = TABLE, k = kbytes, b = burst, th = threshold,
+    )
+}
+
+fn nft(script: &str) -> Result<()> {
+    let mut child = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run nft")?;
+    child.stdin.take().context("nft stdin")?.write_all(script.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("nft: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Drop the guard table if present (startup cleanup and `revert`)
+pub fn remove_table() -> bool {
+    nft(&format!("delete table inet {}\n", TABLE)).is_ok()
+}
+
+/// Installs the table while a stream runs and removes it afterwards
+#[derive(Debug, Default)]
+pub struct StreamGuard {
+    installed: bool,
+    /// nf_conntrack_acct before we turned it on
+    acct_original: Option<String>,
+}
+
+impl StreamGuard {
+    pub fn new() -> Self {
+        // A crash could have left the table behind
+        remove_table();
+        Self::default()
+    }
+
+    /// Follow the stream state; call every tick
+    pub fn update(&mut self, streaming: bool, rate_mbit: u32, threshold_mb: u32) {
+        if streaming && !self.installed {
+            if let Err(e) = self.install(rate_mbit, threshold_mb) {
+                warn!("Failed to install bulk-flow limits: {}", e);
+                return;
+            }
+            info!(event = "stream_guard", old = false, new = true;
+                  "Stream active: limiting bulk TCP flows to {} Mbit/s", rate_mbit);
+            events::record("stream_guard", None, serde_json::json!({ "active": true, "rate_mbit": rate_mbit }));
+        } else if !streaming && self.installed {
+            self.remove();
+            info!(event = "stream_guard", old = true, new = false; "Stream ended: bulk-flow limits removed");
+            events::record("stream_guard", None, serde_json::json!({ "active": false }));
+        }
+    }
+
+    fn install(&mut self, rate_mbit: u32, threshold_mb: u32) -> Result<()> {
+        if let Ok(original) = fs::read_to_string(CONNTRACK_ACCT) {
+            if original.trim() != "1" {
+                fs::write(CONNTRACK_ACCT, "1").context("Failed to enable conntrack accounting")?;
+                self.acct_original = Some(original.trim().to_string());
+            }
+        }
+        remove_table();
+        nft(&ruleset(rate_mbit, threshold_mb))?;
+        self.installed = true;
+        Ok(())
+    }
+
+    /// Remove the table and restore conntrack accounting
+    pub fn remove(&mut self) {
+        remove_table();
+        if let Some(original) = self.acct_original.take() {
+            let _ = fs::write(CONNTRACK_ACCT, original);
+        }
+        self.installed = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ss_ports_and_ruleset() {
+        let ss = "ESTAB  0 0 192.168.1.5:51234 192.168.1.20:47998\n\
+                  UNCONN 0 0 0.0.0.0:5353 0.0.0.0:*\n\
+                  ESTAB  0 0 [fe80::1%wlan0]:48000 [fe80::2]:40000\n";
+        assert_eq!(parse_ss_ports(ss), vec![(51234, 47998), (5353, 0), (48000, 40000)]);
+        assert!(stream_ports(DEFAULT_SUNSHINE_PORT).contains(&47998));
+        assert!(!stream_ports(DEFAULT_SUNSHINE_PORT).contains(&47989));
+
+        let rules = ruleset(8, 20);
+        assert!(rules.contains("rate over 1000 kbytes/second burst 250 kbytes"));
+        assert!(rules.contains("iifname != \"lo\" meta l4proto tcp ct bytes > 20000000 limit name \"bulk_in\" drop"));
+        assert!(rules.contains("oifname != \"lo\" meta l4proto tcp"), "local services aren't throttled");
+        assert!(rules.contains("type filter hook output priority filter"));
+    }
+}
@@ -5,6 +5,7 @@
 
 use anyhow::{Context, Result};
 use log::{info, debug, warn};
+use serde::{Deserialize, Serialize};
 use std::process::Command;
 use std::collections::VecDeque;
 
@@ -42,6 +43,10 @@ pub struct TcManager {
     frozen_bandwidth: Option<u32>,
     /// Throughput-based bandwidth estimate (bytes/sec monitoring)
     throughput_bandwidth: Option<u32>,
+    /// Sunshine base port when shaping a streaming host's egress
+    host_port: Option<u16>,
+    /// Log qdisc changes instead of running tc (`monitor --dry-run`)
+    dry_run: bool,
 }
 
 impl TcManager {
@@ -66,9 +71,23 @@ impl TcManager {
             game_mode_frozen: false,
             frozen_bandwidth: None,
             throughput_bandwidth: None,
+            host_port: None,
+            dry_run: false,
         }
     }
 
+    /// Shape as a streaming host: keep DSCP, no ACK filtering, Sunshine's ports prioritized
+    pub fn for_host(mut self, sunshine_port: u16) -> Self {
+        self.host_port = Some(sunshine_port);
+        self
+    }
+
+    /// Track bandwidth as usual but only log what would be applied
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     /// Calculate median of samples
     fn median(&self) -> Option<u32> {
         if self.sample_window.is_empty() {
@@ -77,7 +96,7 @@ impl TcManager {
         let mut sorted: Vec<u32> = self.sample_window.iter().copied().collect();
         sorted.sort();
         let mid = sorted.len() / 2;
-        if sorted.len() % 2 == 0 && sorted.len() > 1 {
+        if sorted.len().is_multiple_of(2) && sorted.len() > 1 {
             Some((sorted[mid - 1] + sorted[mid]) / 2)
         } else {
             Some(sorted[mid])
@@ -188,7 +207,7 @@ impl TcManager {
 
         // Check direction consistency
         let direction_changed = self.pending_bandwidth.is_some() && 
-                                self.pending_direction_up != !is_decrease;
+                                self.pending_direction_up == is_decrease;
 
         if direction_changed {
             // Direction reversed, reset
@@ -220,6 +239,37 @@ impl TcManager {
         }
     }
 
+    /// Pre-fill the median window with a previously learned bandwidth
+    /// so the first decision doesn't have to wait for warmup
+    pub fn seed(&mut self, mbit: u32) {
+        self.sample_window.clear();
+        for _ in 0..self.window_size.max(1) {
+            self.sample_window.push_back(mbit);
+        }
+        self.stable_ticks = 0;
+        self.pending_bandwidth = None;
+        debug!("CAKE: Seeded window with learned {}Mbit", mbit);
+    }
+
+    /// Last bandwidth actually applied to the qdisc (Mbit)
+    pub fn last_applied(&self) -> Option<u32> {
+        self.last_bandwidth
+    }
+
+    /// Internal state for the daemon's SIGUSR1 dump
+    pub fn dump(&self) -> serde_json::Value {
+        serde_json::json!({
+            "last_applied_mbit": self.last_bandwidth,
+            "window_mbit": self.sample_window,
+            "target_mbit": self.get_target_bandwidth(),
+            "pending_mbit": self.pending_bandwidth,
+            "pending_up": self.pending_direction_up,
+            "stable_ticks": self.stable_ticks,
+            "frozen_mbit": self.game_mode_frozen.then_some(self.frozen_bandwidth).flatten(),
+            "throughput_mbit": self.throughput_bandwidth,
+        })
+    }
+
     /// Get the target bandwidth to apply
     pub fn get_target_bandwidth(&self) -> u32 {
         self.median().unwrap_or(200).max(10)
@@ -228,19 +278,34 @@ impl TcManager {
     /// Apply CAKE qdisc to interface
     pub fn apply_cake(&mut self, interface: &str) -> Result<()> {
         let bandwidth_mbit = self.get_target_bandwidth();
+        if self.dry_run {
+            let previous = self.last_bandwidth.replace(bandwidth_mbit);
+            info!(event = "cake_bandwidth", interface = interface, old:? = previous, new = bandwidth_mbit, dry_run = true;
+                  "[DRY-RUN] Would apply CAKE: {}mbit on {}", bandwidth_mbit, interface);
+            return Ok(());
+        }
         
         info!("Applying CAKE on {} with {}mbit bandwidth", interface, bandwidth_mbit);
         
-        let output = Command::new("tc")
-            .args([
-                "qdisc", "replace", "dev", interface, "root", "cake",
-                "bandwidth", &format!("{}mbit", bandwidth_mbit),
+        let bandwidth = format!("{}mbit", bandwidth_mbit);
+        let mut args = vec!["qdisc", "replace", "dev", interface, "root", "handle", "1:", "cake", "bandwidth", &bandwidth];
+        if self.host_port.is_some() {
+            args.extend([
+                "diffserv4",      // Differentiated services
+                "nowash",         // Keep the DSCP marks Sunshine sets
+                "no-ack-filter",  // Mostly sending, few ACKs to thin out
+            ]);
+        } else {
+            args.extend([
                 "diffserv4",      // Differentiated services
                 "dual-dsthost",   // Fair queuing per destination
                 "nat",            // NAT awareness
                 "wash",           // Clear DSCP on ingress
                 "ack-filter",     // ACK filtering
-            ])
+            ]);
+        }
+        let output = Command::new("tc")
+            .args(&args)
             .output()
             .context("Failed to execute tc command")?;
 
@@ -251,7 +316,7 @@ impl TcManager {
             // Fallback to simpler CAKE config
             let output = Command::new("tc")
                 .args([
-                    "qdisc", "replace", "dev", interface, "root", "cake",
+                    "qdisc", "replace", "dev", interface, "root", "handle", "1:", "cake",
                     "bandwidth", &format!("{}mbit", bandwidth_mbit),
                     "besteffort", "nat",
                 ])
@@ -262,14 +327,24 @@ impl TcManager {
             }
         }
 
-        self.last_bandwidth = Some(bandwidth_mbit);
-        info!("CAKE applied successfully: {}mbit on {}", bandwidth_mbit, interface);
+        if let Some(port) = self.host_port {
+            prioritize_sunshine(interface, port);
+        }
+
+        let previous = self.last_bandwidth.replace(bandwidth_mbit);
+        info!(event = "cake_bandwidth", interface = interface, old:? = previous, new = bandwidth_mbit;
+              "CAKE applied successfully: {}mbit on {}", bandwidth_mbit, interface);
         
         Ok(())
     }
 
-    /// Remove CAKE qdisc from interface
-    pub fn remove_cake(&self, interface: &str) -> Result<()> {
+    /// Remove CAKE qdisc from interface (the kernel puts the default qdisc back)
+    pub fn remove_cake(&mut self, interface: &str) -> Result<()> {
+        self.last_bandwidth = None;
+        if self.dry_run {
+            info!("[DRY-RUN] Would remove CAKE from {}", interface);
+            return Ok(());
+        }
         let output = Command::new("tc")
             .args(["qdisc", "del", "dev", interface, "root"])
             .output();
@@ -284,6 +359,24 @@ impl TcManager {
         Ok(())
     }
 
+    /// Bandwidth of the CAKE qdisc currently on `interface` (Mbit), if any
+    pub fn current_bandwidth(interface: &str) -> Option<u32> {
+        let output = Command::new("tc")
+            .args(["qdisc", "show", "dev", interface])
+            .output()
+            .ok()?;
+        parse_cake_bandwidth(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Counters of the CAKE qdisc currently on `interface` (`tc -s qdisc show`)
+    pub fn stats(interface: &str) -> Option<CakeStats> {
+        let output = Command::new("tc")
+            .args(["-s", "qdisc", "show", "dev", interface])
+            .output()
+            .ok()?;
+        parse_cake_stats(&String::from_utf8_lossy(&output.stdout))
+    }
+
     #[cfg(test)]
     pub fn is_game_mode(&self) -> bool {
         self.game_mode_frozen
@@ -300,6 +393,181 @@ impl TcManager {
     }
 }
 
+/// Sunshine UDP ports (from its base port) and the CAKE tin each goes to:
+/// video in Video, control and audio in Voice (diffserv4 tins are 1:1-1:4, Bulk to Voice)
+pub fn sunshine_port_tins(base: u16) -> [(u16, &'static str); 3] {
+    [(base + 9, "1:3"), (base + 10, "1:4"), (base + 11, "1:4")]
+}
+
+/// Steer outgoing Sunshine streams into CAKE's priority tins via skb priority
+/// (CAKE honours `priority <qdisc handle>:<tin>` set by a filter)
+fn prioritize_sunshine(interface: &str, base_port: u16) {
+    // Replacing the qdisc keeps filters; start from a clean list so ports changes apply
+    let _ = Command::new("tc").args(["filter", "del", "dev", interface, "parent", "1:"]).output();
+    for (port, tin) in sunshine_port_tins(base_port) {
+        let port = port.to_string();
+        for (protocol, selector) in [("ip", "ip"), ("ipv6", "ip6")] {
+            let result = Command::new("tc")
+                .args([
+                    "filter", "add", "dev", interface, "parent", "1:", "protocol", protocol, "prio", "1",
+                    "u32", "match", selector, "sport", &port, "0xffff",
+                    "action", "skbedit", "priority", tin,
+                ])
+                .output();
+            match result {
+                Ok(o) if o.status.success() => {}
+                Ok(o) => warn!("Failed to prioritize UDP {} on {}: {}", port, interface, String::from_utf8_lossy(&o.stderr).trim()),
+                Err(e) => warn!("Failed to run tc filter: {}", e),
+            }
+        }
+    }
+    debug!("Sunshine ports from {} prioritized on {}", base_port, interface);
+}
+
+/// Parse "bandwidth 340Mbit" from `tc qdisc show` output of a CAKE qdisc
+fn parse_cake_bandwidth(qdisc_show: &str) -> Option<u32> {
+    let line = qdisc_show.lines().find(|l| l.contains("cake"))?;
+    let value = line.split("bandwidth ").nth(1)?.split_whitespace().next()?;
+    let digits_end = value.find(|c: char| !c.is_ascii_digit())?;
+    let (num, unit) = value.split_at(digits_end);
+    let num: u32 = num.parse().ok()?;
+    match unit {
+        "Gbit" => Some(num * 1000),
+        "Mbit" => Some(num),
+        "Kbit" => Some(num / 1000),
+        _ => None,
+    }
+}
+
+/// Per-tin counters of a CAKE qdisc (Bulk / Best Effort / Video / Voice with diffserv4)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CakeTin {
+    pub name: String,
+    /// Peak and average sojourn delay of packets in this tin (µs)
+    pub pk_delay_us: u64,
+    pub av_delay_us: u64,
+    pub drops: u64,
+    pub marks: u64,
+}
+
+/// Bufferbloat counters of a CAKE qdisc since it was (re)created
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CakeStats {
+    pub sent_packets: u64,
+    pub dropped: u64,
+    pub backlog_bytes: u64,
+    pub backlog_packets: u64,
+    pub tins: Vec<CakeTin>,
+}
+
+impl CakeStats {
+    /// ECN marks over all tins
+    pub fn marks(&self) -> u64 {
+        self.tins.iter().map(|t| t.marks).sum()
+    }
+
+    /// Highest peak delay of any tin (µs)
+    pub fn peak_delay_us(&self) -> u64 {
+        self.tins.iter().map(|t| t.pk_delay_us).max().unwrap_or(0)
+    }
+
+    /// Compact status text, e.g. `drops 12, marks 0, backlog 0b, peak delay 21us`
+    pub fn summary(&self) -> String {
+        format!("drops {}, marks {}, backlog {}b, peak delay {}",
+                self.dropped, self.marks(), self.backlog_bytes, format_delay(self.peak_delay_us()))
+    }
+}
+
+fn format_delay(us: u64) -> String {
+    if us >= 1000 {
+        format!("{:.1}ms", us as f64 / 1000.0)
+    } else {
+        format!("{}us", us)
+    }
+}
+
+/// `21us` / `5ms` / `1.2s` -> µs
+fn parse_delay_us(value: &str) -> Option<u64> {
+    let digits_end = value.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (num, unit) = value.split_at(digits_end);
+    let num: f64 = num.parse().ok()?;
+    let factor = match unit {
+        "us" => 1.0,
+        "ms" => 1_000.0,
+        "s" => 1_000_000.0,
+        _ => return None,
+    };
+    Some((num * factor) as u64)
+}
+
+/// `1514b` / `15Kb` / `2Mb` -> bytes
+fn parse_bytes(value: &str) -> Option<u64> {
+    let digits_end = value.find(|c: char| !c.is_ascii_digit())?;
+    let (num, unit) = value.split_at(digits_end);
+    let num: u64 = num.parse().ok()?;
+    match unit {
+        "b" => Some(num),
+        "Kb" => Some(num * 1024),
+        "Mb" => Some(num * 1024 * 1024),
+        _ => None,
+    }
+}
+
+/// Parse `tc -s qdisc show` output of a CAKE qdisc (None if there is no CAKE root)
+pub fn parse_cake_stats(output: &str) -> Option<CakeStats> {
+    let mut lines = output.lines().skip_while(|l| !l.starts_with("qdisc cake"));
+    lines.next()?;
+
+    let mut stats = CakeStats::default();
+    let mut tin_names: Vec<String> = Vec::new();
+    for line in lines {
+        if line.starts_with("qdisc ") {
+            break;
+        }
+        let trimmed = line.trim();
+        let fields: Vec<&str> = trimmed.split_whitespace().collect();
+        if let Some(rest) = trimmed.strip_prefix("Sent ") {
+            // Sent 123456 bytes 789 pkt (dropped 12, overlimits 34 requeues 0)
+            let words: Vec<&str> = rest.split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+                .filter(|w| !w.is_empty())
+                .collect();
+            stats.sent_packets = words.get(2).and_then(|v| v.parse().ok()).unwrap_or(0);
+            stats.dropped = words.iter().position(|w| *w == "dropped")
+                .and_then(|i| words.get(i + 1))
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+        } else if fields.first() == Some(&"backlog") && tin_names.is_empty() {
+            // backlog 0b 0p requeues 0
+            stats.backlog_bytes = fields.get(1).and_then(|v| parse_bytes(v)).unwrap_or(0);
+            stats.backlog_packets = fields.get(2)
+                .and_then(|v| v.strip_suffix('p'))
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+        } else if tin_names.is_empty() && !fields.is_empty()
+            && ["Tin", "Bulk", "Best", "Video", "Voice"].contains(&fields[0]) {
+            // Tin header; "Best Effort" is two words
+            tin_names = trimmed.replace("Best Effort", "Best_Effort")
+                .split_whitespace()
+                .map(|n| n.replace('_', " "))
+                .collect();
+            stats.tins = tin_names.iter()
+                .map(|name| CakeTin { name: name.clone(), ..Default::default() })
+                .collect();
+        } else if !tin_names.is_empty() && fields.len() == tin_names.len() + 1 {
+            for (tin, value) in stats.tins.iter_mut().zip(&fields[1..]) {
+                match fields[0] {
+                    "pk_delay" => tin.pk_delay_us = parse_delay_us(value).unwrap_or(0),
+                    "av_delay" => tin.av_delay_us = parse_delay_us(value).unwrap_or(0),
+                    "drops" => tin.drops = value.parse().unwrap_or(0),
+                    "marks" => tin.marks = value.parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Some(stats)
+}
+
 /// Ethtool wrapper for hardware offload settings
 pub struct EthtoolManager;
 
@@ -371,6 +639,50 @@ impl Default for TcManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_cake_bandwidth() {
+        let out = "qdisc cake 8001: root refcnt 2 bandwidth 340Mbit diffserv4 dual-dsthost nat wash ack-filter\n";
+        assert_eq!(parse_cake_bandwidth(out), Some(340));
+        assert_eq!(parse_cake_bandwidth("qdisc cake 8001: root refcnt 2 bandwidth 1Gbit besteffort"), Some(1000));
+        assert_eq!(parse_cake_bandwidth("qdisc cake 8001: root refcnt 2 bandwidth unlimited besteffort"), None);
+        assert_eq!(parse_cake_bandwidth("qdisc fq_codel 0: root refcnt 2 limit 10240p"), None);
+    }
+
+    #[test]
+    fn test_parse_cake_stats() {
+        let out = "qdisc cake 8001: root refcnt 2 bandwidth 340Mbit diffserv4 dual-dsthost nat wash ack-filter split_gso rtt 100ms raw overhead 0
+ Sent 123456 bytes 789 pkt (dropped 12, overlimits 34 requeues 0)
+ backlog 3028b 2p requeues 0
+ memory used: 0b of 15140Kb
+ capacity estimate: 340Mbit
+                   Bulk  Best Effort        Video        Voice
+  thresh      21250Kbit      340Mbit      170Mbit       85Mbit
+  target            5ms          5ms          5ms          5ms
+  pk_delay          0us       1.2ms          0us          5us
+  av_delay          0us         3us          0us          1us
+  backlog            0b       3028b           0b           0b
+  drops               0          12            0            0
+  marks               0           2            0            1
+qdisc ingress ffff: parent ffff:fff1 ----------------
+ Sent 0 bytes 0 pkt (dropped 0, overlimits 0 requeues 0)
+";
+        let stats = parse_cake_stats(out).unwrap();
+        assert_eq!(stats.sent_packets, 789);
+        assert_eq!(stats.dropped, 12);
+        assert_eq!((stats.backlog_bytes, stats.backlog_packets), (3028, 2));
+        assert_eq!(stats.tins.len(), 4);
+        assert_eq!(stats.tins[1].name, "Best Effort");
+        assert_eq!(stats.tins[1].pk_delay_us, 1200);
+        assert_eq!(stats.marks(), 3);
+        assert_eq!(stats.summary(), "drops 12, marks 3, backlog 3028b, peak delay 1.2ms");
+        assert_eq!(parse_cake_stats("qdisc fq_codel 0: root refcnt 2 limit 10240p\n"), None);
+    }
+
+    #[test]
+    fn test_sunshine_port_tins() {
+        assert_eq!(sunshine_port_tins(47989), [(47998, "1:3"), (47999, "1:4"), (48000, "1:4")]);
+    }
+
     #[test]
     fn test_median_filtering() {
         // 3 window, 15mbit/15% threshold, 3 up / 1 down hysteresis
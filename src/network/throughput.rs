@@ -0,0 +1,87 @@
+//! iperf3 throughput validation
+//!
+//! Measures achievable throughput against a user-provided iperf3 server with and
+//! without the CAKE shaper, to check that `cake_overhead_factor` isn't cutting
+//! much more than the headroom CAKE needs.
+
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// CAKE should sit just under the achievable rate so the queue forms in CAKE,
+/// not in the driver/firmware - this is the headroom targeted by suggestions
+const TARGET_HEADROOM: f64 = 0.95;
+
+/// Direction of an iperf3 run, seen from this machine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Upload,
+    Download,
+}
+
+/// Run one iperf3 test and return the received rate in Mbit/s
+pub fn run_iperf3(server: &str, port: u16, secs: u64, direction: Direction) -> Result<f64> {
+    let mut cmd = Command::new("iperf3");
+    cmd.args(["-c", server, "-p", &port.to_string(), "-t", &secs.to_string(), "-J"]);
+    if direction == Direction::Download {
+        cmd.arg("-R");
+    }
+    let output = cmd.output().context("Failed to run iperf3 (is it installed?)")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_iperf3_json(&stdout)
+}
+
+/// Extract `end.sum_received.bits_per_second` (Mbit/s) from `iperf3 -J` output
+fn parse_iperf3_json(json: &str) -> Result<f64> {
+    let value: serde_json::Value = serde_json::from_str(json).context("iperf3 produced no JSON output")?;
+    if let Some(err) = value.get("error").and_then(|e| e.as_str()) {
+        anyhow::bail!("iperf3: {}", err);
+    }
+    let bps = value.pointer("/end/sum_received/bits_per_second")
+        .and_then(|v| v.as_f64())
+        .context("iperf3 output has no end.sum_received result")?;
+    Ok(bps / 1_000_000.0)
+}
+
+/// Percentage of the unshaped rate lost with CAKE in place
+pub fn shaper_cut_pct(shaped_mbit: f64, unshaped_mbit: f64) -> f64 {
+    if unshaped_mbit <= 0.0 {
+        return 0.0;
+    }
+    ((unshaped_mbit - shaped_mbit) / unshaped_mbit * 100.0).max(0.0)
+}
+
+/// Overhead factor that would place CAKE at TARGET_HEADROOM of the achievable rate
+///
+/// CAKE bandwidth scales linearly with the factor, so rescale the current factor
+/// by how far the applied bandwidth is from the target.
+pub fn suggest_overhead_factor(current_factor: f64, cake_mbit: u32, unshaped_mbit: f64) -> f64 {
+    if cake_mbit == 0 {
+        return current_factor;
+    }
+    let target_mbit = unshaped_mbit * TARGET_HEADROOM;
+    (current_factor * target_mbit / cake_mbit as f64).clamp(0.5, 0.95)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_iperf3_json() {
+        let json = r#"{"start":{},"end":{"sum_sent":{"bits_per_second":3.1e8},"sum_received":{"bits_per_second":3.0e8}}}"#;
+        assert!((parse_iperf3_json(json).unwrap() - 300.0).abs() < 1e-9);
+        let err = r#"{"start":{},"end":{},"error":"unable to connect to server: Connection refused"}"#;
+        assert!(parse_iperf3_json(err).unwrap_err().to_string().contains("Connection refused"));
+        assert!(parse_iperf3_json("").is_err());
+    }
+
+    #[test]
+    fn test_cut_and_suggestion() {
+        assert!((shaper_cut_pct(200.0, 400.0) - 50.0).abs() < 1e-9);
+        assert_eq!(shaper_cut_pct(410.0, 400.0), 0.0);
+        // 0.85 * 400 PHY = 340 Mbit CAKE, but 380 achievable -> raise factor
+        let f = suggest_overhead_factor(0.85, 340, 380.0);
+        assert!((f - 0.85 * 361.0 / 340.0).abs() < 1e-9);
+        assert_eq!(suggest_overhead_factor(0.85, 340, 1000.0), 0.95);
+    }
+}
@@ -0,0 +1,297 @@
+//! Data usage accounting
+//!
+//! The Governor samples each interface's rx/tx byte counters every tick anyway; it adds
+//! the deltas to per-day totals and to the game-mode session in progress, and writes
+//! them to /var/lib/hifi-wifi/usage.json every few minutes and when a session ends.
+//! Sessions also carry the system-wide TCP retransmission count from /proc/net/snmp,
+//! so shaping that drops too much shows up as a higher retransmit rate.
+
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const USAGE_PATH: &str = "/var/lib/hifi-wifi/usage.json";
+
+/// Finished sessions kept
+const MAX_SESSIONS: usize = 200;
+/// Days of totals kept
+const MAX_DAYS: usize = 60;
+/// How often the daemon writes pending totals
+const FLUSH_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bytes {
+    pub rx: u64,
+    pub tx: u64,
+}
+
+impl Bytes {
+    fn add(&mut self, other: Bytes) {
+        self.rx += other.rx;
+        self.tx += other.tx;
+    }
+}
+
+/// One game-mode session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub interface: String,
+    /// Unix time
+    pub start: u64,
+    pub end: u64,
+    pub bytes: Bytes,
+    /// TCP segments sent and retransmitted meanwhile (whole system); None without /proc/net/snmp
+    pub tcp_out: Option<u64>,
+    pub tcp_retrans: Option<u64>,
+}
+
+impl Session {
+    pub fn retransmit_pct(&self) -> Option<f64> {
+        let (out, retrans) = (self.tcp_out?, self.tcp_retrans?);
+        (out > 0).then(|| retrans as f64 * 100.0 / out as f64)
+    }
+}
+
+/// What `usage.json` holds
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UsageLog {
+    /// Local date ("2026-10-16") -> interface -> bytes
+    #[serde(default)]
+    pub days: BTreeMap<String, BTreeMap<String, Bytes>>,
+    /// Finished sessions, oldest first
+    #[serde(default)]
+    pub sessions: VecDeque<Session>,
+}
+
+impl UsageLog {
+    pub fn load() -> Self {
+        Self::load_from(Path::new(USAGE_PATH))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn add(&mut self, day: &str, pending: &HashMap<String, Bytes>, finished: Vec<Session>) {
+        let totals = self.days.entry(day.to_string()).or_default();
+        for (interface, bytes) in pending {
+            totals.entry(interface.clone()).or_default().add(*bytes);
+        }
+        while self.days.len() > MAX_DAYS {
+            self.days.pop_first();
+        }
+        self.sessions.extend(finished);
+        while self.sessions.len() > MAX_SESSIONS {
+            self.sessions.pop_front();
+        }
+    }
+
+    /// Per-interface totals over the given days
+    pub fn totals(&self, days: &[String]) -> BTreeMap<String, Bytes> {
+        let mut totals: BTreeMap<String, Bytes> = BTreeMap::new();
+        for day in days.iter().filter_map(|d| self.days.get(d)) {
+            for (interface, bytes) in day {
+                totals.entry(interface.clone()).or_default().add(*bytes);
+            }
+        }
+        totals
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Local UTC offset in seconds, from `date +%z`
+pub fn local_offset() -> i64 {
+    Command::new("date").arg("+%z").output().ok()
+        .and_then(|o| parse_offset(String::from_utf8_lossy(&o.stdout).trim()))
+        .unwrap_or(0)
+}
+
+/// "+0200" / "-0530" -> seconds
+fn parse_offset(s: &str) -> Option<i64> {
+    let sign = match s.get(..1)? { "+" => 1, "-" => -1, _ => return None };
+    let hours: i64 = s.get(1..3)?.parse().ok()?;
+    let minutes: i64 = s.get(3..5)?.parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Local (year, month, day, hour, minute) of a Unix time
+fn civil(unix: u64, offset: i64) -> (i64, u32, u32, u32, u32) {
+    let secs = unix as i64 + offset;
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, (rem / 3600) as u32, (rem % 3600 / 60) as u32)
+}
+
+/// "2026-10-16"
+pub fn date_key(unix: u64, offset: i64) -> String {
+    let (y, m, d, _, _) = civil(unix, offset);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// "2026-10-16 19:05"
+pub fn date_time(unix: u64, offset: i64) -> String {
+    let (y, m, d, hh, mm) = civil(unix, offset);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", y, m, d, hh, mm)
+}
+
+/// "2026-10-16 19:05:42"
+pub fn date_time_secs(unix: u64, offset: i64) -> String {
+    format!("{}:{:02}", date_time(unix, offset), unix % 60)
+}
+
+/// The last `n` local dates, today first
+pub fn last_days(n: u64, offset: i64) -> Vec<String> {
+    let now = unix_now();
+    (0..n).map(|i| date_key(now.saturating_sub(i * 86_400), offset)).collect()
+}
+
+/// (OutSegs, RetransSegs) from the `Tcp:` lines of /proc/net/snmp
+fn parse_snmp(content: &str) -> Option<(u64, u64)> {
+    let mut lines = content.lines().filter(|l| l.starts_with("Tcp:"));
+    let (header, values) = (lines.next()?, lines.next()?);
+    let fields: HashMap<&str, &str> = header.split_whitespace().zip(values.split_whitespace()).collect();
+    Some((fields.get("OutSegs")?.parse().ok()?, fields.get("RetransSegs")?.parse().ok()?))
+}
+
+fn tcp_segments() -> Option<(u64, u64)> {
+    parse_snmp(&fs::read_to_string("/proc/net/snmp").ok()?)
+}
+
+#[derive(Debug)]
+struct OpenSession {
+    start: u64,
+    bytes: Bytes,
+    tcp_at_start: Option<(u64, u64)>,
+}
+
+/// The daemon's in-memory side: totals and sessions not written yet
+#[derive(Debug)]
+pub struct UsageTracker {
+    pending: HashMap<String, Bytes>,
+    open: HashMap<String, OpenSession>,
+    finished: Vec<Session>,
+    last_flush: Instant,
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self { pending: HashMap::new(), open: HashMap::new(), finished: Vec::new(), last_flush: Instant::now() }
+    }
+}
+
+impl UsageTracker {
+    /// Bytes moved on an interface since the last tick
+    pub fn add(&mut self, interface: &str, rx: u64, tx: u64) {
+        let bytes = Bytes { rx, tx };
+        self.pending.entry(interface.to_string()).or_default().add(bytes);
+        if let Some(session) = self.open.get_mut(interface) {
+            session.bytes.add(bytes);
+        }
+    }
+
+    pub fn start_session(&mut self, interface: &str) {
+        self.open.insert(interface.to_string(), OpenSession {
+            start: unix_now(),
+            bytes: Bytes::default(),
+            tcp_at_start: tcp_segments(),
+        });
+    }
+
+    pub fn end_session(&mut self, interface: &str) {
+        let Some(open) = self.open.remove(interface) else { return };
+        let delta = open.tcp_at_start.zip(tcp_segments())
+            .map(|((out0, re0), (out1, re1))| (out1.saturating_sub(out0), re1.saturating_sub(re0)));
+        self.finished.push(Session {
+            interface: interface.to_string(),
+            start: open.start,
+            end: unix_now(),
+            bytes: open.bytes,
+            tcp_out: delta.map(|d| d.0),
+            tcp_retrans: delta.map(|d| d.1),
+        });
+        // Sessions show up in `usage` right away
+        self.last_flush = Instant::now() - FLUSH_INTERVAL;
+    }
+
+    /// End every open session (shutdown)
+    pub fn end_all(&mut self) {
+        let interfaces: Vec<String> = self.open.keys().cloned().collect();
+        for interface in interfaces {
+            self.end_session(&interface);
+        }
+    }
+
+    pub fn flush_due(&self) -> bool {
+        self.last_flush.elapsed() >= FLUSH_INTERVAL
+    }
+
+    /// Add what's pending to usage.json
+    pub fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        if self.pending.is_empty() && self.finished.is_empty() {
+            return;
+        }
+        let mut log = UsageLog::load();
+        log.add(&date_key(unix_now(), local_offset()), &self.pending, std::mem::take(&mut self.finished));
+        match log.save_to(Path::new(USAGE_PATH)) {
+            Ok(()) => self.pending.clear(),
+            Err(e) => debug!("Failed to write {}: {}", USAGE_PATH, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates_and_snmp() {
+        assert_eq!(parse_offset("+0200"), Some(7200));
+        assert_eq!(parse_offset("-0530"), Some(-19_800));
+        // 2026-10-16 22:30 UTC is already the 17th in UTC+2
+        assert_eq!(date_time(1_792_189_800, 0), "2026-10-16 22:30");
+        assert_eq!(date_key(1_792_189_800, 7200), "2026-10-17");
+        assert_eq!(date_key(951_782_400, 0), "2000-02-29");
+
+        let snmp = "Ip: Forwarding DefaultTTL\nIp: 1 64\nTcp: RtoAlgorithm OutSegs RetransSegs\nTcp: 1 1000 25\n";
+        assert_eq!(parse_snmp(snmp), Some((1000, 25)));
+
+        let mut log = UsageLog::default();
+        let pending = HashMap::from([("wlan0".to_string(), Bytes { rx: 10, tx: 5 })]);
+        log.add("2026-10-16", &pending, Vec::new());
+        log.add("2026-10-16", &pending, Vec::new());
+        log.add("2026-10-15", &pending, Vec::new());
+        assert_eq!(log.totals(&["2026-10-16".to_string()])["wlan0"], Bytes { rx: 20, tx: 10 });
+        assert_eq!(log.totals(&["2026-10-16".to_string(), "2026-10-15".to_string()])["wlan0"].rx, 30);
+    }
+}
@@ -15,6 +15,51 @@ pub enum InterfaceType {
     Ethernet,
 }
 
+/// nl80211 interface type of a Wi-Fi interface (`iw dev <iface> info`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WifiMode {
+    /// Client of an AP (managed)
+    Station,
+    /// Hosting a hotspot
+    AccessPoint,
+    /// Wi-Fi Direct group client/owner or P2P device
+    P2p,
+    /// Monitor, mesh, IBSS, ...
+    Other,
+}
+
+impl WifiMode {
+    /// Parse the `type ...` line of `iw dev <iface> info`
+    pub fn parse(iw_info: &str) -> Option<Self> {
+        let iftype = iw_info.lines().find_map(|l| l.trim().strip_prefix("type "))?;
+        Some(match iftype.trim() {
+            "managed" => WifiMode::Station,
+            "AP" | "AP/VLAN" => WifiMode::AccessPoint,
+            t if t.starts_with("P2P") => WifiMode::P2p,
+            _ => WifiMode::Other,
+        })
+    }
+
+    /// Current mode of `ifc_name`; unknown counts as station so detection failures change nothing
+    pub fn detect(ifc_name: &str) -> Self {
+        Command::new("iw")
+            .args(["dev", ifc_name, "info"])
+            .output()
+            .ok()
+            .and_then(|o| Self::parse(&String::from_utf8_lossy(&o.stdout)))
+            .unwrap_or(WifiMode::Station)
+    }
+
+    /// Whether hifi-wifi should tune an interface in this mode
+    pub fn is_optimizable(&self, optimize_hotspot: bool) -> bool {
+        match self {
+            WifiMode::Station | WifiMode::Other => true,
+            WifiMode::AccessPoint => optimize_hotspot,
+            WifiMode::P2p => false,
+        }
+    }
+}
+
 /// Detected driver category for applying specific optimizations
 #[derive(Debug, Clone, PartialEq)]
 pub enum DriverCategory {
@@ -22,14 +67,24 @@ pub enum DriverCategory {
     Rtw88,      // Realtek RTW88
     RtlLegacy,  // Legacy Realtek
     MediaTek,   // MediaTek MT7921/MT76
+    MediaTekWifi7, // MediaTek MT7925 (Wi-Fi 7, MLO)
     Intel,      // Intel iwlwifi
+    IntelWifi7, // Intel BE200 (Wi-Fi 7, MLO)
     Atheros,    // Qualcomm Atheros
+    Ath12k,     // Qualcomm ath12k WCN7850 (Wi-Fi 7, MLO)
     Broadcom,   // Broadcom
     Ralink,     // Ralink/MediaTek Legacy
     Marvell,    // Marvell
     Generic,    // Unknown - apply universal optimizations
 }
 
+impl DriverCategory {
+    /// Wi-Fi 7 families that can associate over several links at once
+    pub fn supports_mlo(&self) -> bool {
+        matches!(self, DriverCategory::MediaTekWifi7 | DriverCategory::IntelWifi7 | DriverCategory::Ath12k)
+    }
+}
+
 /// Represents a detected network interface (WiFi or Ethernet)
 #[derive(Debug, Clone)]
 pub struct WifiInterface {
@@ -37,6 +92,8 @@ pub struct WifiInterface {
     pub driver: String,
     pub category: DriverCategory,
     pub interface_type: InterfaceType,
+    /// nl80211 type at detection (Station for Ethernet)
+    pub mode: WifiMode,
     #[allow(dead_code)]
     pub is_active: bool,
 }
@@ -73,7 +130,10 @@ impl WifiManager {
             let ifc_name = entry.file_name().to_string_lossy().to_string();
             
             // Check if it's a wireless or ethernet interface
-            let interface_type = if ifc_name.starts_with("wl") {
+            // (hotspot/P2P interfaces like ap0 or p2p-wlan0-0 only show up via phy80211)
+            let is_wireless = ifc_name.starts_with("wl")
+                || net_path.join(&ifc_name).join("phy80211").exists();
+            let interface_type = if is_wireless {
                 InterfaceType::Wifi
             } else if ifc_name.starts_with("en") || ifc_name.starts_with("eth") {
                 InterfaceType::Ethernet
@@ -82,8 +142,15 @@ impl WifiManager {
             };
 
             let driver = Self::detect_driver(&ifc_name);
-            let category = Self::categorize_driver(&driver);
+            let category = match Self::categorize_driver(&driver) {
+                DriverCategory::Intel if Self::is_intel_wifi7(&ifc_name) => DriverCategory::IntelWifi7,
+                category => category,
+            };
             let is_active = Self::is_interface_active(&ifc_name);
+            let mode = match interface_type {
+                InterfaceType::Wifi => WifiMode::detect(&ifc_name),
+                InterfaceType::Ethernet => WifiMode::Station,
+            };
 
             if log_output {
                 let type_str = match interface_type {
@@ -99,6 +166,7 @@ impl WifiManager {
                 driver,
                 category,
                 interface_type,
+                mode,
                 is_active,
             });
         }
@@ -125,8 +193,10 @@ impl WifiManager {
             d if d.contains("rtw89") => DriverCategory::Rtw89,
             d if d.contains("rtw88") => DriverCategory::Rtw88,
             d if d.starts_with("rtl") => DriverCategory::RtlLegacy,
+            d if d.starts_with("mt7925") => DriverCategory::MediaTekWifi7,
             d if d.starts_with("mt7") || d.contains("mt76") => DriverCategory::MediaTek,
             d if d.starts_with("iwl") => DriverCategory::Intel,
+            d if d.starts_with("ath12k") => DriverCategory::Ath12k,
             d if d.starts_with("ath") => DriverCategory::Atheros,
             d if d.starts_with("brcm") || d == "wl" => DriverCategory::Broadcom,
             d if d.starts_with("rt2") || d.starts_with("rt5") => DriverCategory::Ralink,
@@ -135,6 +205,14 @@ impl WifiManager {
         }
     }
 
+    /// iwlwifi binds every generation, so Wi-Fi 7 parts are told apart by PCI ID (BE200)
+    fn is_intel_wifi7(ifc_name: &str) -> bool {
+        const WIFI7_PCI_IDS: &[&str] = &["0x272b"];
+        fs::read_to_string(format!("/sys/class/net/{}/device/device", ifc_name))
+            .map(|id| WIFI7_PCI_IDS.contains(&id.trim()))
+            .unwrap_or(false)
+    }
+
     /// Check if interface is currently active (has carrier)
     fn is_interface_active(ifc_name: &str) -> bool {
         let carrier_path = format!("/sys/class/net/{}/carrier", ifc_name);
@@ -143,13 +221,25 @@ impl WifiManager {
             .unwrap_or(false)
     }
 
+    /// Drop P2P interfaces, and hotspot (AP mode) ones unless `optimize_hotspot` is set
+    pub fn retain_optimizable(mut self, optimize_hotspot: bool) -> Self {
+        self.interfaces.retain(|ifc| {
+            let keep = ifc.mode.is_optimizable(optimize_hotspot);
+            if !keep {
+                info!("Skipping {} ({:?} interface, not optimized)", ifc.name, ifc.mode);
+            }
+            keep
+        });
+        self
+    }
+
     /// Get all detected interfaces
     pub fn interfaces(&self) -> &[WifiInterface] {
         &self.interfaces
     }
 
-    /// Disable power saving on an interface using `iw`
-    pub fn disable_power_save(&self, ifc: &WifiInterface) -> Result<()> {
+    /// Disable power saving on an interface using `iw` (only `PowerSaveController` calls this)
+    pub(crate) fn disable_power_save(&self, ifc: &WifiInterface) -> Result<()> {
         // Power save only applies to WiFi
         if ifc.interface_type != InterfaceType::Wifi {
             return Ok(());
@@ -172,8 +262,8 @@ impl WifiManager {
         Ok(())
     }
 
-    /// Enable power saving on an interface
-    pub fn enable_power_save(&self, ifc: &WifiInterface) -> Result<()> {
+    /// Enable power saving on an interface (only `PowerSaveController` calls this)
+    pub(crate) fn enable_power_save(&self, ifc: &WifiInterface) -> Result<()> {
         // Power save only applies to WiFi
         if ifc.interface_type != InterfaceType::Wifi {
             return Ok(());
@@ -205,24 +295,7 @@ impl WifiManager {
                     .output()
                     .context("Failed to get WiFi link stats")?;
 
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                
-                for line in stdout.lines() {
-                    let line = line.trim();
-                    if line.starts_with("signal:") {
-                        if let Some(val) = line.split_whitespace().nth(1) {
-                            stats.signal_dbm = val.parse().unwrap_or(-100);
-                        }
-                    } else if line.starts_with("tx bitrate:") {
-                        if let Some(val) = line.split_whitespace().nth(2) {
-                            stats.tx_bitrate_mbps = val.parse().unwrap_or(0.0);
-                        }
-                    } else if line.starts_with("rx bitrate:") {
-                        if let Some(val) = line.split_whitespace().nth(2) {
-                            stats.rx_bitrate_mbps = val.parse().unwrap_or(0.0);
-                        }
-                    }
-                }
+                stats = LinkStats::parse_iw_link(&String::from_utf8_lossy(&output.stdout));
             },
             InterfaceType::Ethernet => {
                 // Use ethtool to get ethernet speed
@@ -325,8 +398,49 @@ pub struct LinkStats {
     pub rx_bitrate_mbps: f64,
 }
 
+impl LinkStats {
+    /// From `iw dev <iface> link`
+    pub fn parse_iw_link(output: &str) -> Self {
+        let mut stats = Self::default();
+        for line in output.lines() {
+            let line = line.trim();
+            if line.starts_with("signal:") {
+                if let Some(val) = line.split_whitespace().nth(1) {
+                    stats.signal_dbm = val.parse().unwrap_or(-100);
+                }
+            } else if line.starts_with("tx bitrate:") {
+                if let Some(val) = line.split_whitespace().nth(2) {
+                    stats.tx_bitrate_mbps = val.parse().unwrap_or(0.0);
+                }
+            } else if line.starts_with("rx bitrate:") {
+                if let Some(val) = line.split_whitespace().nth(2) {
+                    stats.rx_bitrate_mbps = val.parse().unwrap_or(0.0);
+                }
+            }
+        }
+        stats
+    }
+}
+
 impl Default for WifiManager {
     fn default() -> Self {
         Self::new().unwrap_or(Self { interfaces: Vec::new() })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wifi_mode() {
+        let info = "Interface wlan0\n\tifindex 3\n\twdev 0x1\n\taddr 02:00:00:00:00:01\n\ttype AP\n\twiphy 0\n";
+        assert_eq!(WifiMode::parse(info), Some(WifiMode::AccessPoint));
+        assert_eq!(WifiMode::parse("Interface wlan0\n\ttype managed\n"), Some(WifiMode::Station));
+        assert_eq!(WifiMode::parse("Interface p2p-wlan0-0\n\ttype P2P-client\n"), Some(WifiMode::P2p));
+        assert_eq!(WifiMode::parse(""), None);
+        assert!(!WifiMode::P2p.is_optimizable(true));
+        assert!(WifiMode::AccessPoint.is_optimizable(true));
+        assert!(!WifiMode::AccessPoint.is_optimizable(false));
+    }
+}
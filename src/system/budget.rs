@@ -0,0 +1,216 @@
+//! Resource budget of the daemon itself
+//!
+//! The service unit caps the daemon at MemoryMax=64M and CPUQuota=10%, which also
+//! covers the `tc`/`iw`/`nft` processes it spawns. After each tick the Governor samples
+//! its own RSS and CPU time (children included) against the limits of its cgroup, warns
+//! as they are approached, and stretches the tick interval while the CPU budget is
+//! exceeded. The figures go into metrics.json as `daemon`.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Kernel clock ticks per second of /proc/<pid>/stat (USER_HZ)
+const CLOCK_TICKS: f64 = 100.0;
+/// CPU usage is averaged over this long
+const WINDOW: Duration = Duration::from_secs(30);
+/// Warn at this share of a limit...
+const WARN_FRACTION: f64 = 0.8;
+/// ...and again only after dropping below this share
+const REARM_FRACTION: f64 = 0.6;
+/// The tick interval is stretched to at most this multiple
+const MAX_STRETCH: u64 = 4;
+
+/// The daemon's resource use at the latest tick
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonUsage {
+    pub rss_mb: f64,
+    /// MemoryMax of the unit (None without a limit)
+    pub memory_max_mb: Option<f64>,
+    /// Average over the last 30s, subprocesses included (100 = one core)
+    pub cpu_pct: f64,
+    /// CPUQuota of the unit
+    pub cpu_quota_pct: Option<f64>,
+    /// CPU time the latest tick took
+    pub tick_cpu_ms: u64,
+    /// Current tick interval, longer than configured while over the CPU budget
+    pub tick_secs: u64,
+}
+
+/// `cpu.max`: "<quota> <period>" or "max <period>", as a percentage of one core
+fn parse_cpu_max(s: &str) -> Option<f64> {
+    let mut parts = s.split_whitespace();
+    let quota: f64 = parts.next()?.parse().ok()?;
+    let period: f64 = parts.next()?.parse().ok()?;
+    (period > 0.0).then(|| quota / period * 100.0)
+}
+
+/// Directory of our cgroup (v2) in /sys/fs/cgroup
+fn cgroup_dir() -> Option<String> {
+    let cgroup = fs::read_to_string("/proc/self/cgroup").ok()?;
+    let path = cgroup.lines().find_map(|l| l.strip_prefix("0::"))?;
+    Some(format!("/sys/fs/cgroup{}", path.trim()))
+}
+
+/// (memory.max in bytes, cpu.max in percent); "max" reads as no limit
+fn limits() -> (Option<u64>, Option<f64>) {
+    let Some(dir) = cgroup_dir() else { return (None, None) };
+    let memory = fs::read_to_string(format!("{}/memory.max", dir)).ok().and_then(|s| s.trim().parse().ok());
+    let cpu = fs::read_to_string(format!("{}/cpu.max", dir)).ok().and_then(|s| parse_cpu_max(&s));
+    (memory, cpu)
+}
+
+/// utime + stime + cutime + cstime from a /proc/<pid>/stat line, in seconds
+fn parse_cpu_secs(stat: &str) -> Option<f64> {
+    // The command name may contain spaces; the fields after it are fixed
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks: u64 = fields.get(11..15)?.iter().filter_map(|f| f.parse::<u64>().ok()).sum();
+    Some(ticks as f64 / CLOCK_TICKS)
+}
+
+fn cpu_secs() -> Option<f64> {
+    parse_cpu_secs(&fs::read_to_string("/proc/self/stat").ok()?)
+}
+
+fn rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kb: u64 = status.lines().find_map(|l| l.strip_prefix("VmRSS:"))?
+        .split_whitespace().next()?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Tick interval for the CPU use of the last window: doubled while over the quota,
+/// halved back once well under it
+fn next_tick(base: u64, current: u64, cpu_pct: f64, quota_pct: f64) -> u64 {
+    if cpu_pct > quota_pct {
+        (current * 2).min(base * MAX_STRETCH)
+    } else if cpu_pct < quota_pct * REARM_FRACTION {
+        (current / 2).max(base)
+    } else {
+        current
+    }
+}
+
+/// Warn once per crossing of `WARN_FRACTION`; returns the new warned state
+fn crossed(warned: bool, share: f64) -> bool {
+    if warned { share >= REARM_FRACTION } else { share >= WARN_FRACTION }
+}
+
+pub struct BudgetMonitor {
+    base_tick: u64,
+    tick: u64,
+    memory_max: Option<u64>,
+    cpu_quota_pct: Option<f64>,
+    /// (when, CPU seconds so far), oldest first
+    samples: VecDeque<(Instant, f64)>,
+    usage: DaemonUsage,
+    memory_warned: bool,
+    cpu_warned: bool,
+}
+
+impl BudgetMonitor {
+    pub fn new(base_tick: u64) -> Self {
+        let (memory_max, cpu_quota_pct) = limits();
+        if memory_max.is_some() || cpu_quota_pct.is_some() {
+            info!("Resource budget: memory {}, CPU {}",
+                  memory_max.map(|b| format!("{} MB", b / (1024 * 1024))).unwrap_or_else(|| "unlimited".to_string()),
+                  cpu_quota_pct.map(|p| format!("{:.0}%", p)).unwrap_or_else(|| "unlimited".to_string()));
+        }
+        Self {
+            base_tick,
+            tick: base_tick,
+            memory_max,
+            cpu_quota_pct,
+            samples: VecDeque::new(),
+            usage: DaemonUsage::default(),
+            memory_warned: false,
+            cpu_warned: false,
+        }
+    }
+
+    pub fn usage(&self) -> &DaemonUsage {
+        &self.usage
+    }
+
+    /// Sample after a tick; Some(new interval in seconds) when the tick rate should change
+    pub fn sample(&mut self) -> Option<u64> {
+        let now = Instant::now();
+        let cpu = cpu_secs()?;
+        let rss = rss_bytes().unwrap_or(0);
+        let tick_cpu = self.samples.back().map_or(0.0, |(_, last)| cpu - last);
+        self.samples.push_back((now, cpu));
+
+        self.usage.rss_mb = rss as f64 / (1024.0 * 1024.0);
+        self.usage.memory_max_mb = self.memory_max.map(|b| b as f64 / (1024.0 * 1024.0));
+        self.usage.cpu_quota_pct = self.cpu_quota_pct;
+        self.usage.tick_cpu_ms = (tick_cpu * 1000.0) as u64;
+        self.usage.tick_secs = self.tick;
+
+        if let Some(max) = self.memory_max.filter(|m| *m > 0) {
+            let share = rss as f64 / max as f64;
+            let warned = crossed(self.memory_warned, share);
+            if warned && !self.memory_warned {
+                warn!(event = "memory_budget", rss_mb = self.usage.rss_mb as u64, max_mb = max / (1024 * 1024);
+                      "Daemon memory at {:.0}% of MemoryMax ({:.1} MB)", share * 100.0, self.usage.rss_mb);
+            }
+            self.memory_warned = warned;
+        }
+
+        // Wait for a full window before judging the CPU use
+        let (start, start_cpu) = *self.samples.front()?;
+        if now.duration_since(start) < WINDOW {
+            return None;
+        }
+        let cpu_pct = (cpu - start_cpu) / now.duration_since(start).as_secs_f64() * 100.0;
+        self.usage.cpu_pct = cpu_pct;
+        self.samples.clear();
+        self.samples.push_back((now, cpu));
+
+        let quota = self.cpu_quota_pct?;
+        let warned = crossed(self.cpu_warned, cpu_pct / quota);
+        if warned && !self.cpu_warned {
+            warn!(event = "cpu_budget", cpu_pct = cpu_pct, quota_pct = quota;
+                  "Daemon CPU use at {:.1}% against a {:.0}% quota", cpu_pct, quota);
+        }
+        self.cpu_warned = warned;
+
+        let tick = next_tick(self.base_tick, self.tick, cpu_pct, quota);
+        if tick == self.tick {
+            return None;
+        }
+        if tick > self.tick {
+            warn!(event = "tick_stretched", tick_secs = tick;
+                  "Over the CPU budget ({:.1}% > {:.0}%) - ticking every {}s", cpu_pct, quota, tick);
+        } else {
+            info!("CPU use back to {:.1}% - ticking every {}s", cpu_pct, tick);
+        }
+        self.tick = tick;
+        self.usage.tick_secs = tick;
+        Some(tick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_stretch() {
+        assert_eq!(parse_cpu_max("10000 100000\n"), Some(10.0));
+        assert_eq!(parse_cpu_max("max 100000"), None);
+        let stat = "1234 (hifi wifi) S 1 1234 1234 0 -1 4194560 500 0 0 0 150 50 30 20 20 0 9 0 100 0 0";
+        assert_eq!(parse_cpu_secs(stat), Some(2.5));
+
+        assert_eq!(next_tick(2, 2, 12.0, 10.0), 4);
+        assert_eq!(next_tick(2, 8, 12.0, 10.0), 8);
+        assert_eq!(next_tick(2, 8, 7.0, 10.0), 8);
+        assert_eq!(next_tick(2, 8, 3.0, 10.0), 4);
+        assert_eq!(next_tick(2, 2, 3.0, 10.0), 2);
+
+        assert!(crossed(false, 0.85));
+        assert!(crossed(true, 0.7));
+        assert!(!crossed(true, 0.5));
+    }
+}
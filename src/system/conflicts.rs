@@ -0,0 +1,193 @@
+//! Power management daemon conflict detection
+//!
+//! TLP, power-profiles-daemon and tuned can toggle Wi-Fi power save, runtime PM or
+//! NIC settings behind the Governor's back. We detect them and, when
+//! `[system] power_daemon_coordination` is on, write a drop-in that hands the Wi-Fi
+//! device back to hifi-wifi.
+
+use anyhow::Result;
+use log::{info, warn};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+pub const TLP_DROPIN: &str = "/etc/tlp.d/90-hifi-wifi.conf";
+pub const TUNED_PROFILE_DIR: &str = "/etc/tuned/hifi-wifi";
+/// tuned profile that was active before we switched to our wrapper
+const TUNED_PREVIOUS_PATH: &str = "/var/lib/hifi-wifi/tuned-previous-profile";
+
+/// A power management daemon that may fight the Governor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerDaemon {
+    Tlp,
+    PowerProfilesDaemon,
+    Tuned,
+}
+
+impl PowerDaemon {
+    const ALL: [PowerDaemon; 3] = [PowerDaemon::Tlp, PowerDaemon::PowerProfilesDaemon, PowerDaemon::Tuned];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerDaemon::Tlp => "TLP",
+            PowerDaemon::PowerProfilesDaemon => "power-profiles-daemon",
+            PowerDaemon::Tuned => "tuned",
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            PowerDaemon::Tlp => "tlp.service",
+            PowerDaemon::PowerProfilesDaemon => "power-profiles-daemon.service",
+            PowerDaemon::Tuned => "tuned.service",
+        }
+    }
+
+    /// What the daemon touches that overlaps with us
+    pub fn overlap(self) -> &'static str {
+        match self {
+            PowerDaemon::Tlp => "Wi-Fi power save, runtime PM and PCIe ASPM",
+            PowerDaemon::PowerProfilesDaemon => "platform/CPU profile only (no Wi-Fi settings)",
+            PowerDaemon::Tuned => "NIC settings via its [net] plugin",
+        }
+    }
+
+    /// Whether the daemon can change Wi-Fi settings at all
+    pub fn conflicts(self) -> bool {
+        self != PowerDaemon::PowerProfilesDaemon
+    }
+}
+
+/// Detection result for one running daemon
+#[derive(Debug, Clone)]
+pub struct DaemonStatus {
+    pub daemon: PowerDaemon,
+    /// Our drop-in/profile is in place
+    pub coordinated: bool,
+}
+
+/// Running power management daemons
+pub fn detect() -> Vec<DaemonStatus> {
+    PowerDaemon::ALL.iter()
+        .filter(|d| is_active(d.unit()))
+        .map(|&daemon| DaemonStatus { daemon, coordinated: is_coordinated(daemon) })
+        .collect()
+}
+
+fn is_active(unit: &str) -> bool {
+    Command::new("systemctl")
+        .args(["is-active", "--quiet", unit])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+fn is_coordinated(daemon: PowerDaemon) -> bool {
+    match daemon {
+        PowerDaemon::Tlp => Path::new(TLP_DROPIN).exists(),
+        PowerDaemon::Tuned => tuned_active_profile().as_deref() == Some("hifi-wifi"),
+        PowerDaemon::PowerProfilesDaemon => true,
+    }
+}
+
+/// PCI address of a network interface (e.g. 0000:01:00.0), for TLP's runtime PM denylist
+fn pci_address(interface: &str) -> Option<String> {
+    let link = fs::read_link(format!("/sys/class/net/{}/device", interface)).ok()?;
+    link.file_name().map(|n| n.to_string_lossy().to_string())
+}
+
+fn tuned_active_profile() -> Option<String> {
+    let output = Command::new("tuned-adm").arg("active").output().ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|l| l.strip_prefix("Current active profile:"))
+        .map(|p| p.trim().to_string())
+}
+
+/// Render the TLP drop-in for the given Wi-Fi interfaces
+fn tlp_dropin(interfaces: &[String]) -> String {
+    let pci: Vec<String> = interfaces.iter().filter_map(|i| pci_address(i)).collect();
+    let mut content = String::from(
+        "# Written by hifi-wifi: the hifi-wifi Governor manages Wi-Fi power save.\n\
+         # TLP has no 'leave alone' value, so pin it off and let the Governor decide.\n\
+         WIFI_PWR_ON_AC=off\n\
+         WIFI_PWR_ON_BAT=off\n",
+    );
+    if !pci.is_empty() {
+        content.push_str(&format!("RUNTIME_PM_DENYLIST=\"{}\"\n", pci.join(" ")));
+    }
+    content
+}
+
+/// Render a tuned profile that wraps `base` and keeps its [net] plugin off the Wi-Fi devices
+fn tuned_profile(base: &str, interfaces: &[String]) -> String {
+    let excluded: Vec<String> = interfaces.iter().map(|i| format!("!{}", i)).collect();
+    format!(
+        "# Written by hifi-wifi: {} with Wi-Fi devices excluded from the net plugin\n\
+         [main]\n\
+         summary=hifi-wifi wrapper for {}\n\
+         include={}\n\n\
+         [net]\n\
+         devices={}\n",
+        base, base, base, excluded.join(",")
+    )
+}
+
+/// Hand Wi-Fi devices back to hifi-wifi for every conflicting daemon that is running
+pub fn coordinate(interfaces: &[String]) -> Result<()> {
+    for status in detect() {
+        if status.coordinated || !status.daemon.conflicts() {
+            continue;
+        }
+        match status.daemon {
+            PowerDaemon::Tlp => {
+                fs::create_dir_all("/etc/tlp.d")?;
+                fs::write(TLP_DROPIN, tlp_dropin(interfaces))?;
+                let _ = Command::new("tlp").arg("start").output();
+                info!("Wrote {} so TLP leaves Wi-Fi power management to hifi-wifi", TLP_DROPIN);
+            }
+            PowerDaemon::Tuned => {
+                let Some(base) = tuned_active_profile() else {
+                    warn!("tuned is running but its active profile is unknown; not coordinating");
+                    continue;
+                };
+                fs::create_dir_all(TUNED_PROFILE_DIR)?;
+                fs::write(Path::new(TUNED_PROFILE_DIR).join("tuned.conf"), tuned_profile(&base, interfaces))?;
+                if let Some(parent) = Path::new(TUNED_PREVIOUS_PATH).parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(TUNED_PREVIOUS_PATH, &base)?;
+                let _ = Command::new("tuned-adm").args(["profile", "hifi-wifi"]).output();
+                info!("Switched tuned to 'hifi-wifi' (wraps '{}', Wi-Fi excluded from [net])", base);
+            }
+            PowerDaemon::PowerProfilesDaemon => {}
+        }
+    }
+    Ok(())
+}
+
+/// Remove our drop-ins and restore the previous tuned profile
+pub fn revert() {
+    if fs::remove_file(TLP_DROPIN).is_ok() {
+        let _ = Command::new("tlp").arg("start").output();
+        info!("Removed {}", TLP_DROPIN);
+    }
+    if let Ok(previous) = fs::read_to_string(TUNED_PREVIOUS_PATH) {
+        let _ = Command::new("tuned-adm").args(["profile", previous.trim()]).output();
+        let _ = fs::remove_file(TUNED_PREVIOUS_PATH);
+        info!("Restored tuned profile '{}'", previous.trim());
+    }
+    let _ = fs::remove_dir_all(TUNED_PROFILE_DIR);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuned_profile() {
+        let profile = tuned_profile("powersave", &["wlan0".to_string(), "wlp2s0".to_string()]);
+        assert!(profile.contains("include=powersave\n"));
+        assert!(profile.contains("devices=!wlan0,!wlp2s0\n"));
+    }
+}
@@ -0,0 +1,198 @@
+//! `hifi-wifi doctor` - environment checks
+//!
+//! Each check reports OK / WARN / FAIL with an optional hint. Checks only read
+//! state; fixing things is left to the user or to config options named in hints.
+
+use std::process::Command;
+
+use crate::config::structs::Config;
+use crate::network::ipv6::{self, Ipv6Health};
+use crate::network::mtu;
+use crate::network::nm::NmClient;
+use crate::network::nm_powersave;
+use crate::system::conflicts;
+use crate::system::optimizer;
+
+/// Outcome severity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One check result
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub level: Level,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn ok(message: impl Into<String>) -> Self {
+        Self { level: Level::Ok, message: message.into(), hint: None }
+    }
+
+    fn warn(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { level: Level::Warn, message: message.into(), hint: Some(hint.into()) }
+    }
+
+    fn fail(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { level: Level::Fail, message: message.into(), hint: Some(hint.into()) }
+    }
+}
+
+/// Tools we shell out to
+const REQUIRED_TOOLS: &[&str] = &["tc", "iw", "ip", "nmcli", "ethtool"];
+const OPTIONAL_TOOLS: &[&str] = &["ping", "iperf3", "nft"];
+
+fn has_tool(name: &str) -> bool {
+    Command::new("sh")
+        .args(["-c", &format!("command -v {} >/dev/null", name)])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+fn check_service() -> Check {
+    if !crate::system::sysext::service_installed() {
+        return Check::warn("Service not installed", "Run: sudo hifi-wifi install");
+    }
+    let active = Command::new("systemctl")
+        .args(["is-active", "--quiet", "hifi-wifi.service"])
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if active {
+        Check::ok("Service installed and running")
+    } else {
+        Check::warn("Service installed but not running", "Run: sudo hifi-wifi on")
+    }
+}
+
+fn check_crashes() -> Option<Check> {
+    let (count, latest) = crate::utils::crash::recent();
+    let latest = latest.filter(|_| count > 0)?;
+    Some(Check::warn(format!("Daemon crashed {} time(s) in the last 7 days", count),
+                     format!("Please attach {} to a bug report", latest.display())))
+}
+
+fn check_tools() -> Vec<Check> {
+    let mut checks = Vec::new();
+    let missing: Vec<&str> = REQUIRED_TOOLS.iter().copied().filter(|t| !has_tool(t)).collect();
+    if missing.is_empty() {
+        checks.push(Check::ok(format!("Required tools present ({})", REQUIRED_TOOLS.join(", "))));
+    } else {
+        checks.push(Check::fail(format!("Missing required tools: {}", missing.join(", ")),
+                                "Install them with your distribution's package manager"));
+    }
+    let missing: Vec<&str> = OPTIONAL_TOOLS.iter().copied().filter(|t| !has_tool(t)).collect();
+    if !missing.is_empty() {
+        checks.push(Check::warn(format!("Optional tools missing: {}", missing.join(", ")),
+                                "ping powers latency monitoring and MTU probes; iperf3 is used by `throughput`; nft by mtu_fix = \"mss_clamp\" and bulk_guard"));
+    }
+    checks
+}
+
+fn check_power_daemons() -> Vec<Check> {
+    let daemons = conflicts::detect();
+    if daemons.is_empty() {
+        return vec![Check::ok("No competing power management daemons (TLP, power-profiles-daemon, tuned)")];
+    }
+    daemons.iter().map(|status| {
+        let name = status.daemon.name();
+        if !status.daemon.conflicts() {
+            Check::ok(format!("{} running - touches {}", name, status.daemon.overlap()))
+        } else if status.coordinated {
+            Check::ok(format!("{} running, coordinated with hifi-wifi", name))
+        } else {
+            Check::warn(format!("{} running and may override {}", name, status.daemon.overlap()),
+                        "Set [system] power_daemon_coordination = true to hand Wi-Fi to hifi-wifi")
+        }
+    }).collect()
+}
+
+async fn check_nm_powersave() -> Vec<Check> {
+    let nm = match NmClient::new().await {
+        Ok(nm) => nm,
+        Err(e) => return vec![Check::fail(format!("Cannot reach NetworkManager: {}", e),
+                                          "hifi-wifi requires NetworkManager on the system bus")],
+    };
+    let global = nm_powersave::global_default();
+    match nm.wifi_powersave_settings().await {
+        Ok(settings) => settings.iter().map(|conn| {
+            if nm_powersave::re_enables(conn.value, global) {
+                Check::warn(format!("NetworkManager re-enables power save on '{}' at every reconnect", conn.id),
+                            "Set [power] nm_powersave_override = true (default) and restart the service")
+            } else {
+                Check::ok(format!("NetworkManager powersave on '{}' does not conflict", conn.id))
+            }
+        }).collect(),
+        Err(e) => vec![Check::warn(format!("Could not read NetworkManager profiles: {}", e), "Check `nmcli connection show --active`")],
+    }
+}
+
+fn check_sysctl(config: &Config) -> Vec<Check> {
+    if !config.system.sysctl_enabled {
+        return Vec::new();
+    }
+    let conflicts = optimizer::sysctl_conflicts(&config.system.sysctl_settings());
+    if conflicts.is_empty() {
+        return vec![Check::ok("No conflicting sysctl settings from other tools")];
+    }
+    conflicts.into_iter().map(|(key, value, file)| {
+        Check::warn(format!("{} sets {} = {}", file, key, value),
+                    format!("Remove it there, or set \"{}\" in [system.sysctl] to agree", key))
+    }).collect()
+}
+
+fn check_mtu(config: &Config) -> Vec<Check> {
+    let interfaces = crate::network::wifi::WifiManager::new_quiet()
+        .map(|m| m.interfaces().iter().map(|i| i.name.clone()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    interfaces.iter()
+        .filter_map(|name| mtu::probe(name, config.wifi.stream_host.as_deref()))
+        .map(|report| match report.path_mtu {
+            None => Check::warn(format!("{}: no reply from {} to MTU probes", report.interface, report.target),
+                                "The target may block ping; set [wifi] stream_host to probe the streaming PC"),
+            Some(path) if report.black_hole() => Check::warn(
+                format!("{}: path MTU to {} is {} but the interface uses {} and no ICMP reply says so (black hole)",
+                        report.interface, report.target, path, report.interface_mtu),
+                "Set [wifi] mtu_fix = \"interface\" or \"mss_clamp\" and run: sudo hifi-wifi apply"),
+            Some(path) => Check::ok(format!("{}: path MTU to {} is {}", report.interface, report.target, path)),
+        })
+        .collect()
+}
+
+fn check_ipv6() -> Vec<Check> {
+    let interfaces = crate::network::wifi::WifiManager::new_quiet()
+        .map(|m| m.interfaces().iter().map(|i| i.name.clone()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    interfaces.iter()
+        .map(|name| match ipv6::probe(name) {
+            Ipv6Health::NoRoute => Check::ok(format!("{}: no IPv6 default route (IPv4 only)", name)),
+            Ipv6Health::GatewayDown => Check::warn(
+                format!("{}: IPv6 default route but the gateway doesn't answer", name),
+                "Connections may stall before falling back to IPv4; set [wifi] ipv6_preference = \"deprioritize\""),
+            Ipv6Health::NoInternet { gateway_ms } => Check::warn(
+                format!("{}: IPv6 gateway answers ({:.1}ms) but the internet is unreachable over IPv6", name, gateway_ms),
+                "Connections may stall before falling back to IPv4; set [wifi] ipv6_preference = \"deprioritize\""),
+            Ipv6Health::Ok { gateway_ms, internet_ms } => Check::ok(
+                format!("{}: IPv6 reachable (gateway {:.1}ms, internet {:.1}ms)", name, gateway_ms, internet_ms)),
+        })
+        .collect()
+}
+
+/// Run every check
+pub async fn run_checks(config: &Config) -> Vec<Check> {
+    let mut checks = vec![check_service()];
+    checks.extend(check_crashes());
+    checks.extend(check_tools());
+    checks.extend(check_power_daemons());
+    checks.extend(check_nm_powersave().await);
+    checks.extend(check_sysctl(config));
+    checks.extend(check_mtu(config));
+    checks.extend(check_ipv6());
+    checks
+}
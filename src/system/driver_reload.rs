@@ -0,0 +1,222 @@
+//! Apply driver module parameters without a reboot
+//!
+//! modprobe.d options only take effect when the module is loaded. For parameters
+//! the driver exposes as writable in /sys/module we set them directly; anything
+//! else needs the module unloaded and reloaded, which drops the connection, so
+//! we note the active NetworkManager profile first and bring it back up after.
+
+use anyhow::{bail, Context, Result};
+use log::{info, warn, debug};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::network::wifi::WifiInterface;
+use crate::system::quirks;
+
+/// How long to wait for the interface to come back after modprobe
+const REAPPEAR_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long to wait for NetworkManager to reconnect
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A module parameter whose loaded value differs from our modprobe.d config
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingParam {
+    pub module: String,
+    pub param: String,
+    pub wanted: String,
+    pub current: String,
+}
+
+/// Compare a modprobe value against sysfs, which prints bools as Y/N
+fn same_value(wanted: &str, current: &str) -> bool {
+    match current {
+        "Y" => matches!(wanted, "y" | "Y" | "1"),
+        "N" => matches!(wanted, "n" | "N" | "0"),
+        _ => wanted == current,
+    }
+}
+
+fn param_path(module: &str, param: &str) -> String {
+    format!("/sys/module/{}/parameters/{}", module, param)
+}
+
+/// Parameters of loaded modules that don't match our config yet
+pub fn pending(ifc: &WifiInterface) -> Vec<PendingParam> {
+    quirks::get().module_params(&ifc.category)
+        .into_iter()
+        .filter_map(|(module, param, wanted)| {
+            // Unreadable means the module isn't loaded (or hides the param); nothing to do
+            let current = fs::read_to_string(param_path(&module, &param)).ok()?.trim().to_string();
+            (!same_value(&wanted, &current)).then_some(PendingParam { module, param, wanted, current })
+        })
+        .collect()
+}
+
+/// Try writing parameters through sysfs; returns the ones that need a reload
+fn apply_runtime(params: Vec<PendingParam>) -> Vec<PendingParam> {
+    params
+        .into_iter()
+        .filter(|p| {
+            let path = param_path(&p.module, &p.param);
+            let applied = fs::write(&path, &p.wanted).is_ok()
+                && fs::read_to_string(&path).map(|v| same_value(&p.wanted, v.trim())).unwrap_or(false);
+            if applied {
+                info!("Set {}.{}={} at runtime", p.module, p.param, p.wanted);
+            } else {
+                debug!("{}.{} is not writable at runtime", p.module, p.param);
+            }
+            !applied
+        })
+        .collect()
+}
+
+/// Module bound to the interface's device (the one to reload)
+fn bound_module(iface: &str) -> Option<String> {
+    let link = fs::read_link(format!("/sys/class/net/{}/device/driver/module", iface)).ok()?;
+    link.file_name().map(|n| n.to_string_lossy().to_string())
+}
+
+/// UUID of the NetworkManager profile active on the interface
+fn active_connection(iface: &str) -> Option<String> {
+    let output = Command::new("nmcli")
+        .args(["-g", "GENERAL.CON-UUID", "device", "show", iface])
+        .output()
+        .ok()?;
+    let uuid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!uuid.is_empty()).then_some(uuid)
+}
+
+fn is_connected(iface: &str) -> bool {
+    Command::new("nmcli")
+        .args(["-g", "GENERAL.STATE", "device", "show", iface])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).starts_with("100"))
+        .unwrap_or(false)
+}
+
+fn wait_for(timeout: Duration, mut done: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if done() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    done()
+}
+
+fn modprobe(args: &[&str]) -> Result<()> {
+    let output = Command::new("modprobe").args(args).output().context("Failed to run modprobe")?;
+    if !output.status.success() {
+        bail!("modprobe {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Modules using `module` (`/sys/module/<m>/holders`), e.g. iwlmvm for iwlwifi
+fn holders(module: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(Path::new("/sys/module").join(module).join("holders")) else { return Vec::new() };
+    let mut names: Vec<String> = entries.flatten().filter_map(|e| e.file_name().into_string().ok()).collect();
+    names.sort();
+    names
+}
+
+/// `module` and everything holding it, holders first (the order they must be unloaded in)
+fn unload_order(module: &str, holders_of: &impl Fn(&str) -> Vec<String>) -> Vec<String> {
+    let mut order = Vec::new();
+    for holder in holders_of(module) {
+        for m in unload_order(&holder, holders_of) {
+            if !order.contains(&m) {
+                order.push(m);
+            }
+        }
+    }
+    order.push(module.to_string());
+    order
+}
+
+/// Unload and reload the interface's driver so modprobe.d options apply, then reconnect
+fn reload_module(ifc: &WifiInterface) -> Result<()> {
+    let module = bound_module(&ifc.name)
+        .with_context(|| format!("Cannot tell which module drives {}", ifc.name))?;
+    let connection = active_connection(&ifc.name);
+
+    // A module that others hold can't be removed on its own: unload them first, reload after
+    let order = unload_order(&module, &holders);
+    warn!("Reloading {} - {} will disconnect briefly", order.join(", "), ifc.name);
+    // -r also unloads now-unused dependencies (e.g. rtw89_core), so they pick up options too
+    let mut args = vec!["-r"];
+    args.extend(order.iter().map(String::as_str));
+    let swapped = modprobe(&args).and_then(|()| order.iter().rev().try_for_each(|m| modprobe(&[m])));
+    if let Err(e) = swapped {
+        // Whatever did unload must come back, or the interface stays gone until a reboot
+        for m in order.iter().rev() {
+            if let Err(e) = modprobe(&[m]) {
+                warn!("Could not load {} again: {}", m, e);
+            }
+        }
+        return Err(e.context(format!("Reloading {} failed (tried loading {} again)", module, order.join(", "))));
+    }
+
+    if !wait_for(REAPPEAR_TIMEOUT, || Path::new("/sys/class/net").join(&ifc.name).exists()) {
+        bail!("{} did not reappear after reloading {}", ifc.name, module);
+    }
+
+    if let Some(uuid) = connection {
+        let _ = Command::new("nmcli").args(["connection", "up", "uuid", &uuid, "ifname", &ifc.name]).output();
+        if wait_for(RECONNECT_TIMEOUT, || is_connected(&ifc.name)) {
+            info!("Reconnected {} after driver reload", ifc.name);
+        } else {
+            warn!("{} has not reconnected yet; NetworkManager will keep trying", ifc.name);
+        }
+    }
+    Ok(())
+}
+
+/// Make the running driver match our modprobe.d config, reloading it if sysfs can't
+pub fn reload(ifc: &WifiInterface) -> Result<()> {
+    let params = pending(ifc);
+    if params.is_empty() {
+        info!("Driver parameters for {} already in effect", ifc.name);
+        return Ok(());
+    }
+    let remaining = apply_runtime(params);
+    if remaining.is_empty() {
+        return Ok(());
+    }
+    for p in &remaining {
+        debug!("{}.{} is {}, want {}", p.module, p.param, p.current, p.wanted);
+    }
+    reload_module(ifc)?;
+
+    let still = pending(ifc);
+    if !still.is_empty() {
+        warn!("{} driver parameter(s) still differ after reload (set elsewhere in modprobe.d?)", still.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_value() {
+        assert!(same_value("y", "Y"));
+        assert!(same_value("1", "Y"));
+        assert!(!same_value("0", "Y"));
+        assert!(same_value("1", "1"));
+        assert!(!same_value("1", "2"));
+
+        let holders_of = |m: &str| match m {
+            "iwlwifi" => vec!["iwlmvm".to_string()],
+            "iwlmvm" => vec!["iwlmei".to_string()],
+            _ => Vec::new(),
+        };
+        assert_eq!(unload_order("iwlwifi", &holders_of), vec!["iwlmei", "iwlmvm", "iwlwifi"]);
+        assert_eq!(unload_order("rtw89_pci", &holders_of), vec!["rtw89_pci"]);
+    }
+}
@@ -0,0 +1,267 @@
+//! SELinux policy module and AppArmor profile for the daemon
+//!
+//! Without a policy of its own, the binary in /var/lib/hifi-wifi carries the
+//! `var_lib_t` label on Fedora/Bazzite and systemd can't execute it. `install` picks
+//! the active LSM and installs a matching confinement: a CIL module (no policy devel
+//! package needed) or an AppArmor profile. Both cover the sysfs, /proc/sys/net,
+//! netlink and D-Bus access the daemon uses and start out permissive/complain so a
+//! missing rule shows up as an audit message instead of a broken daemon;
+//! `[system] lsm_enforce = true` makes them enforcing.
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const LSM_LIST: &str = "/sys/kernel/security/lsm";
+const SELINUX_MODULE: &str = "hifi_wifi";
+pub const SELINUX_CIL_PATH: &str = "/var/lib/hifi-wifi/hifi_wifi.cil";
+pub const APPARMOR_PROFILE_PATH: &str = "/etc/apparmor.d/hifi-wifi";
+const BINARY: &str = "/var/lib/hifi-wifi/hifi-wifi";
+/// File types systemd can execute the binary with (module installed, or chcon fallback)
+pub const EXEC_TYPES: &[&str] = &["hifi_wifi_exec_t", "bin_t"];
+
+/// Major LSM that decides what the daemon may do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lsm {
+    SELinux,
+    AppArmor,
+    None,
+}
+
+/// First major LSM in the comma-separated /sys/kernel/security/lsm list
+pub fn parse_lsm_list(content: &str) -> Lsm {
+    content.trim().split(',')
+        .find_map(|name| match name {
+            "selinux" => Some(Lsm::SELinux),
+            "apparmor" => Some(Lsm::AppArmor),
+            _ => None,
+        })
+        .unwrap_or(Lsm::None)
+}
+
+pub fn detect() -> Lsm {
+    match fs::read_to_string(LSM_LIST).map(|c| parse_lsm_list(&c)) {
+        // Built in but disabled at boot (selinux=0) leaves no selinuxfs
+        Ok(Lsm::SELinux) if !Path::new("/sys/fs/selinux/enforce").exists() => Lsm::None,
+        Ok(lsm) => lsm,
+        Err(_) => Lsm::None,
+    }
+}
+
+/// CIL module: daemon domain entered from init, file types for binary and state
+pub fn selinux_module(enforce: bool) -> String {
+    let mut cil = String::from(r#"; hifi-wifi daemon (generated by `hifi-wifi install`)
+(type hifi_wifi_t)
+(type hifi_wifi_exec_t)
+(type hifi_wifi_var_lib_t)
+(roletype system_r hifi_wifi_t)
+(typeattributeset domain (hifi_wifi_t))
+(typeattributeset file_type (hifi_wifi_exec_t hifi_wifi_var_lib_t))
+(typeattributeset exec_type (hifi_wifi_exec_t))
+
+; systemd starts the binary in its own domain
+(typetransition init_t hifi_wifi_exec_t process hifi_wifi_t)
+(allow init_t hifi_wifi_exec_t (file (getattr open read execute map)))
+(allow init_t hifi_wifi_t (process (transition sigchld sigkill signal)))
+(allow hifi_wifi_t hifi_wifi_exec_t (file (entrypoint getattr open read execute map)))
+
+; tc, iw, nft, ethtool, sysctl and friends
+(allow hifi_wifi_t self (capability (net_admin net_raw sys_admin dac_override chown)))
+(allow hifi_wifi_t self (netlink_route_socket (create bind getattr setopt read write nlmsg_read nlmsg_write)))
+(allow hifi_wifi_t self (netlink_generic_socket (create bind getattr setopt read write)))
+(allow hifi_wifi_t self (netlink_netfilter_socket (create bind getattr setopt read write)))
+(allow hifi_wifi_t self (udp_socket (create connect getattr setopt read write)))
+(allow hifi_wifi_t self (rawip_socket (create getattr setopt read write)))
+(allow hifi_wifi_t self (icmp_socket (create getattr setopt read write)))
+(allow hifi_wifi_t self (unix_stream_socket (create connect bind listen accept getattr setopt shutdown read write)))
+; MQTT, and curl for webhooks/notifications
+(allow hifi_wifi_t self (tcp_socket (create connect getattr setopt shutdown read write)))
+(allow hifi_wifi_t port_type (tcp_socket (name_connect)))
+(allow hifi_wifi_t cert_t (dir (getattr search open read)))
+(allow hifi_wifi_t cert_t (file (getattr open read)))
+(allow hifi_wifi_t cert_t (lnk_file (getattr read)))
+(allow hifi_wifi_t bin_t (file (getattr open read execute execute_no_trans map)))
+(allow hifi_wifi_t bin_t (dir (getattr search open read)))
+
+; sysfs, /proc/sys/net, IRQ affinity, debugfs
+(allow hifi_wifi_t sysfs_t (dir (getattr search open read)))
+(allow hifi_wifi_t sysfs_t (file (getattr open read write)))
+(allow hifi_wifi_t sysfs_t (lnk_file (getattr read)))
+(allow hifi_wifi_t proc_t (file (getattr open read)))
+(allow hifi_wifi_t proc_net_t (file (getattr open read)))
+(allow hifi_wifi_t sysctl_t (dir (getattr search)))
+(allow hifi_wifi_t sysctl_net_t (dir (getattr search open read)))
+(allow hifi_wifi_t sysctl_net_t (file (getattr open read write)))
+(allow hifi_wifi_t sysctl_irq_t (dir (getattr search open read)))
+(allow hifi_wifi_t sysctl_irq_t (file (getattr open read write)))
+(allow hifi_wifi_t debugfs_t (dir (getattr search open read)))
+(allow hifi_wifi_t debugfs_t (file (getattr open read write)))
+
+; NetworkManager over D-Bus
+(allow hifi_wifi_t system_dbusd_t (unix_stream_socket (connectto)))
+(allow hifi_wifi_t system_dbusd_t (dbus (send_msg)))
+(allow hifi_wifi_t NetworkManager_t (dbus (send_msg)))
+(allow NetworkManager_t hifi_wifi_t (dbus (send_msg)))
+
+; State in /var/lib/hifi-wifi and /run/hifi-wifi
+(allow hifi_wifi_t hifi_wifi_var_lib_t (dir (getattr search open read write add_name remove_name)))
+(allow hifi_wifi_t hifi_wifi_var_lib_t (file (getattr open read write create append rename unlink setattr)))
+(allow hifi_wifi_t var_run_t (dir (getattr search open read write add_name remove_name create)))
+(allow hifi_wifi_t var_run_t (file (getattr open read write create rename unlink)))
+; The control socket in /run/hifi-wifi, handed to the group listed in /etc/group
+(allow hifi_wifi_t var_run_t (sock_file (getattr create setattr write unlink)))
+(allow hifi_wifi_t passwd_file_t (file (getattr open read)))
+(filecon "/var/lib/hifi-wifi" dir (system_u object_r hifi_wifi_var_lib_t ((s0) (s0))))
+(filecon "/var/lib/hifi-wifi/.*" file (system_u object_r hifi_wifi_var_lib_t ((s0) (s0))))
+(filecon "/var/lib/hifi-wifi/hifi-wifi" file (system_u object_r hifi_wifi_exec_t ((s0) (s0))))
+"#);
+    if !enforce {
+        cil.push_str("\n; Log denials instead of enforcing them ([system] lsm_enforce = false)\n(typepermissive hifi_wifi_t)\n");
+    }
+    cil
+}
+
+/// AppArmor profile attached to the installed binary
+pub fn apparmor_profile(enforce: bool) -> String {
+    let flags = if enforce { "" } else { " flags=(complain)" };
+    format!(r#"# hifi-wifi daemon (generated by `hifi-wifi install`)
+abi <abi/3.0>,
+include <tunables/global>
+
+profile hifi-wifi {binary}{flags} {{
+  include <abstractions/base>
+  include <abstractions/nameservice>
+  include <abstractions/dbus-strict>
+  include <abstractions/ssl_certs>
+
+  capability net_admin,
+  capability net_raw,
+  capability sys_admin,
+  capability dac_override,
+  capability chown,
+
+  network netlink raw,
+  network netlink dgram,
+  network inet dgram,
+  network inet6 dgram,
+  network inet raw,
+  network inet6 raw,
+  # MQTT, curl for webhooks/notifications, the control socket
+  network inet stream,
+  network inet6 stream,
+  network unix stream,
+
+  dbus send bus=system peer=(name=org.freedesktop.NetworkManager),
+  dbus send bus=system peer=(name=org.freedesktop.DBus),
+  dbus receive bus=system peer=(label=unconfined),
+
+  {binary} mr,
+  /usr/{{s,}}bin/{{tc,iw,ip,ss,ping,ethtool,nft,sysctl,iperf3,curl}} ix,
+  /usr/{{s,}}bin/{{nmcli,systemctl,modprobe,tuned-adm,tlp}} Ux,
+
+  /etc/hifi-wifi/** r,
+  /etc/modprobe.d/*hifi-wifi* rw,
+  /etc/sysctl.d/*hifi-wifi* rw,
+  /etc/iwd/** rw,
+  /etc/NetworkManager/conf.d/*hifi-wifi* rw,
+  /var/lib/hifi-wifi/ r,
+  /var/lib/hifi-wifi/** rwk,
+  /run/hifi-wifi/ rw,
+  /run/hifi-wifi/** rw,
+
+  @{{PROC}}/sys/net/** rw,
+  @{{PROC}}/interrupts r,
+  @{{PROC}}/irq/*/smp_affinity rw,
+  @{{PROC}}/@{{pid}}/status r,
+  @{{PROC}}/@{{pid}}/net/** r,
+  @{{sys}}/class/net/ r,
+  @{{sys}}/class/net/** r,
+  @{{sys}}/devices/** r,
+  @{{sys}}/module/** rw,
+  @{{sys}}/class/power_supply/** r,
+  @{{sys}}/kernel/debug/ieee80211/** rw,
+}}
+"#, binary = BINARY, flags = flags)
+}
+
+/// Type field of an SELinux context (`system_u:object_r:bin_t:s0` -> `bin_t`)
+pub fn context_type(context: &str) -> Option<&str> {
+    context.trim().split(':').nth(2)
+}
+
+/// SELinux context of a file
+pub fn file_context(path: &Path) -> Option<String> {
+    let output = Command::new("stat").args(["-c", "%C"]).arg(path).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program).args(args).output()
+        .with_context(|| format!("{} not found", program))?;
+    if !output.status.success() {
+        anyhow::bail!("{}: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Install the policy for the active LSM; returns which one was set up
+pub fn install(enforce: bool) -> Result<Lsm> {
+    let lsm = detect();
+    match lsm {
+        Lsm::SELinux => {
+            fs::write(SELINUX_CIL_PATH, selinux_module(enforce))?;
+            run("semodule", &["-i", SELINUX_CIL_PATH])?;
+            // Labels come from the module's filecon entries
+            run("restorecon", &["-R", "/var/lib/hifi-wifi"])?;
+            info!("Installed SELinux module {} ({})", SELINUX_MODULE, if enforce { "enforcing" } else { "permissive" });
+        }
+        Lsm::AppArmor => {
+            fs::write(APPARMOR_PROFILE_PATH, apparmor_profile(enforce))?;
+            run("apparmor_parser", &["-r", APPARMOR_PROFILE_PATH])?;
+            info!("Installed AppArmor profile {} ({})", APPARMOR_PROFILE_PATH, if enforce { "enforce" } else { "complain" });
+        }
+        Lsm::None => {}
+    }
+    Ok(lsm)
+}
+
+/// Remove whatever `install` set up
+pub fn remove() {
+    if Path::new(SELINUX_CIL_PATH).exists() {
+        if let Err(e) = run("semodule", &["-r", SELINUX_MODULE]) {
+            warn!("Failed to remove SELinux module: {}", e);
+        }
+        let _ = fs::remove_file(SELINUX_CIL_PATH);
+    }
+    if Path::new(APPARMOR_PROFILE_PATH).exists() {
+        let _ = run("apparmor_parser", &["-R", APPARMOR_PROFILE_PATH]);
+        let _ = fs::remove_file(APPARMOR_PROFILE_PATH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lsm_and_profiles() {
+        assert_eq!(parse_lsm_list("lockdown,capability,yama,selinux,bpf\n"), Lsm::SELinux);
+        assert_eq!(parse_lsm_list("lockdown,capability,landlock,yama,apparmor"), Lsm::AppArmor);
+        assert_eq!(parse_lsm_list("capability,yama"), Lsm::None);
+
+        assert!(selinux_module(false).contains("(typepermissive hifi_wifi_t)"));
+        assert!(!selinux_module(true).contains("typepermissive"));
+        assert!(apparmor_profile(false).contains("profile hifi-wifi /var/lib/hifi-wifi/hifi-wifi flags=(complain) {"));
+        assert!(apparmor_profile(true).contains("profile hifi-wifi /var/lib/hifi-wifi/hifi-wifi {"));
+        assert!(apparmor_profile(true).contains("/usr/{s,}bin/{tc,iw,ip,ss,ping,ethtool,nft,sysctl,iperf3,curl} ix,"));
+        assert!(apparmor_profile(true).contains("network inet stream,"));
+        let cil = selinux_module(true);
+        assert!(cil.contains("(unix_stream_socket (create connect bind listen accept"));
+        assert!(cil.contains("(allow hifi_wifi_t var_run_t (sock_file"));
+        assert!(cil.contains("(allow hifi_wifi_t port_type (tcp_socket (name_connect)))"));
+        assert_eq!(context_type("system_u:object_r:hifi_wifi_exec_t:s0\n"), Some("hifi_wifi_exec_t"));
+        assert_eq!(context_type("?"), None);
+    }
+}
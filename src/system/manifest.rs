@@ -0,0 +1,209 @@
+//! Manifest of everything hifi-wifi installs
+//!
+//! `install` records each file, directory, unit and nftables table it creates, with a
+//! SHA-256 of file contents, merged across installs so switching between install
+//! modes doesn't lose track of the old artifacts. `bootstrap` verifies the recorded
+//! files to spot ones an OS update wiped or changed, and `uninstall` removes exactly
+//! what is listed; `--purge` adds whatever `apply` left in /etc and the data
+//! directories, then reports anything still present.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::network::stream_guard;
+use crate::system::{conflicts, optimizer, quirks};
+
+pub const MANIFEST_PATH: &str = "/var/lib/hifi-wifi/manifest.json";
+
+/// Removed as a whole by a purge (state, config, runtime files)
+pub const DATA_DIRS: &[&str] = &["/var/lib/hifi-wifi", "/etc/hifi-wifi", "/run/hifi-wifi"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    File,
+    Dir,
+    Symlink,
+    /// systemd unit file
+    Unit,
+    /// nftables table, `path` holds `<family> <name>`
+    NftTable,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    /// SHA-256 of the contents when recorded (files and units only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// How a recorded artifact differs from what is on disk
+#[derive(Debug, Clone, PartialEq)]
+pub enum Drift {
+    Missing,
+    Modified,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub artifacts: BTreeMap<PathBuf, Artifact>,
+    /// Users whose ~/.bashrc and ~/.config got hifi-wifi entries
+    pub users: BTreeSet<String>,
+}
+
+/// Hex SHA-256 of a file's contents
+pub fn sha256_file(path: &Path) -> Option<String> {
+    let content = fs::read(path).ok()?;
+    Some(Sha256::digest(&content).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn has_checksum(kind: ArtifactKind) -> bool {
+    matches!(kind, ArtifactKind::File | ArtifactKind::Unit)
+}
+
+impl Manifest {
+    pub fn load() -> Self {
+        Self::load_from(Path::new(MANIFEST_PATH))
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record an artifact as it is now; skipped if it doesn't exist (install steps
+    /// can be skipped or fail). nftables tables are recorded as-is.
+    pub fn track(&mut self, path: impl Into<PathBuf>, kind: ArtifactKind) {
+        let path = path.into();
+        if kind != ArtifactKind::NftTable && fs::symlink_metadata(&path).is_err() {
+            return;
+        }
+        let sha256 = if has_checksum(kind) { sha256_file(&path) } else { None };
+        self.artifacts.insert(path, Artifact { kind, sha256 });
+    }
+
+    /// Recorded files that are missing (or dangling symlinks) or no longer match their checksum
+    pub fn verify(&self) -> Vec<(PathBuf, Drift)> {
+        self.artifacts.iter()
+            .filter(|(_, a)| a.kind != ArtifactKind::NftTable)
+            .filter_map(|(path, artifact)| {
+                let present = match artifact.kind {
+                    ArtifactKind::Symlink => fs::metadata(path).is_ok(),
+                    _ => fs::symlink_metadata(path).is_ok(),
+                };
+                if !present {
+                    return Some((path.clone(), Drift::Missing));
+                }
+                let expected = artifact.sha256.as_ref()?;
+                (sha256_file(path).as_ref() != Some(expected)).then(|| (path.clone(), Drift::Modified))
+            })
+            .collect()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(Path::new(MANIFEST_PATH))
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Files `apply`/`monitor` write outside our directories (`revert` normally removes them)
+pub fn runtime_artifacts() -> Vec<(PathBuf, ArtifactKind)> {
+    let mut paths = vec![
+        (optimizer::SYSCTL_CONF_PATH.into(), ArtifactKind::File),
+        (conflicts::TLP_DROPIN.into(), ArtifactKind::File),
+        (conflicts::TUNED_PROFILE_DIR.into(), ArtifactKind::Dir),
+    ];
+    paths.extend(quirks::get().modprobe_files().into_iter()
+        .map(|f| (Path::new("/etc/modprobe.d").join(f), ArtifactKind::File)));
+    paths
+}
+
+/// Remove every artifact; returns the ones still present. Only a `Dir` is removed
+/// recursively: a directory where anything else was recorded is left alone.
+pub fn remove<'a>(artifacts: impl IntoIterator<Item = (&'a Path, ArtifactKind)>) -> Vec<PathBuf> {
+    let mut left = Vec::new();
+    for (path, kind) in artifacts {
+        if kind == ArtifactKind::NftTable {
+            stream_guard::remove_table();
+            continue;
+        }
+        let result = match fs::symlink_metadata(path) {
+            Ok(meta) if meta.is_dir() && kind == ArtifactKind::Dir => fs::remove_dir_all(path),
+            Ok(meta) if meta.is_dir() => {
+                left.push(path.to_path_buf());
+                continue;
+            }
+            Ok(_) => fs::remove_file(path),
+            Err(_) => continue,
+        };
+        if result.is_err() || fs::symlink_metadata(path).is_ok() {
+            left.push(path.to_path_buf());
+        }
+    }
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_verify_and_remove() {
+        let dir = std::env::temp_dir().join(format!("hifi-wifi-manifest-{}", std::process::id()));
+        fs::create_dir_all(dir.join("tree/sub")).unwrap();
+        fs::write(dir.join("tree/sub/file"), "x").unwrap();
+        fs::write(dir.join("unit"), "[Unit]\n").unwrap();
+        fs::write(dir.join("helper"), "#!/bin/sh\n").unwrap();
+        std::os::unix::fs::symlink(dir.join("helper"), dir.join("link")).unwrap();
+
+        let mut manifest = Manifest::default();
+        manifest.track(dir.join("unit"), ArtifactKind::Unit);
+        manifest.track(dir.join("helper"), ArtifactKind::File);
+        manifest.track(dir.join("tree"), ArtifactKind::Dir);
+        manifest.track(dir.join("link"), ArtifactKind::Symlink);
+        manifest.track(dir.join("missing"), ArtifactKind::File);
+        manifest.users.insert("deck".to_string());
+        assert_eq!(manifest.artifacts.len(), 4);
+        assert_eq!(manifest.artifacts[&dir.join("unit")].sha256.as_deref(),
+                   Some("ae6c63cff33bcfa3b6a2d6d0c9dd19521dedaa351146b1a642d2bfc9cf5a1e1f"));
+        assert_eq!(manifest.artifacts[&dir.join("tree")].sha256, None);
+
+        let path = dir.join("manifest.json");
+        manifest.save_to(&path).unwrap();
+        let loaded = Manifest::load_from(&path);
+        assert_eq!(loaded, manifest);
+        assert!(loaded.verify().is_empty());
+
+        fs::write(dir.join("unit"), "[Unit]\nchanged\n").unwrap();
+        fs::remove_file(dir.join("helper")).unwrap();
+        let drift = loaded.verify();
+        assert_eq!(drift, vec![
+            (dir.join("helper"), Drift::Missing),
+            (dir.join("link"), Drift::Missing),
+            (dir.join("unit"), Drift::Modified),
+        ]);
+
+        let left = remove(loaded.artifacts.iter().map(|(p, a)| (p.as_path(), a.kind)));
+        assert!(left.is_empty());
+        fs::create_dir_all(dir.join("etc")).unwrap();
+        assert_eq!(remove([(dir.join("etc").as_path(), ArtifactKind::File)]), vec![dir.join("etc")]);
+        assert!(!dir.join("tree").exists() && !dir.join("unit").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,3 +1,14 @@
 pub mod power;
 pub mod optimizer;
 pub mod cpu;
+pub mod conflicts;
+pub mod doctor;
+pub mod driver_reload;
+pub mod quirks;
+pub mod sysext;
+pub mod lsm;
+pub mod manifest;
+pub mod repair_log;
+pub mod readonly;
+pub mod budget;
+pub mod sleep;
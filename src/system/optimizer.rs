@@ -4,18 +4,116 @@
 
 use anyhow::{Context, Result};
 use log::{info, warn, debug};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::Path;
 use std::process::Command;
 
+use crate::config::structs::DEFAULT_SYSCTL;
+use crate::system::quirks;
+
 use crate::network::wifi::{DriverCategory, WifiInterface, InterfaceType};
 
+pub const SYSCTL_CONF_PATH: &str = "/etc/sysctl.d/99-hifi-wifi.conf";
+
+/// Values that were in effect before we first applied our sysctl settings
+const SYSCTL_BACKUP_PATH: &str = "/var/lib/hifi-wifi/sysctl-backup.json";
+
+/// Where other tools drop sysctl config (checked for conflicting keys)
+const SYSCTL_DIRS: &[&str] = &["/etc/sysctl.d", "/run/sysctl.d", "/usr/local/lib/sysctl.d", "/usr/lib/sysctl.d"];
+
+/// Parse `key = value` lines of a sysctl.d file (keys normalized to dotted form)
+fn parse_sysctl_conf(content: &str) -> Vec<(String, String)> {
+    content.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with(';'))
+        .filter_map(|l| {
+            let (key, value) = l.split_once('=')?;
+            let key = key.trim().trim_start_matches('-').replace('/', ".");
+            Some((key, normalize_sysctl_value(value)))
+        })
+        .collect()
+}
+
+fn normalize_sysctl_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Contents of every sysctl config file not written by us
+fn read_foreign_sysctl_files() -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    if let Ok(content) = fs::read_to_string("/etc/sysctl.conf") {
+        files.push(("/etc/sysctl.conf".to_string(), content));
+    }
+    for dir in SYSCTL_DIRS {
+        let Ok(entries) = fs::read_dir(dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path == Path::new(SYSCTL_CONF_PATH) || path.extension().is_none_or(|e| e != "conf") {
+                continue;
+            }
+            if let Ok(content) = fs::read_to_string(&path) {
+                files.push((path.display().to_string(), content));
+            }
+        }
+    }
+    files
+}
+
+/// (key, their value, file) for every foreign file setting one of our keys differently
+fn find_sysctl_conflicts(settings: &BTreeMap<String, String>, files: &[(String, String)]) -> Vec<(String, String, String)> {
+    let mut conflicts = Vec::new();
+    for (file, content) in files {
+        for (key, value) in parse_sysctl_conf(content) {
+            if let Some(ours) = settings.get(&key) {
+                if normalize_sysctl_value(ours) != value {
+                    conflicts.push((key, value, file.clone()));
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+/// Keys other sysctl config files set to a different value than `settings`
+pub fn sysctl_conflicts(settings: &BTreeMap<String, String>) -> Vec<(String, String, String)> {
+    find_sysctl_conflicts(settings, &read_foreign_sysctl_files())
+}
+
+/// Record current values of the keys we're about to change (first apply only,
+/// so re-applying never captures our own values as the "original")
+fn backup_sysctl_values(settings: &BTreeMap<String, String>) {
+    if Path::new(SYSCTL_BACKUP_PATH).exists() {
+        return;
+    }
+    let mut backup = BTreeMap::new();
+    for key in settings.keys() {
+        if let Ok(o) = Command::new("sysctl").args(["-n", key]).output() {
+            if o.status.success() {
+                backup.insert(key.clone(), normalize_sysctl_value(&String::from_utf8_lossy(&o.stdout)));
+            }
+        }
+    }
+    let write = || -> Result<()> {
+        if let Some(parent) = Path::new(SYSCTL_BACKUP_PATH).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(SYSCTL_BACKUP_PATH, serde_json::to_string_pretty(&backup)?)?;
+        Ok(())
+    };
+    match write() {
+        Ok(()) => debug!("Recorded {} original sysctl value(s)", backup.len()),
+        Err(e) => warn!("Could not record original sysctl values: {}", e),
+    }
+}
+
 /// System optimizer for kernel and driver tuning
 pub struct SystemOptimizer {
     sysctl_enabled: bool,
     irq_affinity_enabled: bool,
     driver_tweaks_enabled: bool,
+    sysctl_settings: BTreeMap<String, String>,
 }
 
 impl SystemOptimizer {
@@ -24,9 +122,16 @@ impl SystemOptimizer {
             sysctl_enabled: sysctl,
             irq_affinity_enabled: irq,
             driver_tweaks_enabled: driver,
+            sysctl_settings: DEFAULT_SYSCTL.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
         }
     }
 
+    /// Use these sysctl settings instead of the defaults (see SystemConfig::sysctl_settings)
+    pub fn with_sysctl_settings(mut self, settings: BTreeMap<String, String>) -> Self {
+        self.sysctl_settings = settings;
+        self
+    }
+
     /// Apply all system optimizations
     pub fn apply(&self, interfaces: &[WifiInterface]) -> Result<()> {
         if self.sysctl_enabled {
@@ -57,24 +162,19 @@ impl SystemOptimizer {
     fn apply_sysctl_tuning(&self) -> Result<()> {
         info!("Applying sysctl network optimizations...");
 
-        let settings = [
-            ("net.ipv4.tcp_congestion_control", "bbr"),
-            ("net.core.rmem_default", "262144"),
-            ("net.core.wmem_default", "262144"),
-            ("net.core.rmem_max", "4194304"),
-            ("net.core.wmem_max", "4194304"),
-            ("net.ipv4.tcp_rmem", "4096 131072 4194304"),
-            ("net.ipv4.tcp_wmem", "4096 65536 4194304"),
-            ("net.ipv4.tcp_fastopen", "3"),
-            ("net.core.netdev_max_backlog", "2000"),
-            ("net.ipv4.tcp_ecn", "1"),
-            ("net.ipv4.tcp_keepalive_time", "60"),
-            ("net.ipv4.tcp_keepalive_intvl", "10"),
-            ("net.ipv4.tcp_keepalive_probes", "6"),
-            ("net.ipv4.tcp_tw_reuse", "1"),
-        ];
+        let settings = &self.sysctl_settings;
+        if settings.is_empty() {
+            info!("No sysctl settings configured, skipping");
+            return Ok(());
+        }
+
+        for (key, value, file) in sysctl_conflicts(settings) {
+            warn!("{} sets {} = {} (hifi-wifi wants {}); whichever file sorts last wins at boot",
+                  file, key, value, settings[&key]);
+        }
+        backup_sysctl_values(settings);
 
-        let sysctl_path = Path::new("/etc/sysctl.d/99-hifi-wifi.conf");
+        let sysctl_path = Path::new(SYSCTL_CONF_PATH);
         let mut config_content = String::from("# hifi-wifi Network Optimizations\n");
         for (key, val) in settings.iter() {
             config_content.push_str(&format!("{} = {}\n", key, val));
@@ -129,77 +229,12 @@ impl SystemOptimizer {
     }
 
     /// Apply driver-specific module parameters
-    /// 
-    /// References:
-    /// - RTW89: https://github.com/lwfinger/rtw89 (disable_aspm_l1, disable_aspm_l1ss for HP/Lenovo)
-    /// - MT7921: https://wiki.archlinux.org/title/Network_configuration/Wireless#mt7921_/_mt7922
-    /// - iwlwifi: https://wiki.archlinux.org/title/Power_management#Intel_wireless_cards_(iwlwifi)
-    /// - ath11k: Steam Deck OLED WCN6855 - limited params, kernel handles most
     fn apply_driver_config(&self, category: &DriverCategory) -> Result<()> {
-        let (filename, config) = match category {
-            DriverCategory::Rtw89 => ("rtw89.conf", r#"# Realtek RTW89 optimizations (RTL8851BE/RTL8852AE/RTL8852BE/RTL8852CE)
-# Disables PCIe Active State Power Management for stability
-# Required for HP/Lenovo laptops with buggy BIOS PCIe implementations
-options rtw89_pci disable_aspm_l1=y disable_aspm_l1ss=y
-# Disable firmware-level power save for consistent low latency
-options rtw89_core disable_ps_mode=y
-"#),
-            DriverCategory::Rtw88 => ("rtw88.conf", r#"# Realtek RTW88 optimizations (RTL8822CE - Steam Deck LCD)
-# Disables PCIe ASPM for stability and lower latency
-options rtw88_pci disable_aspm=1
-# Disables deep low-power states that cause reconnection issues
-options rtw88_core disable_lps_deep=Y
-"#),
-            DriverCategory::RtlLegacy => ("rtl_legacy.conf", r#"# Legacy Realtek optimizations (RTL8192EE/RTL8188EE)
-# swenc=1: Software encryption (more stable on some chips)
-# ips=0: Disable inactive power save
-# fwlps=0: Disable firmware low-power state
-options rtl8192ee swenc=1 ips=0 fwlps=0
-options rtl8188ee swenc=1 ips=0 fwlps=0
-options rtl_pci disable_aspm=1
-"#),
-            DriverCategory::MediaTek => ("mediatek.conf", r#"# MediaTek optimizations (MT7921/MT7922/MT76)
-# Fixes high latency issues documented in Arch Wiki
-options mt7921e disable_aspm=1
-# Disable USB scatter-gather for better stability on USB adapters
-options mt76_usb disable_usb_sg=1
-"#),
-            DriverCategory::Intel => ("iwlwifi.conf", r#"# Intel Wi-Fi optimizations (AX200/AX201/AX210/AX211/BE200)
-# power_save=0: Disable driver-level power saving
-# uapsd_disable=1: Disable U-APSD (unscheduled automatic power save delivery)
-#   - U-APSD can cause latency spikes during gaming
-options iwlwifi power_save=0 uapsd_disable=1
-# power_scheme=1: "Always Active" mode (vs 2=Balanced, 3=Low-power)
-# Prevents WiFi card disappearing on battery or after suspend
-options iwlmvm power_scheme=1
-"#),
-            DriverCategory::Atheros => ("ath_wifi.conf", r#"# Qualcomm Atheros optimizations
-# ath11k: Steam Deck OLED (WCN6855) and other WiFi 6E chips
-# disable_aspm=1: Prevents latency spikes from PCIe power transitions
-options ath11k_pci disable_aspm=1
-# ath9k: Legacy 802.11n chips (AR9285/AR9287/etc)
-# ps_enable=0: Disable hardware power save
-options ath9k ps_enable=0
-"#),
-            DriverCategory::Broadcom => ("broadcom.conf", r#"# Broadcom optimizations
-options brcmfmac roamoff=1
-options wl interference=0
-"#),
-            DriverCategory::Ralink => ("ralink.conf", r#"# Ralink/MediaTek Legacy optimizations
-options rt2800usb nohwcrypt=0
-options rt2800pci nohwcrypt=0
-"#),
-            DriverCategory::Marvell => ("marvell.conf", r#"# Marvell optimizations
-options mwifiex disable_auto_ds=1
-"#),
-            DriverCategory::Generic => ("wifi_generic.conf", r#"# Universal Wi-Fi optimizations
-# Applied for unknown drivers
-"#),
-        };
+        let (filename, config) = quirks::get().modprobe_config(category);
 
         info!("Applying {:?} driver configuration...", category);
 
-        let modprobe_path = Path::new("/etc/modprobe.d").join(filename);
+        let modprobe_path = Path::new("/etc/modprobe.d").join(&filename);
         
         if let Some(parent) = modprobe_path.parent() {
             fs::create_dir_all(parent).ok();
@@ -236,22 +271,13 @@ options mwifiex disable_auto_ds=1
         let interrupts = fs::read_to_string("/proc/interrupts")
             .context("Failed to read /proc/interrupts")?;
 
-        // Special mappings for drivers that report different names in /proc/interrupts
-        // - rtl8192ee reports as "rtl_pci" 
-        // - rtw88_8822ce (Steam Deck LCD) may show as rtw88, rtw_pci, or interface name
-        // - ath11k uses MSI-X with multiple IRQ vectors (ath11k_pci:base, DP, CE0-CE11, MHI)
-        // - Steam Deck OLED (WCN6855) may show as wcn, ath11k, or other variants
-        let search_terms: Vec<&str> = match ifc.driver.as_str() {
-            "rtl8192ee" => vec!["rtl_pci"],
-            "rtw88_8822ce" | "rtw88_pci" | "rtw_pci" => vec!["rtw88", "rtw_pci", &ifc.name],
-            "ath11k_pci" | "ath11k" => vec!["ath11k", "wcn", "MHI", &ifc.name],  // WCN6855 variants
-            _ => vec![ifc.driver.as_str(), &ifc.name],
-        };
+        // Drivers may report different names in /proc/interrupts (see quirks.toml)
+        let search_terms = quirks::get().irq_names(&ifc.driver, &ifc.name);
 
         // Find ALL matching IRQs (important for MSI-X drivers like ath11k)
         let irqs: Vec<String> = interrupts.lines()
             .filter(|line| {
-                search_terms.iter().any(|term| line.contains(term)) || line.contains(&ifc.name)
+                search_terms.iter().any(|term| line.contains(term.as_str()))
             })
             .filter_map(|line| line.trim().split(':').next())
             .map(|s| s.trim().to_string())
@@ -339,27 +365,45 @@ options mwifiex disable_auto_ds=1
         Ok(())
     }
 
-    /// Revert all system optimizations
-    pub fn revert(&self) -> Result<()> {
-        info!("Reverting system optimizations...");
-
-        // Remove sysctl config
-        let _ = fs::remove_file("/etc/sysctl.d/99-hifi-wifi.conf");
+    /// Remove the sysctl config and restore the values recorded before we first applied it
+    pub fn revert_sysctl(&self) {
+        if fs::remove_file(SYSCTL_CONF_PATH).is_ok() {
+            info!("Removed sysctl config");
+        }
 
-        // Remove modprobe configs (list all possible files)
-        let modprobe_files = [
-            "rtw89.conf", "rtw88.conf", "rtl_legacy.conf", "mediatek.conf",
-            "iwlwifi.conf", "ath_wifi.conf", "broadcom.conf", "ralink.conf",
-            "marvell.conf", "wifi_generic.conf",
-        ];
+        let backup: BTreeMap<String, String> = match fs::read_to_string(SYSCTL_BACKUP_PATH)
+            .ok()
+            .and_then(|c| serde_json::from_str(&c).ok())
+        {
+            Some(b) => b,
+            None => {
+                info!("No recorded sysctl values to restore (takes full effect after reboot)");
+                return;
+            }
+        };
+        for (key, value) in &backup {
+            let ok = Command::new("sysctl")
+                .arg("-w")
+                .arg(format!("{}={}", key, value))
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false);
+            if !ok {
+                warn!("Could not restore {} = {}", key, value);
+            }
+        }
+        let _ = fs::remove_file(SYSCTL_BACKUP_PATH);
+        info!("Restored {} original sysctl value(s)", backup.len());
+    }
 
-        for file in modprobe_files {
+    /// Remove modprobe configs (every file the quirks table names)
+    pub fn revert_driver_config(&self) {
+        for file in quirks::get().modprobe_files() {
             let path = Path::new("/etc/modprobe.d").join(file);
-            let _ = fs::remove_file(path);
+            if fs::remove_file(&path).is_ok() {
+                info!("Removed driver config: {}", path.display());
+            }
         }
-
-        info!("System optimizations reverted");
-        Ok(())
     }
 }
 
@@ -368,3 +412,28 @@ impl Default for SystemOptimizer {
         Self::new(true, true, true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sysctl_conflicts() {
+        let settings: BTreeMap<String, String> = [
+            ("net.ipv4.tcp_congestion_control", "bbr"),
+            ("net.ipv4.tcp_rmem", "4096 131072 4194304"),
+            ("net.ipv4.tcp_ecn", "1"),
+        ].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        let files = vec![
+            ("/etc/sysctl.d/50-cachyos.conf".to_string(),
+             "# tuned\nnet.ipv4.tcp_congestion_control = cubic\nnet/ipv4/tcp_rmem = 4096\t131072  4194304\n".to_string()),
+            ("/usr/lib/sysctl.d/10-default.conf".to_string(), "-net.ipv4.tcp_ecn=2\n; comment\nvm.swappiness = 10\n".to_string()),
+        ];
+        let conflicts = find_sysctl_conflicts(&settings, &files);
+        assert_eq!(conflicts, vec![
+            ("net.ipv4.tcp_congestion_control".to_string(), "cubic".to_string(), "/etc/sysctl.d/50-cachyos.conf".to_string()),
+            ("net.ipv4.tcp_ecn".to_string(), "2".to_string(), "/usr/lib/sysctl.d/10-default.conf".to_string()),
+        ]);
+    }
+}
@@ -1,10 +1,15 @@
 //! Power management for Wi-Fi adapters
 //!
-//! Adaptive power management based on AC/battery status.
+//! Adaptive power management based on AC/battery status. The power source is read from
+//! sysfs when the manager is created and again on `refresh` (once per Governor tick);
+//! everything else uses that cached reading.
 
 use log::info;
 use std::fs;
 use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::system::quirks::{self, DeviceQuirk};
 
 /// Power source state
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -14,6 +19,87 @@ pub enum PowerSource {
     Unknown,
 }
 
+/// One entry of /sys/class/power_supply
+#[derive(Debug, Default)]
+struct Supply {
+    name: String,
+    /// `type`: Mains, Battery, USB, USB_PD, ...
+    kind: String,
+    online: bool,
+    /// Battery `status`
+    status: String,
+    /// `scope`: "Device" for peripherals powered on their own
+    scope: String,
+}
+
+/// DMI board and product name, for device quirks
+fn dmi_names() -> &'static str {
+    static NAMES: OnceLock<String> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        ["board_name", "product_name"].iter()
+            .filter_map(|f| fs::read_to_string(format!("/sys/class/dmi/id/{}", f)).ok())
+            .map(|s| s.trim().to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// Power source from the supplies; `quirk` names the device's own chargers and decoys
+fn classify(supplies: &[Supply], quirk: Option<&DeviceQuirk>) -> PowerSource {
+    let matches = |prefixes: Option<&Vec<String>>, name: &str| {
+        prefixes.is_some_and(|p| p.iter().any(|prefix| name.starts_with(prefix.as_str())))
+    };
+    let mut ac_online = false;
+    let mut battery_discharging = false;
+    let mut battery_found = false;
+
+    for supply in supplies {
+        let name = supply.name.as_str();
+        // Skip peripheral batteries
+        if name.contains("hidpp") || name.contains("hid") ||
+           name.contains("mouse") || name.contains("keyboard") ||
+           supply.scope == "Device" || matches(quirk.map(|q| &q.ignore_supplies), name) {
+            continue;
+        }
+
+        // AC adapters, and USB-C/PD chargers (ucsi-source-psy-*, type USB)
+        let charger = name.starts_with("AC") || name.starts_with("ADP") || name.contains("ACAD")
+            || supply.kind == "Mains" || supply.kind.starts_with("USB")
+            || matches(quirk.map(|q| &q.ac_supplies), name);
+        if charger && supply.online {
+            ac_online = true;
+        }
+
+        // Check battery status
+        if name.starts_with("BAT") || name == "battery" {
+            battery_found = true;
+            match supply.status.as_str() {
+                // Battery connected to power = AC
+                "Charging" | "Full" | "Not charging" => ac_online = true,
+                "Discharging" => battery_discharging = true,
+                _ => {}
+            }
+        }
+    }
+
+    // AC takes priority - if adapter is online, we're on AC regardless of battery state
+    if ac_online {
+        return PowerSource::AC;
+    }
+
+    // Only report battery if we found one and it's discharging
+    if battery_found && battery_discharging {
+        return PowerSource::Battery;
+    }
+
+    // No battery = desktop = treat as AC
+    if !battery_found {
+        return PowerSource::AC;
+    }
+
+    PowerSource::Unknown
+}
+
 /// Device type classification
 #[derive(Debug, Clone, PartialEq)]
 pub enum DeviceType {
@@ -25,6 +111,7 @@ pub enum DeviceType {
 /// Manages power-aware Wi-Fi settings
 pub struct PowerManager {
     device_type: DeviceType,
+    source: PowerSource,
 }
 
 impl PowerManager {
@@ -36,6 +123,7 @@ impl PowerManager {
         
         Self {
             device_type,
+            source: current_source,
         }
     }
 
@@ -110,75 +198,36 @@ impl PowerManager {
     /// Detect current power source
     /// FIXED: Collect ALL power supply info first, then decide (prevents race condition)
     pub fn detect_power_source() -> PowerSource {
-        let power_supply = Path::new("/sys/class/power_supply");
-        
-        let mut ac_online = false;
-        let mut battery_discharging = false;
-        let mut battery_found = false;
-        
-        if let Ok(entries) = fs::read_dir(power_supply) {
+        let mut supplies = Vec::new();
+        if let Ok(entries) = fs::read_dir("/sys/class/power_supply") {
             for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_string();
-                
-                // Skip peripheral batteries
-                if name.contains("hidpp") || name.contains("hid") || 
-                   name.contains("mouse") || name.contains("keyboard") {
-                    continue;
-                }
-                
-                // Check AC adapters
-                if name.starts_with("AC") || name.starts_with("ADP") || name.contains("ACAD") {
-                    let online_path = entry.path().join("online");
-                    if let Ok(status) = fs::read_to_string(&online_path) {
-                        if status.trim() == "1" {
-                            ac_online = true;
-                        }
-                    }
-                }
-
-                // Check battery status
-                if name.starts_with("BAT") || name == "battery" {
-                    battery_found = true;
-                    let status_path = entry.path().join("status");
-                    if let Ok(status) = fs::read_to_string(&status_path) {
-                        let status = status.trim();
-                        match status {
-                            "Charging" | "Full" | "Not charging" => {
-                                // Battery connected to power = AC
-                                ac_online = true;
-                            }
-                            "Discharging" => {
-                                battery_discharging = true;
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+                let read = |file: &str| fs::read_to_string(entry.path().join(file)).ok().map(|s| s.trim().to_string());
+                supplies.push(Supply {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    kind: read("type").unwrap_or_default(),
+                    online: read("online").as_deref() == Some("1"),
+                    status: read("status").unwrap_or_default(),
+                    scope: read("scope").unwrap_or_default(),
+                });
             }
         }
-
-        // AC takes priority - if adapter is online, we're on AC regardless of battery state
-        if ac_online {
-            return PowerSource::AC;
-        }
-        
-        // Only report battery if we found one and it's discharging
-        if battery_found && battery_discharging {
-            return PowerSource::Battery;
-        }
-        
-        // No battery = desktop = treat as AC
-        if !battery_found {
-            return PowerSource::AC;
-        }
-
-        PowerSource::Unknown
+        classify(&supplies, quirks::get().device(dmi_names()))
     }
 
-    /// Get current power source (refreshed dynamically)
+    /// Power source as of the last `refresh`
     pub fn power_source(&self) -> PowerSource {
-        // Always get fresh reading
-        Self::detect_power_source()
+        self.source
+    }
+
+    /// Read the power source again; Some(new source) if it changed
+    pub fn refresh(&mut self) -> Option<PowerSource> {
+        let source = Self::detect_power_source();
+        if source == self.source {
+            return None;
+        }
+        info!(event = "power_source", old:? = self.source, new:? = source; "Power source changed: {:?} -> {:?}", self.source, source);
+        self.source = source;
+        Some(source)
     }
 
     /// Get device type
@@ -187,15 +236,12 @@ impl PowerManager {
     }
 
     /// Should power saving be enabled based on current state?
-    /// FIXED: Now refreshes power source dynamically instead of using cached value
     pub fn should_enable_power_save(&self) -> bool {
-        let current_source = Self::detect_power_source();
-        
         match self.device_type {
             DeviceType::Desktop => false, // Always performance mode
             DeviceType::SteamDeck | DeviceType::Laptop => {
                 // Enable power save only when on battery
-                current_source == PowerSource::Battery
+                self.source == PowerSource::Battery
             }
         }
     }
@@ -226,3 +272,27 @@ impl Default for PowerManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let supply = |name: &str, kind: &str, online: bool, status: &str| Supply {
+            name: name.to_string(), kind: kind.to_string(), online, status: status.to_string(), ..Default::default()
+        };
+        // Docked handheld whose battery reports "Discharging" while the PD charger tops up
+        let docked = [supply("BAT0", "Battery", false, "Discharging"), supply("ucsi-source-psy-USBC000:001", "USB", true, "")];
+        assert_eq!(classify(&docked, None), PowerSource::AC);
+        let unplugged = [supply("BAT0", "Battery", false, "Discharging"), supply("ucsi-source-psy-USBC000:001", "USB", false, "")];
+        assert_eq!(classify(&unplugged, None), PowerSource::Battery);
+
+        // A device quirk can name a charger or rule out a misleading supply
+        let odd = [supply("BAT0", "Battery", false, "Discharging"), supply("dock-psy", "Unknown", true, "")];
+        let quirk = DeviceQuirk { ac_supplies: vec!["dock".to_string()], ignore_supplies: Vec::new() };
+        assert_eq!(classify(&odd, Some(&quirk)), PowerSource::AC);
+        let quirk = DeviceQuirk { ac_supplies: Vec::new(), ignore_supplies: vec!["ucsi".to_string()] };
+        assert_eq!(classify(&docked, Some(&quirk)), PowerSource::Battery);
+    }
+}
@@ -0,0 +1,238 @@
+//! Per-driver quirks: /proc/interrupts names, module parameters, game-mode debugfs writes;
+//! per-device power supplies
+//!
+//! Built-in entries live in quirks.toml next to this file; /etc/hifi-wifi/quirks.toml
+//! can add drivers or override them without a new release.
+
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::network::wifi::DriverCategory;
+
+const BUILTIN_QUIRKS: &str = include_str!("quirks.toml");
+const QUIRKS_PATH: &str = "/etc/hifi-wifi/quirks.toml";
+
+static QUIRKS: OnceLock<Quirks> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DriverQuirk {
+    /// Extra names to look for in /proc/interrupts
+    #[serde(default)]
+    pub irq_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CategoryQuirk {
+    /// modprobe.d file name
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Module name -> space-separated `param=value` list
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+    /// debugfs writes while game mode is active (see network::mac80211)
+    #[serde(default)]
+    pub game_mode: Vec<GameModeWrite>,
+}
+
+/// A debugfs file changed for the duration of game mode
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GameModeWrite {
+    /// Relative to the phy's debugfs dir; `{iface}` and `{station}` (the AP) are filled in
+    pub path: String,
+    /// Written when game mode starts
+    pub game: String,
+    /// Written when it ends; unset restores the value read before
+    #[serde(default)]
+    pub restore: Option<String>,
+}
+
+/// Power supplies of a device model (see system::power)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeviceQuirk {
+    /// Name prefixes of supplies that mean external power when online
+    #[serde(default)]
+    pub ac_supplies: Vec<String>,
+    /// Name prefixes of supplies to leave out of power source detection
+    #[serde(default)]
+    pub ignore_supplies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Quirks {
+    #[serde(default)]
+    pub drivers: BTreeMap<String, DriverQuirk>,
+    #[serde(default)]
+    pub categories: BTreeMap<String, CategoryQuirk>,
+    /// Keyed by a substring of the DMI board or product name
+    #[serde(default)]
+    pub devices: BTreeMap<String, DeviceQuirk>,
+}
+
+/// Table key for a driver category
+pub fn category_key(category: &DriverCategory) -> &'static str {
+    match category {
+        DriverCategory::Rtw89 => "rtw89",
+        DriverCategory::Rtw88 => "rtw88",
+        DriverCategory::RtlLegacy => "rtl_legacy",
+        DriverCategory::MediaTek => "mediatek",
+        DriverCategory::MediaTekWifi7 => "mediatek_wifi7",
+        DriverCategory::Intel => "intel",
+        DriverCategory::IntelWifi7 => "intel_wifi7",
+        DriverCategory::Atheros => "atheros",
+        DriverCategory::Ath12k => "ath12k",
+        DriverCategory::Broadcom => "broadcom",
+        DriverCategory::Ralink => "ralink",
+        DriverCategory::Marvell => "marvell",
+        DriverCategory::Generic => "generic",
+    }
+}
+
+/// A file name we may write to and remove from /etc/modprobe.d: `*.conf`, no path parts
+fn is_conf_name(name: &str) -> bool {
+    name.len() > ".conf".len() && name.ends_with(".conf") && !name.contains('/') && !name.contains("..")
+}
+
+impl Quirks {
+    fn builtin() -> Self {
+        toml::from_str(BUILTIN_QUIRKS).expect("built-in quirks.toml is valid")
+    }
+
+    /// Layer user entries over these: drivers are replaced, module options merged
+    fn merge(&mut self, user: Quirks) {
+        self.drivers.extend(user.drivers);
+        self.devices.extend(user.devices);
+        for (key, quirk) in user.categories {
+            let entry = self.categories.entry(key.clone()).or_default();
+            match quirk.file {
+                Some(file) if is_conf_name(&file) => entry.file = Some(file),
+                Some(file) => warn!("Ignoring file = {:?} for [categories.{}] in quirks.toml: must be a plain *.conf name", file, key),
+                None => {}
+            }
+            if !quirk.game_mode.is_empty() {
+                entry.game_mode = quirk.game_mode;
+            }
+            for (module, opts) in quirk.options {
+                if opts.trim().is_empty() {
+                    entry.options.remove(&module);
+                } else {
+                    entry.options.insert(module, opts);
+                }
+            }
+        }
+    }
+
+    fn load() -> Self {
+        let mut quirks = Self::builtin();
+        match fs::read_to_string(QUIRKS_PATH) {
+            Ok(content) => match toml::from_str::<Quirks>(&content) {
+                Ok(user) => {
+                    info!("Loaded driver quirks from {}", QUIRKS_PATH);
+                    quirks.merge(user);
+                }
+                Err(e) => warn!("Failed to parse {}: {}. Using built-in quirks.", QUIRKS_PATH, e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read {}: {}", QUIRKS_PATH, e),
+        }
+        quirks
+    }
+
+    /// Strings identifying this interface's lines in /proc/interrupts
+    pub fn irq_names(&self, driver: &str, iface: &str) -> Vec<String> {
+        let mut names = match self.drivers.get(driver) {
+            Some(q) if !q.irq_names.is_empty() => q.irq_names.clone(),
+            _ => vec![driver.to_string()],
+        };
+        names.push(iface.to_string());
+        names
+    }
+
+    /// `(module, param, value)` to set for a driver category
+    pub fn module_params(&self, category: &DriverCategory) -> Vec<(String, String, String)> {
+        let Some(quirk) = self.categories.get(category_key(category)) else {
+            return Vec::new();
+        };
+        quirk.options.iter()
+            .flat_map(|(module, opts)| {
+                opts.split_whitespace().filter_map(move |kv| {
+                    let (param, value) = kv.split_once('=')?;
+                    Some((module.clone(), param.to_string(), value.to_string()))
+                })
+            })
+            .collect()
+    }
+
+    /// debugfs writes for game mode on a driver category
+    pub fn game_mode_writes(&self, category: &DriverCategory) -> &[GameModeWrite] {
+        self.categories.get(category_key(category)).map(|q| q.game_mode.as_slice()).unwrap_or(&[])
+    }
+
+    /// modprobe.d file name and contents for a driver category
+    pub fn modprobe_config(&self, category: &DriverCategory) -> (String, String) {
+        let key = category_key(category);
+        let quirk = self.categories.get(key).cloned().unwrap_or_default();
+        let file = quirk.file.unwrap_or_else(|| format!("hifi-wifi-{}.conf", key));
+        let mut content = format!("# hifi-wifi driver options ({:?})\n", category);
+        for (module, opts) in &quirk.options {
+            content.push_str(&format!("options {} {}\n", module, opts.trim()));
+        }
+        (file, content)
+    }
+
+    /// Entry for a device whose DMI board/product names are `dmi`
+    pub fn device(&self, dmi: &str) -> Option<&DeviceQuirk> {
+        self.devices.iter().find(|(key, _)| dmi.contains(key.as_str())).map(|(_, q)| q)
+    }
+
+    /// Every modprobe.d file we may have written
+    pub fn modprobe_files(&self) -> Vec<String> {
+        self.categories.iter()
+            .map(|(key, q)| q.file.clone().unwrap_or_else(|| format!("hifi-wifi-{}.conf", key)))
+            .filter(|f| is_conf_name(f))
+            .collect()
+    }
+}
+
+/// Built-in quirks merged with /etc/hifi-wifi/quirks.toml (read once)
+pub fn get() -> &'static Quirks {
+    QUIRKS.get_or_init(Quirks::load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_and_override() {
+        let mut quirks = Quirks::builtin();
+        assert_eq!(quirks.irq_names("rtl8192ee", "wlan0"), vec!["rtl_pci", "wlan0"]);
+        assert_eq!(quirks.irq_names("mt7921e", "wlan0"), vec!["mt7921e", "wlan0"]);
+        let (file, content) = quirks.modprobe_config(&DriverCategory::Intel);
+        assert_eq!(file, "iwlwifi.conf");
+        assert!(content.contains("options iwlwifi power_save=0 uapsd_disable=1"));
+
+        let user: Quirks = toml::from_str(r#"
+            [drivers.mt7921e]
+            irq_names = ["mt7921e", "mt792x"]
+            [categories.intel]
+            options = { iwlmvm = "", iwlwifi = "power_save=0" }
+            [categories.realtek]
+            file = ".."
+            [categories."../x"]
+        "#).unwrap();
+        quirks.merge(user);
+        assert_eq!(quirks.irq_names("mt7921e", "wlan0"), vec!["mt7921e", "mt792x", "wlan0"]);
+        let params = quirks.module_params(&DriverCategory::Intel);
+        assert_eq!(params, vec![("iwlwifi".to_string(), "power_save".to_string(), "0".to_string())]);
+        assert_eq!(quirks.modprobe_config(&DriverCategory::Intel).0, "iwlwifi.conf");
+        assert!(quirks.modprobe_files().iter().all(|f| is_conf_name(f) && f != ".."));
+        assert!(!is_conf_name("../sysctl.conf") && !is_conf_name(".conf") && is_conf_name("rtw89.conf"));
+        assert!(!quirks.game_mode_writes(&DriverCategory::MediaTek).is_empty());
+        assert!(quirks.game_mode_writes(&DriverCategory::Broadcom).is_empty());
+        assert!(quirks.device("ROG Ally RC71L_RC71L RC71L").is_some_and(|q| !q.ac_supplies.is_empty()));
+        assert!(quirks.device("Jupiter Jupiter").is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! SteamOS read-only /usr
+//!
+//! Writing under /usr (polkit actions, uninstall) needs `steamos-readonly disable`.
+//! `ReadonlyGuard` is the only place that toggles it: the outermost guard records
+//! whether /usr was read-only, nested guards just count, and dropping the last one
+//! (also while unwinding from a panic) restores the original state. A process killed
+//! in between leaves a marker that `recover` (run by `bootstrap`) acts on.
+
+use log::{debug, info, warn};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard};
+
+/// Holds the pid of a process that disabled read-only and hasn't restored it yet
+const MARKER_PATH: &str = "/var/lib/hifi-wifi/readonly-restore";
+
+#[derive(Debug, Default)]
+struct Nest {
+    depth: usize,
+    /// The outermost guard disabled read-only and must re-enable it
+    restore: bool,
+}
+
+impl Nest {
+    /// `disable` runs only for the outermost guard and reports whether it made /usr writable
+    fn enter(&mut self, disable: impl FnOnce() -> bool) {
+        if self.depth == 0 {
+            self.restore = disable();
+        }
+        self.depth += 1;
+    }
+
+    /// True when the outermost guard leaves and read-only must be re-enabled
+    fn leave(&mut self) -> bool {
+        self.depth = self.depth.saturating_sub(1);
+        self.depth == 0 && std::mem::take(&mut self.restore)
+    }
+}
+
+static NEST: Mutex<Nest> = Mutex::new(Nest { depth: 0, restore: false });
+
+/// Still usable after a panic elsewhere poisoned it
+fn nest() -> MutexGuard<'static, Nest> {
+    NEST.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Some(true) if /usr is read-only, None without `steamos-readonly` (not SteamOS)
+fn is_readonly() -> Option<bool> {
+    let output = Command::new("steamos-readonly").arg("status").output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim() == "enabled")
+}
+
+fn set_readonly(enable: bool) -> bool {
+    let arg = if enable { "enable" } else { "disable" };
+    match Command::new("steamos-readonly").arg(arg).output() {
+        Ok(o) if o.status.success() => true,
+        Ok(o) => {
+            warn!("steamos-readonly {} failed: {}", arg, String::from_utf8_lossy(&o.stderr).trim());
+            false
+        }
+        Err(e) => {
+            warn!("Failed to run steamos-readonly {}: {}", arg, e);
+            false
+        }
+    }
+}
+
+/// Makes /usr writable for its lifetime
+pub struct ReadonlyGuard(());
+
+impl ReadonlyGuard {
+    pub fn acquire() -> Self {
+        nest().enter(|| {
+            if is_readonly() != Some(true) || !set_readonly(false) {
+                return false;
+            }
+            debug!("Disabled SteamOS read-only mode");
+            let _ = fs::write(MARKER_PATH, std::process::id().to_string());
+            true
+        });
+        ReadonlyGuard(())
+    }
+}
+
+impl Drop for ReadonlyGuard {
+    fn drop(&mut self) {
+        if nest().leave() {
+            if set_readonly(true) {
+                debug!("Re-enabled SteamOS read-only mode");
+            }
+            let _ = fs::remove_file(MARKER_PATH);
+        }
+    }
+}
+
+/// Re-enable read-only if a process that disabled it died before restoring it
+pub fn recover() {
+    let Ok(pid) = fs::read_to_string(MARKER_PATH) else { return };
+    let pid = pid.trim();
+    // A guard of ours or of a live process restores it itself
+    if nest().depth > 0 || (!pid.is_empty() && Path::new("/proc").join(pid).exists()) {
+        return;
+    }
+    info!("Re-enabling SteamOS read-only mode left disabled by an interrupted run (pid {})", pid);
+    if is_readonly() == Some(false) {
+        set_readonly(true);
+    }
+    let _ = fs::remove_file(MARKER_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting() {
+        let mut nest = Nest::default();
+        nest.enter(|| true);
+        nest.enter(|| panic!("inner guards don't toggle"));
+        assert!(!nest.leave());
+        assert!(nest.leave());
+        assert_eq!(nest.depth, 0);
+
+        // Already writable: nothing to restore
+        nest.enter(|| false);
+        assert!(!nest.leave());
+    }
+}
@@ -0,0 +1,116 @@
+//! Repair audit log
+//!
+//! Every fix `check --repair` or `bootstrap` applies (rewriting the unit, reinstalling
+//! polkit actions, which flips SteamOS's /usr read-only state, ...) is appended to
+//! /var/lib/hifi-wifi/repair-log.jsonl. `bootstrap` also uses it to rate-limit itself:
+//! a fix that doesn't stick would otherwise be reapplied on every run.
+
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::network::usage::unix_now;
+
+pub const REPAIR_LOG_PATH: &str = "/var/lib/hifi-wifi/repair-log.jsonl";
+
+/// Rotate once the log exceeds this size
+const MAX_LOG_BYTES: u64 = 128 * 1024;
+
+/// Automatic repairs run at most this often
+pub const AUTO_REPAIR_INTERVAL_SECS: u64 = 3600;
+
+/// One repair action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairRecord {
+    pub ts: u64,
+    /// "check", "bootstrap" or "bootstrap --force"
+    pub trigger: String,
+    pub item: String,
+    pub problem: String,
+    pub fix: String,
+    /// None if the fix was applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RepairRecord {
+    pub fn automatic(&self) -> bool {
+        self.trigger.starts_with("bootstrap")
+    }
+}
+
+/// Append a record; failures are logged at debug and otherwise ignored
+pub fn record(record: &RepairRecord) {
+    if let Err(e) = append_to(Path::new(REPAIR_LOG_PATH), record) {
+        debug!("Failed to record repair of {}: {}", record.item, e);
+    }
+}
+
+fn append_to(path: &Path, record: &RepairRecord) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::metadata(path).map(|m| m.len() > MAX_LOG_BYTES).unwrap_or(false) {
+        fs::rename(path, path.with_extension("jsonl.1"))?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+fn read_from(path: &Path) -> Vec<RepairRecord> {
+    let rotated = fs::read_to_string(path.with_extension("jsonl.1")).unwrap_or_default();
+    let current = fs::read_to_string(path).unwrap_or_default();
+    rotated.lines().chain(current.lines())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// The newest record, if any
+pub fn last() -> Option<RepairRecord> {
+    read_from(Path::new(REPAIR_LOG_PATH)).pop()
+}
+
+/// Seconds until another automatic repair may run (None if one may run now)
+fn wait_secs(records: &[RepairRecord], now: u64) -> Option<u64> {
+    let last = records.iter().rev().find(|r| r.automatic())?;
+    let since = now.saturating_sub(last.ts);
+    (since < AUTO_REPAIR_INTERVAL_SECS).then(|| AUTO_REPAIR_INTERVAL_SECS - since)
+}
+
+/// How long `bootstrap` must still hold off repairing
+pub fn auto_repair_wait() -> Option<u64> {
+    wait_secs(&read_from(Path::new(REPAIR_LOG_PATH)), unix_now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_and_rate_limit() {
+        let path = std::env::temp_dir().join(format!("hifi-wifi-repair-{}.jsonl", std::process::id()));
+        let record = |ts, trigger: &str| RepairRecord {
+            ts,
+            trigger: trigger.to_string(),
+            item: "polkit actions".to_string(),
+            problem: "missing".to_string(),
+            fix: "PolkitActions".to_string(),
+            error: None,
+        };
+        append_to(&path, &record(1_000, "bootstrap")).unwrap();
+        append_to(&path, &record(2_000, "check")).unwrap();
+        let records = read_from(&path);
+        assert_eq!(records.len(), 2);
+        assert!(!records[1].automatic());
+
+        // Manual repairs don't count against the automatic ones
+        assert_eq!(wait_secs(&records, 2_500), Some(AUTO_REPAIR_INTERVAL_SECS - 1_500));
+        assert_eq!(wait_secs(&records, 1_000 + AUTO_REPAIR_INTERVAL_SECS), None);
+        assert_eq!(wait_secs(&records[1..], 2_500), None);
+        let _ = fs::remove_file(&path);
+    }
+}
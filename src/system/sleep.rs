@@ -0,0 +1,39 @@
+//! System suspend (logind)
+//!
+//! logind broadcasts `PrepareForSleep(true)` just before suspending and
+//! `PrepareForSleep(false)` after resuming. The Governor pauses its ticks and probes in
+//! between instead of waking up to a burst of missed ticks while the NIC is still
+//! coming back, then re-optimizes as it does after a reconnect.
+
+use anyhow::{Context, Result};
+use futures_lite::StreamExt;
+use tokio::sync::mpsc::Sender;
+use zbus::{proxy, Connection};
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1Manager {
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
+
+/// Send true on `tx` before the system sleeps and false once it has woken
+pub async fn watch(tx: Sender<bool>) -> Result<()> {
+    let connection = Connection::system().await.context("Failed to connect to system D-Bus")?;
+    let manager = Login1ManagerProxy::new(&connection).await?;
+    let mut signals = manager.receive_prepare_for_sleep().await?;
+    tokio::spawn(async move {
+        // Keep the proxy (and its connection) alive as long as the stream
+        let _manager = manager;
+        while let Some(signal) = signals.next().await {
+            let Ok(args) = signal.args() else { continue };
+            if tx.send(args.start).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! systemd system extension for SteamOS-style immutable systems
+//!
+//! Instead of writing into /usr (wiped by SteamOS updates) and repairing it at boot,
+//! `install --sysext` puts the binary, unit, polkit actions and completion into a
+//! directory extension under /var/lib/extensions. systemd-sysext overlays it onto /usr
+//! at every boot, so an OS update can't remove it. Config stays in /etc, which
+//! already survives updates (sysext only covers /usr and /opt).
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const EXTENSION_NAME: &str = "hifi-wifi";
+pub const EXTENSION_DIR: &str = "/var/lib/extensions/hifi-wifi";
+
+/// One file of the extension, relative to its root (e.g. `usr/bin/hifi-wifi`)
+pub struct ExtensionFile {
+    pub path: &'static str,
+    pub content: Vec<u8>,
+    pub mode: u32,
+}
+
+/// `extension-release` file; `_any` keeps the extension valid across OS version bumps
+pub fn extension_release() -> String {
+    "ID=_any\nEXTENSION_RELOAD_MANAGER=1\n".to_string()
+}
+
+/// Whether the extension is installed
+pub fn installed() -> bool {
+    Path::new(EXTENSION_DIR).is_dir()
+}
+
+/// Whether the daemon's unit is installed, in /etc or shipped by the extension
+pub fn service_installed() -> bool {
+    Path::new("/etc/systemd/system/hifi-wifi.service").exists() || installed()
+}
+
+/// Build the extension tree at `dir`, replacing a previous build as a whole
+///
+/// Files are written to `<dir>.new` first and swapped in, so a failed build leaves the
+/// old extension intact.
+pub fn build(dir: &Path, files: &[ExtensionFile]) -> Result<()> {
+    let staging = PathBuf::from(format!("{}.new", dir.display()));
+    let _ = fs::remove_dir_all(&staging);
+
+    let write = |rel: &str, content: &[u8], mode: u32| -> Result<()> {
+        let path = staging.join(rel);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+        Ok(())
+    };
+    for file in files {
+        write(file.path, &file.content, file.mode)?;
+    }
+    let release = format!("usr/lib/extension-release.d/extension-release.{}", EXTENSION_NAME);
+    write(&release, extension_release().as_bytes(), 0o644)?;
+
+    if dir.exists() {
+        fs::remove_dir_all(dir).context("Failed to remove the previous extension")?;
+    }
+    fs::rename(&staging, dir).context("Failed to move the extension into place")?;
+    Ok(())
+}
+
+/// Merge (or re-merge) all extensions now and at every boot
+pub fn refresh() -> Result<()> {
+    let _ = Command::new("systemctl").args(["enable", "systemd-sysext.service"]).output();
+    let output = Command::new("systemd-sysext").arg("refresh").output()
+        .context("systemd-sysext not found")?;
+    if !output.status.success() {
+        anyhow::bail!("systemd-sysext refresh: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    info!("System extension merged: {}", EXTENSION_DIR);
+    Ok(())
+}
+
+/// Unmerge and delete the extension
+pub fn remove() {
+    if !installed() {
+        return;
+    }
+    if let Err(e) = fs::remove_dir_all(EXTENSION_DIR) {
+        warn!("Failed to remove {}: {}", EXTENSION_DIR, e);
+        return;
+    }
+    if let Err(e) = refresh() {
+        warn!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_replaces_tree() {
+        let dir = std::env::temp_dir().join(format!("hifi-wifi-sysext-{}", std::process::id()));
+        let file = |path, content: &str| ExtensionFile { path, content: content.as_bytes().to_vec(), mode: 0o755 };
+
+        build(&dir, &[file("usr/bin/hifi-wifi", "v1"), file("usr/share/old", "x")]).unwrap();
+        build(&dir, &[file("usr/bin/hifi-wifi", "v2")]).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("usr/bin/hifi-wifi")).unwrap(), "v2");
+        assert!(!dir.join("usr/share/old").exists());
+        let release = fs::read_to_string(dir.join("usr/lib/extension-release.d/extension-release.hifi-wifi")).unwrap();
+        assert!(release.starts_with("ID=_any"));
+        assert_eq!(fs::metadata(dir.join("usr/bin/hifi-wifi")).unwrap().permissions().mode() & 0o777, 0o755);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
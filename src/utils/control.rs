@@ -0,0 +1,285 @@
+//! Control socket
+//!
+//! The daemon listens on a Unix socket for newline-delimited JSON requests and answers
+//! each with one JSON line. `hifi-wifi ctl` and `game-mode` are the clients; the socket
+//! works where D-Bus policy is locked down, and `status` reads the daemon's in-memory
+//! state instead of sampling the hardware again. Anyone may ask for status or pin game
+//! mode (Steam launch scripts run as the user); everything else needs root, checked
+//! against the peer's credentials. "Anyone" means members of `hifi-wifi-ctl` (`install`
+//! adds the user who ran it) once that group exists. A request line is capped at 64 KiB
+//! and at most 16 clients are served at a time, so a local user can't run the root
+//! daemon out of memory.
+//!
+//! Installed with `--on-demand`, systemd owns the socket (hifi-wifi.socket) and starts
+//! the daemon on the first connection, passing the listening socket in.
+//!
+//! ```text
+//! {"cmd":"status"}
+//! {"cmd":"reapply"}
+//! {"cmd":"set-option","key":"game_mode_pps_threshold","value":300}
+//! {"cmd":"game-mode","mode":"on","for_secs":7200}
+//! ```
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::fd::FromRawFd;
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+pub const SOCKET_PATH: &str = "/run/hifi-wifi/control.sock";
+/// Group whose members may connect to the socket
+pub const CONTROL_GROUP: &str = "hifi-wifi-ctl";
+/// Longest request line accepted
+const MAX_LINE: u64 = 64 * 1024;
+/// Clients served at once; more are turned away
+const MAX_CLIENTS: usize = 16;
+/// A client that sends nothing for this long is disconnected, freeing its slot
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// `game-mode` switch: force on or off, or back to traffic detection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum GameModeSwitch {
+    On,
+    Off,
+    Auto,
+}
+
+impl GameModeSwitch {
+    /// The override it sets (None = detect)
+    pub fn forced(self) -> Option<bool> {
+        match self {
+            GameModeSwitch::On => Some(true),
+            GameModeSwitch::Off => Some(false),
+            GameModeSwitch::Auto => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum Request {
+    /// The daemon's in-memory state
+    Status,
+    /// Re-optimize now (same as SIGUSR2)
+    Reapply,
+    /// Change a `[governor]` option until the daemon restarts
+    SetOption { key: String, value: serde_json::Value },
+    /// Pin game mode, optionally only for a while
+    GameMode {
+        mode: GameModeSwitch,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        for_secs: Option<u64>,
+    },
+}
+
+impl Request {
+    /// Allowed for clients that aren't root
+    pub fn unprivileged(&self) -> bool {
+        matches!(self, Request::Status | Request::GameMode { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl Response {
+    pub fn ok(data: Option<serde_json::Value>) -> Self {
+        Self { ok: true, error: None, data }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self { ok: false, error: Some(message.into()), data: None }
+    }
+}
+
+/// A request waiting for the Governor, with where to send the answer
+pub type Pending = (Request, oneshot::Sender<Response>);
+
+/// gid of `name` from an /etc/group listing
+fn parse_group(group_file: &str, name: &str) -> Option<u32> {
+    group_file.lines()
+        .map(|l| l.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.first() == Some(&name))
+        .and_then(|fields| fields.get(2)?.parse().ok())
+}
+
+/// gid of `CONTROL_GROUP`, None before `install` created it
+pub fn control_gid() -> Option<u32> {
+    parse_group(&fs::read_to_string("/etc/group").ok()?, CONTROL_GROUP)
+}
+
+/// First fd systemd passes (`sd_listen_fds`)
+const LISTEN_FDS_START: i32 = 3;
+
+/// `LISTEN_PID`/`LISTEN_FDS` name this process and at least one socket
+fn passed_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> bool {
+    listen_pid.and_then(|p| p.parse::<u32>().ok()) == Some(pid)
+        && listen_fds.and_then(|n| n.parse::<u32>().ok()).is_some_and(|n| n >= 1)
+}
+
+/// Started by hifi-wifi.socket, which owns the socket file
+pub fn socket_activated() -> bool {
+    passed_fds(std::env::var("LISTEN_PID").ok().as_deref(), std::env::var("LISTEN_FDS").ok().as_deref(),
+               std::process::id())
+}
+
+fn bind() -> Result<UnixListener> {
+    if socket_activated() {
+        // SAFETY: systemd hands us fd 3 as an open listening socket that nothing else owns
+        let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(LISTEN_FDS_START) };
+        listener.set_nonblocking(true)?;
+        return Ok(UnixListener::from_std(listener)?);
+    }
+    fs::create_dir_all("/run/hifi-wifi")?;
+    // Left over from a daemon that didn't shut down cleanly
+    let _ = fs::remove_file(SOCKET_PATH);
+    let listener = UnixListener::bind(SOCKET_PATH)
+        .with_context(|| format!("Failed to bind {}", SOCKET_PATH))?;
+    // Root-owned, connectable by the control group; `serve` checks who is asking
+    let group = control_gid().filter(|gid| std::os::unix::fs::chown(SOCKET_PATH, None, Some(*gid)).is_ok());
+    let mode = if group.is_some() { 0o660 } else {
+        debug!("No {} group, control socket open to all local users", CONTROL_GROUP);
+        0o666
+    };
+    fs::set_permissions(SOCKET_PATH, fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+/// Bind the socket (or take systemd's) and hand every request to `tx` (accepting runs
+/// in the background)
+pub fn listen(tx: mpsc::Sender<Pending>) -> Result<()> {
+    let listener = bind()?;
+    let slots = Arc::new(Semaphore::new(MAX_CLIENTS));
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((mut stream, _)) => match Arc::clone(&slots).try_acquire_owned() {
+                    Ok(slot) => {
+                        let tx = tx.clone();
+                        tokio::spawn(async move {
+                            serve(stream, tx).await;
+                            drop(slot);
+                        });
+                    }
+                    Err(_) => {
+                        let _ = stream.write_all(b"{\"ok\":false,\"error\":\"too many clients\"}\n").await;
+                    }
+                },
+                Err(e) => {
+                    // EMFILE and friends pass; back off instead of spinning
+                    warn!("Control socket accept failed: {}", e);
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Remove the socket file (daemon shutdown); systemd's stays for the next start
+pub fn remove() {
+    if !socket_activated() {
+        let _ = fs::remove_file(SOCKET_PATH);
+    }
+}
+
+/// One client connection: a response line for every request line
+async fn serve(stream: UnixStream, tx: mpsc::Sender<Pending>) {
+    let root = stream.peer_cred().is_ok_and(|cred| cred.uid() == 0);
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let mut capped = (&mut reader).take(MAX_LINE + 1);
+        match tokio::time::timeout(IDLE_TIMEOUT, capped.read_line(&mut line)).await {
+            Ok(Ok(0)) | Ok(Err(_)) | Err(_) => return,
+            Ok(Ok(_)) => {}
+        }
+        if line.len() as u64 > MAX_LINE && !line.ends_with('\n') {
+            let _ = write.write_all(b"{\"ok\":false,\"error\":\"request too long\"}\n").await;
+            return;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) if !root && !request.unprivileged() => {
+                Response::error("permission denied (run with sudo)")
+            }
+            Ok(request) => {
+                debug!("Control request: {:?}", request);
+                let (reply_tx, reply_rx) = oneshot::channel();
+                if tx.send((request, reply_tx)).await.is_err() {
+                    Response::error("daemon is shutting down")
+                } else {
+                    reply_rx.await.unwrap_or_else(|_| Response::error("daemon dropped the request"))
+                }
+            }
+            Err(e) => Response::error(format!("bad request: {}", e)),
+        };
+        let mut out = serde_json::to_string(&response).unwrap_or_default();
+        out.push('\n');
+        if write.write_all(out.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Send one request to the running daemon and wait for its answer
+pub async fn send(request: &Request) -> Result<Response> {
+    let stream = UnixStream::connect(SOCKET_PATH).await
+        .with_context(|| format!("Cannot connect to {} (is the service running?)", SOCKET_PATH))?;
+    let (read, mut write) = stream.into_split();
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    write.write_all(line.as_bytes()).await?;
+    let reply = BufReader::new(read).lines().next_line().await?
+        .context("The daemon closed the connection without answering")?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_format() {
+        let request: Request = serde_json::from_str(r#"{"cmd":"set-option","key":"cake_mode","value":"never"}"#).unwrap();
+        assert_eq!(request, Request::SetOption { key: "cake_mode".to_string(), value: serde_json::json!("never") });
+        assert_eq!(serde_json::to_string(&Request::GameMode { mode: GameModeSwitch::Off, for_secs: None }).unwrap(),
+                   r#"{"cmd":"game-mode","mode":"off"}"#);
+        let pinned: Request = serde_json::from_str(r#"{"cmd":"game-mode","mode":"on","for_secs":7200}"#).unwrap();
+        assert!(pinned.unprivileged() && !Request::Reapply.unprivileged());
+        assert_eq!(serde_json::to_string(&Response::ok(None)).unwrap(), r#"{"ok":true}"#);
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"reboot"}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_group() {
+        let groups = "root:x:0:\nwheel:x:998:deck\nhifi-wifi-ctl:x:961:deck,hifi-wifi\n";
+        assert_eq!(parse_group(groups, CONTROL_GROUP), Some(961));
+        assert_eq!(parse_group(groups, "hifi-wifi"), None);
+    }
+
+    #[test]
+    fn test_passed_fds() {
+        assert!(passed_fds(Some("42"), Some("1"), 42));
+        assert!(!passed_fds(Some("41"), Some("1"), 42));
+        assert!(!passed_fds(Some("42"), Some("0"), 42));
+        assert!(!passed_fds(None, None, 42));
+    }
+}
@@ -0,0 +1,152 @@
+//! Crash reports
+//!
+//! `monitor` installs a panic hook that writes the panic message, a backtrace, the
+//! last events and the config file (secrets blanked) to
+//! /var/lib/hifi-wifi/crash/<unix time>.json before the process dies. `status` counts
+//! recent reports and `doctor` points at the latest one for bug reports.
+
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::network::usage::unix_now;
+use crate::utils::events::{self, Event};
+
+pub const CRASH_DIR: &str = "/var/lib/hifi-wifi/crash";
+
+/// Reports kept
+const MAX_REPORTS: usize = 20;
+/// Events included in a report
+const RECENT_EVENTS: usize = 50;
+/// What `status` counts as recent
+pub const RECENT_SECS: u64 = 7 * 86_400;
+/// Config keys whose values stay out of reports
+const SECRET_KEYS: &[&str] = &["password", "username", "url"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub ts: u64,
+    pub version: String,
+    pub thread: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub events: Vec<Event>,
+    /// config.toml as loaded at startup (None without one)
+    pub config: Option<toml::Value>,
+}
+
+fn redact(value: &mut toml::Value) {
+    if let toml::Value::Table(table) = value {
+        for (key, value) in table.iter_mut() {
+            if SECRET_KEYS.contains(&key.as_str()) {
+                *value = toml::Value::String("<redacted>".to_string());
+            } else {
+                redact(value);
+            }
+        }
+    }
+}
+
+/// The config file with secrets blanked
+fn config_snapshot(path: &Path) -> Option<toml::Value> {
+    let mut config: toml::Value = toml::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    redact(&mut config);
+    Some(config)
+}
+
+/// Report files, oldest first
+fn reports(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir).into_iter().flatten()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    // Unix-time names of equal length sort chronologically
+    files.sort();
+    files
+}
+
+fn report_ts(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.json", report.ts));
+    fs::write(&path, serde_json::to_string_pretty(report).unwrap_or_default())?;
+    let files = reports(dir);
+    for old in files.iter().take(files.len().saturating_sub(MAX_REPORTS)) {
+        let _ = fs::remove_file(old);
+    }
+    Ok(path)
+}
+
+/// Write a report on panic, then let the default hook print the message as usual
+pub fn install_panic_hook(config_path: &Path) {
+    let config = config_snapshot(config_path);
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(non-string panic payload)".to_string());
+        let report = CrashReport {
+            ts: unix_now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+            message,
+            location: info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            backtrace: Backtrace::force_capture().to_string(),
+            events: events::read_recent(RECENT_EVENTS),
+            config: config.clone(),
+        };
+        match write_report(Path::new(CRASH_DIR), &report) {
+            Ok(path) => eprintln!("hifi-wifi crashed; report written to {}", path.display()),
+            Err(e) => eprintln!("hifi-wifi crashed; could not write a report to {}: {}", CRASH_DIR, e),
+        }
+        default_hook(info);
+    }));
+}
+
+/// (reports from the last `RECENT_SECS`, path of the newest report)
+pub fn recent() -> (usize, Option<PathBuf>) {
+    let files = reports(Path::new(CRASH_DIR));
+    let since = unix_now().saturating_sub(RECENT_SECS);
+    let count = files.iter().filter(|p| report_ts(p).is_some_and(|ts| ts >= since)).count();
+    (count, files.last().cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_rotate() {
+        let mut config: toml::Value = toml::from_str(
+            "[notify.mqtt]\nbroker = \"10.0.0.2\"\npassword = \"hunter2\"\n[notify.webhook]\nurl = \"https://x/token\"\n",
+        ).unwrap();
+        redact(&mut config);
+        assert_eq!(config["notify"]["mqtt"]["broker"].as_str(), Some("10.0.0.2"));
+        assert_eq!(config["notify"]["mqtt"]["password"].as_str(), Some("<redacted>"));
+        assert_eq!(config["notify"]["webhook"]["url"].as_str(), Some("<redacted>"));
+
+        let dir = std::env::temp_dir().join(format!("hifi-wifi-crash-{}", std::process::id()));
+        for ts in 0..MAX_REPORTS as u64 + 2 {
+            let report = CrashReport {
+                ts: 1_792_189_800 + ts,
+                version: String::new(),
+                thread: "main".to_string(),
+                message: "boom".to_string(),
+                location: None,
+                backtrace: String::new(),
+                events: Vec::new(),
+                config: None,
+            };
+            write_report(&dir, &report).unwrap();
+        }
+        let files = reports(&dir);
+        assert_eq!(files.len(), MAX_REPORTS);
+        assert_eq!(files.last().and_then(|p| report_ts(p)), Some(1_792_189_800 + MAX_REPORTS as u64 + 1));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,77 @@
+//! One daemon at a time
+//!
+//! `monitor` holds an exclusive flock on /run/hifi-wifi/monitor.pid while it runs.
+//! One-shot `apply`/`revert` look at the lock so they don't fight the running daemon
+//! over qdiscs and power save, and a second `monitor` refuses to start.
+//!
+//! A clean shutdown empties the file, so a PID still in it at startup means the last
+//! daemon died (crash, panic, OOM kill) and systemd restarted it.
+
+use anyhow::Result;
+use nix::errno::Errno;
+use nix::fcntl::{Flock, FlockArg};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+
+use crate::error::HifiError;
+
+pub const PID_PATH: &str = "/run/hifi-wifi/monitor.pid";
+
+/// Held for the daemon's lifetime; the kernel drops the lock when the process exits
+pub struct DaemonLock {
+    lock: Flock<File>,
+    /// PID of a daemon that exited without `release` (this boot, as /run is a tmpfs)
+    pub unclean_exit: Option<u32>,
+}
+
+impl DaemonLock {
+    /// Mark the shutdown as clean
+    pub fn release(self) {
+        let _ = self.lock.set_len(0);
+    }
+}
+
+pub fn parse_pid(content: &str) -> Option<u32> {
+    content.trim().parse().ok()
+}
+
+fn read_pid() -> u32 {
+    fs::read_to_string(PID_PATH).ok().and_then(|c| parse_pid(&c)).unwrap_or(0)
+}
+
+/// Take the lock for this process; fails with `DaemonRunning` if another daemon holds it
+pub fn acquire() -> Result<DaemonLock> {
+    fs::create_dir_all("/run/hifi-wifi")?;
+    let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(PID_PATH)?;
+    let mut lock = match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+        Ok(lock) => lock,
+        Err((_, Errno::EWOULDBLOCK)) => return Err(HifiError::DaemonRunning(read_pid()).into()),
+        Err((_, errno)) => return Err(errno.into()),
+    };
+    let unclean_exit = read_pid();
+    lock.set_len(0)?;
+    writeln!(lock, "{}", std::process::id())?;
+    Ok(DaemonLock { lock, unclean_exit: (unclean_exit != 0).then_some(unclean_exit) })
+}
+
+/// PID of the running daemon, if there is one
+pub fn holder() -> Option<u32> {
+    let file = File::open(PID_PATH).ok()?;
+    match Flock::lock(file, FlockArg::LockSharedNonblock) {
+        // Got it, so nobody holds it (dropping releases it again)
+        Ok(_) => None,
+        Err((_, Errno::EWOULDBLOCK)) => Some(read_pid()),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pid() {
+        assert_eq!(parse_pid("1234\n"), Some(1234));
+        assert_eq!(parse_pid(""), None);
+    }
+}
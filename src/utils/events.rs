@@ -0,0 +1,108 @@
+//! Event Log
+//!
+//! Notable governor events (latency spikes, roams, ...) are appended as JSON lines to
+//! /var/lib/hifi-wifi/events.jsonl so they can be reviewed after the fact. The file
+//! is rotated to events.jsonl.1 once it grows past MAX_LOG_BYTES. The daemon also
+//! hands each event to the `[notify]` targets.
+
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::utils::notify;
+
+/// Location of the event log (survives SteamOS updates)
+pub const EVENTS_PATH: &str = "/var/lib/hifi-wifi/events.jsonl";
+
+/// Rotate once the log exceeds this size
+const MAX_LOG_BYTES: u64 = 512 * 1024;
+
+/// One logged event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Unix time in seconds
+    pub ts: u64,
+    /// Event kind, e.g. "latency_spike"
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
+    /// Kind-specific fields
+    #[serde(flatten)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Event {
+    pub fn new(kind: &str, interface: Option<&str>, data: serde_json::Value) -> Self {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let data = match data {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        Self { ts, kind: kind.to_string(), interface: interface.map(str::to_string), data }
+    }
+}
+
+/// Append an event to the log; failures are logged at debug and otherwise ignored
+pub fn record(kind: &str, interface: Option<&str>, data: serde_json::Value) {
+    let event = Event::new(kind, interface, data);
+    if let Err(e) = append_to(Path::new(EVENTS_PATH), &event) {
+        debug!("Failed to record {} event: {}", kind, e);
+    }
+    notify::dispatch(&event);
+}
+
+/// Most recent `limit` events, oldest first
+pub fn read_recent(limit: usize) -> Vec<Event> {
+    read_from(Path::new(EVENTS_PATH), limit)
+}
+
+fn append_to(path: &Path, event: &Event) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::metadata(path).map(|m| m.len() > MAX_LOG_BYTES).unwrap_or(false) {
+        fs::rename(path, path.with_extension("jsonl.1"))?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(event)?)?;
+    Ok(())
+}
+
+fn read_from(path: &Path, limit: usize) -> Vec<Event> {
+    let rotated = fs::read_to_string(path.with_extension("jsonl.1")).unwrap_or_default();
+    let current = fs::read_to_string(path).unwrap_or_default();
+    let events: Vec<Event> = rotated.lines()
+        .chain(current.lines())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = events.len().saturating_sub(limit);
+    events.into_iter().skip(skip).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_read_recent() {
+        let dir = std::env::temp_dir().join(format!("hifi-wifi-events-{}", std::process::id()));
+        let path = dir.join("events.jsonl");
+        for i in 0..3 {
+            let event = Event::new("latency_spike", Some("wlan0"), serde_json::json!({ "peak_ms": 100 + i }));
+            append_to(&path, &event).unwrap();
+        }
+        let recent = read_from(&path, 2);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1].kind, "latency_spike");
+        assert_eq!(recent[1].interface.as_deref(), Some("wlan0"));
+        assert_eq!(recent[1].data["peak_ms"], 102);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
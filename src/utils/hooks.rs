@@ -0,0 +1,144 @@
+//! User hook scripts
+//!
+//! `[hooks]` lists executables in /etc/hifi-wifi/hooks.d to run at a few points (after
+//! apply, before revert, game mode start, roam, reconnect), with the event's details in
+//! `HIFI_WIFI_*` environment variables. They run as root, so only root-owned scripts
+//! that nobody else can write are accepted, and each gets 10 seconds.
+
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::config::structs::HooksConfig;
+
+pub const HOOKS_DIR: &str = "/etc/hifi-wifi/hooks.d";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hook {
+    PostApply,
+    PreRevert,
+    GameModeStart,
+    Roam,
+    Reconnect,
+}
+
+impl Hook {
+    /// Config key, also passed to scripts as HIFI_WIFI_HOOK
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PostApply => "post_apply",
+            Hook::PreRevert => "pre_revert",
+            Hook::GameModeStart => "on_game_mode_start",
+            Hook::Roam => "on_roam",
+            Hook::Reconnect => "on_reconnect",
+        }
+    }
+
+    pub fn scripts(self, config: &HooksConfig) -> &[String] {
+        match self {
+            Hook::PostApply => &config.post_apply,
+            Hook::PreRevert => &config.pre_revert,
+            Hook::GameModeStart => &config.on_game_mode_start,
+            Hook::Roam => &config.on_roam,
+            Hook::Reconnect => &config.on_reconnect,
+        }
+    }
+}
+
+/// Plain file names only, so a config entry can't point outside HOOKS_DIR
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains('/')
+}
+
+/// The script for a configured name, if it is safe to run as root
+fn resolve(name: &str) -> Result<PathBuf> {
+    if !valid_name(name) {
+        bail!("'{}' must be a file name in {}", name, HOOKS_DIR);
+    }
+    let path = Path::new(HOOKS_DIR).join(name);
+    let meta = fs::metadata(&path).with_context(|| format!("{} not found", path.display()))?;
+    if !meta.is_file() || meta.permissions().mode() & 0o111 == 0 {
+        bail!("{} is not an executable file", path.display());
+    }
+    if meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+        bail!("{} must be owned by root and not group/world-writable", path.display());
+    }
+    Ok(path)
+}
+
+/// Run the hook's scripts in order (blocking, up to 10s each); failures are only logged
+pub fn run(config: &HooksConfig, hook: Hook, env: &[(&str, String)]) {
+    for name in hook.scripts(config) {
+        let path = match resolve(name) {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("Skipping {} hook: {}", hook.name(), e);
+                continue;
+            }
+        };
+        info!(event = "hook", hook = hook.name(), script = name.as_str(); "Running {} hook {}", hook.name(), path.display());
+        let child = Command::new(&path)
+            .env("HIFI_WIFI_HOOK", hook.name())
+            .envs(env.iter().map(|(key, value)| (*key, value)))
+            .stdin(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("Failed to run {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => break,
+                Ok(Some(status)) => {
+                    warn!("Hook {} exited with {}", path.display(), status);
+                    break;
+                }
+                Ok(None) if started.elapsed() >= TIMEOUT => {
+                    warn!("Hook {} still running after {}s, killing it", path.display(), TIMEOUT.as_secs());
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break;
+                }
+                Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+                Err(e) => {
+                    warn!("Failed to wait for {}: {}", path.display(), e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// `run` on a background thread, for the daemon's tick
+pub fn spawn(config: &HooksConfig, hook: Hook, env: Vec<(&'static str, String)>) {
+    if hook.scripts(config).is_empty() {
+        return;
+    }
+    let config = config.clone();
+    std::thread::spawn(move || run(&config, hook, &env));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_names() {
+        assert!(valid_name("rgb-indicator.sh"));
+        assert!(!valid_name("../../usr/bin/evil"));
+        assert!(!valid_name("sub/script"));
+        assert!(!valid_name(".hidden"));
+        let config = HooksConfig { on_roam: vec!["notify".to_string()], ..Default::default() };
+        assert_eq!(Hook::Roam.scripts(&config), ["notify".to_string()]);
+        assert!(Hook::PostApply.scripts(&config).is_empty());
+    }
+}
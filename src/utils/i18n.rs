@@ -0,0 +1,346 @@
+//! Minimal message catalog for user-facing output
+//!
+//! Language comes from `[global] language` in config, else LC_ALL / LC_MESSAGES / LANG.
+//! Missing translations fall back to English. Templates use `{}` placeholders filled
+//! in order by `tf`.
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Supported output languages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    Fr,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+const EN: &[(&str, &str)] = &[
+    ("error.not_root", "This application must be run as root."),
+    ("error.try_sudo", "Try: sudo hifi-wifi"),
+    ("error.missing_caps", "This command needs root or these capabilities: {}"),
+    ("error.daemon_running", "The hifi-wifi service is running and already applies these settings. Restart it with `sudo systemctl restart hifi-wifi`, stop it with `sudo hifi-wifi off`, or pass --force."),
+    ("install.title", "=== Installing hifi-wifi Service ==="),
+    ("install.complete", "=== Installation Complete ==="),
+    ("install.started", "Service installed and started."),
+    ("uninstall.title", "=== Uninstalling hifi-wifi Service ==="),
+    ("uninstall.complete", "=== Uninstallation Complete ==="),
+    ("uninstall.purged", "Purge complete: no hifi-wifi files left"),
+    ("uninstall.leftover", "Could not remove: {}"),
+    ("off.title", "=== Turning OFF hifi-wifi ==="),
+    ("off.done", "=== hifi-wifi is OFF ==="),
+    ("off.defaults", "Network is now using default settings."),
+    ("off.hint", "To turn back on: sudo hifi-wifi on"),
+    ("on.title", "=== Turning ON hifi-wifi ==="),
+    ("on.done", "=== hifi-wifi is ON ==="),
+    ("on.active", "Network optimizations are active."),
+    ("on.hint", "Check status: hifi-wifi status"),
+    ("on.not_installed", "hifi-wifi service not installed. Run: sudo hifi-wifi install"),
+    ("status.title", "hifi-wifi v3.0 Status"),
+    ("status.status", "Status:"),
+    ("status.active", "[ACTIVE]"),
+    ("status.inactive", "[INACTIVE]"),
+    ("status.crashes", "Crashes:"),
+    ("status.crash_detail", "daemon crashed {} time(s) in the last 7 days (latest: {})"),
+    ("status.last_repair", "Last repair:"),
+    ("status.repair_detail", "{} {} (by {})"),
+    ("status.repair_failed", "failed: {}"),
+    ("status.system_info", "System Info"),
+    ("status.device", "Device:"),
+    ("status.power", "Power:"),
+    ("status.daemons", "Daemons:"),
+    ("status.coordinated", "coordinated"),
+    ("status.conflict", "conflict"),
+    ("status.battery", "Battery: {}"),
+    ("status.interfaces", "Interfaces & Tweaks"),
+    ("status.no_interfaces", "No network interfaces detected"),
+    ("status.interface_desc", "(Type: {}, Driver: {}, {})"),
+    ("status.mode_hotspot", "[hotspot]"),
+    ("status.mode_p2p", "[P2P, not optimized]"),
+    ("status.cake", "CAKE:"),
+    ("status.bandwidth", "Bandwidth: {}"),
+    ("status.power_save", "Power Save:"),
+    ("status.on", "[ON]"),
+    ("status.off", "[OFF]"),
+    ("status.power_saving", "(Power Saving)"),
+    ("status.performance", "(Performance)"),
+    ("status.eee", "EEE:"),
+    ("status.aqm", "AQM:"),
+    ("status.queue", "Queue:"),
+    ("status.mlo", "MLO:"),
+    ("status.aqm_values", "fq_codel limit {}, quantum {}"),
+    ("status.enabled_tag", "[ENABLED]"),
+    ("status.disabled_tag", "[DISABLED]"),
+    ("status.low_latency", "(Low Latency)"),
+    ("status.na", "[N/A]"),
+    ("status.not_supported", "(Not Supported)"),
+    ("status.unknown", "[UNKNOWN]"),
+    ("status.usb_device", "(USB Device)"),
+    ("status.irq_pin", "IRQ Pin:"),
+    ("status.optimized", "[OPTIMIZED]"),
+    ("status.cpu1", "(CPU 1)"),
+    ("status.cpu1_vectors", "(CPU 1, {} vectors)"),
+    ("status.default_tag", "[DEFAULT]"),
+    ("status.system_managed", "(System Managed)"),
+    ("status.partial", "[PARTIAL]"),
+    ("status.pinned", "({}/{} pinned)"),
+    ("status.not_found", "[NOT FOUND]"),
+    ("status.governor_backend", "Network Governor & Backend"),
+    ("status.backend", "Backend:"),
+    ("status.governor", "Governor:"),
+    ("status.running", "Running"),
+    ("status.stopped", "Stopped"),
+    ("status.qos_mode", "QoS Mode:"),
+    ("status.breathing_cake", "Breathing CAKE (Dynamic)"),
+    ("status.static_cake", "Static CAKE"),
+    ("status.cake_game_only", "(game mode only)"),
+    ("status.cake_never", "Off (cake_mode = never)"),
+    ("status.game_mode", "Game Mode:"),
+    ("status.game_available", "Available (PPS > {})"),
+    ("status.game_forced_on", "Forced ON"),
+    ("status.game_forced_off", "Forced OFF"),
+    ("status.game_forced_left", "{}m left"),
+    ("status.band_steer", "Band Steer:"),
+    ("status.available", "Available"),
+    ("status.disabled", "Disabled"),
+    ("status.active_connection", "Active Connection (NetworkManager)"),
+    ("status.bssid", "BSSID:"),
+    ("status.band", "Band:"),
+    ("status.channel", "{} (Ch {} @ {} MHz)"),
+    ("status.channel_width", "{} (Ch {} @ {} MHz, {} MHz wide)"),
+    ("status.signal", "Signal:"),
+    ("status.link", "Link:"),
+    ("status.score", "Score:"),
+    ("status.for_band_steering", "(for band steering)"),
+    ("status.excellent", "Excellent"),
+    ("status.good", "Good"),
+    ("status.fair", "Fair"),
+    ("status.poor", "Poor"),
+    ("status.type", "Type:"),
+    ("status.wired_ethernet", "Wired Ethernet"),
+    ("status.speed", "Speed:"),
+    ("status.latency", "Latency:"),
+    ("status.gateway", "Gateway:"),
+    ("status.dns", "DNS:"),
+    ("status.dns_slow", "(slow)"),
+    ("status.dns_down", "not answering"),
+    ("status.bloat", "Bloat:"),
+    ("status.bloat_detail", "(+{}ms under load, {}% CAKE drops)"),
+    ("status.bloat_trend", "past sessions: {}"),
+    ("status.roams", "Roams:"),
+    ("status.uptime", "Uptime:"),
+    ("status.uptime_detail", "{} on this AP, {} reconnect(s) today"),
+    ("status.trend", "Trend:"),
+    ("status.trend_detail", "{} {} to {} dBm, last {} min"),
+    ("status.roam_detail", "{} in 24h, last {} min ago"),
+    ("status.roam_damped", "(flap damping)"),
+    ("status.ultra_low", "Ultra-low"),
+    ("status.wired", "(wired)"),
+    ("status.no_latency_data", "(no data - service not running)"),
+    ("status.latency_detail", "(p50 {}ms, p95 {}ms, p99 {}ms, jitter {}ms, loss {}%)"),
+    ("status.spikes", "{} spikes in 10 min"),
+    ("status.last_spike", "(last {}s ago)"),
+    ("status.no_connection", "No active connection found"),
+    ("status.nm_error", "Error querying NetworkManager"),
+];
+
+const FR: &[(&str, &str)] = &[
+    ("error.not_root", "Cette application doit être exécutée en tant que root."),
+    ("error.try_sudo", "Essayez : sudo hifi-wifi"),
+    ("error.missing_caps", "Cette commande nécessite root ou ces capacités : {}"),
+    ("error.daemon_running", "Le service hifi-wifi est actif et applique déjà ces réglages. Redémarrez-le avec `sudo systemctl restart hifi-wifi`, arrêtez-le avec `sudo hifi-wifi off`, ou ajoutez --force."),
+    ("install.title", "=== Installation du service hifi-wifi ==="),
+    ("install.complete", "=== Installation terminée ==="),
+    ("install.started", "Service installé et démarré."),
+    ("uninstall.title", "=== Désinstallation du service hifi-wifi ==="),
+    ("uninstall.complete", "=== Désinstallation terminée ==="),
+    ("uninstall.purged", "Purge terminée : plus aucun fichier hifi-wifi"),
+    ("uninstall.leftover", "Impossible de supprimer : {}"),
+    ("off.title", "=== Désactivation de hifi-wifi ==="),
+    ("off.done", "=== hifi-wifi est DÉSACTIVÉ ==="),
+    ("off.defaults", "Le réseau utilise maintenant les réglages par défaut."),
+    ("off.hint", "Pour réactiver : sudo hifi-wifi on"),
+    ("on.title", "=== Activation de hifi-wifi ==="),
+    ("on.done", "=== hifi-wifi est ACTIVÉ ==="),
+    ("on.active", "Les optimisations réseau sont actives."),
+    ("on.hint", "Vérifier l'état : hifi-wifi status"),
+    ("on.not_installed", "Le service hifi-wifi n'est pas installé. Lancez : sudo hifi-wifi install"),
+    ("status.title", "État de hifi-wifi v3.0"),
+    ("status.status", "État :"),
+    ("status.active", "[ACTIF]"),
+    ("status.inactive", "[INACTIF]"),
+    ("status.crashes", "Plantages :"),
+    ("status.crash_detail", "le démon a planté {} fois ces 7 derniers jours (dernier : {})"),
+    ("status.last_repair", "Réparation :"),
+    ("status.repair_detail", "{} {} (par {})"),
+    ("status.repair_failed", "échec : {}"),
+    ("status.system_info", "Informations système"),
+    ("status.device", "Appareil :"),
+    ("status.power", "Alimentation :"),
+    ("status.daemons", "Démons :"),
+    ("status.coordinated", "coordonné"),
+    ("status.conflict", "conflit"),
+    ("status.battery", "Batterie : {}"),
+    ("status.interfaces", "Interfaces et réglages"),
+    ("status.no_interfaces", "Aucune interface réseau détectée"),
+    ("status.interface_desc", "(Type : {}, pilote : {}, {})"),
+    ("status.mode_hotspot", "[point d'accès]"),
+    ("status.mode_p2p", "[P2P, non optimisé]"),
+    ("status.cake", "CAKE :"),
+    ("status.bandwidth", "Bande passante : {}"),
+    ("status.power_save", "Économie :"),
+    ("status.on", "[ACTIVÉE]"),
+    ("status.off", "[DÉSACTIVÉE]"),
+    ("status.power_saving", "(Économie d'énergie)"),
+    ("status.performance", "(Performances)"),
+    ("status.eee", "EEE :"),
+    ("status.aqm", "AQM :"),
+    ("status.queue", "File :"),
+    ("status.mlo", "MLO :"),
+    ("status.aqm_values", "fq_codel limite {}, quantum {}"),
+    ("status.enabled_tag", "[ACTIVÉ]"),
+    ("status.disabled_tag", "[DÉSACTIVÉ]"),
+    ("status.low_latency", "(Faible latence)"),
+    ("status.na", "[N/D]"),
+    ("status.not_supported", "(Non pris en charge)"),
+    ("status.unknown", "[INCONNU]"),
+    ("status.usb_device", "(Périphérique USB)"),
+    ("status.irq_pin", "IRQ :"),
+    ("status.optimized", "[OPTIMISÉ]"),
+    ("status.cpu1", "(CPU 1)"),
+    ("status.cpu1_vectors", "(CPU 1, {} vecteurs)"),
+    ("status.default_tag", "[PAR DÉFAUT]"),
+    ("status.system_managed", "(Géré par le système)"),
+    ("status.partial", "[PARTIEL]"),
+    ("status.pinned", "({}/{} épinglées)"),
+    ("status.not_found", "[INTROUVABLE]"),
+    ("status.governor_backend", "Gouverneur réseau et backend"),
+    ("status.backend", "Backend :"),
+    ("status.governor", "Gouverneur :"),
+    ("status.running", "En cours"),
+    ("status.stopped", "Arrêté"),
+    ("status.qos_mode", "Mode QoS :"),
+    ("status.breathing_cake", "Breathing CAKE (dynamique)"),
+    ("status.static_cake", "CAKE statique"),
+    ("status.cake_game_only", "(mode jeu uniquement)"),
+    ("status.cake_never", "Désactivé (cake_mode = never)"),
+    ("status.game_mode", "Mode jeu :"),
+    ("status.game_available", "Disponible (PPS > {})"),
+    ("status.game_forced_on", "Forcé ACTIVÉ"),
+    ("status.game_forced_off", "Forcé DÉSACTIVÉ"),
+    ("status.game_forced_left", "encore {} min"),
+    ("status.band_steer", "Bande :"),
+    ("status.available", "Disponible"),
+    ("status.disabled", "Désactivé"),
+    ("status.active_connection", "Connexion active (NetworkManager)"),
+    ("status.bssid", "BSSID :"),
+    ("status.band", "Bande :"),
+    ("status.channel", "{} (canal {} @ {} MHz)"),
+    ("status.channel_width", "{} (canal {} @ {} MHz, largeur {} MHz)"),
+    ("status.signal", "Signal :"),
+    ("status.link", "Lien :"),
+    ("status.score", "Score :"),
+    ("status.for_band_steering", "(pour le choix de bande)"),
+    ("status.excellent", "Excellent"),
+    ("status.good", "Bon"),
+    ("status.fair", "Moyen"),
+    ("status.poor", "Faible"),
+    ("status.type", "Type :"),
+    ("status.wired_ethernet", "Ethernet filaire"),
+    ("status.speed", "Débit :"),
+    ("status.latency", "Latence :"),
+    ("status.gateway", "Passerelle :"),
+    ("status.dns", "DNS :"),
+    ("status.dns_slow", "(lent)"),
+    ("status.dns_down", "ne répond pas"),
+    ("status.bloat", "Bloat :"),
+    ("status.bloat_detail", "(+{}ms en charge, {}% de pertes CAKE)"),
+    ("status.bloat_trend", "sessions précédentes : {}"),
+    ("status.roams", "Roaming :"),
+    ("status.uptime", "Connecté :"),
+    ("status.uptime_detail", "{} sur cet AP, {} reconnexion(s) aujourd'hui"),
+    ("status.trend", "Tendance :"),
+    ("status.trend_detail", "{} {} à {} dBm, {} dernières min"),
+    ("status.roam_detail", "{} en 24 h, dernier il y a {} min"),
+    ("status.roam_damped", "(anti-oscillation)"),
+    ("status.ultra_low", "Très faible"),
+    ("status.wired", "(filaire)"),
+    ("status.no_latency_data", "(pas de données - service arrêté)"),
+    ("status.latency_detail", "(p50 {} ms, p95 {} ms, p99 {} ms, gigue {} ms, perte {} %)"),
+    ("status.spikes", "{} pics en 10 min"),
+    ("status.last_spike", "(dernier il y a {} s)"),
+    ("status.no_connection", "Aucune connexion active"),
+    ("status.nm_error", "Erreur lors de l'interrogation de NetworkManager"),
+];
+
+/// Pick the output language: explicit config value first, then the locale environment
+pub fn init(configured: Option<&str>) {
+    let from_env = || {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|v| !v.is_empty())
+    };
+    let tag = configured.map(str::to_string).or_else(from_env).unwrap_or_default();
+    let _ = LANG.set(parse_lang(&tag));
+}
+
+fn parse_lang(tag: &str) -> Lang {
+    if tag.to_ascii_lowercase().starts_with("fr") { Lang::Fr } else { Lang::En }
+}
+
+fn lang() -> Lang {
+    *LANG.get().unwrap_or(&Lang::En)
+}
+
+fn lookup(table: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+/// Translate a message key (falls back to English, then to the key itself)
+pub fn t(key: &str) -> &str {
+    let translated = match lang() {
+        Lang::Fr => lookup(FR, key),
+        Lang::En => None,
+    };
+    translated.or_else(|| lookup(EN, key)).unwrap_or(key)
+}
+
+/// Translate a template and fill its `{}` placeholders in order
+pub fn tf(key: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut parts = t(key).split("{}").peekable();
+    while let Some(part) = parts.next() {
+        out.push_str(part);
+        if parts.peek().is_some() {
+            if let Some(arg) = args.next() {
+                out.push_str(&arg.to_string());
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogs_consistent() {
+        for (key, fr) in FR {
+            let en = lookup(EN, key).unwrap_or_else(|| panic!("FR key {} missing from EN", key));
+            assert_eq!(en.matches("{}").count(), fr.matches("{}").count(), "placeholder mismatch for {}", key);
+        }
+    }
+
+    #[test]
+    fn test_parse_lang_and_fill() {
+        assert_eq!(parse_lang("fr_FR.UTF-8"), Lang::Fr);
+        assert_eq!(parse_lang("en_US.UTF-8"), Lang::En);
+        assert_eq!(parse_lang(""), Lang::En);
+        assert_eq!(tf("status.pinned", &[&3, &8]), "(3/8 pinned)");
+        assert_eq!(t("no.such.key"), "no.such.key");
+    }
+}
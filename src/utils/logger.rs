@@ -1,3 +1,141 @@
-pub fn init() {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+//! Logger setup
+//!
+//! Text output (default) is env_logger's usual format. JSON output emits one object per
+//! line with any structured key-values attached to the record (event, interface, old,
+//! new, reason...), so `journalctl -o json` pipelines can consume Governor decisions.
+
+use log::kv::{Key, Value, VisitSource};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Log output format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Short module names accepted in [logging.modules], mapped to log targets; any other
+/// name is tried as a module under each of `MODULE_PARENTS` and as a target of its own
+/// (another crate, like `zbus`)
+const MODULE_TARGETS: &[(&str, &str)] = &[
+    ("governor", "hifi_wifi::network::governor"),
+    ("band_steering", "hifi_wifi::band_steering"),
+    ("tc", "hifi_wifi::network::tc"),
+    ("nm", "hifi_wifi::network::nm"),
+    ("wifi", "hifi_wifi::network::wifi"),
+    ("stats", "hifi_wifi::network::stats"),
+    ("state", "hifi_wifi::network::state"),
+    ("backend", "hifi_wifi::network::backend_tuner"),
+    ("optimizer", "hifi_wifi::system::optimizer"),
+    ("power", "hifi_wifi::system::power"),
+    ("cpu", "hifi_wifi::system::cpu"),
+];
+
+const MODULE_PARENTS: &[&str] = &["hifi_wifi::network", "hifi_wifi::system", "hifi_wifi::utils"];
+
+const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Build an env_logger filter string from the base level, per-module overrides
+/// and the CLI verbosity (-v = +1, -q = -1)
+pub fn build_filter(level: &str, modules: &HashMap<String, String>, verbosity: i8) -> String {
+    let base = LEVELS.iter().position(|l| l.eq_ignore_ascii_case(level)).unwrap_or(2) as i8;
+    let effective = (base + verbosity).clamp(0, LEVELS.len() as i8 - 1) as usize;
+
+    let mut filter = LEVELS[effective].to_string();
+    let mut overrides: Vec<_> = modules.iter().collect();
+    overrides.sort();
+    for (module, module_level) in overrides {
+        match MODULE_TARGETS.iter().find(|(short, _)| short == module) {
+            Some((_, target)) => filter.push_str(&format!(",{}={}", target, module_level)),
+            None if module.contains("::") => filter.push_str(&format!(",{}={}", module, module_level)),
+            None => {
+                filter.push_str(&format!(",{}={}", module, module_level));
+                for parent in MODULE_PARENTS {
+                    filter.push_str(&format!(",{}::{}={}", parent, module, module_level));
+                }
+            }
+        }
+    }
+    filter
+}
+
+/// Initialise the global logger. `RUST_LOG`, when set, takes precedence over `filter`.
+pub fn init(format: LogFormat, filter: &str) {
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(filter));
+    if !super::style::enabled() {
+        builder.write_style(env_logger::WriteStyle::Never);
+    }
+
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let ts = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or(0.0);
+
+            let mut obj = serde_json::Map::new();
+            obj.insert("ts".into(), serde_json::json!((ts * 1000.0).round() / 1000.0));
+            obj.insert("level".into(), record.level().as_str().into());
+            obj.insert("module".into(), record.target().into());
+            obj.insert("msg".into(), record.args().to_string().into());
+
+            let mut fields = JsonFields(&mut obj);
+            let _ = record.key_values().visit(&mut fields);
+
+            writeln!(buf, "{}", serde_json::Value::Object(obj))
+        });
+    }
+
+    builder.init();
+}
+
+/// Collects structured key-values into the JSON record
+struct JsonFields<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let json = if let Some(v) = value.to_i64() {
+            serde_json::json!(v)
+        } else if let Some(v) = value.to_u64() {
+            serde_json::json!(v)
+        } else if let Some(v) = value.to_f64() {
+            serde_json::json!(v)
+        } else if let Some(v) = value.to_bool() {
+            serde_json::json!(v)
+        } else {
+            serde_json::json!(value.to_string())
+        };
+        self.0.insert(key.as_str().to_string(), json);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_filter() {
+        let mut modules = HashMap::new();
+        assert_eq!(build_filter("info", &modules, 0), "info");
+        assert_eq!(build_filter("info", &modules, 1), "debug");
+        assert_eq!(build_filter("info", &modules, -5), "error");
+        assert_eq!(build_filter("bogus", &modules, 0), "info");
+
+        modules.insert("governor".to_string(), "debug".to_string());
+        modules.insert("band_steering".to_string(), "warn".to_string());
+        modules.insert("zbus".to_string(), "error".to_string());
+        assert_eq!(
+            build_filter("warn", &modules, 0),
+            "warn,hifi_wifi::band_steering=warn,hifi_wifi::network::governor=debug,zbus=error,\
+             hifi_wifi::network::zbus=error,hifi_wifi::system::zbus=error,hifi_wifi::utils::zbus=error"
+        );
+        let modules = HashMap::from([("scan_abort".to_string(), "debug".to_string())]);
+        assert!(build_filter("info", &modules, 0).contains(",hifi_wifi::network::scan_abort=debug"));
+    }
 }
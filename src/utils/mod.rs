@@ -1,2 +1,11 @@
 pub mod logger;
 pub mod privilege;
+pub mod style;
+pub mod i18n;
+pub mod events;
+pub mod daemon_lock;
+pub mod control;
+pub mod crash;
+pub mod hooks;
+pub mod mqtt;
+pub mod notify;
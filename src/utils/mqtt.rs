@@ -0,0 +1,118 @@
+//! Minimal MQTT 3.1.1 publisher
+//!
+//! Just enough of the protocol for `[notify.mqtt]`: connect (optionally with a
+//! username/password), publish a few QoS 0 messages, disconnect. Events are rare, so a
+//! connection per batch is simpler than keeping one alive across broker restarts.
+
+use anyhow::{bail, Context, Result};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_PORT: u16 = 1883;
+
+/// One message to publish
+pub struct Message {
+    pub topic: String,
+    pub payload: String,
+    pub retain: bool,
+}
+
+/// Remaining-length varint of the fixed header
+fn encode_length(mut len: usize, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+fn encode_str(s: &str, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![header];
+    encode_length(body.len(), &mut out);
+    out.extend_from_slice(body);
+    out
+}
+
+fn connect_packet(client_id: &str, username: Option<&str>, password: Option<&str>) -> Vec<u8> {
+    let mut body = Vec::new();
+    encode_str("MQTT", &mut body);
+    body.push(4); // protocol level 3.1.1
+    let mut flags = 0x02; // clean session
+    if username.is_some() {
+        flags |= 0x80;
+    }
+    if password.is_some() {
+        flags |= 0x40;
+    }
+    body.push(flags);
+    body.extend_from_slice(&30u16.to_be_bytes()); // keepalive (s)
+    encode_str(client_id, &mut body);
+    for field in [username, password].into_iter().flatten() {
+        encode_str(field, &mut body);
+    }
+    packet(0x10, &body)
+}
+
+fn publish_packet(message: &Message) -> Vec<u8> {
+    let mut body = Vec::new();
+    encode_str(&message.topic, &mut body);
+    body.extend_from_slice(message.payload.as_bytes());
+    packet(0x30 | u8::from(message.retain), &body)
+}
+
+/// Connect to `broker` ("host" or "host:port"), publish `messages` at QoS 0, disconnect
+pub fn publish(broker: &str, client_id: &str, username: Option<&str>, password: Option<&str>,
+               messages: &[Message]) -> Result<()> {
+    let target = if broker.contains(':') { broker.to_string() } else { format!("{}:{}", broker, DEFAULT_PORT) };
+    let addr = target.to_socket_addrs()?.next().with_context(|| format!("can't resolve {}", target))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    stream.write_all(&connect_packet(client_id, username, password))?;
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack)?;
+    if connack[0] != 0x20 {
+        bail!("unexpected reply from {} (not an MQTT broker?)", target);
+    }
+    if connack[3] != 0 {
+        // 4 = bad username/password, 5 = not authorized
+        bail!("{} refused the connection (code {})", target, connack[3]);
+    }
+    for message in messages {
+        stream.write_all(&publish_packet(message))?;
+    }
+    stream.write_all(&[0xE0, 0x00])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packets() {
+        let mut len = Vec::new();
+        encode_length(321, &mut len);
+        assert_eq!(len, vec![0xC1, 0x02]);
+
+        let connect = connect_packet("id", Some("u"), None);
+        assert_eq!(connect, vec![0x10, 17, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x82, 0, 30, 0, 2, b'i', b'd', 0, 1, b'u']);
+
+        let publish = publish_packet(&Message { topic: "a/b".to_string(), payload: "on".to_string(), retain: true });
+        assert_eq!(publish, vec![0x31, 7, 0, 3, b'a', b'/', b'b', b'o', b'n']);
+    }
+}
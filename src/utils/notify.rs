@@ -0,0 +1,163 @@
+//! Event notifications
+//!
+//! The daemon passes every event it logs (see `events`) to the notifiers configured
+//! under `[notify]`. They run on one background thread, in order, so a slow or
+//! unreachable broker never holds up a tick.
+//!
+//! - `[notify.mqtt]`: each selected event is published as JSON to
+//!   `<topic_prefix>/event/<kind>`; game mode also keeps a retained `ON`/`OFF` state
+//!   at `<topic_prefix>/game_mode` for Home Assistant.
+//! - `[notify.webhook]`: each selected event is POSTed as JSON (via curl) with a
+//!   readable `text`/`content` line, which Slack/Discord-style webhooks show as is.
+
+use log::{debug, info, warn};
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Mutex, OnceLock};
+
+use crate::config::structs::{MqttConfig, NotifyConfig, WebhookConfig};
+use crate::utils::events::Event;
+use crate::utils::mqtt::{self, Message};
+
+static QUEUE: OnceLock<Mutex<Sender<Event>>> = OnceLock::new();
+
+pub fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Start the notifier thread (daemon only; a no-op when nothing is configured)
+pub fn init(config: &NotifyConfig) {
+    if config.mqtt.is_none() && config.webhook.is_none() {
+        return;
+    }
+    let (tx, rx) = channel::<Event>();
+    if QUEUE.set(Mutex::new(tx)).is_err() {
+        return;
+    }
+    let (mqtt, webhook) = (config.mqtt.clone(), config.webhook.clone());
+    let prefix = mqtt.as_ref()
+        .map(|m| m.topic_prefix.clone().unwrap_or_else(|| format!("hifi-wifi/{}", hostname())))
+        .unwrap_or_default();
+    if let Some(mqtt) = &mqtt {
+        info!("Publishing events ({}) to MQTT broker {} under {}/", mqtt.events.join(", "), mqtt.broker, prefix);
+    }
+    if let Some(webhook) = &webhook {
+        info!("Posting events ({}) to a webhook", webhook.events.join(", "));
+    }
+    let host = hostname();
+    std::thread::spawn(move || {
+        for event in rx {
+            if let Some(mqtt) = mqtt.as_ref().filter(|m| m.events.contains(&event.kind)) {
+                publish_mqtt(mqtt, &prefix, &event);
+            }
+            if let Some(webhook) = webhook.as_ref().filter(|w| w.events.contains(&event.kind)) {
+                post_webhook(webhook, &host, &event);
+            }
+        }
+    });
+}
+
+/// Hand an event to the notifier thread
+pub(crate) fn dispatch(event: &Event) {
+    if let Some(queue) = QUEUE.get() {
+        if let Ok(tx) = queue.lock() {
+            let _ = tx.send(event.clone());
+        }
+    }
+}
+
+fn mqtt_messages(prefix: &str, event: &Event) -> Vec<Message> {
+    let mut messages = vec![Message {
+        topic: format!("{}/event/{}", prefix, event.kind),
+        payload: serde_json::to_string(event).unwrap_or_default(),
+        retain: false,
+    }];
+    if let Some(active) = event.data.get("active").and_then(|v| v.as_bool()).filter(|_| event.kind == "game_mode") {
+        messages.push(Message {
+            topic: format!("{}/game_mode", prefix),
+            payload: if active { "ON" } else { "OFF" }.to_string(),
+            retain: true,
+        });
+    }
+    messages
+}
+
+fn publish_mqtt(config: &MqttConfig, prefix: &str, event: &Event) {
+    let client_id = format!("hifi-wifi-{}", std::process::id());
+    match mqtt::publish(&config.broker, &client_id, config.username.as_deref(), config.password.as_deref(),
+                        &mqtt_messages(prefix, event)) {
+        Ok(()) => debug!("Published {} event to {}", event.kind, config.broker),
+        Err(e) => warn!("Failed to publish {} event to MQTT broker {}: {}", event.kind, config.broker, e),
+    }
+}
+
+/// The JSON a webhook receives: the event plus the host and a one-line description
+fn webhook_payload(host: &str, event: &Event) -> serde_json::Value {
+    let mut text = format!("hifi-wifi on {}: {}", host, event.kind.replace('_', " "));
+    if let Some(interface) = &event.interface {
+        text.push_str(&format!(" on {}", interface));
+    }
+    if !event.data.is_empty() {
+        text.push_str(&format!(" {}", serde_json::Value::Object(event.data.clone())));
+    }
+    serde_json::json!({
+        "text": text,
+        "content": text,
+        "host": host,
+        "event": event,
+    })
+}
+
+fn post_webhook(config: &WebhookConfig, host: &str, event: &Event) {
+    let body = webhook_payload(host, event).to_string();
+    // URL on stdin too, so tokens in it don't show up in `ps`
+    let child = Command::new("curl")
+        .args(["-fsS", "-m", "10", "-X", "POST", "-H", "Content-Type: application/json", "--data-binary", &body, "-K", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to run curl for the {} webhook: {}", event.kind, e);
+            return;
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "url = \"{}\"", config.url.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+    match child.wait_with_output() {
+        Ok(output) if output.status.success() => debug!("Posted {} event to the webhook", event.kind),
+        Ok(output) => warn!("Webhook for {} event failed: {}", event.kind, String::from_utf8_lossy(&output.stderr).trim()),
+        Err(e) => warn!("Webhook for {} event failed: {}", event.kind, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads() {
+        let event = Event::new("game_mode", Some("wlan0"), serde_json::json!({ "active": true, "pps": 900 }));
+        let messages = mqtt_messages("hifi-wifi/deck", &event);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].topic, "hifi-wifi/deck/event/game_mode");
+        assert!(messages[0].payload.contains("\"pps\":900"));
+        assert_eq!((messages[1].topic.as_str(), messages[1].payload.as_str(), messages[1].retain),
+                   ("hifi-wifi/deck/game_mode", "ON", true));
+
+        let roam = Event::new("roam", Some("wlan0"), serde_json::json!({ "from": "a", "to": "b" }));
+        assert_eq!(mqtt_messages("p", &roam).len(), 1);
+
+        let loss = Event::new("sustained_loss", Some("wlan0"), serde_json::json!({ "loss_pct": 12.5 }));
+        let payload = webhook_payload("deck", &loss);
+        assert_eq!(payload["text"], "hifi-wifi on deck: sustained loss on wlan0 {\"loss_pct\":12.5}");
+        assert_eq!(payload["event"]["kind"], "sustained_loss");
+    }
+}
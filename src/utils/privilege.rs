@@ -1,5 +1,96 @@
+//! Privilege checks
+//!
+//! Most runtime work (tc, iw, sysctl under /proc/sys/net, nftables) only needs
+//! CAP_NET_ADMIN, so commands check for the capabilities they use instead of
+//! demanding root. Persistent tuning (files under /etc) still needs real root.
+
 use nix::unistd::Uid;
 
 pub fn is_root() -> bool {
     Uid::effective().is_root()
 }
+
+/// Linux capabilities hifi-wifi uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// qdiscs, iw, /proc/sys/net, nftables, conntrack
+    NetAdmin,
+    /// Raw sockets (ping, packet counters)
+    NetRaw,
+}
+
+impl Capability {
+    /// Bit number in the kernel capability sets
+    fn bit(self) -> u32 {
+        match self {
+            Capability::NetAdmin => 12,
+            Capability::NetRaw => 13,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::NetAdmin => "CAP_NET_ADMIN",
+            Capability::NetRaw => "CAP_NET_RAW",
+        }
+    }
+}
+
+/// What a command needs to run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Requirement {
+    /// Read-only, works as any user
+    None,
+    /// Root, or these effective capabilities
+    Caps(&'static [Capability]),
+    /// Writes system files (/etc, systemd units): root only
+    Root,
+}
+
+/// `CapEff` mask from the contents of /proc/<pid>/status
+pub fn parse_cap_eff(status: &str) -> Option<u64> {
+    status.lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|hex| u64::from_str_radix(hex.trim(), 16).ok())
+}
+
+fn effective_caps() -> u64 {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|s| parse_cap_eff(&s))
+        .unwrap_or(0)
+}
+
+/// Capabilities from `caps` this process doesn't have
+pub fn missing(caps: &[Capability]) -> Vec<Capability> {
+    let effective = effective_caps();
+    caps.iter().copied().filter(|c| effective & (1 << c.bit()) == 0).collect()
+}
+
+/// Ok if this process meets `requirement`, else the capabilities it lacks (empty for `Root`)
+pub fn check(requirement: Requirement) -> Result<(), Vec<Capability>> {
+    match requirement {
+        Requirement::None => Ok(()),
+        _ if is_root() => Ok(()),
+        Requirement::Root => Err(Vec::new()),
+        Requirement::Caps(caps) => {
+            let missing = missing(caps);
+            if missing.is_empty() { Ok(()) } else { Err(missing) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cap_eff() {
+        let status = "Name:\thifi-wifi\nCapInh:\t0000000000000000\nCapPrm:\t0000000000003000\nCapEff:\t0000000000003000\n";
+        let eff = parse_cap_eff(status).unwrap();
+        assert_ne!(eff & (1 << Capability::NetAdmin.bit()), 0);
+        assert_ne!(eff & (1 << Capability::NetRaw.bit()), 0);
+        assert_eq!(eff & (1 << 21), 0);
+        assert_eq!(parse_cap_eff("Name:\tx\n"), None);
+    }
+}
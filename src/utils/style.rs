@@ -0,0 +1,91 @@
+//! Terminal color handling
+//!
+//! Colors are used only when stdout is a terminal, `NO_COLOR` is unset (see
+//! https://no-color.org) and `--no-color` was not given. Everything that prints
+//! ANSI escapes should take them from `palette()`.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// ANSI escape sequences (all empty when color is disabled)
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub red: &'static str,
+    pub green: &'static str,
+    pub yellow: &'static str,
+    pub blue: &'static str,
+    pub cyan: &'static str,
+    pub bold: &'static str,
+    pub dim: &'static str,
+    pub reset: &'static str,
+}
+
+const COLOR: Palette = Palette {
+    red: "\x1b[0;31m",
+    green: "\x1b[0;32m",
+    yellow: "\x1b[0;33m",
+    blue: "\x1b[0;34m",
+    cyan: "\x1b[0;36m",
+    bold: "\x1b[1m",
+    dim: "\x1b[2m",
+    reset: "\x1b[0m",
+};
+
+const PLAIN: Palette = Palette {
+    red: "",
+    green: "",
+    yellow: "",
+    blue: "",
+    cyan: "",
+    bold: "",
+    dim: "",
+    reset: "",
+};
+
+/// Decide once at startup whether color output is allowed
+pub fn init(no_color_flag: bool) {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let enabled = !no_color_flag && !no_color_env && std::io::stdout().is_terminal();
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether color output is enabled
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Current palette
+pub fn palette() -> Palette {
+    if enabled() { COLOR } else { PLAIN }
+}
+
+/// `values` as block characters scaled between `lo` and `hi`, averaged down to `width`
+pub fn sparkline(values: &[f64], lo: f64, hi: f64, width: usize) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    if values.is_empty() || width == 0 || hi <= lo {
+        return String::new();
+    }
+    let chunk = values.len().div_ceil(width);
+    values.chunks(chunk)
+        .map(|c| {
+            let avg = c.iter().sum::<f64>() / c.len() as f64;
+            let level = ((avg - lo) / (hi - lo) * 7.0).round().clamp(0.0, 7.0);
+            BLOCKS[level as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[-90.0, -70.0, -60.0, -30.0], -90.0, -30.0, 10), "▁▃▅█");
+        // Averaged down to the width
+        assert_eq!(sparkline(&[-90.0, -90.0, -30.0, -30.0], -90.0, -30.0, 2), "▁█");
+        assert_eq!(sparkline(&[], -90.0, -30.0, 10), "");
+    }
+}